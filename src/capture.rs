@@ -0,0 +1,670 @@
+//! Session capture recording and replay
+//!
+//! Records the raw [`KeyEvent`] stream of a session to a compact capture file
+//! so it can be fed back through every [`KeyboardTest`](crate::tests::KeyboardTest)
+//! later, e.g. to re-run diagnostics on a flaky keyboard offline or to attach
+//! the capture to an RMA ticket.
+//!
+//! ## File Format
+//!
+//! JSON Lines: one header object, then one object per event with short keys.
+//!
+//! ```text
+//! {"format":"keyboard-testkit-capture","version":1,"created_at":"1760000000Z","tool_version":"0.1.0","source":"evdev","devices":["/dev/input/event3"],"device_info":[{"path":"/dev/input/event3","name":"USB Keyboard","vendor_id":1133,"product_id":49948}]}
+//! {"t":0,"k":30,"e":"p","d":1000,"hw":5312000123,"dev":0,"sc":458756}
+//! {"t":84211,"k":30,"e":"r","d":84211,"hw":5312084334,"dev":0}
+//! ```
+//!
+//! | Key | Meaning |
+//! |-----|---------|
+//! | `t` | Microseconds since the first recorded event |
+//! | `k` | Key code (evdev scancode) |
+//! | `e` | Event type: `p` press, `r` release, `a` auto-repeat |
+//! | `d` | `delta_us` as seen by the listener |
+//! | `hw` | Kernel timestamp in microseconds (omitted when unavailable) |
+//! | `dev` | Index of the source device in the header's `device_info` (omitted when unknown) |
+//! | `sc` | Raw hardware scancode from MSC_SCAN (omitted when the driver sends none) |
+//!
+//! ## Example
+//!
+//! ```no_run
+//! use keyboard_testkit::capture::{Capture, ReplayListener};
+//! use keyboard_testkit::keyboard::KeyEvent;
+//! use std::path::Path;
+//! use std::sync::mpsc;
+//!
+//! let capture = Capture::load(Path::new("flaky.kbcap")).unwrap();
+//! let (tx, rx) = mpsc::channel::<KeyEvent>();
+//!
+//! // Replay at 4x the original speed
+//! let mut replay = ReplayListener::new(capture, 4.0, tx);
+//! while !replay.is_finished() {
+//!     replay.poll();
+//!     while let Ok(event) = rx.try_recv() {
+//!         // app.process_event(&event);
+//!     }
+//! }
+//! ```
+
+use crate::keyboard::{
+    DeviceInfo, InputFidelity, KeyCode, KeyEvent, KeyEventType, RepeatSettings,
+};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Identifier written in the `format` field of every capture header
+pub const CAPTURE_FORMAT: &str = "keyboard-testkit-capture";
+
+/// Current capture file format version
+pub const CAPTURE_VERSION: u32 = 1;
+
+/// Error type for capture operations
+#[derive(Debug)]
+pub enum CaptureError {
+    /// IO error reading or writing the capture file
+    Io(io::Error),
+    /// Malformed line in the capture file
+    Parse {
+        line: usize,
+        error: serde_json::Error,
+    },
+    /// File is empty or does not start with a capture header
+    InvalidHeader,
+    /// Capture was written by a newer, incompatible version
+    UnsupportedVersion(u32),
+}
+
+impl std::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureError::Io(e) => write!(f, "IO error: {}", e),
+            CaptureError::Parse { line, error } => {
+                write!(f, "Parse error on line {}: {}", line, error)
+            }
+            CaptureError::InvalidHeader => write!(f, "Not a keyboard-testkit capture file"),
+            CaptureError::UnsupportedVersion(v) => {
+                write!(
+                    f,
+                    "Unsupported capture version {} (expected {})",
+                    v, CAPTURE_VERSION
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl From<io::Error> for CaptureError {
+    fn from(e: io::Error) -> Self {
+        CaptureError::Io(e)
+    }
+}
+
+/// First line of a capture file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptureHeader {
+    /// Always [`CAPTURE_FORMAT`]
+    pub format: String,
+    /// File format version
+    pub version: u32,
+    /// Capture creation time (seconds since the Unix epoch, `Z` suffixed)
+    pub created_at: String,
+    /// Version of the tool that wrote the capture
+    pub tool_version: String,
+    /// Event source the session ran on: "evdev", "kitty" (terminal with the
+    /// kitty keyboard protocol), "crossterm" (presses only) or "replay"
+    pub source: String,
+    /// Input devices the listener had open
+    #[serde(default)]
+    pub devices: Vec<String>,
+    /// Identity of each device events can be tagged with (`dev` index)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub device_info: Vec<DeviceInfo>,
+    /// Auto-repeat settings of the recorded keyboard (EVIOCGREP)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<RepeatSettings>,
+}
+
+impl CaptureHeader {
+    /// Create a header for a capture starting now
+    pub fn new(source: impl Into<String>, devices: Vec<String>) -> Self {
+        let now_secs = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Self {
+            format: CAPTURE_FORMAT.to_string(),
+            version: CAPTURE_VERSION,
+            created_at: format!("{}Z", now_secs),
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            source: source.into(),
+            devices,
+            device_info: Vec::new(),
+            repeat: None,
+        }
+    }
+
+    /// Record device identities so events can be tagged with their source
+    pub fn with_device_info(mut self, device_info: Vec<DeviceInfo>) -> Self {
+        self.device_info = device_info;
+        self
+    }
+
+    /// Record the keyboard's auto-repeat settings for the typematic test
+    pub fn with_repeat_settings(mut self, repeat: Option<RepeatSettings>) -> Self {
+        self.repeat = repeat;
+        self
+    }
+}
+
+/// A single recorded key event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapturedEvent {
+    /// Microseconds since the first recorded event
+    #[serde(rename = "t")]
+    pub offset_us: u64,
+    /// Key code
+    #[serde(rename = "k")]
+    pub key: u16,
+    /// Event type code (see [`CapturedEvent::event_type`])
+    #[serde(rename = "e")]
+    pub kind: String,
+    /// Listener-reported delta in microseconds
+    #[serde(rename = "d")]
+    pub delta_us: u64,
+    /// Kernel timestamp in microseconds
+    #[serde(rename = "hw", default, skip_serializing_if = "Option::is_none")]
+    pub hw_timestamp_us: Option<u64>,
+    /// Index into the header's `device_info`
+    #[serde(rename = "dev", default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<usize>,
+    /// Raw hardware scancode (MSC_SCAN)
+    #[serde(rename = "sc", default, skip_serializing_if = "Option::is_none")]
+    pub scancode: Option<u32>,
+}
+
+impl CapturedEvent {
+    /// Build a record from a live event
+    pub fn from_event(event: &KeyEvent, offset_us: u64) -> Self {
+        let kind = match event.event_type {
+            KeyEventType::Press => "p",
+            KeyEventType::Release => "r",
+            KeyEventType::Repeat => "a",
+        };
+        Self {
+            offset_us,
+            key: event.key.0,
+            kind: kind.to_string(),
+            delta_us: event.delta_us,
+            hw_timestamp_us: event.hw_timestamp_us,
+            device: None,
+            scancode: event.scancode,
+        }
+    }
+
+    /// Decode the event type, `None` for codes this version doesn't know
+    pub fn event_type(&self) -> Option<KeyEventType> {
+        match self.kind.as_str() {
+            "p" => Some(KeyEventType::Press),
+            "r" => Some(KeyEventType::Release),
+            "a" => Some(KeyEventType::Repeat),
+            _ => None,
+        }
+    }
+
+    /// Rebuild a `KeyEvent`, placing its timestamp at `base + offset`
+    pub fn to_event(&self, base: Instant) -> Option<KeyEvent> {
+        let event_type = self.event_type()?;
+        let timestamp = base + Duration::from_micros(self.offset_us);
+        let mut event = KeyEvent::new(KeyCode(self.key), event_type, timestamp, self.delta_us);
+        event.hw_timestamp_us = self.hw_timestamp_us;
+        event.scancode = self.scancode;
+        Some(event)
+    }
+}
+
+/// Writes key events to a capture file as they arrive
+pub struct CaptureRecorder {
+    writer: BufWriter<File>,
+    /// Timestamp of the first recorded event
+    first_event: Option<Instant>,
+    /// Number of events written
+    event_count: u64,
+    /// Device paths from the header, in `dev` index order
+    device_paths: Vec<String>,
+}
+
+impl CaptureRecorder {
+    /// Create a capture file at `path` and write its header
+    pub fn create(path: &Path, header: &CaptureHeader) -> Result<Self, CaptureError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let line = serde_json::to_string(header)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        writeln!(writer, "{}", line)?;
+        Ok(Self {
+            writer,
+            first_event: None,
+            event_count: 0,
+            device_paths: header.device_info.iter().map(|d| d.path.clone()).collect(),
+        })
+    }
+
+    /// Append an event to the capture
+    pub fn record(&mut self, event: &KeyEvent) -> io::Result<()> {
+        let first = *self.first_event.get_or_insert(event.timestamp);
+        let offset_us = event.timestamp.duration_since(first).as_micros() as u64;
+        let mut record = CapturedEvent::from_event(event, offset_us);
+        record.device = event
+            .device_path()
+            .and_then(|path| self.device_paths.iter().position(|p| p == path));
+        let line = serde_json::to_string(&record)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        writeln!(self.writer, "{}", line)?;
+        self.event_count += 1;
+        Ok(())
+    }
+
+    /// Number of events recorded so far
+    pub fn event_count(&self) -> u64 {
+        self.event_count
+    }
+
+    /// Flush buffered events to disk
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Drop for CaptureRecorder {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+/// A capture file loaded into memory
+#[derive(Debug, Clone)]
+pub struct Capture {
+    /// File header
+    pub header: CaptureHeader,
+    /// Recorded events in file order
+    pub events: Vec<CapturedEvent>,
+}
+
+impl Capture {
+    /// Load a capture file from disk
+    pub fn load(path: &Path) -> Result<Self, CaptureError> {
+        let file = File::open(path)?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    /// Parse a capture from any buffered reader
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, CaptureError> {
+        let mut lines = reader.lines().enumerate();
+
+        let header: CaptureHeader = match lines.next() {
+            Some((_, line)) => {
+                serde_json::from_str(&line?).map_err(|_| CaptureError::InvalidHeader)?
+            }
+            None => return Err(CaptureError::InvalidHeader),
+        };
+        if header.format != CAPTURE_FORMAT {
+            return Err(CaptureError::InvalidHeader);
+        }
+        if header.version > CAPTURE_VERSION {
+            return Err(CaptureError::UnsupportedVersion(header.version));
+        }
+
+        let mut events = Vec::new();
+        for (idx, line) in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event = serde_json::from_str(&line).map_err(|e| CaptureError::Parse {
+                line: idx + 1,
+                error: e,
+            })?;
+            events.push(event);
+        }
+
+        Ok(Self { header, events })
+    }
+
+    /// Duration of the capture from first to last event
+    pub fn duration(&self) -> Duration {
+        let last = self.events.iter().map(|e| e.offset_us).max().unwrap_or(0);
+        Duration::from_micros(last)
+    }
+}
+
+/// Listener that feeds a loaded capture back as live `KeyEvent`s.
+///
+/// Mirrors the `poll()` API of the real listeners so the main loop can use it
+/// in their place. Event timestamps keep the original spacing regardless of
+/// playback speed, so timing tests see the keyboard as recorded.
+pub struct ReplayListener {
+    events: Vec<CapturedEvent>,
+    /// Devices from the capture header, attached to events by `dev` index
+    devices: Vec<Arc<DeviceInfo>>,
+    /// Auto-repeat settings from the capture header
+    repeat: Option<RepeatSettings>,
+    /// Fidelity of the recorded session, from the header's `source`
+    fidelity: Option<InputFidelity>,
+    /// Index of the next event to send
+    next: usize,
+    /// Playback speed multiplier; `0.0` sends everything on the first poll
+    speed: f64,
+    /// When playback started (set on first poll)
+    started: Option<Instant>,
+    event_tx: mpsc::Sender<KeyEvent>,
+}
+
+impl ReplayListener {
+    /// Create a replay listener for a capture at the given speed multiplier
+    pub fn new(capture: Capture, speed: f64, event_tx: mpsc::Sender<KeyEvent>) -> Self {
+        Self {
+            events: capture.events,
+            devices: capture.header.device_info.into_iter().map(Arc::new).collect(),
+            repeat: capture.header.repeat,
+            fidelity: InputFidelity::from_capture_source(&capture.header.source),
+            next: 0,
+            speed: speed.max(0.0),
+            started: None,
+            event_tx,
+        }
+    }
+
+    /// Send all events that are due. Returns the number of events sent.
+    pub fn poll(&mut self) -> usize {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        let elapsed_us = if self.speed > 0.0 {
+            (started.elapsed().as_micros() as f64 * self.speed) as u64
+        } else {
+            u64::MAX
+        };
+
+        let mut sent = 0;
+        while let Some(record) = self.events.get(self.next) {
+            if record.offset_us > elapsed_us {
+                break;
+            }
+            self.next += 1;
+            if let Some(mut event) = record.to_event(started) {
+                event.device = record.device.and_then(|i| self.devices.get(i)).cloned();
+                if self.event_tx.send(event).is_err() {
+                    self.next = self.events.len();
+                    break;
+                }
+                sent += 1;
+            }
+        }
+        sent
+    }
+
+    /// Whether every event has been sent
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.events.len()
+    }
+
+    /// Number of events sent so far
+    pub fn events_sent(&self) -> usize {
+        self.next
+    }
+
+    /// Total events in the capture
+    pub fn total_events(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Devices recorded in the capture header
+    pub fn devices(&self) -> Vec<DeviceInfo> {
+        self.devices.iter().map(|d| (**d).clone()).collect()
+    }
+
+    /// Auto-repeat settings recorded in the capture header
+    pub fn repeat_settings(&self) -> Option<RepeatSettings> {
+        self.repeat
+    }
+
+    /// Input fidelity the capture was recorded at, when known
+    pub fn fidelity(&self) -> Option<InputFidelity> {
+        self.fidelity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn sample_events(base: Instant) -> Vec<KeyEvent> {
+        vec![
+            KeyEvent::new(KeyCode(30), KeyEventType::Press, base, 1000)
+                .with_hw_timestamp(5_000)
+                .with_scancode(0x0007_0004),
+            KeyEvent::new(
+                KeyCode(30),
+                KeyEventType::Release,
+                base + Duration::from_micros(2500),
+                2500,
+            )
+            .with_hw_timestamp(7_500),
+            KeyEvent::new(
+                KeyCode(31),
+                KeyEventType::Press,
+                base + Duration::from_micros(9000),
+                6500,
+            ),
+        ]
+    }
+
+    #[test]
+    fn record_and_load_roundtrip() {
+        let path = std::env::temp_dir().join(format!(
+            "keyboard-testkit-capture-{}.kbcap",
+            std::process::id()
+        ));
+
+        let base = Instant::now();
+        let header = CaptureHeader::new("evdev", vec!["/dev/input/event3".to_string()]);
+        {
+            let mut recorder = CaptureRecorder::create(&path, &header).unwrap();
+            for event in sample_events(base) {
+                recorder.record(&event).unwrap();
+            }
+            assert_eq!(recorder.event_count(), 3);
+        }
+
+        let capture = Capture::load(&path).unwrap();
+        assert_eq!(capture.header, header);
+        assert_eq!(capture.events.len(), 3);
+        assert_eq!(capture.events[1].offset_us, 2500);
+        assert_eq!(capture.events[1].event_type(), Some(KeyEventType::Release));
+        assert_eq!(capture.events[1].hw_timestamp_us, Some(7_500));
+        assert_eq!(capture.events[2].hw_timestamp_us, None);
+        assert_eq!(capture.events[0].scancode, Some(0x0007_0004));
+        assert_eq!(capture.events[1].scancode, None);
+        assert_eq!(capture.duration(), Duration::from_micros(9000));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn device_tags_survive_record_and_replay() {
+        let path = std::env::temp_dir().join(format!(
+            "keyboard-testkit-capture-dev-{}.kbcap",
+            std::process::id()
+        ));
+        let board = DeviceInfo::new("/dev/input/event5", "USB Keyboard", 0x046d, 0xc31c);
+        let header = CaptureHeader::new("evdev", vec![board.path.clone()])
+            .with_device_info(vec![board.clone()]);
+        let base = Instant::now();
+        {
+            let mut recorder = CaptureRecorder::create(&path, &header).unwrap();
+            let tagged = KeyEvent::new(KeyCode(30), KeyEventType::Press, base, 1000)
+                .with_device(Arc::new(board.clone()));
+            recorder.record(&tagged).unwrap();
+            // Untagged events (e.g. crossterm) have no index
+            recorder.record(&sample_events(base)[1]).unwrap();
+        }
+
+        let capture = Capture::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(capture.events[0].device, Some(0));
+        assert_eq!(capture.events[1].device, None);
+
+        let (tx, rx) = mpsc::channel();
+        ReplayListener::new(capture, 0.0, tx).poll();
+        let events: Vec<KeyEvent> = rx.try_iter().collect();
+        assert_eq!(events[0].device.as_deref(), Some(&board));
+        assert!(events[1].device.is_none());
+    }
+
+    #[test]
+    fn old_capture_without_devices_loads() {
+        let text = concat!(
+            r#"{"format":"keyboard-testkit-capture","version":1,"created_at":"0Z","tool_version":"0.1.0","source":"evdev"}"#,
+            "\n",
+            r#"{"t":0,"k":30,"e":"p","d":1000}"#,
+            "\n"
+        );
+        let capture = Capture::from_reader(Cursor::new(text)).unwrap();
+        assert!(capture.header.device_info.is_empty());
+        assert_eq!(capture.events[0].device, None);
+    }
+
+    #[test]
+    fn repeats_and_repeat_settings_replay() {
+        let text = concat!(
+            r#"{"format":"keyboard-testkit-capture","version":1,"created_at":"0Z","tool_version":"0.1.0","source":"evdev","repeat":{"delay_ms":250,"period_ms":33}}"#,
+            "\n",
+            r#"{"t":0,"k":30,"e":"p","d":1000}"#,
+            "\n",
+            r#"{"t":250000,"k":30,"e":"a","d":250000}"#,
+            "\n"
+        );
+        let capture = Capture::from_reader(Cursor::new(text)).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let mut replay = ReplayListener::new(capture, 0.0, tx);
+        replay.poll();
+
+        assert_eq!(replay.repeat_settings().map(|r| r.period_ms), Some(33));
+        let events: Vec<KeyEvent> = rx.try_iter().collect();
+        assert_eq!(events[1].event_type, KeyEventType::Repeat);
+    }
+
+    #[test]
+    fn load_rejects_non_capture() {
+        let result = Capture::from_reader(Cursor::new("{\"hello\":1}\n"));
+        assert!(matches!(result, Err(CaptureError::InvalidHeader)));
+
+        let result = Capture::from_reader(Cursor::new(""));
+        assert!(matches!(result, Err(CaptureError::InvalidHeader)));
+    }
+
+    #[test]
+    fn load_rejects_newer_version() {
+        let mut header = CaptureHeader::new("evdev", Vec::new());
+        header.version = CAPTURE_VERSION + 1;
+        let text = serde_json::to_string(&header).unwrap();
+        let result = Capture::from_reader(Cursor::new(text));
+        assert!(matches!(result, Err(CaptureError::UnsupportedVersion(_))));
+    }
+
+    #[test]
+    fn load_reports_bad_line() {
+        let header = serde_json::to_string(&CaptureHeader::new("evdev", Vec::new())).unwrap();
+        let text = format!(
+            "{}\n{{\"t\":0,\"k\":30,\"e\":\"p\",\"d\":0}}\nnot json\n",
+            header
+        );
+        let result = Capture::from_reader(Cursor::new(text));
+        assert!(matches!(result, Err(CaptureError::Parse { line: 3, .. })));
+    }
+
+    #[test]
+    fn replay_max_speed_sends_everything_in_order() {
+        let base = Instant::now();
+        let capture = Capture {
+            header: CaptureHeader::new("evdev", Vec::new()),
+            events: sample_events(base)
+                .iter()
+                .map(|e| {
+                    CapturedEvent::from_event(
+                        e,
+                        e.timestamp.duration_since(base).as_micros() as u64,
+                    )
+                })
+                .collect(),
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let mut replay = ReplayListener::new(capture, 0.0, tx);
+        assert_eq!(replay.poll(), 3);
+        assert!(replay.is_finished());
+
+        let events: Vec<KeyEvent> = rx.try_iter().collect();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].key, KeyCode(30));
+        assert_eq!(events[1].event_type, KeyEventType::Release);
+        assert_eq!(events[1].hw_timestamp_us, Some(7_500));
+        // Original spacing is preserved in the rebuilt timestamps
+        assert_eq!(
+            events[2].timestamp.duration_since(events[0].timestamp),
+            Duration::from_micros(9000)
+        );
+    }
+
+    #[test]
+    fn replay_realtime_holds_back_future_events() {
+        let capture = Capture {
+            header: CaptureHeader::new("evdev", Vec::new()),
+            events: vec![
+                CapturedEvent {
+                    offset_us: 0,
+                    key: 30,
+                    kind: "p".to_string(),
+                    delta_us: 0,
+                    hw_timestamp_us: None,
+                    device: None,
+                    scancode: None,
+                },
+                CapturedEvent {
+                    offset_us: 60_000_000,
+                    key: 30,
+                    kind: "r".to_string(),
+                    delta_us: 0,
+                    hw_timestamp_us: None,
+                    device: None,
+                    scancode: None,
+                },
+            ],
+        };
+
+        let (tx, _rx) = mpsc::channel();
+        let mut replay = ReplayListener::new(capture, 1.0, tx);
+        assert_eq!(replay.poll(), 1);
+        assert!(!replay.is_finished());
+        assert_eq!(replay.events_sent(), 1);
+        assert_eq!(replay.total_events(), 2);
+    }
+
+    #[test]
+    fn unknown_event_kind_is_skipped() {
+        let record = CapturedEvent {
+            offset_us: 0,
+            key: 30,
+            kind: "x".to_string(),
+            delta_us: 0,
+            hw_timestamp_us: None,
+            device: None,
+            scancode: None,
+        };
+        assert!(record.to_event(Instant::now()).is_none());
+    }
+}
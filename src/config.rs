@@ -44,6 +44,8 @@ pub enum ConfigError {
     Parse(toml::de::Error),
     /// Failed to serialize config
     Serialize(toml::ser::Error),
+    /// File parsed but its contents are not valid
+    Invalid(String),
 }
 
 impl std::fmt::Display for ConfigError {
@@ -53,6 +55,7 @@ impl std::fmt::Display for ConfigError {
             ConfigError::Io(e) => write!(f, "IO error: {}", e),
             ConfigError::Parse(e) => write!(f, "Parse error: {}", e),
             ConfigError::Serialize(e) => write!(f, "Serialize error: {}", e),
+            ConfigError::Invalid(msg) => write!(f, "Invalid: {}", msg),
         }
     }
 }
@@ -98,6 +101,34 @@ pub fn config_path() -> Result<PathBuf, ConfigError> {
     Ok(app_dir.join("config.toml"))
 }
 
+/// Returns the directory searched for named acceptance profiles.
+///
+/// This is a `profiles` directory next to the config file, e.g.
+/// `~/.config/keyboard-testkit/profiles/` on Linux. The directory is not
+/// created; profiles are only ever read from it.
+pub fn profiles_dir() -> Result<PathBuf, ConfigError> {
+    let config_dir = platform_config_dir().ok_or(ConfigError::NoConfigDir)?;
+    Ok(config_dir.join("keyboard-testkit").join("profiles"))
+}
+
+/// Returns the directory searched for named keyboard matrix definitions.
+///
+/// Like [`profiles_dir`], this sits next to the config file
+/// (`~/.config/keyboard-testkit/matrices/` on Linux) and is not created.
+pub fn matrices_dir() -> Result<PathBuf, ConfigError> {
+    let config_dir = platform_config_dir().ok_or(ConfigError::NoConfigDir)?;
+    Ok(config_dir.join("keyboard-testkit").join("matrices"))
+}
+
+/// Returns the user's directory of mapper presets.
+///
+/// Like [`profiles_dir`], this sits next to the config file
+/// (`~/.config/keyboard-testkit/presets/` on Linux) and is not created.
+pub fn presets_dir() -> Result<PathBuf, ConfigError> {
+    let config_dir = platform_config_dir().ok_or(ConfigError::NoConfigDir)?;
+    Ok(config_dir.join("keyboard-testkit").join("presets"))
+}
+
 /// Get the platform-specific config directory without the `dirs` crate.
 fn platform_config_dir() -> Option<PathBuf> {
     #[cfg(target_os = "linux")]
@@ -140,6 +171,9 @@ pub struct Config {
     /// OEM key and remapping settings
     #[serde(default)]
     pub oem_keys: OemKeyConfig,
+    /// Auto-repeat test settings
+    #[serde(default)]
+    pub typematic: TypematicConfig,
 }
 
 /// Polling rate test configuration
@@ -193,6 +227,19 @@ impl Default for HoldReleaseConfig {
     }
 }
 
+/// Auto-repeat test configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypematicConfig {
+    /// Allowed deviation of measured delay/rate from the configured values, in percent
+    pub tolerance_pct: u32,
+}
+
+impl Default for TypematicConfig {
+    fn default() -> Self {
+        Self { tolerance_pct: 10 }
+    }
+}
+
 /// UI configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UiConfig {
@@ -0,0 +1,301 @@
+//! udev hwdb keymap generation
+//!
+//! The [`mapper`](crate::mapper) fixes keys by grabbing the keyboard and
+//! re-emitting remapped events through uinput, which means a root daemon
+//! running for as long as the keyboard is attached. For plain one-to-one
+//! remaps the kernel can do the same job itself: udev loads scancode →
+//! keycode overrides from the hardware database and installs them with
+//! EVIOCSKEYCODE when the device appears.
+//!
+//! Keycode mappings (from the OEM key config or a mapper preset) are turned
+//! into scancode mappings using the MSC_SCAN codes observed while testing,
+//! and written as a hwdb file matching the keyboard's vendor and product.
+//!
+//! ## File Format
+//!
+//! ```text
+//! # ROG/Launch (0x094) -> LeftMeta (0x07D)
+//! evdev:input:b*v0B05p19B6*
+//!  KEYBOARD_KEY_ff3100a6=125
+//! ```
+//!
+//! Install it under `/etc/udev/hwdb.d/` and run
+//! `systemd-hwdb update && udevadm trigger`, or apply it to the running
+//! device right away with [`HwdbKeymap::apply`].
+
+use crate::keyboard::keymap::{get_key_info, KeyCode};
+use crate::keyboard::{DeviceInfo, KeyEvent, KeyEventType};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// System directory udev reads local hwdb files from
+pub const HWDB_DIR: &str = "/etc/udev/hwdb.d";
+
+/// Scancodes seen for each keycode, per keyboard model
+#[derive(Debug, Clone, Default)]
+pub struct ScancodeLog {
+    /// (vendor, product) → keycode → scancodes that produced it
+    models: HashMap<(u16, u16), BTreeMap<u16, BTreeSet<u32>>>,
+    /// Most recent device that reported a scancode
+    last_device: Option<DeviceInfo>,
+}
+
+impl ScancodeLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember the scancode behind a key press, if the event carries one
+    pub fn record(&mut self, event: &KeyEvent) {
+        if event.event_type != KeyEventType::Press {
+            return;
+        }
+        let (Some(device), Some(scancode)) = (event.device.as_deref(), event.scancode) else {
+            return;
+        };
+        self.models
+            .entry((device.vendor_id, device.product_id))
+            .or_default()
+            .entry(event.key.0)
+            .or_default()
+            .insert(scancode);
+        if self.last_device.as_ref() != Some(device) {
+            self.last_device = Some(device.clone());
+        }
+    }
+
+    /// Keycode → scancodes observed on keyboards of the same model as `device`
+    pub fn scancodes_for(&self, device: &DeviceInfo) -> Option<&BTreeMap<u16, BTreeSet<u32>>> {
+        self.models.get(&(device.vendor_id, device.product_id))
+    }
+
+    /// The device that most recently reported a scancode
+    pub fn last_device(&self) -> Option<&DeviceInfo> {
+        self.last_device.as_ref()
+    }
+
+    pub fn clear(&mut self) {
+        self.models.clear();
+        self.last_device = None;
+    }
+}
+
+/// A hwdb keymap for one keyboard model
+#[derive(Debug, Clone)]
+pub struct HwdbKeymap {
+    /// Keyboard the keymap was built for
+    pub device: DeviceInfo,
+    /// Scancode → keycode overrides
+    pub entries: BTreeMap<u32, u16>,
+    /// Source keycodes of the mappings that produced each entry
+    pub sources: BTreeMap<u32, u16>,
+    /// Mapped keycodes that were never seen with a scancode, so they
+    /// could not be expressed in the hwdb
+    pub unresolved: Vec<u16>,
+}
+
+impl HwdbKeymap {
+    /// Resolve keycode mappings to scancodes observed on `device`.
+    ///
+    /// Identity mappings are skipped; they change nothing at the kernel level.
+    pub fn build(device: &DeviceInfo, mappings: &HashMap<u16, u16>, log: &ScancodeLog) -> Self {
+        let observed = log.scancodes_for(device);
+        let mut entries = BTreeMap::new();
+        let mut sources = BTreeMap::new();
+        let mut unresolved = Vec::new();
+
+        let mut sorted: Vec<(u16, u16)> = mappings
+            .iter()
+            .filter(|(from, to)| from != to)
+            .map(|(&from, &to)| (from, to))
+            .collect();
+        sorted.sort_unstable();
+
+        for (from, to) in sorted {
+            match observed.and_then(|o| o.get(&from)) {
+                Some(scancodes) => {
+                    for &scancode in scancodes {
+                        entries.insert(scancode, to);
+                        sources.insert(scancode, from);
+                    }
+                }
+                None => unresolved.push(from),
+            }
+        }
+
+        Self {
+            device: device.clone(),
+            entries,
+            sources,
+            unresolved,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The udev modalias match for this keyboard model
+    pub fn match_line(&self) -> String {
+        format!(
+            "evdev:input:b*v{:04X}p{:04X}*",
+            self.device.vendor_id, self.device.product_id
+        )
+    }
+
+    /// File name to install under [`HWDB_DIR`]
+    pub fn file_name(&self) -> String {
+        format!(
+            "70-keyboard-testkit-{:04x}-{:04x}.hwdb",
+            self.device.vendor_id, self.device.product_id
+        )
+    }
+
+    /// Render the hwdb file content
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# Generated by Keyboard TestKit\n");
+        out.push_str(&format!(
+            "# Device: {} ({})\n",
+            self.device.label(),
+            self.device.usb_id()
+        ));
+        out.push_str("# Apply with: systemd-hwdb update && udevadm trigger\n");
+        for (scancode, to) in &self.entries {
+            let from = self.sources.get(scancode).copied().unwrap_or(0);
+            out.push_str(&format!(
+                "# {} (0x{:03X}) -> {} (0x{:03X})\n",
+                get_key_info(KeyCode::new(from)).name,
+                from,
+                get_key_info(KeyCode::new(*to)).name,
+                to
+            ));
+        }
+        out.push_str(&self.match_line());
+        out.push('\n');
+        for (scancode, to) in &self.entries {
+            out.push_str(&format!(" KEYBOARD_KEY_{:x}={}\n", scancode, to));
+        }
+        out
+    }
+
+    /// Write the hwdb file into `dir`, returning its path
+    pub fn write_to(&self, dir: &Path) -> io::Result<PathBuf> {
+        let path = dir.join(self.file_name());
+        fs::write(&path, self.render())?;
+        Ok(path)
+    }
+
+    /// Install the scancode overrides on a running device via
+    /// EVIOCSKEYCODE_V2, returning how many were applied. The change lasts
+    /// until the device is reconnected; the hwdb file makes it permanent.
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self, device_path: &Path) -> io::Result<usize> {
+        use std::os::unix::io::AsRawFd;
+
+        let file = fs::OpenOptions::new().read(true).open(device_path)?;
+        for (&scancode, &keycode) in &self.entries {
+            let mut entry = InputKeymapEntry {
+                flags: 0,
+                len: 4,
+                index: 0,
+                keycode: keycode as u32,
+                scancode: [0; 32],
+            };
+            entry.scancode[..4].copy_from_slice(&scancode.to_ne_bytes());
+            // SAFETY: EVIOCSKEYCODE_V2 reads one input_keymap_entry, which
+            // InputKeymapEntry matches; the fd is valid for the call.
+            let rc = unsafe { libc::ioctl(file.as_raw_fd(), EVIOCSKEYCODE_V2, &entry) };
+            if rc < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(self.entries.len())
+    }
+}
+
+/// Matches struct input_keymap_entry
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct InputKeymapEntry {
+    flags: u8,
+    len: u8,
+    index: u16,
+    keycode: u32,
+    scancode: [u8; 32],
+}
+
+/// _IOW('E', 0x04, struct input_keymap_entry)
+#[cfg(target_os = "linux")]
+const EVIOCSKEYCODE_V2: libc::c_ulong = 0x40284504;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    fn asus() -> DeviceInfo {
+        DeviceInfo::new("/dev/input/event4", "ASUS N-KEY Device", 0x0b05, 0x19b6)
+    }
+
+    fn press(key: u16, scancode: u32, device: &DeviceInfo) -> KeyEvent {
+        KeyEvent::new(KeyCode::new(key), KeyEventType::Press, Instant::now(), 0)
+            .with_device(Arc::new(device.clone()))
+            .with_scancode(scancode)
+    }
+
+    #[test]
+    fn mappings_resolve_to_observed_scancodes() {
+        let device = asus();
+        let mut log = ScancodeLog::new();
+        log.record(&press(148, 0xff31_00a6, &device));
+        log.record(&press(30, 0x7_0004, &device));
+
+        let mappings = HashMap::from([(148, 125), (202, 183), (248, 248)]);
+        let keymap = HwdbKeymap::build(&device, &mappings, &log);
+
+        assert_eq!(keymap.entries.get(&0xff31_00a6), Some(&125));
+        assert_eq!(keymap.entries.len(), 1);
+        // Never pressed, so there is no scancode to map; identity is skipped
+        assert_eq!(keymap.unresolved, vec![202]);
+    }
+
+    #[test]
+    fn keys_sharing_a_keycode_are_all_mapped() {
+        let device = asus();
+        let mut log = ScancodeLog::new();
+        log.record(&press(240, 0xff31_0001, &device));
+        log.record(&press(240, 0xff31_0002, &device));
+
+        let keymap = HwdbKeymap::build(&device, &HashMap::from([(240, 183)]), &log);
+        assert_eq!(keymap.entries.len(), 2);
+    }
+
+    #[test]
+    fn other_models_are_not_mixed_in() {
+        let device = asus();
+        let other = DeviceInfo::new("/dev/input/event7", "USB Keyboard", 0x046d, 0xc31c);
+        let mut log = ScancodeLog::new();
+        log.record(&press(148, 0x7_0068, &other));
+
+        let keymap = HwdbKeymap::build(&device, &HashMap::from([(148, 125)]), &log);
+        assert!(keymap.is_empty());
+        assert_eq!(log.last_device(), Some(&other));
+    }
+
+    #[test]
+    fn render_matches_vendor_and_product() {
+        let device = asus();
+        let mut log = ScancodeLog::new();
+        log.record(&press(148, 0xff31_00a6, &device));
+
+        let keymap = HwdbKeymap::build(&device, &HashMap::from([(148, 125)]), &log);
+        let hwdb = keymap.render();
+        assert!(hwdb.contains("\nevdev:input:b*v0B05p19B6*\n"));
+        assert!(hwdb.contains("\n KEYBOARD_KEY_ff3100a6=125\n"));
+        assert_eq!(keymap.file_name(), "70-keyboard-testkit-0b05-19b6.hwdb");
+    }
+}
@@ -0,0 +1,200 @@
+//! Input device identity
+//!
+//! Every evdev node the listener opens is described by a [`DeviceInfo`]
+//! taken from sysfs, and each [`KeyEvent`](super::KeyEvent) it produces
+//! carries that identity. This lets a session be narrowed to the keyboard
+//! under test when several are attached, e.g. a laptop's internal keyboard
+//! alongside the external board being measured.
+//!
+//! [`DeviceCapabilities`] goes further: what the device itself declares
+//! through evdev ioctls, including every key code it claims to send, so a
+//! session can show which declared keys were never pressed.
+
+use super::{keymap, KeyCode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+#[cfg(target_os = "linux")]
+use std::fs;
+#[cfg(target_os = "linux")]
+use std::path::Path;
+
+/// Identity of an input device
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    /// Device node, e.g. /dev/input/event5
+    pub path: String,
+    /// Device name reported by the driver
+    #[serde(default)]
+    pub name: String,
+    /// USB/Bluetooth vendor ID (0 when unknown)
+    #[serde(default)]
+    pub vendor_id: u16,
+    /// USB/Bluetooth product ID (0 when unknown)
+    #[serde(default)]
+    pub product_id: u16,
+}
+
+impl DeviceInfo {
+    pub fn new(
+        path: impl Into<String>,
+        name: impl Into<String>,
+        vendor_id: u16,
+        product_id: u16,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            name: name.into(),
+            vendor_id,
+            product_id,
+        }
+    }
+
+    /// Read name and IDs for an evdev node from /sys/class/input.
+    ///
+    /// Missing sysfs entries leave the name empty and the IDs at 0.
+    #[cfg(target_os = "linux")]
+    pub fn from_sysfs(event_path: &Path) -> Self {
+        let node = event_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        let sysfs = format!("/sys/class/input/{}/device", node);
+        let read = |attr: &str| {
+            fs::read_to_string(format!("{}/{}", sysfs, attr))
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default()
+        };
+        let hex_id = |attr: &str| u16::from_str_radix(&read(attr), 16).unwrap_or(0);
+
+        Self {
+            path: event_path.display().to_string(),
+            name: read("name"),
+            vendor_id: hex_id("id/vendor"),
+            product_id: hex_id("id/product"),
+        }
+    }
+
+    /// Vendor and product as `vvvv:pppp`
+    pub fn usb_id(&self) -> String {
+        format!("{:04x}:{:04x}", self.vendor_id, self.product_id)
+    }
+
+    /// Short display label: the device name, or the path if it has none
+    pub fn label(&self) -> &str {
+        if self.name.is_empty() {
+            &self.path
+        } else {
+            &self.name
+        }
+    }
+}
+
+/// What a device declares about itself: EVIOCGNAME, EVIOCGPHYS, EVIOCGID
+/// and the EV_KEY bitmap from EVIOCGBIT
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceCapabilities {
+    /// Device name reported by the driver
+    pub name: String,
+    /// Physical topology, e.g. usb-0000:00:14.0-2/input0
+    #[serde(default)]
+    pub phys: String,
+    /// Kernel bus type (`BUS_*`)
+    pub bustype: u16,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub version: u16,
+    /// Key codes the device declares it can send
+    pub keys: BTreeSet<u16>,
+}
+
+impl DeviceCapabilities {
+    /// Key codes whose bits are set in an EVIOCGBIT(EV_KEY) bitmap
+    pub fn keys_from_bitmap(bitmap: &[u8]) -> BTreeSet<u16> {
+        bitmap
+            .iter()
+            .enumerate()
+            .flat_map(|(byte, bits)| {
+                (0..8)
+                    .filter(move |bit| bits & (1 << bit) != 0)
+                    .map(move |bit| (byte * 8 + bit) as u16)
+            })
+            .collect()
+    }
+
+    /// Name of the kernel bus type
+    pub fn bus_name(&self) -> &'static str {
+        match self.bustype {
+            0x01 => "PCI",
+            0x03 => "USB",
+            0x05 => "Bluetooth",
+            0x06 => "virtual",
+            0x11 => "i8042",
+            0x18 => "I2C",
+            0x19 => "host",
+            0x1C => "SPI",
+            _ => "other",
+        }
+    }
+
+    /// Declared keys as inclusive runs of consecutive codes
+    pub fn key_ranges(&self) -> Vec<(u16, u16)> {
+        let mut ranges: Vec<(u16, u16)> = Vec::new();
+        for &key in &self.keys {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == key => *end = key,
+                _ => ranges.push((key, key)),
+            }
+        }
+        ranges
+    }
+}
+
+/// Display names for key codes, with hex for codes the keymap doesn't know
+pub fn key_code_names(codes: impl IntoIterator<Item = u16>) -> String {
+    codes
+        .into_iter()
+        .map(|code| match keymap::get_key_info(KeyCode(code)).name {
+            "Unknown" => format!("0x{:X}", code),
+            name => name.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usb_id_is_zero_padded_hex() {
+        let device = DeviceInfo::new("/dev/input/event3", "Keyboard", 0x046d, 0xc31c);
+        assert_eq!(device.usb_id(), "046d:c31c");
+    }
+
+    #[test]
+    fn label_falls_back_to_path() {
+        let device = DeviceInfo::new("/dev/input/event3", "", 0, 0);
+        assert_eq!(device.label(), "/dev/input/event3");
+    }
+
+    #[test]
+    fn key_bitmap_decodes_to_codes_and_ranges() {
+        // KEY_ESC(1)..KEY_3(4), then KEY_A (30)
+        let bitmap = [0b0001_1110, 0, 0, 0b0100_0000];
+        let capabilities = DeviceCapabilities {
+            keys: DeviceCapabilities::keys_from_bitmap(&bitmap),
+            ..DeviceCapabilities::default()
+        };
+        assert_eq!(capabilities.keys.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3, 4, 30]);
+        assert_eq!(capabilities.key_ranges(), vec![(1, 4), (30, 30)]);
+        assert_eq!(key_code_names([30, 0x2F0]), "A 0x2F0");
+    }
+
+    #[test]
+    fn ids_default_when_missing() {
+        let device: DeviceInfo = serde_json::from_str(r#"{"path":"/dev/input/event1"}"#).unwrap();
+        assert_eq!(device.vendor_id, 0);
+        assert!(device.name.is_empty());
+    }
+}
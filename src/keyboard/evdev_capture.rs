@@ -0,0 +1,439 @@
+//! Dedicated evdev capture thread
+//!
+//! Polling [`EvdevListener`] from the UI loop ties reads to the render rate:
+//! between two frames an 8 kHz keyboard can fill the kernel's per-client
+//! buffer, and events are lost before they are ever read. [`EvdevCapture`]
+//! moves the listener onto its own thread, which blocks in `epoll_wait` on
+//! every device fd (and the hotplug watch) and reads as soon as the kernel
+//! has events. Key events reach the UI through a bounded queue; when the UI
+//! falls behind and the queue is full, new presses and repeats are dropped
+//! and counted instead of growing memory without limit. Releases of keys
+//! whose press got through are never dropped: they wait until there is
+//! room, so a full queue cannot leave a key held down forever. A key whose
+//! press was dropped has its release dropped too.
+//!
+//! The thread can optionally run with SCHED_FIFO priority so reads are not
+//! delayed behind ordinary processes (needs root or `CAP_SYS_NICE`).
+
+use super::evdev_listener::{EvdevError, EvdevListener};
+use super::{KeyEvent, KeyEventType};
+use std::collections::{HashSet, VecDeque};
+use std::io;
+use std::os::unix::io::RawFd;
+use std::os::unix::thread::JoinHandleExt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+
+/// Default size of the queue to the UI: two seconds of an 8 kHz keyboard
+pub const DEFAULT_QUEUE_CAPACITY: usize = 16_384;
+
+/// How long the thread waits in epoll before checking for shutdown
+const WAIT_TIMEOUT_MS: i32 = 100;
+
+/// Capture thread settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureOptions {
+    /// Key events the queue to the UI holds before new presses and repeats
+    /// are dropped
+    pub queue_capacity: usize,
+    /// SCHED_FIFO priority (1-99) for the capture thread; `None` keeps the
+    /// default scheduler
+    pub realtime_priority: Option<i32>,
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        Self {
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            realtime_priority: None,
+        }
+    }
+}
+
+/// An epoll instance watching a fixed set of fds for input
+struct Epoll {
+    fd: RawFd,
+}
+
+impl Epoll {
+    fn new(watched: &[RawFd]) -> io::Result<Self> {
+        // SAFETY: epoll_create1 takes no pointers; a negative result is an error.
+        let fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let epoll = Self { fd };
+        for &target in watched {
+            let mut event = libc::epoll_event {
+                events: libc::EPOLLIN as u32,
+                u64: target as u64,
+            };
+            // SAFETY: `event` is a live local the kernel only reads; both
+            // fds are valid (the target is owned by the listener).
+            if unsafe { libc::epoll_ctl(fd, libc::EPOLL_CTL_ADD, target, &mut event) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(epoll)
+    }
+
+    /// Block until a watched fd is readable or the timeout passes
+    fn wait(&self, timeout_ms: i32) {
+        let mut ready = [libc::epoll_event { events: 0, u64: 0 }; 16];
+        // SAFETY: the kernel writes at most `ready.len()` entries into the
+        // live local array. EINTR and timeouts just return early; the
+        // caller reads every device regardless.
+        unsafe {
+            libc::epoll_wait(self.fd, ready.as_mut_ptr(), ready.len() as i32, timeout_ms);
+        }
+    }
+}
+
+impl Drop for Epoll {
+    fn drop(&mut self) {
+        // SAFETY: closing the epoll fd we own.
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+/// State shared between the capture thread and its owner
+struct Shared {
+    listener: Mutex<EvdevListener>,
+    running: AtomicBool,
+    /// Key events dropped because the queue was full
+    dropped: AtomicU64,
+}
+
+/// An [`EvdevListener`] running on its own thread
+pub struct EvdevCapture {
+    shared: Arc<Shared>,
+    events: mpsc::Receiver<KeyEvent>,
+    thread: Option<JoinHandle<()>>,
+    /// Why SCHED_FIFO could not be set, when it was requested
+    priority_error: Option<io::Error>,
+}
+
+impl EvdevCapture {
+    /// Open a listener with `open` and start capturing from it.
+    ///
+    /// `open` receives the sender the listener should deliver to, so any
+    /// constructor works: `EvdevCapture::spawn(EvdevListener::new, options)`.
+    pub fn spawn<F>(open: F, options: CaptureOptions) -> Result<Self, EvdevError>
+    where
+        F: FnOnce(mpsc::Sender<KeyEvent>) -> Result<EvdevListener, EvdevError>,
+    {
+        // The listener sends into an unbounded staging channel that the
+        // thread drains into the bounded queue after every read
+        let (staging_tx, staging_rx) = mpsc::channel();
+        let listener = open(staging_tx)?;
+        let (queue_tx, events) = mpsc::sync_channel(options.queue_capacity.max(1));
+
+        let shared = Arc::new(Shared {
+            listener: Mutex::new(listener),
+            running: AtomicBool::new(true),
+            dropped: AtomicU64::new(0),
+        });
+        let thread = {
+            let shared = Arc::clone(&shared);
+            std::thread::Builder::new()
+                .name("evdev-capture".to_string())
+                .spawn(move || capture_loop(&shared, &staging_rx, &queue_tx))?
+        };
+
+        let priority_error = options
+            .realtime_priority
+            .and_then(|priority| set_fifo_priority(&thread, priority).err());
+
+        Ok(Self {
+            shared,
+            events,
+            thread: Some(thread),
+            priority_error,
+        })
+    }
+
+    /// Lock the listener for queries and device writes (LEDs, grabs, ...).
+    /// The capture thread only holds the lock while reading.
+    pub fn listener(&self) -> MutexGuard<'_, EvdevListener> {
+        self.shared
+            .listener
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Key events captured since the last call, oldest first
+    pub fn try_iter(&self) -> mpsc::TryIter<'_, KeyEvent> {
+        self.events.try_iter()
+    }
+
+    /// Key events dropped because the UI did not drain the queue in time
+    pub fn dropped_count(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Why the requested SCHED_FIFO priority could not be applied
+    pub fn priority_error(&self) -> Option<&io::Error> {
+        self.priority_error.as_ref()
+    }
+}
+
+impl Drop for EvdevCapture {
+    fn drop(&mut self) {
+        self.shared.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Give the capture thread real-time FIFO scheduling
+fn set_fifo_priority(thread: &JoinHandle<()>, priority: i32) -> io::Result<()> {
+    let param = libc::sched_param {
+        sched_priority: priority,
+    };
+    // SAFETY: the pthread handle is valid while `thread` is not joined, and
+    // `param` is a live local the call only reads.
+    let result =
+        unsafe { libc::pthread_setschedparam(thread.as_pthread_t(), libc::SCHED_FIFO, &param) };
+    if result != 0 {
+        return Err(io::Error::from_raw_os_error(result));
+    }
+    Ok(())
+}
+
+/// Wait for input, read it, and move the events to the bounded queue until
+/// the owner shuts the capture down
+fn capture_loop(
+    shared: &Shared,
+    staging: &mpsc::Receiver<KeyEvent>,
+    queue: &mpsc::SyncSender<KeyEvent>,
+) {
+    let lock = || {
+        shared
+            .listener
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    };
+    let mut epoll: Option<(u64, Epoll)> = None;
+    let mut forwarder = Forwarder::default();
+
+    while shared.running.load(Ordering::SeqCst) {
+        // Re-register after hotplug; the generation changes even when a
+        // new device reuses a closed one's fd number
+        let (generation, fds, enabled) = {
+            let listener = lock();
+            (listener.generation(), listener.poll_fds(), listener.is_enabled())
+        };
+        if epoll.as_ref().map(|(g, _)| *g) != Some(generation) {
+            epoll = Epoll::new(&fds).ok().map(|e| (generation, e));
+        }
+
+        match &epoll {
+            Some((_, epoll)) if enabled => epoll.wait(WAIT_TIMEOUT_MS),
+            // Nothing to wait on: check back after the timeout
+            _ => std::thread::sleep(std::time::Duration::from_millis(WAIT_TIMEOUT_MS as u64)),
+        }
+
+        lock().poll();
+
+        // The owner is gone once the queue disconnects
+        if forwarder.flush(queue).is_err() {
+            return;
+        }
+        for event in staging.try_iter() {
+            match forwarder.forward(event, queue) {
+                Ok(true) => {}
+                Ok(false) => {
+                    shared.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(Disconnected) => return,
+            }
+        }
+    }
+}
+
+/// The UI side of the queue is gone
+struct Disconnected;
+
+/// A key on one device
+type KeyId = (Option<String>, u16);
+
+/// Moves events into the bounded queue without splitting a press from its
+/// release
+#[derive(Default)]
+struct Forwarder {
+    /// Keys whose press was queued and whose release has not been
+    down: HashSet<KeyId>,
+    /// Keys whose press was dropped, so their repeats and release are too
+    dropped_down: HashSet<KeyId>,
+    /// Releases waiting for room in the queue, oldest first. At most one
+    /// per key in `down`, so this stays small.
+    backlog: VecDeque<KeyEvent>,
+}
+
+impl Forwarder {
+    /// Queue as many held-back releases as there is room for
+    fn flush(&mut self, queue: &mpsc::SyncSender<KeyEvent>) -> Result<(), Disconnected> {
+        while let Some(event) = self.backlog.pop_front() {
+            match queue.try_send(event) {
+                Ok(()) => {}
+                Err(mpsc::TrySendError::Full(event)) => {
+                    self.backlog.push_front(event);
+                    break;
+                }
+                Err(mpsc::TrySendError::Disconnected(_)) => return Err(Disconnected),
+            }
+        }
+        Ok(())
+    }
+
+    /// Queue one event; `Ok(false)` when it was dropped
+    fn forward(
+        &mut self,
+        event: KeyEvent,
+        queue: &mpsc::SyncSender<KeyEvent>,
+    ) -> Result<bool, Disconnected> {
+        let id = (event.device.as_ref().map(|d| d.path.clone()), event.key.as_u16());
+        match event.event_type {
+            KeyEventType::Release if self.dropped_down.remove(&id) => Ok(false),
+            KeyEventType::Release if self.down.remove(&id) => {
+                self.backlog.push_back(event);
+                self.flush(queue)?;
+                Ok(true)
+            }
+            KeyEventType::Repeat if self.dropped_down.contains(&id) => Ok(false),
+            event_type => {
+                // Nothing may overtake a held-back release
+                let sent = self.backlog.is_empty() && self.try_send(event, queue)?;
+                if event_type == KeyEventType::Press {
+                    if sent {
+                        self.down.insert(id);
+                    } else {
+                        self.dropped_down.insert(id);
+                    }
+                }
+                Ok(sent)
+            }
+        }
+    }
+
+    fn try_send(
+        &self,
+        event: KeyEvent,
+        queue: &mpsc::SyncSender<KeyEvent>,
+    ) -> Result<bool, Disconnected> {
+        match queue.try_send(event) {
+            Ok(()) => Ok(true),
+            Err(mpsc::TrySendError::Full(_)) => Ok(false),
+            Err(mpsc::TrySendError::Disconnected(_)) => Err(Disconnected),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    /// A file of raw `input_event` records standing in for a device node;
+    /// epoll rejects regular files, so the thread falls back to timed reads
+    fn event_file(name: &str, presses: &[u16]) -> PathBuf {
+        let keys: Vec<(u16, i32)> = presses.iter().map(|&code| (code, 1)).collect();
+        key_event_file(name, &keys)
+    }
+
+    /// Like [`event_file`], with a value (1 press, 0 release) for each key
+    fn key_event_file(name: &str, keys: &[(u16, i32)]) -> PathBuf {
+        let mut bytes = Vec::new();
+        for (i, &(code, value)) in keys.iter().enumerate() {
+            for (event_type, code, value) in [(1u16, code, value), (0, 0, 0)] {
+                bytes.extend_from_slice(&1i64.to_ne_bytes());
+                bytes.extend_from_slice(&(i as i64 * 1000).to_ne_bytes());
+                bytes.extend_from_slice(&event_type.to_ne_bytes());
+                bytes.extend_from_slice(&code.to_ne_bytes());
+                bytes.extend_from_slice(&value.to_ne_bytes());
+            }
+        }
+        let path = std::env::temp_dir().join(format!(
+            "keyboard-testkit-capture-{}-{}",
+            std::process::id(),
+            name
+        ));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn wait_for(mut condition: impl FnMut() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !condition() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn events_reach_the_queue_in_order() {
+        let path = event_file("order", &[30, 48, 46]);
+        let capture =
+            EvdevCapture::spawn(|tx| EvdevListener::for_device(&path, tx), CaptureOptions::default())
+                .unwrap();
+
+        let mut codes = Vec::new();
+        wait_for(|| {
+            codes.extend(capture.try_iter().map(|e| e.key.as_u16()));
+            codes.len() >= 3
+        });
+        assert_eq!(codes, vec![30, 48, 46]);
+        assert_eq!(capture.dropped_count(), 0);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn full_queue_drops_and_counts() {
+        let path = event_file("overflow", &[16, 17, 18, 19, 20]);
+        let options = CaptureOptions {
+            queue_capacity: 2,
+            ..CaptureOptions::default()
+        };
+        let capture = EvdevCapture::spawn(|tx| EvdevListener::for_device(&path, tx), options).unwrap();
+
+        wait_for(|| capture.dropped_count() == 3);
+        assert_eq!(capture.dropped_count(), 3);
+        // The oldest events were kept
+        let codes: Vec<u16> = capture.try_iter().map(|e| e.key.as_u16()).collect();
+        assert_eq!(codes, vec![16, 17]);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn full_queue_keeps_releases_of_queued_presses() {
+        let path = key_event_file(
+            "releases",
+            &[(30, 1), (48, 1), (30, 0), (46, 1), (48, 0), (46, 0)],
+        );
+        let options = CaptureOptions {
+            queue_capacity: 2,
+            ..CaptureOptions::default()
+        };
+        let capture = EvdevCapture::spawn(|tx| EvdevListener::for_device(&path, tx), options).unwrap();
+
+        // C's press finds the queue full, so its release goes with it; A's
+        // and B's releases wait for room
+        wait_for(|| capture.dropped_count() == 2);
+        let mut events = Vec::new();
+        wait_for(|| {
+            events.extend(
+                capture
+                    .try_iter()
+                    .map(|e| (e.key.as_u16(), e.event_type == KeyEventType::Press)),
+            );
+            events.len() >= 4
+        });
+        assert_eq!(events, vec![(30, true), (48, true), (30, false), (48, false)]);
+        assert_eq!(capture.dropped_count(), 2);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -2,16 +2,33 @@
 //!
 //! This module provides raw scancode detection via evdev, which can detect
 //! OEM keys and other special keys that device_query cannot handle.
+//!
+//! Events carry the kernel's CLOCK_MONOTONIC timestamp, so timing tests
+//! measure the keyboard rather than the UI poll loop.
+//!
+//! `/dev/input` is watched with inotify, so keyboards plugged in after
+//! startup are opened and unplugged ones are closed. Each change is queued
+//! as a [`DeviceChange`] for the UI to report.
+//!
+//! When a reader falls behind, the kernel discards the device's queued
+//! events and reports `SYN_DROPPED`. The listener then skips the rest of the
+//! broken frame, reads the real key state with EVIOCGKEY, and sends
+//! corrective presses/releases so held-key tracking does not drift into
+//! phantom stuck keys. Each drop is counted ([`EvdevListener::sync_drops`]).
+//! The same resync runs when a device is opened, so keys already held when
+//! the tool starts (or a keyboard is plugged in) are reported as pressed.
 
-use super::{KeyCode, KeyEvent, KeyEventType};
+use super::{
+    DeviceCapabilities, DeviceInfo, KeyCode, KeyEvent, KeyEventType, Led, RepeatSettings,
+};
 use libc;
 use std::collections::HashSet;
 use std::fs::{self, File};
 use std::io::{self, Read};
-use std::os::unix::io::AsRawFd;
-use std::path::PathBuf;
-use std::sync::mpsc;
-use std::time::Instant;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
 
 /// Error type for evdev operations
 #[derive(Debug)]
@@ -62,12 +79,66 @@ struct InputEvent {
     value: i32,
 }
 
+impl InputEvent {
+    /// Kernel timestamp of this event in microseconds
+    fn timestamp_us(&self) -> u64 {
+        (self.tv_sec as u64)
+            .saturating_mul(1_000_000)
+            .saturating_add(self.tv_usec as u64)
+    }
+}
+
+const EV_SYN: u16 = 0x00;
 const EV_KEY: u16 = 0x01;
+const EV_MSC: u16 = 0x04;
+const EV_LED: u16 = 0x11;
+const MSC_SCAN: u16 = 0x04;
+const SYN_REPORT: u16 = 0x00;
+const SYN_DROPPED: u16 = 0x03;
 const INPUT_EVENT_SIZE: usize = std::mem::size_of::<InputEvent>();
 
+const INPUT_DIR: &str = "/dev/input";
+// Fixed part of struct inotify_event: wd, mask, cookie, len
+const INOTIFY_HEADER_SIZE: usize = 16;
+
+// EVIOCSCLOCKID ioctl to select the clock used for event timestamps
+const EVIOCSCLOCKID: libc::c_ulong = 0x400445a0;
+// EVIOCGREP ioctl to read the auto-repeat delay and period (unsigned int[2])
+const EVIOCGREP: libc::c_ulong = 0x80084503;
+// EVIOCGBIT(EV_LED, 8): which LEDs the device has
+const EVIOCGBIT_LED: libc::c_ulong = 0x80084531;
+// EVIOCGLED(8): which LEDs are currently lit
+const EVIOCGLED: libc::c_ulong = 0x80084519;
+/// EVIOCGRAB: exclusive access to a device
+const EVIOCGRAB: libc::c_ulong = 0x40044590;
+/// EVIOCGID: struct input_id (bustype, vendor, product, version)
+const EVIOCGID: libc::c_ulong = 0x80084502;
+/// EVIOCGNAME with a 256-byte buffer
+const EVIOCGNAME: libc::c_ulong = 0x81004506;
+/// EVIOCGPHYS with a 256-byte buffer
+const EVIOCGPHYS: libc::c_ulong = 0x81004507;
+/// EVIOCGBIT(EV_KEY) with a KEY_MAX-sized (96-byte) buffer
+const EVIOCGBIT_KEY: libc::c_ulong = 0x80604521;
+/// EVIOCGKEY with a KEY_MAX-sized (96-byte) buffer: keys currently down
+const EVIOCGKEY: libc::c_ulong = 0x80604518;
+
+/// Current CLOCK_MONOTONIC time in microseconds
+fn monotonic_now_us() -> u64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: clock_gettime writes into the timespec we own; CLOCK_MONOTONIC
+    // is always available on Linux.
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts);
+    }
+    (ts.tv_sec as u64) * 1_000_000 + (ts.tv_nsec as u64) / 1_000
+}
+
 /// Find all keyboard input devices
-fn find_keyboard_devices() -> Result<Vec<PathBuf>, EvdevError> {
-    let input_dir = PathBuf::from("/dev/input");
+pub(crate) fn find_keyboard_devices() -> Result<Vec<PathBuf>, EvdevError> {
+    let input_dir = PathBuf::from(INPUT_DIR);
     if !input_dir.exists() {
         return Err(EvdevError::EnumerationFailed(
             "/dev/input does not exist".to_string(),
@@ -99,8 +170,90 @@ fn find_keyboard_devices() -> Result<Vec<PathBuf>, EvdevError> {
     Ok(keyboards)
 }
 
+/// Read what a device declares about itself through evdev ioctls
+pub fn read_capabilities(path: &Path) -> io::Result<DeviceCapabilities> {
+    capabilities_of(&File::open(path)?)
+}
+
+/// Capabilities of every detected keyboard, or why each could not be read
+pub fn keyboard_capabilities() -> Result<Vec<(PathBuf, io::Result<DeviceCapabilities>)>, EvdevError>
+{
+    Ok(find_keyboard_devices()?
+        .into_iter()
+        .map(|path| {
+            let capabilities = read_capabilities(&path);
+            (path, capabilities)
+        })
+        .collect())
+}
+
+fn capabilities_of(file: &File) -> io::Result<DeviceCapabilities> {
+    let fd = file.as_raw_fd();
+
+    let mut id = [0u16; 4];
+    // SAFETY: EVIOCGID writes one struct input_id (four u16s) into `id`.
+    if unsafe { libc::ioctl(fd, EVIOCGID, id.as_mut_ptr()) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut keys = [0u8; 96];
+    // SAFETY: the request encodes the 96-byte buffer size, so the kernel
+    // writes at most that much into `keys`.
+    if unsafe { libc::ioctl(fd, EVIOCGBIT_KEY, keys.as_mut_ptr()) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let string = |request: libc::c_ulong| {
+        let mut buffer = [0u8; 256];
+        // SAFETY: the request encodes the 256-byte buffer size. A device
+        // without the string fails the ioctl, leaving it empty.
+        let len = unsafe { libc::ioctl(fd, request, buffer.as_mut_ptr()) };
+        if len <= 0 {
+            return String::new();
+        }
+        let end = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+        String::from_utf8_lossy(&buffer[..end]).trim().to_string()
+    };
+
+    Ok(DeviceCapabilities {
+        name: string(EVIOCGNAME),
+        phys: string(EVIOCGPHYS),
+        bustype: id[0],
+        vendor_id: id[1],
+        product_id: id[2],
+        version: id[3],
+        keys: DeviceCapabilities::keys_from_bitmap(&keys),
+    })
+}
+
+/// Keys the kernel currently reports as held on an evdev device (EVIOCGKEY)
+pub fn read_key_state(file: &File) -> io::Result<HashSet<u16>> {
+    let mut bitmap = [0u8; 96];
+    // SAFETY: the request encodes the 96-byte buffer size, so the kernel
+    // writes at most that much into `bitmap`.
+    if unsafe { libc::ioctl(file.as_raw_fd(), EVIOCGKEY, bitmap.as_mut_ptr()) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(DeviceCapabilities::keys_from_bitmap(&bitmap).into_iter().collect())
+}
+
+/// Events that bring `tracked` in line with the kernel's `actual` key
+/// state, as (code, pressed): releases first, then presses, each in code
+/// order
+pub fn key_state_corrections(tracked: &HashSet<u16>, actual: &HashSet<u16>) -> Vec<(u16, bool)> {
+    let mut released: Vec<u16> = tracked.difference(actual).copied().collect();
+    let mut pressed: Vec<u16> = actual.difference(tracked).copied().collect();
+    released.sort_unstable();
+    pressed.sort_unstable();
+    released
+        .into_iter()
+        .map(|code| (code, false))
+        .chain(pressed.into_iter().map(|code| (code, true)))
+        .collect()
+}
+
 /// Check if a device is a keyboard by examining /sys/class/input
-fn is_keyboard_device(device_path: &std::path::Path) -> bool {
+fn is_keyboard_device(device_path: &Path) -> bool {
     let device_name = device_path.file_name().and_then(|n| n.to_str());
     if let Some(name) = device_name {
         // Try to read device capabilities from sysfs
@@ -134,12 +287,206 @@ fn is_keyboard_device(device_path: &std::path::Path) -> bool {
     false
 }
 
+/// A keyboard appearing or disappearing while the listener runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceChange {
+    /// A keyboard was plugged in and opened
+    Added(DeviceInfo),
+    /// A keyboard was unplugged
+    Removed(DeviceInfo),
+    /// Reading a keyboard failed and it was closed
+    Failed(DeviceInfo, String),
+}
+
+/// An opened evdev node and its per-device key state
+struct OpenDevice {
+    file: File,
+    info: Arc<DeviceInfo>,
+    /// Keys held on this device (scancodes)
+    pressed: HashSet<u16>,
+    /// MSC_SCAN seen in the current frame, for the EV_KEY that follows it
+    pending_scan: Option<u32>,
+    /// After SYN_DROPPED: skipping events until the next SYN_REPORT
+    dropping: bool,
+    /// Key state not yet read from the kernel since the device was opened
+    needs_sync: bool,
+    /// Event timestamps are CLOCK_MONOTONIC. When EVIOCSCLOCKID fails they
+    /// are REALTIME and cannot be compared with `Instant`, so events are
+    /// stamped with the read time instead.
+    monotonic_clock: bool,
+}
+
+impl OpenDevice {
+    /// Open an evdev node non-blocking with CLOCK_MONOTONIC timestamps
+    fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let fd = file.as_raw_fd();
+        // SAFETY: fcntl F_GETFL/F_SETFL are safe operations on valid file descriptors.
+        // The fd is valid because it was obtained from a successfully opened File.
+        // O_NONBLOCK flag modification does not affect memory safety.
+        unsafe {
+            let flags = libc::fcntl(fd, libc::F_GETFL);
+            libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+        }
+        // Ask for CLOCK_MONOTONIC event timestamps so they can be
+        // compared against Instant (the kernel default is REALTIME).
+        // SAFETY: EVIOCSCLOCKID reads a single c_int from the pointer,
+        // which points to a live local. Failure leaves the default clock.
+        let monotonic_clock = unsafe {
+            let clock_id: libc::c_int = libc::CLOCK_MONOTONIC;
+            libc::ioctl(fd, EVIOCSCLOCKID, &clock_id as *const libc::c_int) == 0
+        };
+        Ok(Self {
+            file,
+            info: Arc::new(DeviceInfo::from_sysfs(path)),
+            pressed: HashSet::new(),
+            pending_scan: None,
+            dropping: false,
+            needs_sync: true,
+            monotonic_clock,
+        })
+    }
+
+    /// End a SYN_DROPPED gap, or start tracking a newly opened device:
+    /// adopt the kernel's key state and return the presses/releases that
+    /// were missed, timestamped at the resync
+    fn resync(&mut self, now: Instant, hw_us: u64) -> Vec<KeyEvent> {
+        self.dropping = false;
+        self.needs_sync = false;
+        let Ok(actual) = read_key_state(&self.file) else {
+            return Vec::new();
+        };
+        let events = key_state_corrections(&self.pressed, &actual)
+            .into_iter()
+            .map(|(scancode, pressed)| {
+                let event_type = if pressed {
+                    KeyEventType::Press
+                } else {
+                    KeyEventType::Release
+                };
+                KeyEvent::new(KeyCode::new(scancode), event_type, now, 0)
+                    .with_hw_timestamp(hw_us)
+                    .with_device(Arc::clone(&self.info))
+            })
+            .collect();
+        self.pressed = actual;
+        events
+    }
+}
+
+/// Read an 8-byte LED bitmask (EVIOCGBIT_LED or EVIOCGLED) from a device
+fn led_bitmask(file: &File, request: libc::c_ulong) -> io::Result<u64> {
+    let mut mask = [0u8; 8];
+    // SAFETY: both requests write at most 8 bytes into `mask`, a live local
+    // array of exactly that size.
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), request, mask.as_mut_ptr()) };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(u64::from_ne_bytes(mask))
+}
+
+/// Non-blocking inotify watch on /dev/input
+struct InputWatch {
+    file: File,
+}
+
+impl InputWatch {
+    fn new() -> io::Result<Self> {
+        // SAFETY: inotify_init1 takes only flags and returns a new fd or -1.
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: fd is a freshly created inotify descriptor owned by nobody else,
+        // so the File takes sole ownership and closes it on drop.
+        let file = unsafe { File::from_raw_fd(fd) };
+
+        let dir = std::ffi::CString::new(INPUT_DIR).expect("path has no NUL");
+        // IN_ATTRIB catches udev fixing up permissions after the node is created
+        let mask = libc::IN_CREATE | libc::IN_DELETE | libc::IN_ATTRIB;
+        // SAFETY: dir is a valid NUL-terminated string that outlives the call.
+        if unsafe { libc::inotify_add_watch(fd, dir.as_ptr(), mask) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { file })
+    }
+
+    /// Drain pending notifications as (mask, file name) pairs
+    fn read_events(&mut self) -> Vec<(u32, String)> {
+        let mut events = Vec::new();
+        let mut buffer = [0u8; 4096];
+        loop {
+            match self.file.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => events.extend(parse_inotify_events(&buffer[..n])),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            }
+        }
+        events
+    }
+}
+
+/// Split a buffer of raw `struct inotify_event` records into (mask, name)
+fn parse_inotify_events(buffer: &[u8]) -> Vec<(u32, String)> {
+    let mut events = Vec::new();
+    let mut offset = 0;
+    while offset + INOTIFY_HEADER_SIZE <= buffer.len() {
+        let field = |at: usize| {
+            u32::from_ne_bytes(buffer[offset + at..offset + at + 4].try_into().unwrap())
+        };
+        let mask = field(4);
+        let len = field(12) as usize;
+        let name_start = offset + INOTIFY_HEADER_SIZE;
+        let name_end = (name_start + len).min(buffer.len());
+        // The name is NUL-padded to an aligned length
+        let name = buffer[name_start..name_end]
+            .split(|&b| b == 0)
+            .next()
+            .unwrap_or_default();
+        events.push((mask, String::from_utf8_lossy(name).into_owned()));
+        offset = name_start + len;
+    }
+    events
+}
+
+/// How a failed device read should be handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReadFailure {
+    /// Nothing more to read right now
+    Drained,
+    /// Interrupted; read again
+    Retry,
+    /// The device is gone (ENODEV)
+    Unplugged,
+    /// Any other error; the device is closed
+    Fatal,
+}
+
+fn classify_read_error(e: &io::Error) -> ReadFailure {
+    match e.kind() {
+        io::ErrorKind::WouldBlock => ReadFailure::Drained,
+        io::ErrorKind::Interrupted => ReadFailure::Retry,
+        _ if e.raw_os_error() == Some(libc::ENODEV) => ReadFailure::Unplugged,
+        _ => ReadFailure::Fatal,
+    }
+}
+
 /// Evdev-based keyboard listener for raw scancode detection
 pub struct EvdevListener {
-    devices: Vec<File>,
+    devices: Vec<OpenDevice>,
     device_paths: Vec<PathBuf>,
-    pressed_keys: HashSet<u16>,
+    watch: Option<InputWatch>,
+    /// Hotplug changes not yet collected by `take_changes`
+    changes: Vec<DeviceChange>,
+    /// Bumped whenever a device is opened or closed
+    generation: u64,
+    /// SYN_DROPPED reports received (kernel buffer overruns)
+    sync_drops: u64,
     last_poll: Instant,
+    /// Kernel timestamp of the last key event sent, for per-event deltas
+    last_hw_us: Option<u64>,
     event_tx: mpsc::Sender<KeyEvent>,
     buffer: Vec<u8>,
     enabled: bool,
@@ -152,20 +499,8 @@ impl EvdevListener {
         let mut devices = Vec::new();
 
         for path in &device_paths {
-            match File::open(path) {
-                Ok(file) => {
-                    // Opened evdev device
-                    // Set non-blocking mode
-                    let fd = file.as_raw_fd();
-                    // SAFETY: fcntl F_GETFL/F_SETFL are safe operations on valid file descriptors.
-                    // The fd is valid because it was obtained from a successfully opened File.
-                    // O_NONBLOCK flag modification does not affect memory safety.
-                    unsafe {
-                        let flags = libc::fcntl(fd, libc::F_GETFL);
-                        libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
-                    }
-                    devices.push(file);
-                }
+            match OpenDevice::open(path) {
+                Ok(device) => devices.push(device),
                 Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
                     // Permission denied, skipping device
                     continue;
@@ -180,15 +515,39 @@ impl EvdevListener {
             ));
         }
 
-        Ok(Self {
+        // Without inotify the listener still works, just without hotplug
+        Ok(Self::from_devices(devices, InputWatch::new().ok(), event_tx))
+    }
+
+    /// Listen to a single device only, without hotplug
+    pub fn for_device(path: &Path, event_tx: mpsc::Sender<KeyEvent>) -> Result<Self, EvdevError> {
+        let device = OpenDevice::open(path)?;
+        Ok(Self::from_devices(vec![device], None, event_tx))
+    }
+
+    fn from_devices(
+        devices: Vec<OpenDevice>,
+        watch: Option<InputWatch>,
+        event_tx: mpsc::Sender<KeyEvent>,
+    ) -> Self {
+        let device_paths = devices
+            .iter()
+            .map(|d| PathBuf::from(&d.info.path))
+            .collect();
+
+        Self {
             devices,
             device_paths,
-            pressed_keys: HashSet::new(),
+            watch,
+            changes: Vec::new(),
+            generation: 0,
+            sync_drops: 0,
             last_poll: Instant::now(),
+            last_hw_us: None,
             event_tx,
             buffer: vec![0u8; INPUT_EVENT_SIZE * 64], // Buffer for multiple events
             enabled: true,
-        })
+        }
     }
 
     /// Try to create an evdev listener, return None if not available
@@ -216,9 +575,157 @@ impl EvdevListener {
         &self.device_paths
     }
 
-    /// Get currently pressed keys (scancodes)
-    pub fn pressed_keys(&self) -> &HashSet<u16> {
-        &self.pressed_keys
+    /// Identity of each open device, in the same order as `device_paths`
+    pub fn devices(&self) -> Vec<DeviceInfo> {
+        self.devices.iter().map(|d| (*d.info).clone()).collect()
+    }
+
+    /// Declared capabilities of each open device that could be read
+    pub fn capabilities(&self) -> Vec<(DeviceInfo, DeviceCapabilities)> {
+        self.devices
+            .iter()
+            .filter_map(|d| Some(((*d.info).clone(), capabilities_of(&d.file).ok()?)))
+            .collect()
+    }
+
+    /// Get currently pressed keys (scancodes) across all devices
+    pub fn pressed_keys(&self) -> HashSet<u16> {
+        self.devices
+            .iter()
+            .flat_map(|d| d.pressed.iter().copied())
+            .collect()
+    }
+
+    /// Auto-repeat settings of the device at `path`, or of the first open
+    /// device that reports them when `path` is None or not open
+    pub fn repeat_settings(&self, path: Option<&str>) -> Option<RepeatSettings> {
+        let query = |device: &OpenDevice| {
+            let mut rep = [0 as libc::c_uint; 2];
+            // SAFETY: EVIOCGREP writes two unsigned ints into `rep`, which is
+            // a live local array of exactly that size.
+            let result = unsafe { libc::ioctl(device.file.as_raw_fd(), EVIOCGREP, rep.as_mut_ptr()) };
+            (result >= 0).then(|| RepeatSettings {
+                delay_ms: rep[0],
+                period_ms: rep[1],
+            })
+        };
+
+        let selected = path.and_then(|p| self.devices.iter().find(|d| d.info.path == p));
+        match selected {
+            Some(device) => query(device),
+            None => self.devices.iter().find_map(query),
+        }
+    }
+
+    /// The device at `path`, or the first open device with LEDs when
+    /// `path` is None or not open, together with the LEDs it has
+    pub fn led_device(&self, path: Option<&str>) -> Option<(DeviceInfo, Vec<Led>)> {
+        let query = |device: &OpenDevice| {
+            let leds = led_bitmask(&device.file, EVIOCGBIT_LED)
+                .map(Led::from_mask)
+                .unwrap_or_default();
+            (!leds.is_empty()).then(|| ((*device.info).clone(), leds))
+        };
+
+        let selected = path.and_then(|p| self.devices.iter().find(|d| d.info.path == p));
+        match selected {
+            Some(device) => query(device),
+            None => self.devices.iter().find_map(query),
+        }
+    }
+
+    /// LEDs the kernel currently has lit on the device at `path`
+    pub fn led_state(&self, path: &str) -> io::Result<Vec<Led>> {
+        let device = self
+            .devices
+            .iter()
+            .find(|d| d.info.path == path)
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::ENODEV))?;
+        led_bitmask(&device.file, EVIOCGLED).map(Led::from_mask)
+    }
+
+    /// Switch an LED on the device at `path` by writing an EV_LED event
+    pub fn set_led(&self, path: &str, led: Led, on: bool) -> io::Result<()> {
+        use std::io::Write;
+
+        // The listener's own handles are read-only
+        let mut file = fs::OpenOptions::new().write(true).open(path)?;
+        let mut bytes = Vec::with_capacity(INPUT_EVENT_SIZE * 2);
+        for (event_type, code, value) in [(EV_LED, led.code(), on as i32), (EV_SYN, 0, 0)] {
+            let event = InputEvent {
+                tv_sec: 0,
+                tv_usec: 0,
+                event_type,
+                code,
+                value,
+            };
+            // SAFETY: InputEvent is #[repr(C)] plain data of INPUT_EVENT_SIZE
+            // bytes; the slice does not outlive `event`.
+            bytes.extend_from_slice(unsafe {
+                std::slice::from_raw_parts(&event as *const InputEvent as *const u8, INPUT_EVENT_SIZE)
+            });
+        }
+        file.write_all(&bytes)
+    }
+
+    /// Take or release exclusive access to every open device (EVIOCGRAB),
+    /// so its events reach only this listener
+    pub fn set_grab(&self, grab: bool) -> io::Result<()> {
+        for device in &self.devices {
+            // SAFETY: EVIOCGRAB takes an int argument by value; the fd is
+            // valid for as long as `device` is open.
+            let result = unsafe {
+                libc::ioctl(device.file.as_raw_fd(), EVIOCGRAB, grab as libc::c_int)
+            };
+            if result < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// File descriptors `poll` reads: every open device plus the hotplug
+    /// watch, for a capture thread to wait on
+    pub(crate) fn poll_fds(&self) -> Vec<RawFd> {
+        self.devices
+            .iter()
+            .map(|d| d.file.as_raw_fd())
+            .chain(self.watch.as_ref().map(|w| w.file.as_raw_fd()))
+            .collect()
+    }
+
+    /// Changes whenever a device is opened or closed, so a set of fds from
+    /// `poll_fds` can be checked for staleness (fd numbers get reused)
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Keys the kernel reports held (EVIOCGKEY) across the open devices, or
+    /// only on `path` when given. `None` when no device could be queried.
+    pub fn kernel_key_state(&self, path: Option<&str>) -> Option<HashSet<u16>> {
+        let mut held = HashSet::new();
+        let mut queried = false;
+        for device in &self.devices {
+            if path.is_some_and(|p| p != device.info.path) {
+                continue;
+            }
+            if let Ok(keys) = read_key_state(&device.file) {
+                held.extend(keys);
+                queried = true;
+            }
+        }
+        queried.then_some(held)
+    }
+
+    /// Times the kernel dropped queued events (SYN_DROPPED) this session;
+    /// each was followed by an EVIOCGKEY resync
+    pub fn sync_drops(&self) -> u64 {
+        self.sync_drops
+    }
+
+    /// Hotplug changes since the last call, oldest first
+    pub fn take_changes(&mut self) -> Vec<DeviceChange> {
+        std::mem::take(&mut self.changes)
     }
 
     /// Poll for keyboard events
@@ -229,14 +736,30 @@ impl EvdevListener {
         }
 
         let now = Instant::now();
-        let delta_us = now.duration_since(self.last_poll).as_micros() as u64;
+        let now_mono_us = monotonic_now_us();
+        let poll_delta_us = now.duration_since(self.last_poll).as_micros() as u64;
         self.last_poll = now;
 
+        self.handle_hotplug();
+
         let mut event_count = 0;
+        let mut closed = Vec::new();
+
+        for (index, device) in self.devices.iter_mut().enumerate() {
+            // Keys already held when the device was opened
+            if device.needs_sync {
+                let hw_us = monotonic_now_us();
+                for event in device.resync(now, hw_us) {
+                    if self.event_tx.send(event).is_err() {
+                        self.enabled = false;
+                        return event_count;
+                    }
+                    event_count += 1;
+                }
+            }
 
-        for device in &mut self.devices {
             loop {
-                match device.read(&mut self.buffer) {
+                match device.file.read(&mut self.buffer) {
                     Ok(bytes_read) if bytes_read >= INPUT_EVENT_SIZE => {
                         // Process all complete events in the buffer
                         let num_events = bytes_read / INPUT_EVENT_SIZE;
@@ -253,38 +776,113 @@ impl EvdevListener {
                                 std::ptr::read(event_bytes.as_ptr() as *const InputEvent)
                             };
 
-                            // We only care about key events
+                            // The driver reports the raw scancode just before the
+                            // key event it belongs to, within the same frame
+                            match (input_event.event_type, input_event.code) {
+                                (EV_SYN, SYN_DROPPED) => {
+                                    device.dropping = true;
+                                    device.pending_scan = None;
+                                    self.sync_drops += 1;
+                                    continue;
+                                }
+                                // The rest of the broken frame is unreliable;
+                                // resync from the kernel state once it ends
+                                _ if device.dropping => {
+                                    if input_event.event_type == EV_SYN
+                                        && input_event.code == SYN_REPORT
+                                    {
+                                        let hw_us = monotonic_now_us();
+                                        for event in device.resync(now, hw_us) {
+                                            if self.event_tx.send(event).is_err() {
+                                                self.enabled = false;
+                                                return event_count;
+                                            }
+                                            self.last_hw_us = Some(hw_us);
+                                            event_count += 1;
+                                        }
+                                    }
+                                    continue;
+                                }
+                                (EV_MSC, MSC_SCAN) => {
+                                    device.pending_scan = Some(input_event.value as u32);
+                                    continue;
+                                }
+                                (EV_SYN, _) => {
+                                    device.pending_scan = None;
+                                    continue;
+                                }
+                                _ => {}
+                            }
+
+                            // Otherwise we only care about key events
                             if input_event.event_type == EV_KEY {
+                                let scan = device.pending_scan.take();
                                 let scancode = input_event.code;
                                 let pressed = input_event.value != 0; // 1 = press, 2 = repeat, 0 = release
+                                let repeat = input_event.value == 2;
 
-                                // Skip key repeats (value == 2)
-                                if input_event.value == 2 {
-                                    continue;
-                                }
-
-                                // Track key state
-                                if pressed {
-                                    if !self.pressed_keys.insert(scancode) {
+                                // Track key state per device, so the same key held
+                                // on two keyboards is reported for each
+                                if repeat {
+                                    if !device.pressed.contains(&scancode) {
+                                        // Repeat for a press we never saw
+                                        continue;
+                                    }
+                                } else if pressed {
+                                    if !device.pressed.insert(scancode) {
                                         // Key was already pressed, skip
                                         continue;
                                     }
-                                } else if !self.pressed_keys.remove(&scancode) {
+                                } else if !device.pressed.remove(&scancode) {
                                     // Key wasn't pressed, skip
                                     continue;
                                 }
 
+                                // Timestamp from the kernel rather than the poll loop:
+                                // place the Instant at the kernel's event time and take
+                                // the delta from the previous kernel timestamp. Without
+                                // a monotonic kernel clock, fall back to the read time.
+                                let (hw_us, timestamp) = if device.monotonic_clock {
+                                    let hw_us = input_event.timestamp_us();
+                                    let age =
+                                        Duration::from_micros(now_mono_us.saturating_sub(hw_us));
+                                    (hw_us, now.checked_sub(age).unwrap_or(now))
+                                } else {
+                                    (now_mono_us, now)
+                                };
+                                let delta_us = self
+                                    .last_hw_us
+                                    .map(|last| hw_us.saturating_sub(last))
+                                    .unwrap_or(poll_delta_us);
+                                // Repeats don't move the baseline, so press/release
+                                // deltas stay comparable with repeat off
+                                if !repeat {
+                                    self.last_hw_us = Some(hw_us);
+                                }
+
                                 // Create and send the event
                                 let event = KeyEvent::new(
                                     KeyCode::new(scancode),
-                                    if pressed {
+                                    if repeat {
+                                        KeyEventType::Repeat
+                                    } else if pressed {
                                         KeyEventType::Press
                                     } else {
                                         KeyEventType::Release
                                     },
-                                    now,
+                                    timestamp,
                                     delta_us,
-                                );
+                                )
+                                .with_device(Arc::clone(&device.info));
+                                let event = if device.monotonic_clock {
+                                    event.with_hw_timestamp(hw_us)
+                                } else {
+                                    event
+                                };
+                                let event = match scan {
+                                    Some(scan) => event.with_scancode(scan),
+                                    None => event,
+                                };
                                 if self.event_tx.send(event).is_err() {
                                     eprintln!("[WARN]  Event channel disconnected, disabling evdev listener");
                                     self.enabled = false;
@@ -295,21 +893,103 @@ impl EvdevListener {
                         }
                     }
                     Ok(_) => break, // Not enough bytes for a complete event
-                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
-                    // FIXME: Other read errors are silently swallowed. Should distinguish
-                    // between transient errors (retry) and fatal ones (disable device).
-                    Err(_) => break,
+                    Err(e) => match classify_read_error(&e) {
+                        ReadFailure::Drained => break,
+                        ReadFailure::Retry => continue,
+                        ReadFailure::Unplugged => {
+                            closed.push((index, DeviceChange::Removed((*device.info).clone())));
+                            break;
+                        }
+                        ReadFailure::Fatal => {
+                            let info = (*device.info).clone();
+                            closed.push((index, DeviceChange::Failed(info, e.to_string())));
+                            break;
+                        }
+                    },
                 }
             }
         }
 
+        // Remove from the back so earlier indices stay valid
+        for (index, change) in closed.into_iter().rev() {
+            event_count += self.close_device(index, change);
+        }
+
         event_count
     }
 
+    /// Open keyboards that appeared in /dev/input and close ones that vanished
+    fn handle_hotplug(&mut self) {
+        let Some(watch) = self.watch.as_mut() else {
+            return;
+        };
+
+        for (mask, name) in watch.read_events() {
+            if !name.starts_with("event") {
+                continue;
+            }
+            let path = Path::new(INPUT_DIR).join(&name);
+            let open_index = self.device_paths.iter().position(|p| *p == path);
+
+            if mask & libc::IN_DELETE != 0 {
+                // Usually already closed by ENODEV on read
+                if let Some(index) = open_index {
+                    let info = (*self.devices[index].info).clone();
+                    self.close_device(index, DeviceChange::Removed(info));
+                }
+            } else if open_index.is_none() && is_keyboard_device(&path) {
+                // A node created before udev grants access fails with EACCES;
+                // the IN_ATTRIB that follows the permission change retries it.
+                if let Ok(device) = OpenDevice::open(&path) {
+                    self.generation += 1;
+                    self.changes.push(DeviceChange::Added((*device.info).clone()));
+                    self.device_paths.push(path);
+                    self.devices.push(device);
+                }
+            }
+        }
+    }
+
+    /// Drop a device, releasing any keys it still held so tests do not
+    /// see them as stuck. Returns the number of release events sent.
+    fn close_device(&mut self, index: usize, change: DeviceChange) -> usize {
+        let device = self.devices.remove(index);
+        self.device_paths.remove(index);
+        self.changes.push(change);
+        self.generation += 1;
+
+        let now = Instant::now();
+        let hw_us = monotonic_now_us();
+        let mut sent = 0;
+        let mut held: Vec<u16> = device.pressed.into_iter().collect();
+        held.sort_unstable();
+        for scancode in held {
+            let delta_us = self
+                .last_hw_us
+                .map(|last| hw_us.saturating_sub(last))
+                .unwrap_or(0);
+            self.last_hw_us = Some(hw_us);
+            let event = KeyEvent::new(KeyCode::new(scancode), KeyEventType::Release, now, delta_us)
+                .with_hw_timestamp(hw_us)
+                .with_device(Arc::clone(&device.info));
+            if self.event_tx.send(event).is_err() {
+                self.enabled = false;
+                break;
+            }
+            sent += 1;
+        }
+        sent
+    }
+
     /// Reset the listener state
     pub fn reset(&mut self) {
-        self.pressed_keys.clear();
+        for device in &mut self.devices {
+            device.pressed.clear();
+            // Report keys still held as fresh presses on the next poll
+            device.needs_sync = true;
+        }
         self.last_poll = Instant::now();
+        self.last_hw_us = None;
     }
 }
 
@@ -345,6 +1025,140 @@ mod tests {
         }
     }
 
+    #[test]
+    fn input_event_timestamp_us() {
+        let event = InputEvent {
+            tv_sec: 12,
+            tv_usec: 345_678,
+            event_type: EV_KEY,
+            code: 30,
+            value: 1,
+        };
+        assert_eq!(event.timestamp_us(), 12_345_678);
+    }
+
+    fn inotify_record(mask: u32, name: &str, padded_len: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&1i32.to_ne_bytes()); // wd
+        bytes.extend_from_slice(&mask.to_ne_bytes());
+        bytes.extend_from_slice(&0u32.to_ne_bytes()); // cookie
+        bytes.extend_from_slice(&padded_len.to_ne_bytes());
+        let mut name_bytes = name.as_bytes().to_vec();
+        name_bytes.resize(padded_len as usize, 0);
+        bytes.extend_from_slice(&name_bytes);
+        bytes
+    }
+
+    #[test]
+    fn parses_consecutive_inotify_records() {
+        let mut buffer = inotify_record(libc::IN_CREATE, "event7", 16);
+        buffer.extend(inotify_record(libc::IN_DELETE, "event12", 16));
+
+        let events = parse_inotify_events(&buffer);
+        assert_eq!(
+            events,
+            vec![
+                (libc::IN_CREATE, "event7".to_string()),
+                (libc::IN_DELETE, "event12".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_inotify_record_is_ignored() {
+        let buffer = inotify_record(libc::IN_CREATE, "event7", 16);
+        assert!(parse_inotify_events(&buffer[..10]).is_empty());
+    }
+
+    #[test]
+    fn corrections_release_missing_keys_and_press_new_ones() {
+        let tracked: HashSet<u16> = [30, 31, 42].into_iter().collect();
+        let actual: HashSet<u16> = [42, 48, 46].into_iter().collect();
+        assert_eq!(
+            key_state_corrections(&tracked, &actual),
+            vec![(30, false), (31, false), (46, true), (48, true)]
+        );
+        assert!(key_state_corrections(&actual, &actual).is_empty());
+    }
+
+    #[test]
+    fn syn_dropped_skips_the_broken_frame_and_is_counted() {
+        // A regular file stands in for the device; EVIOCGKEY fails on it,
+        // so no corrections are sent and only the skipping is observable
+        let records: [(u16, u16, i32); 8] = [
+            (EV_KEY, 30, 1),
+            (EV_SYN, SYN_REPORT, 0),
+            (EV_SYN, SYN_DROPPED, 0),
+            (EV_KEY, 48, 1),
+            (EV_SYN, SYN_REPORT, 0),
+            (EV_KEY, 46, 1),
+            (EV_SYN, SYN_REPORT, 0),
+            (EV_KEY, 30, 0),
+        ];
+        let mut bytes = Vec::new();
+        for (event_type, code, value) in records {
+            bytes.extend_from_slice(&1i64.to_ne_bytes());
+            bytes.extend_from_slice(&0i64.to_ne_bytes());
+            bytes.extend_from_slice(&event_type.to_ne_bytes());
+            bytes.extend_from_slice(&code.to_ne_bytes());
+            bytes.extend_from_slice(&value.to_ne_bytes());
+        }
+        let path = std::env::temp_dir().join(format!(
+            "keyboard-testkit-syn-dropped-{}",
+            std::process::id()
+        ));
+        fs::write(&path, bytes).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let mut listener = EvdevListener::for_device(&path, tx).unwrap();
+        listener.poll();
+        let codes: Vec<u16> = rx.try_iter().map(|e| e.key.as_u16()).collect();
+        assert_eq!(codes, vec![30, 46, 30]);
+        assert_eq!(listener.sync_drops(), 1);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn events_without_a_monotonic_clock_use_the_read_time() {
+        // EVIOCSCLOCKID fails on a regular file, so its timestamps (here a
+        // REALTIME-like value) must not be taken as kernel time
+        let mut bytes = Vec::new();
+        for (event_type, code, value) in [(EV_KEY, 30u16, 1i32), (EV_SYN, SYN_REPORT, 0)] {
+            bytes.extend_from_slice(&1_700_000_000i64.to_ne_bytes());
+            bytes.extend_from_slice(&0i64.to_ne_bytes());
+            bytes.extend_from_slice(&event_type.to_ne_bytes());
+            bytes.extend_from_slice(&code.to_ne_bytes());
+            bytes.extend_from_slice(&value.to_ne_bytes());
+        }
+        let path = std::env::temp_dir().join(format!(
+            "keyboard-testkit-realtime-clock-{}",
+            std::process::id()
+        ));
+        fs::write(&path, bytes).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let mut listener = EvdevListener::for_device(&path, tx).unwrap();
+        let before = Instant::now();
+        listener.poll();
+        let events: Vec<KeyEvent> = rx.try_iter().collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].hw_timestamp_us, None);
+        assert!(events[0].timestamp >= before);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn read_errors_are_classified() {
+        let unplugged = io::Error::from_raw_os_error(libc::ENODEV);
+        assert_eq!(classify_read_error(&unplugged), ReadFailure::Unplugged);
+        let drained = io::Error::from_raw_os_error(libc::EAGAIN);
+        assert_eq!(classify_read_error(&drained), ReadFailure::Drained);
+        let interrupted = io::Error::from_raw_os_error(libc::EINTR);
+        assert_eq!(classify_read_error(&interrupted), ReadFailure::Retry);
+        let io_error = io::Error::from_raw_os_error(libc::EIO);
+        assert_eq!(classify_read_error(&io_error), ReadFailure::Fatal);
+    }
+
     #[test]
     fn test_evdev_status() {
         let status = evdev_status();
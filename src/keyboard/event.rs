@@ -1,8 +1,12 @@
 //! Keyboard event types and crossterm-based listener
 
-use super::KeyCode;
-use crossterm::event::KeyCode as CtKeyCode;
-use std::sync::mpsc;
+use super::{DeviceInfo, KeyCode};
+use crossterm::event::{
+    KeyCode as CtKeyCode, KeyEvent as CtKeyEvent, KeyEventKind, KeyModifiers, MediaKeyCode,
+    ModifierKeyCode,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::{mpsc, Arc};
 use std::time::Instant;
 
 /// Type of keyboard event
@@ -12,6 +16,78 @@ pub enum KeyEventType {
     Press,
     /// Key was released
     Release,
+    /// Auto-repeat while the key is held (evdev value 2)
+    Repeat,
+}
+
+/// Auto-repeat configuration of an input device, as reported by EVIOCGREP
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepeatSettings {
+    /// Time from press to the first repeat
+    pub delay_ms: u32,
+    /// Time between repeats
+    pub period_ms: u32,
+}
+
+impl RepeatSettings {
+    /// Repeats per second (0 when repeat is disabled)
+    pub fn rate_hz(&self) -> f64 {
+        if self.period_ms == 0 {
+            0.0
+        } else {
+            1000.0 / self.period_ms as f64
+        }
+    }
+}
+
+/// How faithfully the event source reports what the keyboard did
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputFidelity {
+    /// Kernel evdev events: real press/repeat/release, scancodes and
+    /// hardware timestamps
+    Evdev,
+    /// Terminal with the kitty keyboard protocol: real press/repeat/release
+    /// and left/right modifiers, timed when the terminal delivers them
+    Enhanced,
+    /// Plain terminal input: presses only, each followed by a synthesized
+    /// release
+    Basic,
+}
+
+impl InputFidelity {
+    pub fn label(self) -> &'static str {
+        match self {
+            InputFidelity::Evdev => "evdev (kernel)",
+            InputFidelity::Enhanced => "terminal (kitty protocol)",
+            InputFidelity::Basic => "terminal (presses only)",
+        }
+    }
+
+    /// Whether releases are real, so hold, bounce and rollover results
+    /// mean something
+    pub fn has_releases(self) -> bool {
+        self != InputFidelity::Basic
+    }
+
+    /// Capture header `source` for a session at this fidelity
+    pub fn capture_source(self) -> &'static str {
+        match self {
+            InputFidelity::Evdev => "evdev",
+            InputFidelity::Enhanced => "kitty",
+            InputFidelity::Basic => "crossterm",
+        }
+    }
+
+    /// Fidelity of a recorded session from its capture header `source`
+    pub fn from_capture_source(source: &str) -> Option<Self> {
+        match source {
+            "evdev" => Some(InputFidelity::Evdev),
+            "kitty" => Some(InputFidelity::Enhanced),
+            "crossterm" => Some(InputFidelity::Basic),
+            _ => None,
+        }
+    }
 }
 
 /// A keyboard event with timing information
@@ -19,12 +95,21 @@ pub enum KeyEventType {
 pub struct KeyEvent {
     /// The key code
     pub key: KeyCode,
-    /// Type of event (press/release)
+    /// Type of event (press/release/repeat)
     pub event_type: KeyEventType,
     /// When the event occurred
     pub timestamp: Instant,
     /// Time since last event (for polling rate calculation)
     pub delta_us: u64,
+    /// Kernel timestamp in microseconds (CLOCK_MONOTONIC), when the source
+    /// provides one. This is when the driver saw the key, not when we polled.
+    pub hw_timestamp_us: Option<u64>,
+    /// Device the event came from, when the source knows it (evdev, replay)
+    pub device: Option<Arc<DeviceInfo>>,
+    /// Raw hardware scancode from MSC_SCAN, when the driver reports one.
+    /// For USB HID this is the usage (`page << 16 | id`), so keys that share
+    /// a keycode such as KEY_UNKNOWN can still be told apart.
+    pub scancode: Option<u32>,
 }
 
 impl KeyEvent {
@@ -34,6 +119,65 @@ impl KeyEvent {
             event_type,
             timestamp,
             delta_us,
+            hw_timestamp_us: None,
+            device: None,
+            scancode: None,
+        }
+    }
+
+    /// Attach the source device
+    pub fn with_device(mut self, device: Arc<DeviceInfo>) -> Self {
+        self.device = Some(device);
+        self
+    }
+
+    /// Path of the source device, if known
+    pub fn device_path(&self) -> Option<&str> {
+        self.device.as_deref().map(|d| d.path.as_str())
+    }
+
+    /// Attach the raw hardware scancode (MSC_SCAN)
+    pub fn with_scancode(mut self, scancode: u32) -> Self {
+        self.scancode = Some(scancode);
+        self
+    }
+
+    /// Attach a kernel timestamp (microseconds, CLOCK_MONOTONIC)
+    pub fn with_hw_timestamp(mut self, hw_timestamp_us: u64) -> Self {
+        self.hw_timestamp_us = Some(hw_timestamp_us);
+        self
+    }
+
+    /// Get the point in time this event occurred
+    pub fn time(&self) -> EventTime {
+        EventTime {
+            instant: self.timestamp,
+            hw_us: self.hw_timestamp_us,
+        }
+    }
+}
+
+/// Point in time of a key event, for interval measurements.
+///
+/// Tests store this instead of a bare `Instant` so that intervals are taken
+/// from kernel timestamps whenever both ends have one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventTime {
+    /// Application-side timestamp
+    pub instant: Instant,
+    /// Kernel timestamp in microseconds, if known
+    pub hw_us: Option<u64>,
+}
+
+impl EventTime {
+    /// Microseconds elapsed since `earlier`.
+    ///
+    /// Uses kernel timestamps when both events carry one, otherwise falls
+    /// back to the application-side `Instant`s.
+    pub fn micros_since(&self, earlier: EventTime) -> u64 {
+        match (self.hw_us, earlier.hw_us) {
+            (Some(now), Some(then)) => now.saturating_sub(then),
+            _ => self.instant.duration_since(earlier.instant).as_micros() as u64,
         }
     }
 }
@@ -43,11 +187,18 @@ impl KeyEvent {
 /// Unlike the previous device_query-based listener that polled hardware state,
 /// this listener is fed crossterm events from the main loop and translates them
 /// into the internal KeyEvent format for test processing.
+///
+/// At [`InputFidelity::Basic`] the terminal only reports presses, so each
+/// press is followed by a synthesized release to keep keys from looking
+/// stuck. With the kitty keyboard protocol ([`InputFidelity::Enhanced`])
+/// the terminal's own repeat and release events are passed through.
 pub struct KeyboardListener {
     last_poll: Instant,
     event_tx: mpsc::Sender<KeyEvent>,
     /// Whether the event channel is still connected
     channel_alive: bool,
+    /// What the terminal reports
+    fidelity: InputFidelity,
 }
 
 impl KeyboardListener {
@@ -57,6 +208,36 @@ impl KeyboardListener {
             last_poll: Instant::now(),
             event_tx,
             channel_alive: true,
+            fidelity: InputFidelity::Basic,
+        }
+    }
+
+    /// Set what the terminal reports
+    pub fn set_fidelity(&mut self, fidelity: InputFidelity) {
+        self.fidelity = fidelity;
+    }
+
+    /// What the terminal reports; a Basic listener becomes Enhanced on the
+    /// first real release (e.g. the Windows console reports them natively)
+    pub fn fidelity(&self) -> InputFidelity {
+        self.fidelity
+    }
+
+    /// Feed a crossterm key event of any kind (press, repeat or release).
+    /// Returns true if the event was sent successfully.
+    pub fn send_key(&mut self, key: &CtKeyEvent) -> bool {
+        match key.kind {
+            KeyEventKind::Press if self.fidelity == InputFidelity::Basic => {
+                self.send_press(key.code) && self.send_release(key.code)
+            }
+            KeyEventKind::Press => self.send_press(key.code),
+            KeyEventKind::Repeat => self.send_event(key.code, KeyEventType::Repeat),
+            KeyEventKind::Release => {
+                if self.fidelity == InputFidelity::Basic {
+                    self.fidelity = InputFidelity::Enhanced;
+                }
+                self.send_release(key.code)
+            }
         }
     }
 
@@ -107,6 +288,23 @@ impl KeyboardListener {
     }
 }
 
+/// Fold Shift into the character for UI shortcuts.
+///
+/// Under the kitty protocol Shift+s arrives as `Char('s')` with SHIFT, so
+/// shortcuts bound to `'S'` or `'?'` would never match. Like
+/// `crossterm_to_keycode`, this assumes a US layout.
+pub fn shifted_key(key: CtKeyEvent) -> CtKeyEvent {
+    if !key.modifiers.contains(KeyModifiers::SHIFT) {
+        return key;
+    }
+    let code = match key.code {
+        CtKeyCode::Char(c) if c.is_ascii_lowercase() => CtKeyCode::Char(c.to_ascii_uppercase()),
+        CtKeyCode::Char('/') => CtKeyCode::Char('?'),
+        code => code,
+    };
+    CtKeyEvent { code, ..key }
+}
+
 /// Convert a crossterm KeyCode to an evdev-compatible KeyCode
 fn crossterm_to_keycode(ct: CtKeyCode) -> KeyCode {
     let code = match ct {
@@ -189,6 +387,23 @@ fn crossterm_to_keycode(ct: CtKeyCode) -> KeyCode {
         CtKeyCode::Right => 106,
         CtKeyCode::NumLock => 69,
         CtKeyCode::PrintScreen => 99,
+        CtKeyCode::Menu => 127,
+        // Modifier and media keys are only reported with the kitty protocol
+        CtKeyCode::Modifier(ModifierKeyCode::LeftShift) => 42,
+        CtKeyCode::Modifier(ModifierKeyCode::RightShift) => 54,
+        CtKeyCode::Modifier(ModifierKeyCode::LeftControl) => 29,
+        CtKeyCode::Modifier(ModifierKeyCode::RightControl) => 97,
+        CtKeyCode::Modifier(ModifierKeyCode::LeftAlt) => 56,
+        CtKeyCode::Modifier(ModifierKeyCode::RightAlt) => 100,
+        CtKeyCode::Modifier(ModifierKeyCode::LeftSuper) => 125,
+        CtKeyCode::Modifier(ModifierKeyCode::RightSuper) => 126,
+        CtKeyCode::Media(MediaKeyCode::MuteVolume) => 113,
+        CtKeyCode::Media(MediaKeyCode::LowerVolume) => 114,
+        CtKeyCode::Media(MediaKeyCode::RaiseVolume) => 115,
+        CtKeyCode::Media(MediaKeyCode::TrackNext) => 163,
+        CtKeyCode::Media(MediaKeyCode::PlayPause) => 164,
+        CtKeyCode::Media(MediaKeyCode::TrackPrevious) => 165,
+        CtKeyCode::Media(MediaKeyCode::Stop) => 166,
         // Uppercase letters map to the same scancode (shift is a modifier)
         CtKeyCode::Char(c) if c.is_ascii_uppercase() => {
             return crossterm_to_keycode(CtKeyCode::Char(c.to_ascii_lowercase()));
@@ -197,3 +412,113 @@ fn crossterm_to_keycode(ct: CtKeyCode) -> KeyCode {
     };
     KeyCode(code)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn micros_since_prefers_kernel_timestamps() {
+        let now = Instant::now();
+        let a = KeyEvent::new(KeyCode(30), KeyEventType::Press, now, 0).with_hw_timestamp(1_000);
+        let b = KeyEvent::new(KeyCode(30), KeyEventType::Press, now, 0).with_hw_timestamp(2_250);
+        assert_eq!(b.time().micros_since(a.time()), 1_250);
+    }
+
+    #[test]
+    fn micros_since_falls_back_to_instant() {
+        let now = Instant::now();
+        let a = KeyEvent::new(KeyCode(30), KeyEventType::Press, now, 0).with_hw_timestamp(1_000);
+        let later = now + Duration::from_micros(500);
+        let b = KeyEvent::new(KeyCode(30), KeyEventType::Press, later, 0);
+        assert_eq!(b.time().micros_since(a.time()), 500);
+    }
+
+    fn ct_key(code: CtKeyCode, kind: KeyEventKind) -> CtKeyEvent {
+        CtKeyEvent::new_with_kind(code, KeyModifiers::NONE, kind)
+    }
+
+    fn drain(rx: &mpsc::Receiver<KeyEvent>) -> Vec<(u16, KeyEventType)> {
+        rx.try_iter().map(|e| (e.key.0, e.event_type)).collect()
+    }
+
+    #[test]
+    fn basic_terminal_presses_get_a_synthesized_release() {
+        let (tx, rx) = mpsc::channel();
+        let mut listener = KeyboardListener::new(tx);
+        listener.send_key(&ct_key(CtKeyCode::Char('a'), KeyEventKind::Press));
+        assert_eq!(
+            drain(&rx),
+            vec![(30, KeyEventType::Press), (30, KeyEventType::Release)]
+        );
+    }
+
+    #[test]
+    fn kitty_protocol_events_pass_through() {
+        let (tx, rx) = mpsc::channel();
+        let mut listener = KeyboardListener::new(tx);
+        listener.set_fidelity(InputFidelity::Enhanced);
+        let shift = CtKeyCode::Modifier(ModifierKeyCode::RightShift);
+        listener.send_key(&ct_key(shift, KeyEventKind::Press));
+        listener.send_key(&ct_key(shift, KeyEventKind::Repeat));
+        listener.send_key(&ct_key(shift, KeyEventKind::Release));
+        assert_eq!(
+            drain(&rx),
+            vec![
+                (54, KeyEventType::Press),
+                (54, KeyEventType::Repeat),
+                (54, KeyEventType::Release)
+            ]
+        );
+    }
+
+    #[test]
+    fn kitty_shift_letters_become_uppercase_shortcuts() {
+        let shift_s = CtKeyEvent::new_with_kind(
+            CtKeyCode::Char('s'),
+            KeyModifiers::SHIFT,
+            KeyEventKind::Press,
+        );
+        assert_eq!(shifted_key(shift_s).code, CtKeyCode::Char('S'));
+        let shift_slash = CtKeyEvent::new_with_kind(
+            CtKeyCode::Char('/'),
+            KeyModifiers::SHIFT,
+            KeyEventKind::Press,
+        );
+        assert_eq!(shifted_key(shift_slash).code, CtKeyCode::Char('?'));
+
+        // Legacy terminals already send the shifted character
+        let legacy = CtKeyEvent::new_with_kind(
+            CtKeyCode::Char('R'),
+            KeyModifiers::SHIFT,
+            KeyEventKind::Press,
+        );
+        assert_eq!(shifted_key(legacy).code, CtKeyCode::Char('R'));
+        let plain = ct_key(CtKeyCode::Char('r'), KeyEventKind::Press);
+        assert_eq!(shifted_key(plain).code, CtKeyCode::Char('r'));
+    }
+
+    #[test]
+    fn a_real_release_upgrades_basic_fidelity() {
+        let (tx, rx) = mpsc::channel();
+        let mut listener = KeyboardListener::new(tx);
+        listener.send_key(&ct_key(CtKeyCode::Char('a'), KeyEventKind::Release));
+        assert_eq!(listener.fidelity(), InputFidelity::Enhanced);
+
+        drain(&rx);
+        listener.send_key(&ct_key(CtKeyCode::Char('a'), KeyEventKind::Press));
+        assert_eq!(drain(&rx), vec![(30, KeyEventType::Press)]);
+    }
+
+    #[test]
+    fn fidelity_round_trips_through_capture_source() {
+        for fidelity in [InputFidelity::Evdev, InputFidelity::Enhanced, InputFidelity::Basic] {
+            assert_eq!(
+                InputFidelity::from_capture_source(fidelity.capture_source()),
+                Some(fidelity)
+            );
+        }
+        assert_eq!(InputFidelity::from_capture_source("replay"), None);
+    }
+}
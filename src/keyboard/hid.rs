@@ -0,0 +1,635 @@
+//! USB HID report descriptor inspection
+//!
+//! The report descriptor is the keyboard's own statement of what it sends.
+//! It decides the rollover ceiling before any switch is pressed: a
+//! boot-protocol style report has a fixed array of key slots (usually six),
+//! while an NKRO report carries one bit per key. It also shows which usage
+//! pages the OEM keys arrive on (Consumer, System Control or a vendor page).
+//!
+//! Descriptors are read from `/sys/class/hidraw/*/device/report_descriptor`
+//! for the evdev keyboards the listener opens; [`ReportDescriptor::parse`]
+//! works on raw bytes and is platform independent.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+#[cfg(target_os = "linux")]
+use std::fs;
+#[cfg(target_os = "linux")]
+use std::path::Path;
+
+/// HID usage page for keyboard/keypad keys
+pub const PAGE_KEYBOARD: u16 = 0x07;
+/// HID usage page for consumer controls (media, brightness, ...)
+pub const PAGE_CONSUMER: u16 = 0x0C;
+/// First vendor-defined usage page
+pub const PAGE_VENDOR_MIN: u16 = 0xFF00;
+
+/// Main item kind a field was declared with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReportKind {
+    Input,
+    Output,
+    Feature,
+}
+
+impl ReportKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReportKind::Input => "Input",
+            ReportKind::Output => "Output",
+            ReportKind::Feature => "Feature",
+        }
+    }
+}
+
+/// One Input/Output/Feature main item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportField {
+    pub kind: ReportKind,
+    /// Report ID in effect (None when the device uses no IDs)
+    pub report_id: Option<u8>,
+    pub usage_page: u16,
+    /// Lowest declared usage (0 when none was declared)
+    pub usage_min: u32,
+    /// Highest declared usage
+    pub usage_max: u32,
+    /// Bits per element
+    pub report_size: u32,
+    /// Number of elements
+    pub report_count: u32,
+    /// Constant (padding) field
+    pub constant: bool,
+    /// Variable (one element per usage) rather than array
+    pub variable: bool,
+}
+
+impl ReportField {
+    /// Total bits this field occupies in its report. Size and count come
+    /// straight from the descriptor, so a malformed one saturates rather
+    /// than overflowing.
+    pub fn bits(&self) -> u32 {
+        self.report_size.saturating_mul(self.report_count)
+    }
+}
+
+/// A Collection main item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collection {
+    pub usage_page: u16,
+    pub usage: u32,
+    /// Collection type (0 = physical, 1 = application, 2 = logical, ...)
+    pub collection_type: u8,
+    /// Nesting depth, 0 for top level
+    pub depth: usize,
+    /// Report IDs of the fields declared inside
+    pub report_ids: Vec<u8>,
+}
+
+impl Collection {
+    /// Whether the collection is on a vendor-defined usage page
+    pub fn is_vendor(&self) -> bool {
+        self.usage_page >= PAGE_VENDOR_MIN
+    }
+}
+
+/// How an input report encodes pressed keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEncoding {
+    /// Fixed number of key slots (boot protocol uses 6)
+    Array { slots: u32 },
+    /// One bit per key; any number of keys can be reported
+    Bitmap { keys: u32 },
+}
+
+/// A keyboard input report found in a descriptor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyboardReport {
+    pub report_id: Option<u8>,
+    pub encoding: KeyEncoding,
+    /// Whether there is a modifier bitmap (Left Ctrl .. Right GUI)
+    pub has_modifiers: bool,
+    /// Report length on the wire, including the report ID byte
+    pub bytes: usize,
+}
+
+impl KeyboardReport {
+    /// Boot-protocol layout: 8 bytes, no report ID, modifiers + 6 slots
+    pub fn is_boot_compatible(&self) -> bool {
+        self.report_id.is_none()
+            && self.has_modifiers
+            && self.encoding == KeyEncoding::Array { slots: 6 }
+    }
+
+    pub fn describe(&self) -> String {
+        match self.encoding {
+            KeyEncoding::Array { slots } if self.is_boot_compatible() => {
+                format!("{}-key array (boot protocol)", slots)
+            }
+            KeyEncoding::Array { slots } => format!("{}-key array", slots),
+            KeyEncoding::Bitmap { keys } => format!("NKRO bitmap ({} keys)", keys),
+        }
+    }
+}
+
+/// Rollover ceiling implied by a device's keyboard reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HidRollover {
+    /// At most this many non-modifier keys per report
+    KeyArray(usize),
+    /// Bitmap report; the descriptor does not limit rollover
+    Bitmap,
+}
+
+/// A parsed HID report descriptor
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReportDescriptor {
+    /// Descriptor length in bytes
+    pub length: usize,
+    pub fields: Vec<ReportField>,
+    pub collections: Vec<Collection>,
+}
+
+/// Global item state (pushed and popped as a unit)
+#[derive(Debug, Clone, Copy, Default)]
+struct GlobalState {
+    usage_page: u16,
+    report_size: u32,
+    report_count: u32,
+    report_id: Option<u8>,
+}
+
+impl ReportDescriptor {
+    /// Parse raw descriptor bytes.
+    ///
+    /// Long items are skipped and a truncated trailing item ends parsing;
+    /// everything parsed up to that point is kept.
+    pub fn parse(data: &[u8]) -> Self {
+        let mut descriptor = ReportDescriptor {
+            length: data.len(),
+            ..Default::default()
+        };
+        let mut global = GlobalState::default();
+        let mut global_stack: Vec<GlobalState> = Vec::new();
+        let mut usages: Vec<(Option<u16>, u32)> = Vec::new();
+        let mut usage_min: Option<u32> = None;
+        let mut usage_max: Option<u32> = None;
+        let mut open: Vec<usize> = Vec::new();
+
+        let mut i = 0;
+        while i < data.len() {
+            let prefix = data[i];
+            if prefix == 0xFE {
+                // Long item: prefix, size, tag, data
+                let size = data.get(i + 1).copied().unwrap_or(0) as usize;
+                i += 3 + size;
+                continue;
+            }
+            let size = [0, 1, 2, 4][(prefix & 0x03) as usize];
+            let Some(bytes) = data.get(i + 1..i + 1 + size) else {
+                break;
+            };
+            let value = bytes
+                .iter()
+                .rev()
+                .fold(0u32, |acc, &b| (acc << 8) | b as u32);
+            let item_type = (prefix >> 2) & 0x03;
+            let tag = prefix >> 4;
+            i += 1 + size;
+
+            match item_type {
+                // Main items
+                0 => {
+                    let kind = match tag {
+                        0x8 => Some(ReportKind::Input),
+                        0x9 => Some(ReportKind::Output),
+                        0xB => Some(ReportKind::Feature),
+                        _ => None,
+                    };
+                    if let Some(kind) = kind {
+                        let (page, min, max) = if let (Some(min), Some(max)) = (usage_min, usage_max)
+                        {
+                            (global.usage_page, min, max)
+                        } else if !usages.is_empty() {
+                            let page = usages[0].0.unwrap_or(global.usage_page);
+                            let min = usages.iter().map(|u| u.1).min().unwrap_or(0);
+                            let max = usages.iter().map(|u| u.1).max().unwrap_or(0);
+                            (page, min, max)
+                        } else {
+                            (global.usage_page, 0, 0)
+                        };
+                        descriptor.fields.push(ReportField {
+                            kind,
+                            report_id: global.report_id,
+                            usage_page: page,
+                            usage_min: min,
+                            usage_max: max,
+                            report_size: global.report_size,
+                            report_count: global.report_count,
+                            constant: value & 0x01 != 0,
+                            variable: value & 0x02 != 0,
+                        });
+                        if let Some(id) = global.report_id {
+                            for &c in &open {
+                                let ids = &mut descriptor.collections[c].report_ids;
+                                if !ids.contains(&id) {
+                                    ids.push(id);
+                                }
+                            }
+                        }
+                    } else if tag == 0xA {
+                        let (page, usage) = usages
+                            .first()
+                            .map(|&(p, u)| (p.unwrap_or(global.usage_page), u))
+                            .unwrap_or((global.usage_page, 0));
+                        descriptor.collections.push(Collection {
+                            usage_page: page,
+                            usage,
+                            collection_type: value as u8,
+                            depth: open.len(),
+                            report_ids: Vec::new(),
+                        });
+                        open.push(descriptor.collections.len() - 1);
+                    } else if tag == 0xC {
+                        open.pop();
+                    }
+                    usages.clear();
+                    usage_min = None;
+                    usage_max = None;
+                }
+                // Global items
+                1 => match tag {
+                    0x0 => global.usage_page = value as u16,
+                    0x7 => global.report_size = value,
+                    0x8 => global.report_id = Some(value as u8),
+                    0x9 => global.report_count = value,
+                    0xA => global_stack.push(global),
+                    0xB => global = global_stack.pop().unwrap_or(global),
+                    _ => {}
+                },
+                // Local items; 4-byte usages carry their own page
+                2 => {
+                    let (page, usage) = if size == 4 {
+                        (Some((value >> 16) as u16), value & 0xFFFF)
+                    } else {
+                        (None, value)
+                    };
+                    match tag {
+                        0x0 => usages.push((page, usage)),
+                        0x1 => usage_min = Some(usage),
+                        0x2 => usage_max = Some(usage),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        descriptor
+    }
+
+    /// Report lengths in bytes keyed by kind and report ID
+    pub fn report_sizes(&self) -> BTreeMap<(ReportKind, Option<u8>), usize> {
+        let mut bits: BTreeMap<(ReportKind, Option<u8>), u32> = BTreeMap::new();
+        for field in &self.fields {
+            let total = bits.entry((field.kind, field.report_id)).or_default();
+            *total = total.saturating_add(field.bits());
+        }
+        bits.into_iter()
+            .map(|((kind, id), bits)| {
+                let bytes = bits.div_ceil(8) as usize + usize::from(id.is_some());
+                ((kind, id), bytes)
+            })
+            .collect()
+    }
+
+    /// Input reports that carry keyboard-page keys
+    pub fn keyboard_reports(&self) -> Vec<KeyboardReport> {
+        let sizes = self.report_sizes();
+        let mut reports: Vec<KeyboardReport> = Vec::new();
+        for field in &self.fields {
+            if field.kind != ReportKind::Input
+                || field.constant
+                || field.usage_page != PAGE_KEYBOARD
+            {
+                continue;
+            }
+            let is_modifiers =
+                field.variable && field.usage_min >= 0xE0 && field.usage_max <= 0xE7;
+            let encoding = if is_modifiers {
+                None
+            } else if field.variable && field.report_size == 1 {
+                Some(KeyEncoding::Bitmap {
+                    keys: field.report_count,
+                })
+            } else if !field.variable {
+                Some(KeyEncoding::Array {
+                    slots: field.report_count,
+                })
+            } else {
+                None
+            };
+
+            let index = match reports.iter().position(|r| r.report_id == field.report_id) {
+                Some(index) => index,
+                None => {
+                    reports.push(KeyboardReport {
+                        report_id: field.report_id,
+                        encoding: KeyEncoding::Array { slots: 0 },
+                        has_modifiers: false,
+                        bytes: sizes
+                            .get(&(ReportKind::Input, field.report_id))
+                            .copied()
+                            .unwrap_or(0),
+                    });
+                    reports.len() - 1
+                }
+            };
+            let report = &mut reports[index];
+            if is_modifiers {
+                report.has_modifiers = true;
+            }
+            // A bitmap anywhere in the report wins over key slots
+            match (encoding, report.encoding) {
+                (Some(bitmap @ KeyEncoding::Bitmap { .. }), _) => report.encoding = bitmap,
+                (Some(array @ KeyEncoding::Array { .. }), KeyEncoding::Array { .. }) => {
+                    report.encoding = array
+                }
+                _ => {}
+            }
+        }
+        reports.retain(|r| r.encoding != KeyEncoding::Array { slots: 0 });
+        reports
+    }
+
+    /// Rollover ceiling: unlimited if any keyboard report is a bitmap,
+    /// otherwise the largest key array. None without keyboard reports.
+    pub fn rollover(&self) -> Option<HidRollover> {
+        let reports = self.keyboard_reports();
+        if reports
+            .iter()
+            .any(|r| matches!(r.encoding, KeyEncoding::Bitmap { .. }))
+        {
+            return Some(HidRollover::Bitmap);
+        }
+        reports
+            .iter()
+            .filter_map(|r| match r.encoding {
+                KeyEncoding::Array { slots } => Some(slots as usize),
+                KeyEncoding::Bitmap { .. } => None,
+            })
+            .max()
+            .map(HidRollover::KeyArray)
+    }
+
+    /// Declared input usage ranges per page, merged and sorted by page
+    pub fn input_usages(&self) -> BTreeMap<u16, (u32, u32)> {
+        let mut pages: BTreeMap<u16, (u32, u32)> = BTreeMap::new();
+        for field in &self.fields {
+            if field.kind != ReportKind::Input || field.constant {
+                continue;
+            }
+            let range = pages
+                .entry(field.usage_page)
+                .or_insert((field.usage_min, field.usage_max));
+            range.0 = range.0.min(field.usage_min);
+            range.1 = range.1.max(field.usage_max);
+        }
+        pages
+    }
+
+    /// Input fields outside the keyboard page, i.e. where OEM keys arrive
+    pub fn oem_inputs(&self) -> Vec<&ReportField> {
+        self.fields
+            .iter()
+            .filter(|f| f.kind == ReportKind::Input && !f.constant)
+            .filter(|f| f.usage_page == PAGE_CONSUMER || f.usage_page >= PAGE_VENDOR_MIN)
+            .collect()
+    }
+
+    /// Top-level collections on vendor-defined pages
+    pub fn vendor_collections(&self) -> Vec<&Collection> {
+        self.collections
+            .iter()
+            .filter(|c| c.depth == 0 && c.is_vendor())
+            .collect()
+    }
+}
+
+/// Human-readable name for a usage page
+pub fn usage_page_name(page: u16) -> String {
+    match page {
+        0x01 => "Generic Desktop".to_string(),
+        0x07 => "Keyboard".to_string(),
+        0x08 => "LED".to_string(),
+        0x09 => "Button".to_string(),
+        0x0C => "Consumer".to_string(),
+        p if p >= PAGE_VENDOR_MIN => format!("Vendor 0x{:04X}", p),
+        p => format!("Page 0x{:02X}", p),
+    }
+}
+
+/// Describe an MSC_SCAN value: HID usages (`page << 16 | id`) are shown
+/// with their page name, anything else (e.g. AT set 1) as plain hex
+pub fn describe_scancode(scancode: u32) -> String {
+    let page = (scancode >> 16) as u16;
+    if page == 0 {
+        format!("0x{:02X}", scancode)
+    } else {
+        format!("{} 0x{:02X}", usage_page_name(page), scancode & 0xFFFF)
+    }
+}
+
+/// A keyboard event device and its HID descriptor, if it has one
+#[derive(Debug, Clone)]
+pub struct HidDevice {
+    /// evdev node, e.g. /dev/input/event5
+    pub event_path: PathBuf,
+    /// Device name from sysfs
+    pub name: String,
+    /// Matching hidraw node in sysfs, e.g. /sys/class/hidraw/hidraw2
+    pub hidraw: Option<PathBuf>,
+    /// Parsed descriptor (None for non-HID keyboards such as PS/2)
+    pub descriptor: Option<ReportDescriptor>,
+}
+
+/// Find the hidraw node backed by the same HID device as an evdev node
+#[cfg(target_os = "linux")]
+pub fn hidraw_for_event(event_path: &Path) -> Option<PathBuf> {
+    let name = event_path.file_name()?.to_str()?;
+    let hid_device = fs::canonicalize(format!("/sys/class/input/{}/device/device", name)).ok()?;
+    fs::read_dir("/sys/class/hidraw")
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|hidraw| fs::canonicalize(hidraw.join("device")).ok().as_ref() == Some(&hid_device))
+}
+
+/// Read and parse the report descriptor for each detected keyboard
+#[cfg(target_os = "linux")]
+pub fn inspect_keyboards() -> Result<Vec<HidDevice>, super::evdev_listener::EvdevError> {
+    let devices = super::evdev_listener::find_keyboard_devices()?;
+    Ok(devices
+        .into_iter()
+        .map(|event_path| {
+            let sysfs_name = event_path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default()
+                .to_string();
+            let name = fs::read_to_string(format!("/sys/class/input/{}/device/name", sysfs_name))
+                .map(|n| n.trim().to_string())
+                .unwrap_or_default();
+            let hidraw = hidraw_for_event(&event_path);
+            let descriptor = hidraw
+                .as_ref()
+                .and_then(|h| fs::read(h.join("device/report_descriptor")).ok())
+                .map(|bytes| ReportDescriptor::parse(&bytes));
+            HidDevice {
+                event_path,
+                name,
+                hidraw,
+                descriptor,
+            }
+        })
+        .collect())
+}
+
+/// Rollover ceiling of each keyboard with a HID keyboard report, keyed by
+/// evdev node. Limits are not merged across devices: an NKRO keyboard says
+/// nothing about the 6KRO one next to it.
+pub fn device_rollovers(devices: &[HidDevice]) -> HashMap<String, HidRollover> {
+    devices
+        .iter()
+        .filter_map(|d| {
+            let rollover = d.descriptor.as_ref()?.rollover()?;
+            Some((d.event_path.to_string_lossy().into_owned(), rollover))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// HID 1.11 Appendix B.1 boot keyboard
+    const BOOT_KEYBOARD: &[u8] = &[
+        0x05, 0x01, 0x09, 0x06, 0xA1, 0x01, 0x05, 0x07, 0x19, 0xE0, 0x29, 0xE7, 0x15, 0x00,
+        0x25, 0x01, 0x75, 0x01, 0x95, 0x08, 0x81, 0x02, 0x95, 0x01, 0x75, 0x08, 0x81, 0x01,
+        0x95, 0x05, 0x75, 0x01, 0x05, 0x08, 0x19, 0x01, 0x29, 0x05, 0x91, 0x02, 0x95, 0x01,
+        0x75, 0x03, 0x91, 0x01, 0x95, 0x06, 0x75, 0x08, 0x15, 0x00, 0x25, 0x65, 0x05, 0x07,
+        0x19, 0x00, 0x29, 0x65, 0x81, 0x00, 0xC0,
+    ];
+
+    /// Report 1: modifiers + 120-key bitmap; report 3: consumer control;
+    /// report 5: 16-byte vendor collection
+    const NKRO_KEYBOARD: &[u8] = &[
+        0x05, 0x01, 0x09, 0x06, 0xA1, 0x01, 0x85, 0x01, 0x05, 0x07, 0x19, 0xE0, 0x29, 0xE7,
+        0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x08, 0x81, 0x02, 0x19, 0x00, 0x29, 0x77,
+        0x95, 0x78, 0x81, 0x02, 0xC0, //
+        0x05, 0x0C, 0x09, 0x01, 0xA1, 0x01, 0x85, 0x03, 0x19, 0x00, 0x2A, 0x3C, 0x02, 0x15,
+        0x00, 0x26, 0x3C, 0x02, 0x75, 0x10, 0x95, 0x01, 0x81, 0x00, 0xC0, //
+        0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0x85, 0x05, 0x09, 0x02, 0x15, 0x00, 0x26,
+        0xFF, 0x00, 0x75, 0x08, 0x95, 0x10, 0x81, 0x02, 0xC0,
+    ];
+
+    #[test]
+    fn boot_keyboard_is_six_key_array() {
+        let descriptor = ReportDescriptor::parse(BOOT_KEYBOARD);
+        let reports = descriptor.keyboard_reports();
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].is_boot_compatible());
+        assert_eq!(reports[0].bytes, 8);
+        assert_eq!(reports[0].describe(), "6-key array (boot protocol)");
+        assert_eq!(descriptor.rollover(), Some(HidRollover::KeyArray(6)));
+    }
+
+    #[test]
+    fn boot_keyboard_report_sizes() {
+        let sizes = ReportDescriptor::parse(BOOT_KEYBOARD).report_sizes();
+        assert_eq!(sizes[&(ReportKind::Input, None)], 8);
+        assert_eq!(sizes[&(ReportKind::Output, None)], 1); // LEDs
+    }
+
+    #[test]
+    fn nkro_bitmap_is_detected() {
+        let descriptor = ReportDescriptor::parse(NKRO_KEYBOARD);
+        let reports = descriptor.keyboard_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].report_id, Some(1));
+        assert_eq!(reports[0].encoding, KeyEncoding::Bitmap { keys: 120 });
+        assert_eq!(reports[0].bytes, 17);
+        assert_eq!(descriptor.rollover(), Some(HidRollover::Bitmap));
+    }
+
+    #[test]
+    fn vendor_and_consumer_inputs_are_listed() {
+        let descriptor = ReportDescriptor::parse(NKRO_KEYBOARD);
+
+        let vendor = descriptor.vendor_collections();
+        assert_eq!(vendor.len(), 1);
+        assert_eq!(vendor[0].usage_page, 0xFF00);
+        assert_eq!(vendor[0].report_ids, vec![5]);
+
+        let oem: Vec<(Option<u8>, u16)> = descriptor
+            .oem_inputs()
+            .iter()
+            .map(|f| (f.report_id, f.usage_page))
+            .collect();
+        assert_eq!(oem, vec![(Some(3), PAGE_CONSUMER), (Some(5), 0xFF00)]);
+
+        let usages = descriptor.input_usages();
+        assert_eq!(usages[&PAGE_CONSUMER], (0, 0x23C));
+        assert_eq!(usages[&PAGE_KEYBOARD], (0, 0xE7));
+    }
+
+    #[test]
+    fn push_pop_restores_globals() {
+        // Usage Page 7, Push, Usage Page 0x0C, Pop, Report Size 1, Count 8, Input
+        let data = [
+            0x05, 0x07, 0xA4, 0x05, 0x0C, 0xB4, 0x75, 0x01, 0x95, 0x08, 0x81, 0x02,
+        ];
+        let descriptor = ReportDescriptor::parse(&data);
+        assert_eq!(descriptor.fields[0].usage_page, PAGE_KEYBOARD);
+    }
+
+    #[test]
+    fn oversized_report_count_saturates() {
+        // Usage Page 7, Report Size 8, Report Count 0xFFFFFFFF, Input,
+        // then a second 8-bit Input in the same report
+        let data = [
+            0x05, 0x07, 0x75, 0x08, 0x97, 0xFF, 0xFF, 0xFF, 0xFF, 0x81, 0x00, 0x95, 0x01,
+            0x81, 0x01,
+        ];
+        let descriptor = ReportDescriptor::parse(&data);
+        assert_eq!(descriptor.fields[0].report_count, u32::MAX);
+        assert_eq!(descriptor.fields[0].bits(), u32::MAX);
+        let sizes = descriptor.report_sizes();
+        assert_eq!(sizes[&(ReportKind::Input, None)], u32::MAX.div_ceil(8) as usize);
+        descriptor.keyboard_reports();
+        descriptor.rollover();
+    }
+
+    #[test]
+    fn truncated_descriptor_keeps_parsed_items() {
+        // Cut inside the second Input item
+        let descriptor = ReportDescriptor::parse(&BOOT_KEYBOARD[..27]);
+        assert_eq!(descriptor.fields.len(), 1);
+        assert_eq!(descriptor.length, 27);
+    }
+
+    #[test]
+    fn usage_page_names() {
+        assert_eq!(usage_page_name(0x07), "Keyboard");
+        assert_eq!(usage_page_name(0xFF31), "Vendor 0xFF31");
+        assert_eq!(usage_page_name(0x0D), "Page 0x0D");
+    }
+
+    #[test]
+    fn scancodes_show_hid_page_when_present() {
+        assert_eq!(describe_scancode(0x0007_0004), "Keyboard 0x04");
+        assert_eq!(describe_scancode(0x000C_00E9), "Consumer 0xE9");
+        // AT set 1 scancodes carry no page
+        assert_eq!(describe_scancode(0x1E), "0x1E");
+    }
+}
@@ -374,6 +374,7 @@ pub static KEYMAP: LazyLock<HashMap<KeyCode, KeyInfo>> = LazyLock::new(|| {
     map.insert(KeyCode(51), KeyInfo::new("Comma", ",", 4, 8, 1.0));
     map.insert(KeyCode(52), KeyInfo::new("Period", ".", 4, 9, 1.0));
     map.insert(KeyCode(53), KeyInfo::new("Slash", "/", 4, 10, 1.0));
+    map.insert(KeyCode(86), KeyInfo::new("102nd", "\\", 4, 1, 1.0)); // ISO key left of Z
     map.insert(
         KeyCode(54),
         KeyInfo::new("RightShift", "Shift", 4, 11, 2.75),
@@ -389,6 +390,11 @@ pub static KEYMAP: LazyLock<HashMap<KeyCode, KeyInfo>> = LazyLock::new(|| {
     map.insert(KeyCode(127), KeyInfo::new("Menu", "Menu", 5, 6, 1.25));
     map.insert(KeyCode(97), KeyInfo::new("RightCtrl", "Ctrl", 5, 7, 1.25));
 
+    // JIS bottom row
+    map.insert(KeyCode(94), KeyInfo::new("Muhenkan", "無", 5, 3, 1.0));
+    map.insert(KeyCode(92), KeyInfo::new("Henkan", "変", 5, 5, 1.0));
+    map.insert(KeyCode(93), KeyInfo::new("Katakana", "カ", 5, 6, 1.0));
+
     // Arrow keys
     map.insert(KeyCode(103), KeyInfo::new("Up", "↑", 5, 9, 1.0));
     map.insert(KeyCode(105), KeyInfo::new("Left", "←", 6, 8, 1.0));
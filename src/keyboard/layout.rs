@@ -179,6 +179,19 @@ pub fn layout_rows(layout: KeyboardLayout) -> [Vec<VisualKey>; 5] {
     [row0, row1, row2, row3, row4]
 }
 
+/// Arrow cluster keys, rendered beside the main block.
+///
+/// Order is up, left, down, right (same for all layouts).
+pub fn arrow_keys() -> [VisualKey; 4] {
+    let w = 4;
+    [
+        VisualKey { label: "\u{25B2}", code: 103, width: w }, // ▲
+        VisualKey { label: "\u{25C0}", code: 105, width: w }, // ◀
+        VisualKey { label: "\u{25BC}", code: 108, width: w }, // ▼
+        VisualKey { label: "\u{25B6}", code: 106, width: w }, // ▶
+    ]
+}
+
 #[cfg(target_os = "linux")]
 fn detect_linux_layout() -> KeyboardLayout {
     // Try setxkbmap first
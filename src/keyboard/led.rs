@@ -0,0 +1,71 @@
+//! Keyboard indicator LEDs
+//!
+//! The lock and status LEDs a keyboard can have, numbered as the kernel's
+//! `LED_*` codes so they can be written as `EV_LED` events and decoded from
+//! the EVIOCGBIT/EVIOCGLED bitmasks.
+
+/// A keyboard indicator LED
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Led {
+    NumLock,
+    CapsLock,
+    ScrollLock,
+    Compose,
+    Kana,
+}
+
+impl Led {
+    /// Every LED the LED test knows how to drive, in kernel code order
+    pub const ALL: [Led; 5] = [
+        Led::NumLock,
+        Led::CapsLock,
+        Led::ScrollLock,
+        Led::Compose,
+        Led::Kana,
+    ];
+
+    /// Kernel `LED_*` code
+    pub fn code(self) -> u16 {
+        match self {
+            Led::NumLock => 0x00,
+            Led::CapsLock => 0x01,
+            Led::ScrollLock => 0x02,
+            Led::Compose => 0x03,
+            Led::Kana => 0x04,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Led::NumLock => "Num Lock",
+            Led::CapsLock => "Caps Lock",
+            Led::ScrollLock => "Scroll Lock",
+            Led::Compose => "Compose",
+            Led::Kana => "Kana",
+        }
+    }
+
+    /// LEDs whose bits are set in a kernel LED bitmask
+    pub fn from_mask(mask: u64) -> Vec<Led> {
+        Self::ALL
+            .into_iter()
+            .filter(|led| mask & (1 << led.code()) != 0)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask_bits_follow_kernel_codes() {
+        // LED_NUML | LED_CAPSL | LED_SCROLLL, as on a full-size keyboard
+        assert_eq!(
+            Led::from_mask(0b111),
+            vec![Led::NumLock, Led::CapsLock, Led::ScrollLock]
+        );
+        assert_eq!(Led::from_mask(1 << 4), vec![Led::Kana]);
+        assert!(Led::from_mask(0).is_empty());
+    }
+}
@@ -0,0 +1,381 @@
+//! Keyboard switch matrix definitions
+//!
+//! Most keyboards scan their switches as a grid of row and column lines. How
+//! that grid is wired decides which chords ghost (a phantom key registers) or
+//! block (a real key is suppressed). A [`KeyboardMatrix`] describes the grid
+//! so rollover failures can be predicted and explained.
+//!
+//! Matrices are loaded from TOML files:
+//!
+//! ```toml
+//! name = "Office membrane"
+//! behavior = "block"   # "ghost" (no diodes), "block" (anti-ghosting), "diodes"
+//! key_limit = 6        # optional report limit, modifiers excluded
+//!
+//! # One array per row line; each entry is the evdev scancode on that
+//! # column line, or 0 where no switch is fitted.
+//! rows = [
+//!     [16, 17, 18, 19],   # Q W E R
+//!     [30, 31, 32, 33],   # A S D F
+//!     [44, 45, 46, 47],   # Z X C V
+//! ]
+//! ```
+//!
+//! `--matrix` accepts a path or the name of a file in
+//! [`matrices_dir`](crate::config::matrices_dir).
+
+use super::keymap::{self, KeyCode};
+use crate::config::{matrices_dir, ConfigError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// How the controller behaves when held keys close a matrix rectangle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatrixBehavior {
+    /// No diodes: the fourth corner of a rectangle registers as a ghost
+    #[default]
+    Ghost,
+    /// No diodes, but firmware refuses a key that would create a ghost
+    Block,
+    /// Per-switch diodes: every key reads correctly
+    Diodes,
+}
+
+/// On-disk matrix definition
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MatrixFile {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    behavior: MatrixBehavior,
+    #[serde(default)]
+    key_limit: Option<usize>,
+    rows: Vec<Vec<u16>>,
+}
+
+/// A keyboard's switch matrix
+#[derive(Debug, Clone)]
+pub struct KeyboardMatrix {
+    /// Matrix name shown in results
+    pub name: String,
+    /// Ghosting behavior of the controller
+    pub behavior: MatrixBehavior,
+    /// Maximum non-modifier keys reported at once (e.g. 6 for boot protocol)
+    pub key_limit: Option<usize>,
+    /// Scancode at each row/column (0 = no switch)
+    rows: Vec<Vec<u16>>,
+    /// Row and column of each scancode
+    positions: HashMap<KeyCode, (usize, usize)>,
+}
+
+/// What a matrix predicts for a set of held keys
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatrixPrediction {
+    /// Keys expected to register without being held
+    pub ghosted: Vec<KeyCode>,
+    /// Held keys expected not to register
+    pub blocked: Vec<KeyCode>,
+    /// Held keys that are not in the matrix (no prediction possible)
+    pub unmapped: Vec<KeyCode>,
+}
+
+impl KeyboardMatrix {
+    /// Build a matrix from rows of scancodes, rejecting duplicate switches
+    pub fn new(
+        name: impl Into<String>,
+        behavior: MatrixBehavior,
+        rows: Vec<Vec<u16>>,
+    ) -> Result<Self, ConfigError> {
+        let mut positions = HashMap::new();
+        for (r, row) in rows.iter().enumerate() {
+            for (c, &code) in row.iter().enumerate() {
+                if code == 0 {
+                    continue;
+                }
+                if let Some((r0, c0)) = positions.insert(KeyCode(code), (r, c)) {
+                    return Err(ConfigError::Invalid(format!(
+                        "scancode {} appears at row {} col {} and row {} col {}",
+                        code, r0, c0, r, c
+                    )));
+                }
+            }
+        }
+        if positions.is_empty() {
+            return Err(ConfigError::Invalid("matrix has no switches".to_string()));
+        }
+
+        Ok(Self {
+            name: name.into(),
+            behavior,
+            key_limit: None,
+            rows,
+            positions,
+        })
+    }
+
+    /// Load a matrix definition from a TOML file.
+    ///
+    /// A matrix without a `name` is named after its file stem.
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path)?;
+        let file: MatrixFile = toml::from_str(&contents)?;
+        let name = if file.name.is_empty() {
+            path.file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        } else {
+            file.name
+        };
+        let mut matrix = Self::new(name, file.behavior, file.rows)?;
+        matrix.key_limit = file.key_limit;
+        Ok(matrix)
+    }
+
+    /// Load a matrix given either a file path or a name in `matrices_dir`
+    pub fn resolve(name_or_path: &str) -> Result<Self, ConfigError> {
+        let path = Path::new(name_or_path);
+        if path.exists() {
+            return Self::load_from(path);
+        }
+        Self::load_from(&matrices_dir()?.join(format!("{}.toml", name_or_path)))
+    }
+
+    /// Row and column of a key, if it is in the matrix
+    pub fn position(&self, key: KeyCode) -> Option<(usize, usize)> {
+        self.positions.get(&key).copied()
+    }
+
+    /// Number of switches in the matrix
+    pub fn switch_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Keys that would falsely register while `held` are down (no diodes).
+    ///
+    /// Held keys join their row and column lines; any switch whose row and
+    /// column end up electrically connected reads as pressed.
+    pub fn ghosts_of(&self, held: &[KeyCode]) -> Vec<KeyCode> {
+        let row_count = self.rows.len();
+        let col_count = self.rows.iter().map(Vec::len).max().unwrap_or(0);
+        // Union-find over row lines (0..row_count) and column lines after them
+        let mut parent: Vec<usize> = (0..row_count + col_count).collect();
+        fn find(parent: &mut [usize], mut x: usize) -> usize {
+            while parent[x] != x {
+                parent[x] = parent[parent[x]];
+                x = parent[x];
+            }
+            x
+        }
+
+        for key in held {
+            if let Some((r, c)) = self.position(*key) {
+                let a = find(&mut parent, r);
+                let b = find(&mut parent, row_count + c);
+                parent[a] = b;
+            }
+        }
+
+        let mut ghosts = Vec::new();
+        for (r, row) in self.rows.iter().enumerate() {
+            for (c, &code) in row.iter().enumerate() {
+                let key = KeyCode(code);
+                if code == 0 || held.contains(&key) {
+                    continue;
+                }
+                if find(&mut parent, r) == find(&mut parent, row_count + c) {
+                    ghosts.push(key);
+                }
+            }
+        }
+        ghosts
+    }
+
+    /// Predict what registers when `held` keys are pressed in the given order
+    pub fn predict(&self, held: &[KeyCode]) -> MatrixPrediction {
+        let mut prediction = MatrixPrediction::default();
+        let mut registered: Vec<KeyCode> = Vec::new();
+        let mut reported = 0usize;
+
+        for &key in held {
+            if self.position(key).is_none() {
+                prediction.unmapped.push(key);
+                continue;
+            }
+            let counts_toward_limit = !keymap::is_modifier(key);
+            if counts_toward_limit && self.key_limit.is_some_and(|limit| reported >= limit) {
+                prediction.blocked.push(key);
+                continue;
+            }
+            if self.behavior == MatrixBehavior::Block {
+                let mut candidate = registered.clone();
+                candidate.push(key);
+                if !self.ghosts_of(&candidate).is_empty() {
+                    prediction.blocked.push(key);
+                    continue;
+                }
+            }
+            registered.push(key);
+            if counts_toward_limit {
+                reported += 1;
+            }
+        }
+
+        if self.behavior == MatrixBehavior::Ghost {
+            prediction.ghosted = self.ghosts_of(&registered);
+        }
+        prediction
+    }
+
+    /// Three-key chords that close a matrix rectangle, each with the
+    /// fourth corner that would ghost.
+    ///
+    /// Only rectangles whose four corners all have switches are returned,
+    /// at most `limit` of them, spread across different rows.
+    pub fn rectangle_chords(&self, limit: usize) -> Vec<(Vec<KeyCode>, KeyCode)> {
+        let mut chords = Vec::new();
+        let mut used_rows = Vec::new();
+        for r1 in 0..self.rows.len() {
+            for r2 in r1 + 1..self.rows.len() {
+                if used_rows.contains(&r1) || used_rows.contains(&r2) {
+                    continue;
+                }
+                let cols = self.rows[r1].len().min(self.rows[r2].len());
+                'search: for c1 in 0..cols {
+                    for c2 in c1 + 1..cols {
+                        let corners = [
+                            self.rows[r1][c1],
+                            self.rows[r1][c2],
+                            self.rows[r2][c1],
+                            self.rows[r2][c2],
+                        ];
+                        if corners.contains(&0) {
+                            continue;
+                        }
+                        chords.push((
+                            vec![KeyCode(corners[0]), KeyCode(corners[1]), KeyCode(corners[2])],
+                            KeyCode(corners[3]),
+                        ));
+                        used_rows.extend([r1, r2]);
+                        break 'search;
+                    }
+                }
+                if chords.len() >= limit {
+                    return chords;
+                }
+            }
+        }
+        chords
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Q W E / A S D / Z X C
+    fn grid(behavior: MatrixBehavior) -> KeyboardMatrix {
+        KeyboardMatrix::new(
+            "test",
+            behavior,
+            vec![vec![16, 17, 18], vec![30, 31, 32], vec![44, 45, 46]],
+        )
+        .unwrap()
+    }
+
+    fn keys(codes: &[u16]) -> Vec<KeyCode> {
+        codes.iter().map(|&c| KeyCode(c)).collect()
+    }
+
+    #[test]
+    fn rectangle_ghosts_fourth_corner() {
+        let matrix = grid(MatrixBehavior::Ghost);
+        // A + S + W closes the Q/W/A/S rectangle
+        assert_eq!(matrix.ghosts_of(&keys(&[30, 31, 17])), keys(&[16]));
+        // Keys on one row never ghost
+        assert!(matrix.ghosts_of(&keys(&[30, 31, 32])).is_empty());
+    }
+
+    #[test]
+    fn ghosting_is_transitive() {
+        let matrix = grid(MatrixBehavior::Ghost);
+        // Q + W + S + D connects rows 0,1 with cols 0,1,2
+        let ghosts = matrix.ghosts_of(&keys(&[16, 17, 31, 32]));
+        assert_eq!(ghosts, keys(&[18, 30]));
+    }
+
+    #[test]
+    fn ghost_behavior_predicts_ghosts() {
+        let prediction = grid(MatrixBehavior::Ghost).predict(&keys(&[30, 31, 17]));
+        assert_eq!(prediction.ghosted, keys(&[16]));
+        assert!(prediction.blocked.is_empty());
+    }
+
+    #[test]
+    fn block_behavior_blocks_closing_key() {
+        let prediction = grid(MatrixBehavior::Block).predict(&keys(&[30, 31, 17]));
+        assert!(prediction.ghosted.is_empty());
+        assert_eq!(prediction.blocked, keys(&[17]));
+    }
+
+    #[test]
+    fn diodes_predict_clean_reads() {
+        let prediction = grid(MatrixBehavior::Diodes).predict(&keys(&[30, 31, 17]));
+        assert_eq!(prediction, MatrixPrediction::default());
+    }
+
+    #[test]
+    fn key_limit_blocks_extra_keys_but_not_modifiers() {
+        let mut matrix = KeyboardMatrix::new(
+            "6kro",
+            MatrixBehavior::Diodes,
+            vec![vec![16, 17, 18, 19, 20, 21, 22, 42]],
+        )
+        .unwrap();
+        matrix.key_limit = Some(6);
+        let prediction = matrix.predict(&keys(&[16, 17, 18, 19, 20, 21, 22, 42]));
+        assert_eq!(prediction.blocked, keys(&[22]));
+    }
+
+    #[test]
+    fn unmapped_keys_are_reported() {
+        let prediction = grid(MatrixBehavior::Ghost).predict(&keys(&[30, 57]));
+        assert_eq!(prediction.unmapped, keys(&[57]));
+    }
+
+    #[test]
+    fn duplicate_scancode_is_invalid() {
+        let result = KeyboardMatrix::new("dup", MatrixBehavior::Ghost, vec![vec![30, 30]]);
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn rectangle_chords_use_distinct_rows() {
+        let chords = grid(MatrixBehavior::Ghost).rectangle_chords(4);
+        assert_eq!(chords[0], (keys(&[16, 17, 30]), KeyCode(31)));
+        assert_eq!(chords.len(), 1); // only rows 0/1 then row 2 is alone
+    }
+
+    #[test]
+    fn load_from_toml() {
+        let path = std::env::temp_dir().join(format!(
+            "keyboard-testkit-matrix-{}.toml",
+            std::process::id()
+        ));
+        fs::write(
+            &path,
+            "behavior = \"block\"\nkey_limit = 6\nrows = [[16, 17], [30, 0]]\n",
+        )
+        .unwrap();
+        let matrix = KeyboardMatrix::load_from(&path).unwrap();
+        fs::remove_file(&path).ok();
+        assert!(matrix.name.starts_with("keyboard-testkit-matrix-"));
+        assert_eq!(matrix.behavior, MatrixBehavior::Block);
+        assert_eq!(matrix.key_limit, Some(6));
+        assert_eq!(matrix.switch_count(), 3);
+        assert_eq!(matrix.position(KeyCode(30)), Some((1, 0)));
+    }
+}
@@ -5,13 +5,17 @@
 //! ## Components
 //!
 //! - [`KeyEvent`] - Represents a single keyboard event with timing data
+//! - [`DeviceInfo`] - Source device identity (path, name, vendor/product ID)
 //! - [`KeyEventType`] - Press or Release event types
+//! - [`EventTime`] - Event timestamp, preferring kernel time when available
 //! - [`KeyboardListener`] - Converts crossterm key events for test processing
 //! - [`KeyboardState`] - Tracks per-key and global state statistics
 //! - [`KeyState`] - Per-key metrics (press count, durations, polling intervals)
 //! - [`KeyCode`] - Platform-independent key identifier (Linux evdev scancodes)
 //! - [`KeyInfo`] - Key metadata including name, label, and position
 //! - [`remap`] - Key remapping and OEM/FN key restoration
+//! - [`hid`] - USB HID report descriptor parsing (boot vs NKRO, vendor pages)
+//! - [`matrix`] - Switch matrix definitions for ghosting/blocking prediction
 //!
 //! ## Usage
 //!
@@ -35,19 +39,35 @@
 //! }
 //! ```
 
+pub mod device;
 mod event;
+pub mod hid;
 pub mod keymap;
 pub mod layout;
+pub mod led;
+pub mod matrix;
 pub mod remap;
 mod state;
 
+#[cfg(target_os = "linux")]
+pub mod evdev_capture;
 #[cfg(target_os = "linux")]
 pub mod evdev_listener;
 
-pub use event::{KeyEvent, KeyEventType, KeyboardListener};
+pub use device::{key_code_names, DeviceCapabilities, DeviceInfo};
+pub use event::{
+    shifted_key, EventTime, InputFidelity, KeyEvent, KeyEventType, KeyboardListener,
+    RepeatSettings,
+};
 pub use keymap::{get_key_info, KeyCode, KeyInfo, KEYMAP};
 pub use layout::KeyboardLayout;
+pub use led::Led;
 pub use state::{KeyState, KeyboardState};
 
 #[cfg(target_os = "linux")]
-pub use evdev_listener::{evdev_status, is_evdev_available, EvdevListener};
+pub use evdev_capture::{CaptureOptions, EvdevCapture};
+#[cfg(target_os = "linux")]
+pub use evdev_listener::{
+    evdev_status, is_evdev_available, key_state_corrections, keyboard_capabilities,
+    read_capabilities, read_key_state, DeviceChange, EvdevError, EvdevListener,
+};
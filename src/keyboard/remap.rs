@@ -41,6 +41,54 @@ pub enum FnKeyMode {
     MapToMedia,
 }
 
+impl FnKeyMode {
+    /// The FN+key table this mode applies, for the modes that remap keys.
+    /// The mapper daemon uses it to build an Fn layer.
+    pub fn combo_table(self) -> Option<HashMap<u16, u16>> {
+        match self {
+            FnKeyMode::MapToFKeys => Some(fn_fkey_combos()),
+            FnKeyMode::MapToMedia => Some(fn_media_combos()),
+            _ => None,
+        }
+    }
+}
+
+/// Scancodes treated as the FN key by default (KEY_FN, KEY_FN_ESC)
+pub const DEFAULT_FN_SCANCODES: [u16; 2] = [464, 480];
+
+/// FN + number row → F1-F12, plus Backspace → Delete
+pub fn fn_fkey_combos() -> HashMap<u16, u16> {
+    HashMap::from([
+        (2, 59),   // 1 -> F1
+        (3, 60),   // 2 -> F2
+        (4, 61),   // 3 -> F3
+        (5, 62),   // 4 -> F4
+        (6, 63),   // 5 -> F5
+        (7, 64),   // 6 -> F6
+        (8, 65),   // 7 -> F7
+        (9, 66),   // 8 -> F8
+        (10, 67),  // 9 -> F9
+        (11, 68),  // 0 -> F10
+        (12, 87),  // - -> F11
+        (13, 88),  // = -> F12
+        (14, 111), // Backspace -> Delete
+    ])
+}
+
+/// FN + arrows/Space/Esc → media and power keys (common laptop layout),
+/// plus Backspace → Delete
+pub fn fn_media_combos() -> HashMap<u16, u16> {
+    HashMap::from([
+        (105, 165), // Left -> Previous
+        (106, 163), // Right -> Next
+        (103, 115), // Up -> Volume Up
+        (108, 114), // Down -> Volume Down
+        (57, 164),  // Space -> Play/Pause
+        (1, 142),   // Esc -> Sleep
+        (14, 111),  // Backspace -> Delete
+    ])
+}
+
 /// Describes how to handle unmapped/unknown key presses
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum UnknownKeyBehavior {
@@ -110,7 +158,7 @@ pub struct KeyRemapper {
     unknown_behavior: UnknownKeyBehavior,
     /// History of captured OEM/unknown keys
     captured_keys: HashMap<u16, CapturedKey>,
-    /// FN+key to result mappings
+    /// Configured FN+key combinations, applied on top of the mode's table
     fn_combos: HashMap<u16, u16>,
     /// Whether remapping is enabled
     enabled: bool,
@@ -125,49 +173,16 @@ impl Default for KeyRemapper {
 impl KeyRemapper {
     /// Create a new key remapper with default settings
     pub fn new() -> Self {
-        let mut remapper = Self {
+        Self {
             mappings: HashMap::new(),
             fn_mode: FnKeyMode::CaptureOnly,
             fn_held: false,
-            fn_scancodes: vec![464, 480], // Common FN key scancodes
+            fn_scancodes: DEFAULT_FN_SCANCODES.to_vec(),
             unknown_behavior: UnknownKeyBehavior::CaptureAndPassThrough,
             captured_keys: HashMap::new(),
             fn_combos: HashMap::new(),
             enabled: true,
-        };
-
-        // Set up default FN+key combos (number row -> F-keys)
-        remapper.setup_default_fn_combos();
-
-        remapper
-    }
-
-    /// Set up default FN+key combinations
-    fn setup_default_fn_combos(&mut self) {
-        // FN + number row -> F1-F10
-        self.fn_combos.insert(2, 59); // 1 -> F1
-        self.fn_combos.insert(3, 60); // 2 -> F2
-        self.fn_combos.insert(4, 61); // 3 -> F3
-        self.fn_combos.insert(5, 62); // 4 -> F4
-        self.fn_combos.insert(6, 63); // 5 -> F5
-        self.fn_combos.insert(7, 64); // 6 -> F6
-        self.fn_combos.insert(8, 65); // 7 -> F7
-        self.fn_combos.insert(9, 66); // 8 -> F8
-        self.fn_combos.insert(10, 67); // 9 -> F9
-        self.fn_combos.insert(11, 68); // 0 -> F10
-        self.fn_combos.insert(12, 87); // - -> F11
-        self.fn_combos.insert(13, 88); // = -> F12
-
-        // FN + arrow keys -> media controls (common laptop layout)
-        self.fn_combos.insert(105, 165); // Left -> Previous
-        self.fn_combos.insert(106, 163); // Right -> Next
-        self.fn_combos.insert(103, 115); // Up -> Volume Up
-        self.fn_combos.insert(108, 114); // Down -> Volume Down
-
-        // FN + other common mappings
-        self.fn_combos.insert(57, 164); // Space -> Play/Pause
-        self.fn_combos.insert(14, 111); // Backspace -> Delete
-        self.fn_combos.insert(1, 142); // Esc -> Sleep
+        }
     }
 
     /// Enable or disable remapping
@@ -247,11 +262,23 @@ impl KeyRemapper {
         self.fn_combos.remove(&key)
     }
 
-    /// Get all FN+key combinations
+    /// Get the configured FN+key combinations
     pub fn fn_combos(&self) -> &HashMap<u16, u16> {
         &self.fn_combos
     }
 
+    /// The FN+key table in effect: the mode's table plus the configured
+    /// combinations, or nothing for modes that don't remap. Matches the Fn
+    /// layer the mapper daemon builds from the same settings.
+    pub fn fn_combo_table(&self) -> HashMap<u16, u16> {
+        let mut table = match self.fn_mode.combo_table() {
+            Some(table) => table,
+            None => return HashMap::new(),
+        };
+        table.extend(self.fn_combos.iter().map(|(&key, &result)| (key, result)));
+        table
+    }
+
     /// Get captured/unknown keys
     pub fn captured_keys(&self) -> &HashMap<u16, CapturedKey> {
         &self.captured_keys
@@ -277,8 +304,8 @@ impl KeyRemapper {
         }
 
         // Handle FN+key combinations when FN is held
-        if self.fn_held && pressed && self.fn_mode != FnKeyMode::Disabled {
-            if let Some(&result) = self.fn_combos.get(&scancode) {
+        if self.fn_held && pressed {
+            if let Some(&result) = self.fn_combo_table().get(&scancode) {
                 // Capture the original key
                 self.capture_key(scancode, pressed, None);
 
@@ -331,7 +358,7 @@ impl KeyRemapper {
     /// Get recently captured keys sorted by timestamp (most recent first)
     pub fn recent_captured(&self, count: usize) -> Vec<&CapturedKey> {
         let mut keys: Vec<_> = self.captured_keys.values().collect();
-        keys.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        keys.sort_by_key(|k| std::cmp::Reverse(k.timestamp));
         keys.into_iter().take(count).collect()
     }
 
@@ -478,6 +505,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_fn_mode_combo_tables() {
+        let fkeys = FnKeyMode::MapToFKeys.combo_table().unwrap();
+        assert_eq!(fkeys.get(&13), Some(&88)); // = -> F12
+        assert!(!fkeys.contains_key(&105));
+        let media = FnKeyMode::MapToMedia.combo_table().unwrap();
+        assert_eq!(media.get(&105), Some(&165)); // Left -> Previous
+        assert!(!media.contains_key(&2));
+        assert!(FnKeyMode::CaptureOnly.combo_table().is_none());
+    }
+
+    #[test]
+    fn test_fn_combo_table_follows_mode() {
+        let mut remapper = KeyRemapper::new();
+        remapper.add_fn_combo(16, 113); // Q -> Mute
+        assert!(remapper.fn_combo_table().is_empty());
+
+        remapper.set_fn_mode(FnKeyMode::MapToFKeys);
+        remapper.process_key(KeyCode::new(464), true);
+        assert!(!matches!(
+            remapper.process_key(KeyCode::new(105), true),
+            RemapResult::FnCombo { .. }
+        ));
+        assert!(matches!(
+            remapper.process_key(KeyCode::new(16), true),
+            RemapResult::FnCombo { result, .. } if result.as_u16() == 113
+        ));
+
+        remapper.set_fn_mode(FnKeyMode::MapToMedia);
+        assert!(matches!(
+            remapper.process_key(KeyCode::new(105), true),
+            RemapResult::FnCombo { result, .. } if result.as_u16() == 165
+        ));
+    }
+
     #[test]
     fn test_disabled_remapping() {
         let mut remapper = KeyRemapper::new();
@@ -101,6 +101,9 @@ impl KeyboardState {
 
     /// Process a key event and update state
     pub fn process_event(&mut self, event: &KeyEvent) {
+        if event.event_type == KeyEventType::Repeat {
+            return;
+        }
         self.total_events += 1;
 
         // Record global interval
@@ -128,6 +131,7 @@ impl KeyboardState {
                     self.max_simultaneous = self.pressed_keys.len();
                 }
             }
+            KeyEventType::Repeat => {}
             KeyEventType::Release => {
                 key_state.is_pressed = false;
                 key_state.last_release = Some(event.timestamp);
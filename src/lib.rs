@@ -10,6 +10,9 @@
 //! - **N-Key Rollover (NKRO)**: Measure simultaneous key capability and ghosting
 //! - **Event Timing**: Measure inter-event timing per-key and globally
 //! - **Virtual Keyboard Testing**: Compare physical vs virtual key events
+//! - **Key Coverage**: Guided check that every key on the layout registers
+//! - **Session Capture**: Record raw key events and replay them through every test
+//! - **Acceptance Profiles**: Grade a session PASS/FAIL against per-SKU thresholds
 //!
 //! ## Architecture
 //!
@@ -20,6 +23,10 @@
 //! - [`ui`]: Terminal UI components using ratatui
 //! - [`config`]: Configuration structures for all test parameters
 //! - [`report`]: Session report generation and export (JSON, CSV, Markdown, Text)
+//! - [`capture`]: Recording and replay of raw key event streams
+//! - [`profile`]: Acceptance profiles for pass/fail grading
+//! - [`hwdb`]: udev hwdb keymap generation for kernel-level key remapping
+//! - `selftest`: uinput loopback calibration of the measurement pipeline (Linux)
 //!
 //! ## Example
 //!
@@ -35,14 +42,20 @@
 //! // Process events and track statistics...
 //! ```
 
+pub mod capture;
 pub mod config;
+pub mod hwdb;
 pub mod keyboard;
 #[cfg(target_os = "linux")]
 pub mod mapper;
+pub mod profile;
 pub mod report;
+#[cfg(target_os = "linux")]
+pub mod selftest;
 pub mod tests;
 pub mod ui;
 pub mod utils;
 
 pub use config::{config_path, Config, ConfigError};
+pub use profile::AcceptanceProfile;
 pub use report::SessionReport;
@@ -12,6 +12,34 @@
 //! sudo keyboard-testkit --mapper-uninstall           # Remove systemd service
 //! sudo keyboard-testkit --list-presets               # Show available presets
 //! ```
+//!
+//! ## Session Capture
+//!
+//! Record a session's raw key events and replay them later through all tests:
+//! ```bash
+//! keyboard-testkit --record flaky.kbcap              # Record while testing
+//! keyboard-testkit --replay flaky.kbcap --speed 4    # Replay at 4x speed
+//! ```
+//!
+//! ## Headless Mode
+//!
+//! Run the test suite without a UI for CI and production lines. The exit code
+//! is 0 when no test reports an error, 1 when any does, and 2 when the session
+//! could not run at all:
+//! ```bash
+//! sudo keyboard-testkit --headless --duration 30 --report unit-0042.json
+//! keyboard-testkit --headless --replay flaky.kbcap --report flaky.md
+//! ```
+//!
+//! `--self-test` plays scripted input through a uinput virtual keyboard and
+//! checks the polling rate, bounce and rollover tests report what was sent,
+//! with the same exit codes.
+//!
+//! With `--profile <name|file>` the session is graded against an acceptance
+//! profile instead, and the exit code follows the profile verdict.
+//!
+//! `--matrix <name|file>` loads a switch matrix definition so the rollover
+//! ghosting check can predict and explain ghosted or blocked chords.
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
@@ -24,10 +52,15 @@ macro_rules! debug   { ($($arg:tt)*) => { if cfg!(debug_assertions) { eprintln!(
 
 use crossterm::{
     event::{
-        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode as CtKeyCode, KeyModifiers,
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode as CtKeyCode, KeyEventKind,
+        KeyModifiers, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
+        PushKeyboardEnhancementFlags,
     },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
 use ratatui::{
     backend::CrosstermBackend,
@@ -43,24 +76,36 @@ use std::sync::mpsc;
 use std::sync::Arc;
 
 use keyboard_testkit::{
+    capture::{Capture, CaptureHeader, CaptureRecorder, ReplayListener},
     config::Config,
-    keyboard::{KeyEvent, KeyboardListener},
+    keyboard::{
+        matrix::KeyboardMatrix, shifted_key, DeviceInfo, InputFidelity, KeyEvent, KeyboardListener,
+        RepeatSettings,
+    },
+    profile::AcceptanceProfile,
+    report::SessionReport,
     ui::{
-        App, AppState, AppView, HelpPanel, KeyboardVisual, ResultsPanel, SettingsPanel,
+        App, AppState, AppView, DevicePanel, HelpPanel, KeyboardVisual, ResultsPanel, SettingsPanel,
         ShortcutOverlay, StatusBar, TabBar,
     },
 };
 
 #[cfg(target_os = "linux")]
-use keyboard_testkit::keyboard::{evdev_status, EvdevListener};
+use keyboard_testkit::keyboard::{
+    evdev_status, hid, keymap, read_capabilities, CaptureOptions, DeviceChange, EvdevCapture,
+    EvdevListener, KeyCode,
+};
+#[cfg(target_os = "linux")]
+use keyboard_testkit::tests::KeyStateDesync;
 
 #[cfg(target_os = "linux")]
-use keyboard_testkit::mapper;
+use keyboard_testkit::{hwdb, mapper, selftest};
 
 /// Restore the terminal to its original state.
 ///
 /// Called from the panic hook, signal handler cleanup, and normal exit.
 fn restore_terminal() {
+    pop_keyboard_enhancement();
     let _ = disable_raw_mode();
     let _ = execute!(
         std::io::stdout(),
@@ -69,6 +114,34 @@ fn restore_terminal() {
     );
 }
 
+/// Whether the kitty keyboard protocol flags were pushed and need popping.
+static KEYBOARD_ENHANCED: AtomicBool = AtomicBool::new(false);
+
+/// Ask the terminal for press/repeat/release events and distinct modifier
+/// keys (kitty keyboard protocol). Returns false when it is not supported.
+///
+/// Alternate (shifted) keys are deliberately not requested: they would turn
+/// Shift+1 into `'!'`, which has no scancode, and break press/release pairing
+/// for the tests. UI shortcuts fold Shift in with `shifted_key` instead.
+fn enable_keyboard_enhancement() -> bool {
+    if !matches!(supports_keyboard_enhancement(), Ok(true)) {
+        return false;
+    }
+    let flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+        | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+        | KeyboardEnhancementFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES;
+    let pushed = execute!(std::io::stdout(), PushKeyboardEnhancementFlags(flags)).is_ok();
+    KEYBOARD_ENHANCED.store(pushed, Ordering::SeqCst);
+    pushed
+}
+
+/// Undo `enable_keyboard_enhancement`, if it took effect
+fn pop_keyboard_enhancement() {
+    if KEYBOARD_ENHANCED.swap(false, Ordering::SeqCst) {
+        let _ = execute!(std::io::stdout(), PopKeyboardEnhancementFlags);
+    }
+}
+
 /// Global flag for signal handler to indicate shutdown.
 static SIGNAL_RUNNING: std::sync::OnceLock<Arc<AtomicBool>> = std::sync::OnceLock::new();
 
@@ -102,57 +175,176 @@ fn install_signal_handler(running: Arc<AtomicBool>) {
 /// Parse CLI arguments and return the mode to run
 fn parse_args() -> CliMode {
     let args: Vec<String> = std::env::args().collect();
+    parse_args_from(&args).unwrap_or_else(CliMode::Usage)
+}
+
+/// Parse a command line; a malformed option is returned as its usage error
+fn parse_args_from(args: &[String]) -> std::result::Result<CliMode, String> {
+    let path = |v: &str| Some(std::path::PathBuf::from(v));
+    let text = |v: &str| Some(v.to_string());
 
     // Check for mapper-related flags
     if args.iter().any(|a| a == "--mapper") {
-        let preset = args
-            .windows(2)
-            .find(|w| w[0] == "--preset")
-            .map(|w| w[1].clone());
+        let preset = option_value(args, "--preset", "a preset name or file", text)?;
 
-        let device = args
-            .windows(2)
-            .find(|w| w[0] == "--device")
-            .map(|w| std::path::PathBuf::from(&w[1]));
+        // Repeat --device to grab several devices with the same mappings
+        let devices = option_values(args, "--device", "an input device path", path)?;
 
-        return CliMode::Mapper { preset, device };
+        return Ok(CliMode::Mapper { preset, devices });
     }
 
     if args.iter().any(|a| a == "--mapper-install") {
-        let preset = args
-            .windows(2)
-            .find(|w| w[0] == "--preset")
-            .map(|w| w[1].clone());
-        return CliMode::MapperInstall { preset };
+        let preset = option_value(args, "--preset", "a preset name or file", text)?;
+        return Ok(CliMode::MapperInstall { preset });
     }
 
     if args.iter().any(|a| a == "--mapper-uninstall") {
-        return CliMode::MapperUninstall;
+        return Ok(CliMode::MapperUninstall);
     }
 
     if args.iter().any(|a| a == "--list-presets") {
-        return CliMode::ListPresets;
+        return Ok(CliMode::ListPresets);
     }
 
     if args.iter().any(|a| a == "--list-devices") {
-        return CliMode::ListDevices;
+        return Ok(CliMode::ListDevices);
+    }
+
+    if args.iter().any(|a| a == "--hid-info") {
+        return Ok(CliMode::HidInfo);
+    }
+
+    let rt_priority = option_value(args, "--rt-priority", "a priority from 1 to 99", |v| {
+        v.parse::<i32>().ok().filter(|p| (1..=99).contains(p))
+    })?;
+
+    if args.iter().any(|a| a == "--self-test") {
+        return Ok(CliMode::SelfTest { rt_priority });
     }
 
     if args.iter().any(|a| a == "--help" || a == "-h") {
-        return CliMode::Help;
+        return Ok(CliMode::Help);
     }
 
-    CliMode::Tui
+    let record = option_value(args, "--record", "a capture file path", path)?;
+    let replay = option_value(args, "--replay", "a capture file path", path)?;
+
+    // "max" replays as fast as possible (speed 0)
+    let speed = option_value(args, "--speed", "a number above 0 or max", |v| {
+        if v == "max" {
+            return Some(0.0);
+        }
+        v.parse::<f64>().ok().filter(|x| x.is_finite() && *x > 0.0)
+    })?;
+
+    let duration_secs = option_value(
+        args,
+        "--duration",
+        "a whole number of seconds above 0",
+        |v| v.parse::<u64>().ok().filter(|&secs| secs > 0),
+    )?;
+
+    let report = option_value(args, "--report", "a report file path", path)?;
+    let profile = option_value(args, "--profile", "a profile name or file", text)?;
+    let matrix = option_value(args, "--matrix", "a matrix name or file", text)?;
+    let device = option_value(args, "--device", "an input device path", text)?;
+
+    let options = SessionOptions {
+        record,
+        replay,
+        speed,
+        duration_secs,
+        report,
+        profile,
+        matrix,
+        device,
+        rt_priority,
+    };
+
+    if args.iter().any(|a| a == "--headless") {
+        return Ok(CliMode::Headless(options));
+    }
+
+    Ok(CliMode::Tui(options))
+}
+
+/// Value of `flag`, if given, checked by `parse`; a missing or rejected
+/// value is a usage error naming what was `expected`
+fn option_value<T>(
+    args: &[String],
+    flag: &str,
+    expected: &str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> std::result::Result<Option<T>, String> {
+    Ok(option_values(args, flag, expected, parse)?.into_iter().next())
+}
+
+/// Values of every occurrence of a repeatable `flag`, checked like
+/// [`option_value`]. Another flag in the value's place counts as missing.
+fn option_values<T>(
+    args: &[String],
+    flag: &str,
+    expected: &str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> std::result::Result<Vec<T>, String> {
+    let mut values = Vec::new();
+    for (position, _) in args.iter().enumerate().filter(|(_, a)| *a == flag) {
+        match args.get(position + 1) {
+            Some(value) if !value.starts_with("--") => {
+                let parsed = parse(value).ok_or_else(|| {
+                    format!("invalid {} '{}': expected {}", flag, value, expected)
+                })?;
+                values.push(parsed);
+            }
+            _ => return Err(format!("{} needs a value: {}", flag, expected)),
+        }
+    }
+    Ok(values)
 }
 
+/// Event source and capture options for a test session
+struct SessionOptions {
+    /// Write every key event to this capture file
+    record: Option<std::path::PathBuf>,
+    /// Feed events from this capture file instead of the keyboard
+    replay: Option<std::path::PathBuf>,
+    /// Replay speed multiplier (0 = as fast as possible)
+    speed: Option<f64>,
+    /// Headless session length in seconds
+    duration_secs: Option<u64>,
+    /// Headless report output path
+    report: Option<std::path::PathBuf>,
+    /// Acceptance profile name or file to grade the session against
+    profile: Option<String>,
+    /// Switch matrix name or file for ghosting predictions
+    matrix: Option<String>,
+    /// Only test events from this device path
+    device: Option<String>,
+    /// SCHED_FIFO priority for the evdev capture thread
+    rt_priority: Option<i32>,
+}
+
+/// Headless exit code: suite ran and passed (no test error, or profile PASS)
+const EXIT_PASS: i32 = 0;
+/// Headless exit code: a test reported an error, or the profile verdict is FAIL
+const EXIT_FAIL: i32 = 1;
+/// Headless exit code: the session could not run (no input, bad capture, ...)
+const EXIT_SETUP_ERROR: i32 = 2;
+
+/// How often held keys are compared with the kernel's state (EVIOCGKEY)
+#[cfg(target_os = "linux")]
+const KERNEL_STATE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
 /// CLI operating mode
 enum CliMode {
     /// Normal TUI mode
-    Tui,
+    Tui(SessionOptions),
+    /// Run the test suite without a UI and exit with a pass/fail code
+    Headless(SessionOptions),
     /// Run as key mapper daemon
     Mapper {
         preset: Option<String>,
-        device: Option<std::path::PathBuf>,
+        devices: Vec<std::path::PathBuf>,
     },
     /// Install mapper as systemd service
     MapperInstall { preset: Option<String> },
@@ -162,26 +354,34 @@ enum CliMode {
     ListPresets,
     /// List input devices
     ListDevices,
+    /// Show parsed HID report descriptors for detected keyboards
+    HidInfo,
+    /// Check the measurements against a uinput loopback keyboard
+    SelfTest { rt_priority: Option<i32> },
     /// Show help
     Help,
+    /// Reject a malformed command line
+    Usage(String),
 }
 
 fn main() -> Result<()> {
     let mode = parse_args();
 
-    match mode {
+    let options = match mode {
         CliMode::Help => {
             print_help();
             return Ok(());
         }
 
+        CliMode::Usage(message) => {
+            eprintln!("Error: {}", message);
+            eprintln!("Run keyboard-testkit --help for usage.");
+            std::process::exit(EXIT_SETUP_ERROR);
+        }
+
         #[cfg(target_os = "linux")]
         CliMode::ListPresets => {
-            println!("Available key mapping presets:\n");
-            for (name, desc) in mapper::MapperPreset::available() {
-                println!("  {:15} {}", name, desc);
-            }
-            println!("\nUsage: keyboard-testkit --mapper --preset <name>");
+            print_presets();
             return Ok(());
         }
 
@@ -191,9 +391,9 @@ fn main() -> Result<()> {
             match mapper::find_mapper_devices(None) {
                 Ok(devices) => {
                     for (path, name) in &devices {
-                        println!("  {} - {}", path.display(), name);
+                        print_device_capabilities(path, name);
                     }
-                    println!("\nUsage: keyboard-testkit --mapper --device <path>");
+                    println!("Usage: keyboard-testkit --mapper --device <path>");
                 }
                 Err(e) => {
                     println!("Error: {}", e);
@@ -204,14 +404,25 @@ fn main() -> Result<()> {
         }
 
         #[cfg(target_os = "linux")]
-        CliMode::Mapper { preset, device } => {
+        CliMode::HidInfo => {
+            print_hid_info();
+            return Ok(());
+        }
+
+        #[cfg(target_os = "linux")]
+        CliMode::SelfTest { rt_priority } => {
+            std::process::exit(run_self_test(rt_priority));
+        }
+
+        #[cfg(target_os = "linux")]
+        CliMode::Mapper { preset, devices } => {
             info!("Keyboard TestKit v{} — Mapper Daemon", env!("CARGO_PKG_VERSION"));
 
             let running = Arc::new(AtomicBool::new(true));
             install_signal_handler(running.clone());
 
             if let Err(e) =
-                mapper::run_mapper(preset.as_deref(), device, &[], running)
+                mapper::run_mapper(preset.as_deref(), &devices, &[], running)
             {
                 error!("Mapper error: {}", e);
                 return Err(e.into());
@@ -249,10 +460,25 @@ fn main() -> Result<()> {
             return Ok(());
         }
 
-        CliMode::Tui => {
-            // Fall through to normal TUI mode
+        #[cfg(not(target_os = "linux"))]
+        CliMode::HidInfo => {
+            eprintln!("HID descriptor inspection is only supported on Linux.");
+            return Ok(());
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        CliMode::SelfTest { .. } => {
+            eprintln!("The uinput self-test is only supported on Linux.");
+            std::process::exit(EXIT_SETUP_ERROR);
+        }
+
+        CliMode::Headless(options) => {
+            std::process::exit(run_headless(options));
         }
-    }
+
+        // Fall through to normal TUI mode
+        CliMode::Tui(options) => options,
+    };
 
     // Normal TUI mode
     info!("Keyboard TestKit v{}", env!("CARGO_PKG_VERSION"));
@@ -277,9 +503,10 @@ fn main() -> Result<()> {
     install_signal_handler(running.clone());
 
     // Run the application; cleanup runs regardless of success or failure
-    let result = run_app(&mut terminal, running);
+    let result = run_app(&mut terminal, running, options);
 
     // Cleanup terminal — always runs, even after signal or error
+    pop_keyboard_enhancement();
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
@@ -306,15 +533,30 @@ fn print_help() {
     println!();
     println!("OPTIONS:");
     println!("  -h, --help                    Show this help message");
+    println!("  --record <file>               Record raw key events to a capture file");
+    println!("  --replay <file>               Replay a capture file instead of the keyboard");
+    println!("  --speed <x|max>               Replay speed multiplier (default 1)");
+    println!("  --matrix <name|file>          Load a switch matrix to predict ghosting");
+    println!("  --device <path>               Only test events from this input device");
+    println!("  --rt-priority <1-99>          Run evdev capture with SCHED_FIFO priority (root)");
+    println!();
+    println!("HEADLESS OPTIONS:");
+    println!("  --headless                    Run all tests without a UI; exit 1 on any error");
+    println!("  --duration <secs>             Session length (default: polling test duration)");
+    println!("  --report <file>               Report path (.json, .csv, .md or .txt)");
+    println!("  --replay <file>               Test a capture instead of the keyboard");
+    println!("  --profile <name|file>         Grade against an acceptance profile (also in TUI)");
+    println!("  --self-test                   Check measurements with a virtual keyboard (Linux, root)");
     println!();
     println!("MAPPER OPTIONS (Linux only):");
     println!("  --mapper                      Run as a key mapping daemon");
-    println!("  --preset <name>               Use a vendor preset (e.g. asus-g14)");
-    println!("  --device <path>               Target specific input device");
+    println!("  --preset <name|file>          Use a vendor preset (e.g. asus-g14) or preset file");
+    println!("  --device <path>               Target specific input device (repeatable)");
     println!("  --mapper-install              Install as a systemd service (runs on boot)");
     println!("  --mapper-uninstall            Remove the systemd service");
-    println!("  --list-presets                List available vendor presets");
+    println!("  --list-presets                List built-in and file presets (and invalid files)");
     println!("  --list-devices                List detected input devices");
+    println!("  --hid-info                    Show HID report descriptors (boot/NKRO, vendor pages)");
     println!();
     println!("EXAMPLES:");
     println!("  # Map ASUS G14 special keys");
@@ -326,6 +568,18 @@ fn print_help() {
     println!("  # Use a specific device");
     println!("  sudo keyboard-testkit --mapper --device /dev/input/event5");
     println!();
+    println!("  # Production-line check: 30s on the live keyboard, non-zero exit on failure");
+    println!("  sudo keyboard-testkit --headless --duration 30 --report unit.json");
+    println!();
+    println!("  # Grade against the SKU spec in ~/.config/keyboard-testkit/profiles/k70.toml");
+    println!("  sudo keyboard-testkit --headless --profile k70 --report unit.md");
+    println!();
+    println!("  # Check this machine's polling/bounce/rollover numbers before testing a keyboard");
+    println!("  sudo keyboard-testkit --self-test");
+    println!();
+    println!("  # Re-run all tests against a recorded session, as fast as possible");
+    println!("  keyboard-testkit --replay flaky.kbcap --speed max");
+    println!();
     println!("  # Custom mappings via config file");
     println!("  # Edit ~/.config/keyboard-testkit/config.toml:");
     println!("  # [oem_keys]");
@@ -337,11 +591,13 @@ fn print_help() {
     println!("  Space            Pause/Resume");
     println!("  q/Esc            Quit");
     println!("  ?                Help");
+    println!("  Ctrl+Q/N/P/R/E   Quit/next/prev/reset/export in the Coverage view");
 }
 
 fn run_app(
     terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
     running: Arc<AtomicBool>,
+    options: SessionOptions,
 ) -> Result<Option<(u64, String)>> {
     // TODO: Support --config <path> CLI argument for custom config locations.
     let config = Config::load().unwrap_or_else(|e| {
@@ -356,6 +612,13 @@ fn run_app(
         config.ui.theme,
     );
     let mut app = App::new(config.clone());
+    if let Some(name) = &options.profile {
+        app.profile = Some(load_profile(name)?);
+    }
+    if let Some(name) = &options.matrix {
+        app.rollover_test.set_matrix(Some(load_matrix(name)?));
+    }
+    app.device_filter = options.device.clone();
 
     // Create keyboard event channel
     let (event_tx, event_rx) = mpsc::channel::<KeyEvent>();
@@ -363,16 +626,38 @@ fn run_app(
     // Create keyboard listener (crossterm-based fallback for non-Linux)
     let mut listener = KeyboardListener::new(event_tx.clone());
 
-    // On Linux, try to use evdev for better OEM key detection
+    // Replay a capture file in place of the live keyboard
+    let mut replay = match &options.replay {
+        Some(path) => {
+            app.set_status(format!("Replaying {}", path.display()));
+            Some(load_replay(
+                path,
+                options.speed.unwrap_or(1.0),
+                event_tx.clone(),
+            )?)
+        }
+        None => None,
+    };
+
+    // On Linux, try to use evdev for better OEM key detection. Devices are
+    // read on a dedicated capture thread, independent of the render rate.
     #[cfg(target_os = "linux")]
-    let mut evdev_listener = {
-        match EvdevListener::try_new(event_tx) {
-            Some(evdev) => {
-                let status = evdev_status();
+    let evdev_capture = if replay.is_some() {
+        None
+    } else {
+        match EvdevCapture::spawn(EvdevListener::new, capture_options(options.rt_priority)) {
+            Ok(capture) => {
+                let status = match capture.priority_error() {
+                    Some(e) => format!("{} (real-time priority unavailable: {})", evdev_status(), e),
+                    None => evdev_status(),
+                };
                 app.set_status(format!("Evdev: {}", status));
-                Some(evdev)
+                if let Ok(devices) = hid::inspect_keyboards() {
+                    app.apply_hid_devices(&devices);
+                }
+                Some(capture)
             }
-            None => {
+            Err(_) => {
                 app.set_status(
                     "Evdev unavailable - using crossterm fallback (limited OEM key support)".to_string(),
                 );
@@ -382,13 +667,71 @@ fn run_app(
     };
 
     #[cfg(target_os = "linux")]
-    let use_evdev = evdev_listener.is_some();
+    let use_evdev = evdev_capture.is_some();
 
     #[cfg(not(target_os = "linux"))]
     let use_evdev = false;
 
+    // Without evdev, real releases need the kitty keyboard protocol
+    let fidelity = match &replay {
+        Some(replay_listener) => replay_listener.fidelity(),
+        None if use_evdev => Some(InputFidelity::Evdev),
+        None if enable_keyboard_enhancement() => Some(InputFidelity::Enhanced),
+        None => Some(InputFidelity::Basic),
+    };
+    if let Some(fidelity) = fidelity {
+        listener.set_fidelity(fidelity);
+    }
+    app.input_fidelity = fidelity;
+
+    // Devices events can be tagged with, for the picker and captures, and
+    // the auto-repeat settings the typematic test compares against
+    #[cfg(target_os = "linux")]
+    let (session_devices, repeat_settings) = match (&replay, &evdev_capture) {
+        (Some(replay_listener), _) => (replay_listener.devices(), replay_listener.repeat_settings()),
+        (None, Some(capture)) => {
+            let evdev = capture.listener();
+            (evdev.devices(), evdev.repeat_settings(options.device.as_deref()))
+        }
+        (None, None) => (Vec::new(), None),
+    };
+    #[cfg(not(target_os = "linux"))]
+    let (session_devices, repeat_settings) = replay
+        .as_ref()
+        .map(|r| (r.devices(), r.repeat_settings()))
+        .unwrap_or_default();
+    app.register_devices(session_devices.clone());
+    app.typematic_test.set_configured(repeat_settings);
+    #[cfg(target_os = "linux")]
+    if let Some(ref capture) = evdev_capture {
+        load_capabilities(&mut app, &capture.listener());
+    }
+
+    // Show which keyboard the LED check will drive before it starts
+    #[cfg(target_os = "linux")]
+    if let Some((device, leds)) = evdev_capture
+        .as_ref()
+        .and_then(|capture| capture.listener().led_device(options.device.as_deref()))
+    {
+        app.led_test.set_device(Some(device), leds);
+    }
+
+    // Record the session's event stream if requested
+    let mut recorder = match &options.record {
+        Some(path) => {
+            let source = match fidelity {
+                Some(fidelity) if replay.is_none() => fidelity.capture_source(),
+                _ => "replay",
+            };
+            Some(create_recorder(path, source, session_devices, repeat_settings)?)
+        }
+        None => None,
+    };
+
     // Main loop
     let tick_rate = config.refresh_interval();
+    #[cfg(target_os = "linux")]
+    let mut last_kernel_check = std::time::Instant::now();
 
     loop {
         // Check if Ctrl+C was pressed
@@ -397,23 +740,58 @@ fn run_app(
         }
 
         // Poll keyboard state - use evdev on Linux if available, otherwise fallback
-        #[cfg(target_os = "linux")]
-        {
-            if let Some(ref mut evdev) = evdev_listener {
-                evdev.poll();
-            } else {
-                listener.poll();
+        if let Some(ref mut replay_listener) = replay {
+            let was_finished = replay_listener.is_finished();
+            replay_listener.poll();
+            if !was_finished && replay_listener.is_finished() {
+                app.set_status(format!(
+                    "Replay complete: {} event(s)",
+                    replay_listener.total_events()
+                ));
+            }
+        } else {
+            #[cfg(target_os = "linux")]
+            {
+                if let Some(ref capture) = evdev_capture {
+                    let changes = capture.listener().take_changes();
+                    if !changes.is_empty() {
+                        apply_device_changes(&mut app, changes);
+                        load_capabilities(&mut app, &capture.listener());
+                    }
+                    update_dropped_events(&mut app, capture);
+                } else {
+                    listener.poll();
+                }
             }
-        }
 
-        #[cfg(not(target_os = "linux"))]
-        listener.poll();
+            #[cfg(not(target_os = "linux"))]
+            listener.poll();
+        }
 
         // Process keyboard events
-        while let Ok(key_event) = event_rx.try_recv() {
+        #[cfg(target_os = "linux")]
+        let captured = evdev_capture.iter().flat_map(|capture| capture.try_iter());
+        #[cfg(not(target_os = "linux"))]
+        let captured = std::iter::empty();
+        for key_event in captured.chain(event_rx.try_iter()) {
+            if let Some(ref mut rec) = recorder {
+                if let Err(e) = rec.record(&key_event) {
+                    error!("Capture write failed: {}", e);
+                    app.set_status(format!("Recording stopped: {}", e));
+                    recorder = None;
+                }
+            }
             app.process_event(&key_event);
         }
 
+        #[cfg(target_os = "linux")]
+        if let Some(ref capture) = evdev_capture {
+            if last_kernel_check.elapsed() >= KERNEL_STATE_CHECK_INTERVAL {
+                last_kernel_check = std::time::Instant::now();
+                check_kernel_key_state(&mut app, capture);
+            }
+        }
+
         // Get current theme
         let colors = app.theme_colors;
 
@@ -445,9 +823,12 @@ fn run_app(
                 .border_style(Style::default().fg(colors.dim));
             let kb_inner = kb_block.inner(chunks[1]);
             frame.render_widget(kb_block, chunks[1]);
-            let kb_visual = KeyboardVisual::new(&app.keyboard_state)
+            let mut kb_visual = KeyboardVisual::new(&app.keyboard_state)
                 .theme(colors)
                 .layout(app.keyboard_layout);
+            if app.view == AppView::Coverage {
+                kb_visual = kb_visual.coverage(&app.coverage_test);
+            }
             frame.render_widget(kb_visual, kb_inner);
 
             // Main content area
@@ -455,6 +836,10 @@ fn run_app(
                 AppView::Help => {
                     frame.render_widget(HelpPanel::new().theme(colors), chunks[2]);
                 }
+                AppView::Devices => {
+                    let panel = DevicePanel::new(&app.devices, app.devices_selected).theme(colors);
+                    frame.render_widget(panel, chunks[2]);
+                }
                 AppView::Settings => {
                     let items = app.settings_items();
                     let panel =
@@ -495,20 +880,25 @@ fn run_app(
             if let Event::Key(key) = event::read()? {
                 // When evdev is not in use, feed crossterm key events to the
                 // listener for test processing (polling rate, rollover, etc.)
-                if !use_evdev {
-                    listener.send_press(key.code);
+                if !use_evdev && replay.is_none() {
+                    listener.send_key(&key);
+                    if app.input_fidelity != Some(listener.fidelity()) {
+                        app.input_fidelity = Some(listener.fidelity());
+                    }
                 }
 
-                // Settings view has its own key handling
-                if app.view == AppView::Settings {
+                // Shortcuts like 'S' and 'R' need the shifted character
+                let key = shifted_key(key);
+                if key.kind == KeyEventKind::Release {
+                    // Releases (kitty protocol) only feed the tests
+                } else if app.view == AppView::Settings {
+                    // Settings view has its own key handling
                     match key.code {
                         CtKeyCode::Char('q') | CtKeyCode::Esc => {
                             app.view = AppView::Dashboard; // Back to dashboard
                         }
-                        CtKeyCode::Up => {
-                            if app.settings_selected > 0 {
-                                app.settings_selected -= 1;
-                            }
+                        CtKeyCode::Up if app.settings_selected > 0 => {
+                            app.settings_selected -= 1;
                         }
                         CtKeyCode::Down => {
                             let item_count = app.settings_items().len();
@@ -530,6 +920,33 @@ fn run_app(
                         }
                         _ => {}
                     }
+                } else if app.view == AppView::Devices {
+                    match key.code {
+                        CtKeyCode::Char('q') | CtKeyCode::Esc => {
+                            app.view = AppView::Dashboard;
+                        }
+                        CtKeyCode::Up if app.devices_selected > 0 => {
+                            app.devices_selected -= 1;
+                        }
+                        CtKeyCode::Down if app.devices_selected < app.devices.len() => {
+                            app.devices_selected += 1;
+                        }
+                        CtKeyCode::Enter => app.select_highlighted_device(),
+                        CtKeyCode::Tab => app.next_view(),
+                        _ => {}
+                    }
+                } else if app.view == AppView::Coverage {
+                    // Every plain key is under test here, so controls need Ctrl
+                    if key.modifiers.contains(KeyModifiers::CONTROL) {
+                        match key.code {
+                            CtKeyCode::Char('q') => app.quit(),
+                            CtKeyCode::Char('n') => app.next_view(),
+                            CtKeyCode::Char('p') => app.prev_view(),
+                            CtKeyCode::Char('r') => app.reset_current(),
+                            CtKeyCode::Char('e') => export_report(&mut app),
+                            _ => {}
+                        }
+                    }
                 } else {
                     match key.code {
                         CtKeyCode::Char('q') | CtKeyCode::Esc => {
@@ -546,6 +963,9 @@ fn run_app(
                         CtKeyCode::Char('S') => {
                             app.view = AppView::Settings;
                         }
+                        CtKeyCode::Char('d') => {
+                            app.view = AppView::Devices;
+                        }
                         CtKeyCode::Char('1') if app.shortcuts_enabled => {
                             app.view = AppView::Dashboard
                         }
@@ -576,53 +996,68 @@ fn run_app(
                         CtKeyCode::Char('0') if app.shortcuts_enabled => {
                             app.view = AppView::Help
                         }
-                        CtKeyCode::Char('v') => {
-                            if app.view == AppView::Virtual {
-                                app.virtual_test.request_virtual_test();
-                            }
+                        CtKeyCode::Char('g') if app.view == AppView::Rollover => {
+                            app.start_ghosting_guide();
                         }
-                        CtKeyCode::Char('a') => {
-                            if app.view == AppView::OemKeys {
-                                app.add_oem_mapping_for_last_unknown();
-                            }
+                        CtKeyCode::Char('v') if app.view == AppView::Virtual => {
+                            app.virtual_test.request_virtual_test();
                         }
-                        CtKeyCode::Char('f') => {
-                            if app.view == AppView::OemKeys {
-                                app.cycle_fn_mode();
-                            }
+                        CtKeyCode::Char('a') if app.view == AppView::OemKeys => {
+                            app.add_oem_mapping_for_last_unknown();
                         }
-                        CtKeyCode::Char('c') => {
-                            if app.view == AppView::OemKeys {
-                                app.clear_oem_mappings();
-                            }
+                        CtKeyCode::Char('f') if app.view == AppView::OemKeys => {
+                            app.cycle_fn_mode();
+                        }
+                        CtKeyCode::Char('c') if app.view == AppView::OemKeys => {
+                            app.clear_oem_mappings();
+                        }
+                        #[cfg(target_os = "linux")]
+                        CtKeyCode::Char('l') if app.view == AppView::Leds => {
+                            start_led_check(
+                                &mut app,
+                                evdev_capture.as_ref().map(|c| c.listener()).as_deref(),
+                            );
+                        }
+                        CtKeyCode::Char('y') if app.view == AppView::Leds => {
+                            app.answer_led_check(true);
+                        }
+                        CtKeyCode::Char('n') if app.view == AppView::Leds => {
+                            app.answer_led_check(false);
+                        }
+                        #[cfg(target_os = "linux")]
+                        CtKeyCode::Char('w') if app.view == AppView::OemKeys => {
+                            export_hwdb(&mut app, false);
+                        }
+                        #[cfg(target_os = "linux")]
+                        CtKeyCode::Char('W') if app.view == AppView::OemKeys => {
+                            export_hwdb(&mut app, true);
                         }
                         CtKeyCode::Char('?') => app.view = AppView::Help,
-                        CtKeyCode::Char(' ') => app.toggle_pause(),
-                        CtKeyCode::Char('r') => app.reset_current(),
-                        CtKeyCode::Char('R') => app.reset_all(),
-                        CtKeyCode::Char('e') => {
-                            let filename = {
-                                use std::time::SystemTime;
-                                let secs = SystemTime::now()
-                                    .duration_since(SystemTime::UNIX_EPOCH)
-                                    .unwrap_or_default()
-                                    .as_secs();
-                                format!("keyboard_report_{}.json", secs)
-                            };
-                            match app.export_report(&filename) {
-                                Ok(_) => info!("Report exported to {}", filename),
-                                Err(e) => {
-                                    error!("Export failed: {}", e);
-                                    app.set_status(format!("Export failed: {}", e));
-                                }
+                        CtKeyCode::Char(' ') => {
+                            app.toggle_pause();
+                            // Events were ignored while paused: start the
+                            // kernel comparison over from the kernel's state
+                            #[cfg(target_os = "linux")]
+                            if let (AppState::Running, Some(capture)) = (app.state, &evdev_capture) {
+                                resync_kernel_key_state(&mut app, capture);
+                                last_kernel_check = std::time::Instant::now();
                             }
                         }
+                        CtKeyCode::Char('r') => app.reset_current(),
+                        CtKeyCode::Char('R') => app.reset_all(),
+                        CtKeyCode::Char('e') => export_report(&mut app),
                         _ => {}
                     }
                 }
             }
         }
 
+        // Perform LED writes queued by the LED check
+        #[cfg(target_os = "linux")]
+        if let Some(ref capture) = evdev_capture {
+            run_led_commands(&mut app, &capture.listener());
+        }
+
         // Execute pending virtual key sends
         if app.virtual_test.has_pending_send() {
             match app.virtual_test.execute_virtual_send() {
@@ -643,6 +1078,10 @@ fn run_app(
         }
     }
 
+    if let Some(rec) = recorder {
+        info!("Recorded {} event(s) to capture", rec.event_count());
+    }
+
     info!(
         "Session ended: {} events in {}",
         app.total_events,
@@ -650,3 +1089,691 @@ fn run_app(
     );
     Ok(Some((app.total_events, app.elapsed_formatted())))
 }
+
+/// Export the session report from the TUI, reporting the outcome in the status bar
+fn export_report(app: &mut App) {
+    let filename = default_report_filename();
+    match app.export_report(&filename) {
+        Ok(_) => info!("Report exported to {}", filename),
+        Err(e) => {
+            error!("Export failed: {}", e);
+            app.set_status(format!("Export failed: {}", e));
+        }
+    }
+}
+
+/// Pick the keyboard whose LEDs to check (the device under test, or the
+/// first with LEDs) and start the check from its current LED state
+#[cfg(target_os = "linux")]
+fn start_led_check(app: &mut App, evdev: Option<&EvdevListener>) {
+    let found = evdev.and_then(|ev| ev.led_device(app.device_filter.as_deref()));
+    let lit = match (&found, evdev) {
+        (Some((device, _)), Some(ev)) => ev.led_state(&device.path).unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    let (device, leds) = found.map_or((None, Vec::new()), |(d, l)| (Some(d), l));
+    app.led_test.set_device(device, leds);
+    app.start_led_check(lit);
+}
+
+/// Write queued LED changes to the device and report the read-back state
+#[cfg(target_os = "linux")]
+fn run_led_commands(app: &mut App, evdev: &EvdevListener) {
+    let Some(path) = app.led_test.device().map(|d| d.path.clone()) else {
+        return;
+    };
+    for command in app.led_test.take_commands() {
+        let result = evdev
+            .set_led(&path, command.led, command.on)
+            .and_then(|_| evdev.led_state(&path))
+            .map(|lit| lit.contains(&command.led))
+            .map_err(|e| e.to_string());
+        if let Err(e) = &result {
+            warn!("LED write to {} failed: {}", path, e);
+        }
+        app.led_test.command_done(command, result);
+    }
+}
+
+/// Write a udev hwdb keymap for the keyboard under test, and with `apply`
+/// also install it on the running device
+#[cfg(target_os = "linux")]
+fn export_hwdb(app: &mut App, apply: bool) {
+    // A preset made for this keyboard supplies mappings the session lacks
+    let base = app
+        .selected_device()
+        .or_else(|| app.scancodes.last_device())
+        .and_then(mapper::MapperPreset::for_device)
+        .map(|p| p.mappings)
+        .unwrap_or_default();
+
+    let Some(keymap) = app.hwdb_keymap(&base) else {
+        app.set_status("No scancodes seen yet. Press the keys to remap first.".to_string());
+        return;
+    };
+    if keymap.is_empty() {
+        app.set_status(format!(
+            "No mapped key has a known scancode ({} unresolved). Press them first.",
+            keymap.unresolved.len()
+        ));
+        return;
+    }
+
+    // Without root, leave the file in the working directory to copy over
+    let written = keymap
+        .write_to(std::path::Path::new(hwdb::HWDB_DIR))
+        .or_else(|_| keymap.write_to(std::path::Path::new(".")));
+    let mut status = match written {
+        Ok(path) => {
+            info!("Wrote hwdb keymap to {}", path.display());
+            format!("Wrote {} hwdb entries to {}", keymap.entries.len(), path.display())
+        }
+        Err(e) => {
+            error!("hwdb export failed: {}", e);
+            app.set_status(format!("hwdb export failed: {}", e));
+            return;
+        }
+    };
+    if !keymap.unresolved.is_empty() {
+        status.push_str(&format!(", {} unresolved", keymap.unresolved.len()));
+    }
+
+    if apply {
+        match keymap.apply(std::path::Path::new(&keymap.device.path)) {
+            Ok(n) => status.push_str(&format!(", applied {} to {}", n, keymap.device.path)),
+            Err(e) => {
+                warn!("EVIOCSKEYCODE_V2 on {} failed: {}", keymap.device.path, e);
+                status.push_str(&format!(", apply failed: {}", e));
+            }
+        }
+    }
+    app.set_status(status);
+}
+
+/// Default file name for exported reports
+fn default_report_filename() -> String {
+    use std::time::SystemTime;
+    let secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    format!("keyboard_report_{}.json", secs)
+}
+
+/// Load a capture file and wrap it in a replay listener
+fn load_replay(
+    path: &std::path::Path,
+    speed: f64,
+    event_tx: mpsc::Sender<KeyEvent>,
+) -> Result<ReplayListener> {
+    let capture = Capture::load(path)
+        .map_err(|e| format!("Failed to load capture {}: {}", path.display(), e))?;
+    info!(
+        "Replaying {} event(s) from {} (recorded via {})",
+        capture.events.len(),
+        path.display(),
+        capture.header.source
+    );
+    Ok(ReplayListener::new(capture, speed, event_tx))
+}
+
+/// Load an acceptance profile by name or path
+fn load_profile(name_or_path: &str) -> Result<AcceptanceProfile> {
+    let profile = AcceptanceProfile::resolve(name_or_path)
+        .map_err(|e| format!("Failed to load profile {}: {}", name_or_path, e))?;
+    info!("Grading against acceptance profile '{}'", profile.name);
+    Ok(profile)
+}
+
+/// Load a keyboard matrix definition by name or path
+fn load_matrix(name_or_path: &str) -> Result<KeyboardMatrix> {
+    let matrix = KeyboardMatrix::resolve(name_or_path)
+        .map_err(|e| format!("Failed to load matrix {}: {}", name_or_path, e))?;
+    info!(
+        "Loaded keyboard matrix '{}' ({} switches)",
+        matrix.name,
+        matrix.switch_count()
+    );
+    Ok(matrix)
+}
+
+/// Capture thread settings from the command line
+#[cfg(target_os = "linux")]
+fn capture_options(rt_priority: Option<i32>) -> CaptureOptions {
+    CaptureOptions {
+        realtime_priority: rt_priority,
+        ..CaptureOptions::default()
+    }
+}
+
+/// Pick up the capture queue's overflow count and the kernel's SYN_DROPPED
+/// count, flagging new drops
+#[cfg(target_os = "linux")]
+fn update_dropped_events(app: &mut App, capture: &EvdevCapture) {
+    let dropped = capture.dropped_count();
+    if dropped > app.dropped_events {
+        app.set_status(format!("Capture queue full: {} event(s) dropped", dropped));
+    }
+    app.dropped_events = dropped;
+
+    let sync_drops = capture.listener().sync_drops();
+    if sync_drops > app.sync_drops {
+        app.set_status(format!(
+            "Kernel dropped events ({} time(s)); key state resynced",
+            sync_drops
+        ));
+    }
+    app.sync_drops = sync_drops;
+}
+
+/// List built-in and file presets, with the files that failed to load
+#[cfg(target_os = "linux")]
+fn print_presets() {
+    let catalog = mapper::PresetCatalog::load();
+
+    println!("Available key mapping presets:\n");
+    for (name, preset) in &catalog.presets {
+        println!("  {:15} {}", name, preset.description);
+        let source = match &preset.source {
+            Some(path) => path.display().to_string(),
+            None => "built-in".to_string(),
+        };
+        let rules = match_rules(preset.device_match.as_deref(), preset.vendor_id, preset.product_id);
+        println!(
+            "  {:15} {}, {}{}",
+            "",
+            preset.bindings().summary(),
+            source,
+            if rules.is_empty() {
+                String::new()
+            } else {
+                format!(", matches {}", rules)
+            }
+        );
+        for input in &preset.inputs {
+            println!(
+                "  {:15} + {} on the device matching {}",
+                "",
+                input.bindings().summary(),
+                match_rules(input.device_match.as_deref(), input.vendor_id, input.product_id)
+            );
+        }
+    }
+
+    if !catalog.errors.is_empty() {
+        println!("\nInvalid preset files (skipped):\n");
+        for (path, e) in &catalog.errors {
+            println!("  {}: {}", path.display(), e);
+        }
+    }
+
+    let dirs: Vec<String> = mapper::preset_dirs()
+        .iter()
+        .map(|d| d.display().to_string())
+        .collect();
+    println!("\nPreset files are read from: {}", dirs.join(", "));
+    println!("Usage: keyboard-testkit --mapper --preset <name|file>");
+}
+
+/// Describe a preset's device match rules, e.g. `name contains "asus" and vendor 0b05`
+#[cfg(target_os = "linux")]
+fn match_rules(pattern: Option<&str>, vendor_id: Option<u16>, product_id: Option<u16>) -> String {
+    let mut rules = Vec::new();
+    if let Some(pattern) = pattern {
+        rules.push(format!("name contains \"{}\"", pattern));
+    }
+    match (vendor_id, product_id) {
+        (Some(vendor), Some(product)) => rules.push(format!("id {:04x}:{:04x}", vendor, product)),
+        (Some(vendor), None) => rules.push(format!("vendor {:04x}", vendor)),
+        _ => {}
+    }
+    rules.join(" and ")
+}
+
+/// Print one device's identity and declared keys for `--list-devices`
+#[cfg(target_os = "linux")]
+fn print_device_capabilities(path: &std::path::Path, name: &str) {
+    /// Key ranges shown before the rest are summarized
+    const MAX_RANGES: usize = 8;
+
+    println!("  {} - {}", path.display(), name);
+    match read_capabilities(path) {
+        Ok(caps) => {
+            println!(
+                "    Bus:   {} {:04x}:{:04x} (version {:04x})",
+                caps.bus_name(),
+                caps.vendor_id,
+                caps.product_id,
+                caps.version
+            );
+            if !caps.phys.is_empty() {
+                println!("    Phys:  {}", caps.phys);
+            }
+            let ranges = caps.key_ranges();
+            let mut shown: Vec<String> = ranges
+                .iter()
+                .take(MAX_RANGES)
+                .map(|&(start, end)| {
+                    if start == end {
+                        start.to_string()
+                    } else {
+                        format!("{}-{}", start, end)
+                    }
+                })
+                .collect();
+            if ranges.len() > MAX_RANGES {
+                shown.push(format!("... {} more range(s)", ranges.len() - MAX_RANGES));
+            }
+            println!("    Keys:  {} declared [{}]", caps.keys.len(), shown.join(", "));
+        }
+        Err(e) => println!("    Capabilities unreadable: {}", e),
+    }
+    println!();
+}
+
+/// Compare the held keys of the device(s) under test with the kernel's view
+#[cfg(target_os = "linux")]
+fn check_kernel_key_state(app: &mut App, capture: &EvdevCapture) -> Vec<(KeyCode, KeyStateDesync)> {
+    let kernel = capture
+        .listener()
+        .kernel_key_state(app.device_filter.as_deref());
+    match kernel {
+        Some(kernel) => app.check_kernel_key_state(&kernel),
+        None => Vec::new(),
+    }
+}
+
+/// Re-read the held keys of the device(s) under test after a pause
+#[cfg(target_os = "linux")]
+fn resync_kernel_key_state(app: &mut App, capture: &EvdevCapture) {
+    let kernel = capture
+        .listener()
+        .kernel_key_state(app.device_filter.as_deref());
+    if let Some(kernel) = kernel {
+        app.resync_key_state(&kernel);
+    }
+}
+
+/// Attach each open keyboard's declared capabilities to its device entry
+#[cfg(target_os = "linux")]
+fn load_capabilities(app: &mut App, listener: &EvdevListener) {
+    for (device, capabilities) in listener.capabilities() {
+        app.set_device_capabilities(device, capabilities);
+    }
+}
+
+/// Report keyboards the evdev listener opened or closed since the last poll
+#[cfg(target_os = "linux")]
+fn apply_device_changes(app: &mut App, changes: Vec<DeviceChange>) {
+    for change in changes {
+        match change {
+            DeviceChange::Added(device) => app.device_connected(device),
+            DeviceChange::Removed(device) => app.device_disconnected(&device, None),
+            DeviceChange::Failed(device, e) => app.device_disconnected(&device, Some(&e)),
+        }
+    }
+}
+
+/// Create a capture file for recording the session's event stream
+fn create_recorder(
+    path: &std::path::Path,
+    source: &str,
+    devices: Vec<DeviceInfo>,
+    repeat: Option<RepeatSettings>,
+) -> Result<CaptureRecorder> {
+    let paths = devices.iter().map(|d| d.path.clone()).collect();
+    let header = CaptureHeader::new(source, paths)
+        .with_device_info(devices)
+        .with_repeat_settings(repeat);
+    let recorder = CaptureRecorder::create(path, &header)
+        .map_err(|e| format!("Failed to create capture {}: {}", path.display(), e))?;
+    info!("Recording key events to {}", path.display());
+    Ok(recorder)
+}
+
+/// Print each keyboard's parsed HID report descriptor
+#[cfg(target_os = "linux")]
+fn print_hid_info() {
+    let devices = match hid::inspect_keyboards() {
+        Ok(devices) => devices,
+        Err(e) => {
+            println!("Error: {}", e);
+            println!("Try running with sudo for device access.");
+            return;
+        }
+    };
+
+    for device in &devices {
+        println!("{} - {}", device.event_path.display(), device.name);
+        let (Some(hidraw), Some(descriptor)) = (&device.hidraw, &device.descriptor) else {
+            println!("  No HID report descriptor (not a HID device, or unreadable)\n");
+            continue;
+        };
+        println!("  Descriptor:  {} ({} bytes)", hidraw.display(), descriptor.length);
+        match descriptor.rollover() {
+            Some(hid::HidRollover::KeyArray(slots)) => println!(
+                "  Rollover:    {} keys + modifiers (key array report)",
+                slots
+            ),
+            Some(hid::HidRollover::Bitmap) => println!("  Rollover:    none (NKRO bitmap report)"),
+            None => println!("  Rollover:    unknown (no HID keyboard report)"),
+        }
+
+        let reports = descriptor.keyboard_reports();
+        if reports.is_empty() {
+            println!("  Keys:        no keyboard-page input report");
+        }
+        for report in &reports {
+            let id = report
+                .report_id
+                .map(|id| format!("report {}", id))
+                .unwrap_or_else(|| "no report ID".to_string());
+            println!("  Keys:        {}, {} bytes, {}", report.describe(), report.bytes, id);
+        }
+
+        for (page, (min, max)) in descriptor.input_usages() {
+            println!(
+                "  Usages:      {:18} 0x{:X}-0x{:X}",
+                hid::usage_page_name(page),
+                min,
+                max
+            );
+        }
+
+        for collection in descriptor.vendor_collections() {
+            let ids: Vec<String> = collection.report_ids.iter().map(|id| id.to_string()).collect();
+            println!(
+                "  Vendor:      {} usage 0x{:X}, reports [{}]",
+                hid::usage_page_name(collection.usage_page),
+                collection.usage,
+                ids.join(", ")
+            );
+        }
+        println!();
+    }
+}
+
+/// Run the test suite without a terminal UI and return the process exit code
+fn run_headless(options: SessionOptions) -> i32 {
+    info!("Keyboard TestKit v{} — Headless", env!("CARGO_PKG_VERSION"));
+
+    let running = Arc::new(AtomicBool::new(true));
+    install_signal_handler(running.clone());
+
+    let report = match run_headless_session(&options, running) {
+        Ok(report) => report,
+        Err(e) => {
+            error!("Headless session failed: {}", e);
+            return EXIT_SETUP_ERROR;
+        }
+    };
+
+    println!("Total events: {}", report.summary.total_events);
+    println!("Max rollover: {}KRO", report.summary.max_rollover);
+    println!("Issues:       {}", report.summary.issues_detected);
+    if report.metadata.dropped_events > 0 {
+        println!("Dropped:      {} event(s) (capture queue full)", report.metadata.dropped_events);
+    }
+    if report.metadata.sync_drops > 0 {
+        println!("Overruns:     {} (SYN_DROPPED, key state resynced)", report.metadata.sync_drops);
+    }
+
+    if let Some(acceptance) = &report.acceptance {
+        println!("Profile:      {}", acceptance.profile);
+        for criterion in acceptance.failures() {
+            println!(
+                "  [FAIL] {}: {} (required {})",
+                criterion.label, criterion.measured, criterion.required
+            );
+        }
+        for entry in report.session_errors() {
+            println!("  [ERR] Session: {} = {}", entry.label, entry.value);
+        }
+        return if report.passed() {
+            println!("RESULT: PASS");
+            EXIT_PASS
+        } else {
+            println!("RESULT: FAIL");
+            EXIT_FAIL
+        };
+    }
+
+    let errors = report.errors();
+    if errors.is_empty() {
+        println!("RESULT: PASS");
+        EXIT_PASS
+    } else {
+        for (category, entry) in &errors {
+            println!(
+                "  [ERR] {}: {} = {}",
+                category,
+                entry.label.trim(),
+                entry.value
+            );
+        }
+        println!("RESULT: FAIL ({} error(s))", errors.len());
+        EXIT_FAIL
+    }
+}
+
+/// Run the uinput loopback self-test and return the process exit code
+#[cfg(target_os = "linux")]
+fn run_self_test(rt_priority: Option<i32>) -> i32 {
+    info!("Keyboard TestKit v{} — Self-Test", env!("CARGO_PKG_VERSION"));
+
+    let running = Arc::new(AtomicBool::new(true));
+    install_signal_handler(running.clone());
+
+    let config = Config::load().unwrap_or_else(|e| {
+        warn!("Failed to load config: {}. Using defaults.", e);
+        Config::default()
+    });
+
+    let outcomes = match selftest::run(&config, capture_options(rt_priority), &running) {
+        Ok(outcomes) => outcomes,
+        Err(selftest::SelfTestError::Interrupted) => {
+            println!("RESULT: FAIL (interrupted)");
+            return EXIT_FAIL;
+        }
+        Err(e) => {
+            error!("Self-test failed to run: {}", e);
+            return EXIT_SETUP_ERROR;
+        }
+    };
+
+    for outcome in &outcomes {
+        println!(
+            "  [{}] {:22} expected {:>10}  measured {:>10}  ({}/{} events)",
+            if outcome.passed { "OK" } else { "FAIL" },
+            outcome.scenario,
+            outcome.expected,
+            outcome.measured,
+            outcome.events,
+            outcome.expected_events
+        );
+    }
+
+    let failed = outcomes.iter().filter(|o| !o.passed).count();
+    if failed == 0 {
+        println!("RESULT: PASS");
+        EXIT_PASS
+    } else {
+        println!("RESULT: FAIL ({} scenario(s))", failed);
+        EXIT_FAIL
+    }
+}
+
+/// Feed events through every test until the duration elapses (or the replay
+/// ends), then write the session report
+fn run_headless_session(
+    options: &SessionOptions,
+    running: Arc<AtomicBool>,
+) -> Result<SessionReport> {
+    let config = Config::load().unwrap_or_else(|e| {
+        warn!("Failed to load config: {}. Using defaults.", e);
+        Config::default()
+    });
+    let mut app = App::new(config.clone());
+    if let Some(name) = &options.profile {
+        app.profile = Some(load_profile(name)?);
+    }
+    if let Some(name) = &options.matrix {
+        app.rollover_test.set_matrix(Some(load_matrix(name)?));
+    }
+    app.device_filter = options.device.clone();
+
+    let (event_tx, event_rx) = mpsc::channel::<KeyEvent>();
+
+    // Headless replays run as fast as possible unless a speed is given
+    let mut replay = match &options.replay {
+        Some(path) => Some(load_replay(
+            path,
+            options.speed.unwrap_or(0.0),
+            event_tx.clone(),
+        )?),
+        None => None,
+    };
+
+    #[cfg(target_os = "linux")]
+    let evdev_capture = if replay.is_some() {
+        None
+    } else {
+        let capture =
+            EvdevCapture::spawn(EvdevListener::new, capture_options(options.rt_priority))
+                .map_err(|e| format!("Evdev unavailable: {}", e))?;
+        info!("Evdev: {}", evdev_status());
+        if let Some(e) = capture.priority_error() {
+            warn!("Real-time priority unavailable: {}", e);
+        }
+        if let Ok(devices) = hid::inspect_keyboards() {
+            app.apply_hid_devices(&devices);
+        }
+        Some(capture)
+    };
+
+    #[cfg(not(target_os = "linux"))]
+    if replay.is_none() {
+        return Err("Headless mode needs evdev (Linux) or --replay <file>".into());
+    }
+
+    #[cfg(target_os = "linux")]
+    let (session_devices, repeat_settings) = match (&replay, &evdev_capture) {
+        (Some(replay_listener), _) => (replay_listener.devices(), replay_listener.repeat_settings()),
+        (None, Some(capture)) => {
+            let evdev = capture.listener();
+            (evdev.devices(), evdev.repeat_settings(options.device.as_deref()))
+        }
+        (None, None) => (Vec::new(), None),
+    };
+    #[cfg(not(target_os = "linux"))]
+    let (session_devices, repeat_settings) = replay
+        .as_ref()
+        .map(|r| (r.devices(), r.repeat_settings()))
+        .unwrap_or_default();
+    app.register_devices(session_devices.clone());
+    app.typematic_test.set_configured(repeat_settings);
+    #[cfg(target_os = "linux")]
+    if let Some(ref capture) = evdev_capture {
+        load_capabilities(&mut app, &capture.listener());
+    }
+    app.input_fidelity = match &replay {
+        Some(replay_listener) => replay_listener.fidelity(),
+        None => Some(InputFidelity::Evdev),
+    };
+
+    let mut recorder = match &options.record {
+        Some(path) => {
+            let source = if replay.is_some() { "replay" } else { "evdev" };
+            Some(create_recorder(path, source, session_devices, repeat_settings)?)
+        }
+        None => None,
+    };
+
+    // A replay runs to the end of the capture unless capped explicitly
+    let duration = match (options.duration_secs, &replay) {
+        (Some(secs), _) => Some(std::time::Duration::from_secs(secs)),
+        (None, Some(_)) => None,
+        (None, None) => Some(std::time::Duration::from_secs(
+            config.polling.test_duration_secs,
+        )),
+    };
+    if let Some(limit) = duration {
+        info!("Collecting events for {}s", limit.as_secs());
+    }
+
+    let start = std::time::Instant::now();
+    #[cfg(target_os = "linux")]
+    let mut last_kernel_check = start;
+    while running.load(Ordering::SeqCst) {
+        if duration.is_some_and(|limit| start.elapsed() >= limit) {
+            break;
+        }
+
+        if let Some(ref mut replay_listener) = replay {
+            replay_listener.poll();
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(ref capture) = evdev_capture {
+            let changes = capture.listener().take_changes();
+            for change in &changes {
+                match change {
+                    DeviceChange::Added(device) => info!("Keyboard connected: {}", device.path),
+                    DeviceChange::Removed(device) => warn!("Keyboard disconnected: {}", device.path),
+                    DeviceChange::Failed(device, e) => error!("Keyboard {} failed: {}", device.path, e),
+                }
+            }
+            if !changes.is_empty() {
+                apply_device_changes(&mut app, changes);
+                load_capabilities(&mut app, &capture.listener());
+            }
+            update_dropped_events(&mut app, capture);
+        }
+
+        #[cfg(target_os = "linux")]
+        let captured = evdev_capture.iter().flat_map(|capture| capture.try_iter());
+        #[cfg(not(target_os = "linux"))]
+        let captured = std::iter::empty();
+        for key_event in captured.chain(event_rx.try_iter()) {
+            if let Some(ref mut rec) = recorder {
+                rec.record(&key_event)?;
+            }
+            app.process_event(&key_event);
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(ref capture) = evdev_capture {
+            if last_kernel_check.elapsed() >= KERNEL_STATE_CHECK_INTERVAL {
+                last_kernel_check = std::time::Instant::now();
+                for (key, kind) in check_kernel_key_state(&mut app, capture) {
+                    warn!(
+                        "Key state desync: {} ({})",
+                        keymap::get_key_info(key).name,
+                        kind.label()
+                    );
+                }
+            }
+        }
+
+        if replay.as_ref().is_some_and(|r| r.is_finished()) {
+            break;
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+
+    if let Some(rec) = recorder {
+        info!("Recorded {} event(s) to capture", rec.event_count());
+    }
+
+    let report = app.generate_report();
+    let path = options
+        .report
+        .clone()
+        .unwrap_or_else(|| std::path::PathBuf::from(default_report_filename()));
+    report
+        .export(&path)
+        .map_err(|e| format!("Failed to write report {}: {}", path.display(), e))?;
+    info!("Report written to {}", path.display());
+
+    Ok(report)
+}
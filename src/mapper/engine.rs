@@ -0,0 +1,988 @@
+//! Key processing for the mapper daemon
+//!
+//! [`MapperEngine`] turns one device's key events into the key events
+//! written to the virtual keyboard. It does no I/O: the daemon passes in
+//! each press, release and repeat with the time it was read, calls
+//! [`MapperEngine::tick`] once [`MapperEngine::deadline`] has passed, and
+//! emits whatever comes back.
+//!
+//! ## Tap-Hold Keys
+//!
+//! A dual-role key sends one key when tapped and another while held, e.g.
+//! Caps Lock as Esc on tap and Left Ctrl on hold. Nothing is sent when it
+//! goes down; keys pressed after it are held back until it is decided:
+//!
+//! - released within the tapping term: a tap (the tap key is pressed and
+//!   released)
+//! - still down when the tapping term ends: a hold (the hold key stays down
+//!   until the dual-role key is released)
+//! - with permissive hold, another key pressed *and released* within the
+//!   tapping term: a hold, so a quick Ctrl+C does not wait out the term
+//!
+//! The held-back keys are then replayed in their original order.
+//!
+//! ## Layers
+//!
+//! A layer is a named set of remaps switched on by a layer key, which sends
+//! nothing itself. A momentary layer is active while its key is held (like
+//! Fn); a toggle layer flips on and off with each press. When several are
+//! active, the most recently activated layer that maps a key wins, and keys
+//! no active layer maps fall through to the plain remaps. Tap-hold keys are
+//! the same on every layer. A key keeps the output it was pressed with, so
+//! letting go of a layer key while another key is held does not leave that
+//! key stuck down.
+//!
+//! [`Layer::from_fn_mode`] builds the Fn layer for the TUI's
+//! [`FnKeyMode::MapToFKeys`] and [`FnKeyMode::MapToMedia`] tables.
+//!
+//! ## Chords
+//!
+//! A chord is a set of keys pressed within a short window that sends one
+//! key instead, e.g. J+K as Esc. Pressing a chord key holds it back:
+//!
+//! - once the held-back keys are exactly a chord and no longer chord
+//!   starts with them, the chord fires at once
+//! - when the window ends, a complete chord fires; anything else is
+//!   replayed as the plain presses it was
+//! - any other event (a release, a repeat, a key that cannot extend a
+//!   chord) ends the window early in the same way, then goes through
+//!
+//! A chord's output goes down when it fires and up when the first of its
+//! keys is released; the remaining releases are dropped.
+//!
+//! ## Leader Sequences
+//!
+//! Pressing the leader key starts a sequence. The keys typed after it are
+//! swallowed; once they spell a configured sequence its output is tapped.
+//! A key no sequence continues with, or a pause longer than the leader
+//! timeout, cancels the sequence without sending anything.
+//!
+//! The stages run in order: leader sequences first, then chords, then
+//! tap-hold keys, layers and remaps. Chord and sequence outputs are sent
+//! as they are, but still wait behind an undecided tap-hold key.
+
+use crate::keyboard::remap::FnKeyMode;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Tapping term used when a preset does not set one
+pub const DEFAULT_TAPPING_TERM: Duration = Duration::from_millis(200);
+
+/// Chord window used when a preset does not set one
+pub const DEFAULT_CHORD_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Leader sequence timeout used when a preset does not set one
+pub const DEFAULT_LEADER_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// A key event for the virtual keyboard: (key code, value) with
+/// 1 = press, 0 = release, 2 = repeat
+pub type KeyOutput = (u16, i32);
+
+/// A dual-role key: one key on tap, another while held
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapHold {
+    /// Source key
+    pub key: u16,
+    /// Key sent when the source key is tapped
+    pub tap: u16,
+    /// Key held down while the source key is held
+    pub hold: u16,
+    /// How long the key must be held to count as a hold
+    pub tapping_term: Duration,
+    /// Decide a hold as soon as another key is pressed and released while
+    /// the key is down, instead of waiting for the tapping term
+    pub permissive_hold: bool,
+}
+
+/// How a layer key switches its layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayerActivation {
+    /// Active while the layer key is held
+    #[default]
+    Momentary,
+    /// Each press of the layer key turns the layer on or off
+    Toggle,
+}
+
+/// A named set of remaps active while its layer key is held or toggled on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layer {
+    pub name: String,
+    /// Key that switches the layer; it sends nothing itself
+    pub key: u16,
+    pub activation: LayerActivation,
+    /// Remaps while the layer is active: source scancode → target scancode
+    pub remaps: HashMap<u16, u16>,
+}
+
+impl Layer {
+    /// A momentary "fn" layer on `key` with the table of an FN mode that
+    /// remaps keys (`MapToFKeys` or `MapToMedia`)
+    pub fn from_fn_mode(mode: FnKeyMode, key: u16) -> Option<Self> {
+        Some(Self {
+            name: "fn".to_string(),
+            key,
+            activation: LayerActivation::Momentary,
+            remaps: mode.combo_table()?,
+        })
+    }
+}
+
+/// Keys pressed together within a short window that send one key instead
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chord {
+    /// Source keys, at least two, in any order
+    pub keys: Vec<u16>,
+    /// Key sent while the chord is held
+    pub output: u16,
+    /// How long after the first key the others may follow
+    pub timeout: Duration,
+}
+
+/// Keys typed after the leader key that send one key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sequence {
+    /// Keys typed after the leader, in order
+    pub keys: Vec<u16>,
+    /// Key tapped when the sequence is complete
+    pub output: u16,
+}
+
+/// A leader key and the sequences it starts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Leader {
+    /// Key that starts a sequence; it sends nothing itself
+    pub key: u16,
+    /// How long to wait for each next key before giving up
+    pub timeout: Duration,
+    /// Sequences, none of which starts another
+    pub sequences: Vec<Sequence>,
+}
+
+/// Everything the mapper does with one device's keys
+#[derive(Debug, Clone, Default)]
+pub struct KeyBindings {
+    /// Plain remaps: source scancode → target scancode
+    pub remaps: HashMap<u16, u16>,
+    /// Dual-role keys; these take precedence over a remap of the same key
+    pub tap_holds: Vec<TapHold>,
+    /// Layers, switched by their layer keys
+    pub layers: Vec<Layer>,
+    /// Chords, checked before any other binding
+    pub chords: Vec<Chord>,
+    /// Leader key and its sequences, checked before chords
+    pub leader: Option<Leader>,
+}
+
+impl KeyBindings {
+    /// Bindings made only of plain remaps
+    pub fn from_remaps(remaps: HashMap<u16, u16>) -> Self {
+        Self {
+            remaps,
+            ..Self::default()
+        }
+    }
+
+    /// Whether there is nothing to map
+    pub fn is_empty(&self) -> bool {
+        self.remaps.is_empty()
+            && self.tap_holds.is_empty()
+            && self.layers.is_empty()
+            && self.chords.is_empty()
+            && self.leader.is_none()
+    }
+
+    /// Count of each kind of binding, e.g. `3 mapping(s), 1 layer(s)`
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("{} mapping(s)", self.remaps.len())];
+        if !self.tap_holds.is_empty() {
+            parts.push(format!("{} tap-hold key(s)", self.tap_holds.len()));
+        }
+        if !self.layers.is_empty() {
+            parts.push(format!("{} layer(s)", self.layers.len()));
+        }
+        if !self.chords.is_empty() {
+            parts.push(format!("{} chord(s)", self.chords.len()));
+        }
+        if let Some(leader) = &self.leader {
+            parts.push(format!("{} leader sequence(s)", leader.sequences.len()));
+        }
+        parts.join(", ")
+    }
+}
+
+/// A dual-role key waiting for its tap/hold decision
+#[derive(Debug)]
+struct PendingTap {
+    tap_hold: TapHold,
+    since: Instant,
+    /// Events that arrived while undecided, replayed once it is decided
+    queued: Vec<(Input, i32, Instant)>,
+}
+
+/// What the tap-hold/layer/remap stage receives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Input {
+    /// A source key, still to be mapped
+    Key(u16),
+    /// A key produced by a chord or leader sequence, sent as it is
+    Output(u16),
+}
+
+/// Chord keys pressed so far, waiting to see whether a chord completes
+#[derive(Debug)]
+struct PendingChord {
+    /// Keys in press order, with their press times
+    keys: Vec<(u16, Instant)>,
+    /// When the chord gives up waiting for more keys
+    deadline: Instant,
+}
+
+/// A chord that fired and has keys still down
+#[derive(Debug)]
+struct ActiveChord {
+    /// Chord keys not yet released
+    keys: Vec<u16>,
+    /// Output key, until the first chord key is released
+    output: Option<u16>,
+}
+
+/// Key event processor for one input device
+#[derive(Debug)]
+pub struct MapperEngine {
+    remaps: HashMap<u16, u16>,
+    tap_holds: HashMap<u16, TapHold>,
+    layers: Vec<Layer>,
+    chords: Vec<Chord>,
+    leader: Option<Leader>,
+    /// Indices into `layers` of the active layers, most recent last
+    active_layers: Vec<usize>,
+    pending_tap: Option<PendingTap>,
+    pending_chord: Option<PendingChord>,
+    active_chords: Vec<ActiveChord>,
+    /// Keys typed since the leader key, and when the last one was pressed
+    leader_typed: Option<(Vec<u16>, Instant)>,
+    /// Keys consumed by a leader sequence whose release is still to come
+    swallowed: HashSet<u16>,
+    /// Output key sent for each source key currently down, so a release
+    /// always matches its press
+    down: HashMap<u16, u16>,
+}
+
+impl MapperEngine {
+    pub fn new(bindings: &KeyBindings) -> Self {
+        Self {
+            remaps: bindings.remaps.clone(),
+            tap_holds: bindings.tap_holds.iter().map(|th| (th.key, *th)).collect(),
+            layers: bindings.layers.clone(),
+            chords: bindings.chords.clone(),
+            leader: bindings.leader.clone(),
+            active_layers: Vec::new(),
+            pending_tap: None,
+            pending_chord: None,
+            active_chords: Vec::new(),
+            leader_typed: None,
+            swallowed: HashSet::new(),
+            down: HashMap::new(),
+        }
+    }
+
+    /// Process a key event read at `at`
+    pub fn key(&mut self, code: u16, value: i32, at: Instant) -> Vec<KeyOutput> {
+        let mut out = Vec::new();
+        self.expire(at, &mut out);
+        self.leader_stage(code, value, at, &mut out);
+        out
+    }
+
+    /// Settle whatever timed out by `now`
+    pub fn tick(&mut self, now: Instant) -> Vec<KeyOutput> {
+        let mut out = Vec::new();
+        self.expire(now, &mut out);
+        out
+    }
+
+    /// When [`tick`](Self::tick) next has something to decide
+    pub fn deadline(&self) -> Option<Instant> {
+        [
+            self.leader_deadline(),
+            self.pending_chord.as_ref().map(|p| p.deadline),
+            self.tap_deadline(),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+
+    /// Names of the active layers, most recently activated last
+    pub fn active_layers(&self) -> Vec<&str> {
+        self.active_layers
+            .iter()
+            .map(|&i| self.layers[i].name.as_str())
+            .collect()
+    }
+
+    /// Drop held-back events and release every output key still down, for
+    /// a device that went away
+    pub fn release_all(&mut self) -> Vec<KeyOutput> {
+        self.pending_tap = None;
+        self.pending_chord = None;
+        self.leader_typed = None;
+        self.swallowed.clear();
+        self.active_layers.clear();
+        let mut released: Vec<u16> = self.down.drain().map(|(_, output)| output).collect();
+        released.extend(self.active_chords.drain(..).filter_map(|c| c.output));
+        released.sort_unstable();
+        released.dedup();
+        released.into_iter().map(|code| (code, 0)).collect()
+    }
+
+    /// Code sent for a plain key: the top active layer that maps it, else
+    /// the plain remaps
+    fn output_code(&self, code: u16) -> u16 {
+        self.active_layers
+            .iter()
+            .rev()
+            .find_map(|&i| self.layers[i].remaps.get(&code))
+            .or_else(|| self.remaps.get(&code))
+            .copied()
+            .unwrap_or(code)
+    }
+
+    /// Apply a layer key event to its layer
+    fn switch_layer(&mut self, index: usize, value: i32) {
+        let active = self.active_layers.iter().position(|&i| i == index);
+        match (value, self.layers[index].activation, active) {
+            (1, LayerActivation::Toggle, Some(position)) => {
+                self.active_layers.remove(position);
+            }
+            (1, _, None) => self.active_layers.push(index),
+            (0, LayerActivation::Momentary, Some(position)) => {
+                self.active_layers.remove(position);
+            }
+            _ => {}
+        }
+    }
+
+    fn leader_deadline(&self) -> Option<Instant> {
+        let (_, last) = self.leader_typed.as_ref()?;
+        Some(*last + self.leader.as_ref()?.timeout)
+    }
+
+    fn tap_deadline(&self) -> Option<Instant> {
+        self.pending_tap
+            .as_ref()
+            .map(|p| p.since + p.tap_hold.tapping_term)
+    }
+
+    /// Settle everything whose time ran out by `now`: an unfinished leader
+    /// sequence is dropped, a pending chord fires or is let through, and a
+    /// pending dual-role key becomes a hold
+    fn expire(&mut self, now: Instant, out: &mut Vec<KeyOutput>) {
+        if self
+            .leader_deadline()
+            .is_some_and(|deadline| now >= deadline)
+        {
+            self.leader_typed = None;
+        }
+        if self
+            .pending_chord
+            .as_ref()
+            .is_some_and(|p| now >= p.deadline)
+        {
+            self.resolve_chord(out);
+        }
+        self.expire_tap(now, out);
+    }
+
+    fn expire_tap(&mut self, now: Instant, out: &mut Vec<KeyOutput>) {
+        if self.tap_deadline().is_some_and(|deadline| now >= deadline) {
+            self.resolve_tap(true, out);
+        }
+    }
+
+    /// First stage: the leader key and the sequences typed after it
+    fn leader_stage(&mut self, code: u16, value: i32, at: Instant, out: &mut Vec<KeyOutput>) {
+        // Keys consumed by a sequence are released without a trace
+        if value != 1 && self.swallowed.contains(&code) {
+            if value == 0 {
+                self.swallowed.remove(&code);
+            }
+            return;
+        }
+        if value == 1 {
+            if let Some((typed, last)) = &mut self.leader_typed {
+                typed.push(code);
+                *last = at;
+                self.swallowed.insert(code);
+                let typed = typed.clone();
+                self.match_sequence(&typed, at, out);
+                return;
+            }
+            if self
+                .leader
+                .as_ref()
+                .is_some_and(|leader| leader.key == code)
+            {
+                // Chord keys pressed before the leader go out first
+                self.resolve_chord(out);
+                self.leader_typed = Some((Vec::new(), at));
+                self.swallowed.insert(code);
+                return;
+            }
+        }
+        self.chord_stage(code, value, at, out);
+    }
+
+    /// Send a completed sequence's output, or drop a sequence nothing
+    /// starts with
+    fn match_sequence(&mut self, typed: &[u16], at: Instant, out: &mut Vec<KeyOutput>) {
+        let Some(leader) = &self.leader else {
+            return;
+        };
+        if let Some(sequence) = leader.sequences.iter().find(|s| s.keys == typed) {
+            let output = sequence.output;
+            self.leader_typed = None;
+            self.process(Input::Output(output), 1, at, out);
+            self.process(Input::Output(output), 0, at, out);
+        } else if !leader.sequences.iter().any(|s| s.keys.starts_with(typed)) {
+            self.leader_typed = None;
+        }
+    }
+
+    /// Second stage: chord keys are held back until a chord fires or
+    /// cannot fire any more
+    fn chord_stage(&mut self, code: u16, value: i32, at: Instant, out: &mut Vec<KeyOutput>) {
+        // The first key released ends a fired chord's output; the others
+        // and any repeats are dropped
+        if let Some(index) = self
+            .active_chords
+            .iter()
+            .position(|c| c.keys.contains(&code))
+        {
+            if value == 0 {
+                let chord = &mut self.active_chords[index];
+                chord.keys.retain(|&key| key != code);
+                let output = chord.output.take();
+                if chord.keys.is_empty() {
+                    self.active_chords.remove(index);
+                }
+                if let Some(output) = output {
+                    self.process(Input::Output(output), 0, at, out);
+                }
+            }
+            return;
+        }
+
+        if let Some(pending) = &mut self.pending_chord {
+            let held = pending.keys.iter().any(|&(key, _)| key == code);
+            if held && value == 2 {
+                return;
+            }
+            if value == 1 && !held {
+                let mut keys: Vec<u16> = pending.keys.iter().map(|&(key, _)| key).collect();
+                keys.push(code);
+                if self.chords.iter().any(|c| is_subset(&keys, &c.keys)) {
+                    pending.keys.push((code, at));
+                    // Fire as soon as no longer chord can still complete
+                    let longer = self
+                        .chords
+                        .iter()
+                        .any(|c| c.keys.len() > keys.len() && is_subset(&keys, &c.keys));
+                    if !longer && self.exact_chord(&keys).is_some() {
+                        self.resolve_chord(out);
+                    }
+                    return;
+                }
+            }
+            // Anything else settles the pending keys first, in order
+            self.resolve_chord(out);
+            self.chord_stage(code, value, at, out);
+            return;
+        }
+
+        if value == 1 {
+            let deadline = self
+                .chords
+                .iter()
+                .filter(|c| c.keys.contains(&code))
+                .map(|c| c.timeout)
+                .max();
+            if let Some(timeout) = deadline {
+                self.pending_chord = Some(PendingChord {
+                    keys: vec![(code, at)],
+                    deadline: at + timeout,
+                });
+                return;
+            }
+        }
+        self.process(Input::Key(code), value, at, out);
+    }
+
+    /// The chord made of exactly these keys
+    fn exact_chord(&self, keys: &[u16]) -> Option<&Chord> {
+        self.chords
+            .iter()
+            .find(|c| c.keys.len() == keys.len() && is_subset(keys, &c.keys))
+    }
+
+    /// Fire the pending chord if its keys make one, otherwise let the held
+    /// back presses through in order
+    fn resolve_chord(&mut self, out: &mut Vec<KeyOutput>) {
+        let Some(pending) = self.pending_chord.take() else {
+            return;
+        };
+        let keys: Vec<u16> = pending.keys.iter().map(|&(key, _)| key).collect();
+        match self.exact_chord(&keys).map(|c| c.output) {
+            Some(output) => {
+                let at = pending.keys.last().map_or_else(Instant::now, |&(_, at)| at);
+                self.active_chords.push(ActiveChord {
+                    keys,
+                    output: Some(output),
+                });
+                self.process(Input::Output(output), 1, at, out);
+            }
+            None => {
+                for (key, at) in pending.keys {
+                    self.expire_tap(at, out);
+                    self.process(Input::Key(key), 1, at, out);
+                }
+            }
+        }
+    }
+
+    /// Last stage: tap-hold keys, layers and remaps
+    fn process(&mut self, input: Input, value: i32, at: Instant, out: &mut Vec<KeyOutput>) {
+        if let Some(pending) = &mut self.pending_tap {
+            if input == Input::Key(pending.tap_hold.key) {
+                // Released within the term: a tap. Repeats are dropped.
+                if value == 0 {
+                    self.resolve_tap(false, out);
+                }
+                return;
+            }
+            pending.queued.push((input, value, at));
+            let nested_tap = value == 0
+                && pending
+                    .queued
+                    .iter()
+                    .any(|&(queued, value, _)| queued == input && value == 1);
+            if nested_tap && pending.tap_hold.permissive_hold {
+                self.resolve_tap(true, out);
+            }
+            return;
+        }
+
+        let code = match input {
+            Input::Output(code) => {
+                out.push((code, value));
+                return;
+            }
+            Input::Key(code) => code,
+        };
+
+        if let Some(index) = self.layers.iter().position(|layer| layer.key == code) {
+            self.switch_layer(index, value);
+            return;
+        }
+
+        match value {
+            1 => {
+                if let Some(&tap_hold) = self.tap_holds.get(&code) {
+                    self.pending_tap = Some(PendingTap {
+                        tap_hold,
+                        since: at,
+                        queued: Vec::new(),
+                    });
+                    return;
+                }
+                let output = self.output_code(code);
+                self.down.insert(code, output);
+                out.push((output, 1));
+            }
+            0 => {
+                let output = self
+                    .down
+                    .remove(&code)
+                    .unwrap_or_else(|| self.output_code(code));
+                out.push((output, 0));
+            }
+            _ => {
+                let output = self
+                    .down
+                    .get(&code)
+                    .copied()
+                    .unwrap_or_else(|| self.output_code(code));
+                out.push((output, value));
+            }
+        }
+    }
+
+    /// Settle the pending dual-role key and replay what was held back
+    fn resolve_tap(&mut self, hold: bool, out: &mut Vec<KeyOutput>) {
+        let Some(pending) = self.pending_tap.take() else {
+            return;
+        };
+        let tap_hold = pending.tap_hold;
+        if hold {
+            self.down.insert(tap_hold.key, tap_hold.hold);
+            out.push((tap_hold.hold, 1));
+        } else {
+            out.push((tap_hold.tap, 1));
+            out.push((tap_hold.tap, 0));
+        }
+        for (input, value, at) in pending.queued {
+            self.expire_tap(at, out);
+            self.process(input, value, at, out);
+        }
+    }
+}
+
+/// Whether every key in `keys` is in `of`
+fn is_subset(keys: &[u16], of: &[u16]) -> bool {
+    keys.iter().all(|key| of.contains(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAPS: u16 = 58;
+    const ESC: u16 = 1;
+    const LEFT_CTRL: u16 = 29;
+    const A: u16 = 30;
+    const C: u16 = 46;
+
+    fn engine(permissive_hold: bool) -> MapperEngine {
+        let mut bindings = engine_bindings();
+        bindings.tap_holds[0].permissive_hold = permissive_hold;
+        MapperEngine::new(&bindings)
+    }
+
+    fn engine_bindings() -> KeyBindings {
+        let mut bindings = KeyBindings::from_remaps(HashMap::from([(A, 48)]));
+        bindings.tap_holds.push(TapHold {
+            key: CAPS,
+            tap: ESC,
+            hold: LEFT_CTRL,
+            tapping_term: DEFAULT_TAPPING_TERM,
+            permissive_hold: false,
+        });
+        bindings
+    }
+
+    fn ms(start: Instant, millis: u64) -> Instant {
+        start + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn plain_keys_are_remapped() {
+        let mut engine = engine(false);
+        let t = Instant::now();
+        assert_eq!(engine.key(A, 1, t), vec![(48, 1)]);
+        assert_eq!(engine.key(A, 2, t), vec![(48, 2)]);
+        assert_eq!(engine.key(A, 0, t), vec![(48, 0)]);
+        assert_eq!(engine.key(C, 1, t), vec![(C, 1)]);
+    }
+
+    #[test]
+    fn quick_release_is_a_tap() {
+        let mut engine = engine(false);
+        let t = Instant::now();
+        assert!(engine.key(CAPS, 1, t).is_empty());
+        assert_eq!(engine.deadline(), Some(ms(t, 200)));
+        assert_eq!(engine.key(CAPS, 0, ms(t, 80)), vec![(ESC, 1), (ESC, 0)]);
+        assert_eq!(engine.deadline(), None);
+    }
+
+    #[test]
+    fn held_past_the_term_is_a_hold() {
+        let mut engine = engine(false);
+        let t = Instant::now();
+        engine.key(CAPS, 1, t);
+        assert!(engine.key(C, 1, ms(t, 50)).is_empty());
+        assert!(engine.tick(ms(t, 150)).is_empty());
+        assert_eq!(engine.tick(ms(t, 200)), vec![(LEFT_CTRL, 1), (C, 1)]);
+        assert_eq!(engine.key(C, 0, ms(t, 250)), vec![(C, 0)]);
+        assert_eq!(engine.key(CAPS, 0, ms(t, 300)), vec![(LEFT_CTRL, 0)]);
+    }
+
+    #[test]
+    fn late_release_without_a_tick_is_still_a_hold() {
+        let mut engine = engine(false);
+        let t = Instant::now();
+        engine.key(CAPS, 1, t);
+        assert_eq!(
+            engine.key(CAPS, 0, ms(t, 400)),
+            vec![(LEFT_CTRL, 1), (LEFT_CTRL, 0)]
+        );
+    }
+
+    #[test]
+    fn nested_tap_waits_for_the_term_without_permissive_hold() {
+        let mut engine = engine(false);
+        let t = Instant::now();
+        engine.key(CAPS, 1, t);
+        assert!(engine.key(C, 1, ms(t, 30)).is_empty());
+        assert!(engine.key(C, 0, ms(t, 60)).is_empty());
+        // Caps released inside the term: Esc, then the held-back C
+        assert_eq!(
+            engine.key(CAPS, 0, ms(t, 90)),
+            vec![(ESC, 1), (ESC, 0), (C, 1), (C, 0)]
+        );
+    }
+
+    #[test]
+    fn permissive_hold_decides_on_a_nested_tap() {
+        let mut engine = engine(true);
+        let t = Instant::now();
+        engine.key(CAPS, 1, t);
+        assert!(engine.key(C, 1, ms(t, 30)).is_empty());
+        assert_eq!(
+            engine.key(C, 0, ms(t, 60)),
+            vec![(LEFT_CTRL, 1), (C, 1), (C, 0)]
+        );
+        assert_eq!(engine.key(CAPS, 0, ms(t, 90)), vec![(LEFT_CTRL, 0)]);
+    }
+
+    #[test]
+    fn rolling_past_the_dual_role_key_is_a_tap() {
+        // Caps down, A down, Caps up, A up: typing, not a chord
+        let mut engine = engine(true);
+        let t = Instant::now();
+        engine.key(CAPS, 1, t);
+        engine.key(A, 1, ms(t, 40));
+        assert_eq!(
+            engine.key(CAPS, 0, ms(t, 70)),
+            vec![(ESC, 1), (ESC, 0), (48, 1)]
+        );
+        assert_eq!(engine.key(A, 0, ms(t, 100)), vec![(48, 0)]);
+    }
+
+    const FN: u16 = 464;
+    const NUM_LOCK: u16 = 69;
+    const KEY_1: u16 = 2;
+    const F1: u16 = 59;
+
+    fn layered() -> MapperEngine {
+        let mut bindings = KeyBindings::from_remaps(HashMap::from([(KEY_1, 30)]));
+        bindings
+            .layers
+            .push(Layer::from_fn_mode(FnKeyMode::MapToFKeys, FN).unwrap());
+        bindings.layers.push(Layer {
+            name: "numpad".to_string(),
+            key: NUM_LOCK,
+            activation: LayerActivation::Toggle,
+            remaps: HashMap::from([(KEY_1, 79), (C, 81)]),
+        });
+        MapperEngine::new(&bindings)
+    }
+
+    #[test]
+    fn momentary_layer_is_active_while_held() {
+        let mut engine = layered();
+        let t = Instant::now();
+        assert!(engine.key(FN, 1, t).is_empty());
+        assert_eq!(engine.active_layers(), vec!["fn"]);
+        assert_eq!(engine.key(KEY_1, 1, t), vec![(F1, 1)]);
+        assert_eq!(engine.key(KEY_1, 0, t), vec![(F1, 0)]);
+        assert!(engine.key(FN, 0, t).is_empty());
+        assert_eq!(engine.key(KEY_1, 1, t), vec![(30, 1)]);
+    }
+
+    #[test]
+    fn key_keeps_its_output_across_a_layer_change() {
+        let mut engine = layered();
+        let t = Instant::now();
+        engine.key(FN, 1, t);
+        assert_eq!(engine.key(KEY_1, 1, t), vec![(F1, 1)]);
+        engine.key(FN, 0, t);
+        assert_eq!(engine.key(KEY_1, 2, t), vec![(F1, 2)]);
+        assert_eq!(engine.key(KEY_1, 0, t), vec![(F1, 0)]);
+    }
+
+    #[test]
+    fn toggle_layer_flips_on_press_and_latest_layer_wins() {
+        let mut engine = layered();
+        let t = Instant::now();
+        engine.key(NUM_LOCK, 1, t);
+        engine.key(NUM_LOCK, 0, t);
+        assert_eq!(engine.active_layers(), vec!["numpad"]);
+        assert_eq!(engine.key(KEY_1, 1, t), vec![(79, 1)]);
+        engine.key(KEY_1, 0, t);
+
+        // Fn on top of numpad: Fn's table wins, unmapped keys fall through
+        engine.key(FN, 1, t);
+        assert_eq!(engine.key(KEY_1, 1, t), vec![(F1, 1)]);
+        assert_eq!(engine.key(C, 1, t), vec![(81, 1)]);
+        assert_eq!(engine.key(A, 1, t), vec![(A, 1)]);
+        engine.key(FN, 0, t);
+
+        engine.key(NUM_LOCK, 1, t);
+        engine.key(NUM_LOCK, 0, t);
+        assert!(engine.active_layers().is_empty());
+    }
+
+    #[test]
+    fn release_all_lets_go_of_held_outputs() {
+        let mut engine = engine(false);
+        let t = Instant::now();
+        engine.key(A, 1, t);
+        engine.key(CAPS, 1, ms(t, 10));
+        engine.tick(ms(t, 300));
+        assert_eq!(engine.release_all(), vec![(LEFT_CTRL, 0), (48, 0)]);
+        assert!(engine.release_all().is_empty());
+    }
+
+    const J: u16 = 36;
+    const K: u16 = 37;
+    const L: u16 = 38;
+    const SEMICOLON: u16 = 39;
+    const RIGHT_ALT: u16 = 100;
+    const T: u16 = 20;
+    const E: u16 = 18;
+    const F13: u16 = 183;
+    const F14: u16 = 184;
+
+    fn chorded() -> MapperEngine {
+        let mut bindings = engine_bindings();
+        bindings.chords.push(Chord {
+            keys: vec![J, K],
+            output: ESC,
+            timeout: DEFAULT_CHORD_TIMEOUT,
+        });
+        bindings.chords.push(Chord {
+            keys: vec![J, L],
+            output: F13,
+            timeout: DEFAULT_CHORD_TIMEOUT,
+        });
+        bindings.chords.push(Chord {
+            keys: vec![J, K, SEMICOLON],
+            output: F14,
+            timeout: DEFAULT_CHORD_TIMEOUT,
+        });
+        bindings.leader = Some(Leader {
+            key: RIGHT_ALT,
+            timeout: DEFAULT_LEADER_TIMEOUT,
+            sequences: vec![
+                Sequence {
+                    keys: vec![T, E],
+                    output: F13,
+                },
+                Sequence {
+                    keys: vec![A],
+                    output: F14,
+                },
+            ],
+        });
+        MapperEngine::new(&bindings)
+    }
+
+    #[test]
+    fn chord_fires_once_no_longer_chord_can_complete() {
+        let mut engine = chorded();
+        let t = Instant::now();
+        assert!(engine.key(J, 1, t).is_empty());
+        assert_eq!(engine.key(L, 1, ms(t, 10)), vec![(F13, 1)]);
+        assert_eq!(engine.deadline(), None);
+        assert!(engine.key(L, 2, ms(t, 300)).is_empty());
+        assert_eq!(engine.key(J, 0, ms(t, 400)), vec![(F13, 0)]);
+        assert!(engine.key(L, 0, ms(t, 410)).is_empty());
+    }
+
+    #[test]
+    fn chord_that_may_grow_waits_for_its_window() {
+        let mut engine = chorded();
+        let t = Instant::now();
+        engine.key(J, 1, t);
+        assert!(engine.key(K, 1, ms(t, 10)).is_empty());
+        assert_eq!(engine.deadline(), Some(ms(t, 50)));
+        assert_eq!(engine.tick(ms(t, 50)), vec![(ESC, 1)]);
+        assert_eq!(engine.key(K, 0, ms(t, 100)), vec![(ESC, 0)]);
+        assert!(engine.key(J, 0, ms(t, 110)).is_empty());
+
+        // The third key within the window makes the longer chord
+        engine.key(J, 1, ms(t, 200));
+        engine.key(K, 1, ms(t, 210));
+        assert_eq!(engine.key(SEMICOLON, 1, ms(t, 220)), vec![(F14, 1)]);
+    }
+
+    #[test]
+    fn release_inside_the_window_fires_a_complete_chord_first() {
+        let mut engine = chorded();
+        let t = Instant::now();
+        engine.key(J, 1, t);
+        engine.key(K, 1, ms(t, 10));
+        assert_eq!(engine.key(J, 0, ms(t, 20)), vec![(ESC, 1), (ESC, 0)]);
+        assert!(engine.key(K, 0, ms(t, 30)).is_empty());
+    }
+
+    #[test]
+    fn keys_that_make_no_chord_are_replayed_in_order() {
+        let mut engine = chorded();
+        let t = Instant::now();
+        engine.key(K, 1, t);
+        assert_eq!(engine.key(A, 1, ms(t, 10)), vec![(K, 1), (48, 1)]);
+        assert_eq!(engine.key(K, 0, ms(t, 20)), vec![(K, 0)]);
+
+        // A lone chord key is let through when its window ends
+        assert!(engine.key(J, 1, ms(t, 100)).is_empty());
+        assert_eq!(engine.tick(ms(t, 150)), vec![(J, 1)]);
+        assert_eq!(engine.key(J, 2, ms(t, 400)), vec![(J, 2)]);
+        assert_eq!(engine.key(J, 0, ms(t, 450)), vec![(J, 0)]);
+    }
+
+    #[test]
+    fn chord_output_waits_behind_a_pending_tap_hold() {
+        let mut engine = chorded();
+        let t = Instant::now();
+        engine.key(CAPS, 1, t);
+        engine.key(J, 1, ms(t, 10));
+        assert!(engine.key(L, 1, ms(t, 20)).is_empty());
+        assert_eq!(engine.tick(ms(t, 200)), vec![(LEFT_CTRL, 1), (F13, 1)]);
+    }
+
+    #[test]
+    fn leader_sequence_taps_its_output() {
+        let mut engine = chorded();
+        let t = Instant::now();
+        assert!(engine.key(RIGHT_ALT, 1, t).is_empty());
+        assert!(engine.key(RIGHT_ALT, 0, ms(t, 50)).is_empty());
+        assert!(engine.key(T, 1, ms(t, 100)).is_empty());
+        assert!(engine.key(T, 0, ms(t, 150)).is_empty());
+        assert_eq!(engine.key(E, 1, ms(t, 200)), vec![(F13, 1), (F13, 0)]);
+        assert!(engine.key(E, 2, ms(t, 700)).is_empty());
+        assert!(engine.key(E, 0, ms(t, 800)).is_empty());
+        assert_eq!(engine.key(E, 1, ms(t, 900)), vec![(E, 1)]);
+    }
+
+    #[test]
+    fn leader_sequence_is_dropped_on_a_mismatch_or_a_pause() {
+        let mut engine = chorded();
+        let t = Instant::now();
+        engine.key(RIGHT_ALT, 1, t);
+        assert!(engine.key(C, 1, ms(t, 100)).is_empty());
+        assert!(engine.key(C, 0, ms(t, 150)).is_empty());
+        assert_eq!(engine.key(T, 1, ms(t, 200)), vec![(T, 1)]);
+        engine.key(T, 0, ms(t, 250));
+
+        engine.key(RIGHT_ALT, 1, ms(t, 300));
+        engine.key(T, 1, ms(t, 400));
+        assert_eq!(engine.deadline(), Some(ms(t, 1400)));
+        assert!(engine.tick(ms(t, 1400)).is_empty());
+        assert_eq!(engine.key(E, 1, ms(t, 1500)), vec![(E, 1)]);
+        assert!(engine.key(T, 0, ms(t, 1600)).is_empty());
+    }
+
+    #[test]
+    fn key_held_before_the_leader_is_released_normally() {
+        let mut engine = chorded();
+        let t = Instant::now();
+        assert_eq!(engine.key(A, 1, t), vec![(48, 1)]);
+        engine.key(RIGHT_ALT, 1, ms(t, 10));
+        assert_eq!(engine.key(A, 0, ms(t, 20)), vec![(48, 0)]);
+        assert_eq!(engine.key(A, 1, ms(t, 30)), vec![(F14, 1), (F14, 0)]);
+    }
+}
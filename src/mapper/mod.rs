@@ -0,0 +1,2381 @@
+//! System-level key mapper daemon for special keyboard keys
+//!
+//! This module provides a background key remapping service that:
+//! - Grabs input devices via evdev for exclusive access
+//! - Creates a virtual keyboard via uinput to emit remapped keys
+//! - Runs as a systemd service for startup persistence
+//!
+//! ## How It Works
+//!
+//! 1. Discovers keyboard input devices in `/dev/input/`
+//! 2. Grabs exclusive access to the target devices (prevents duplicate events):
+//!    the keyboard plus any vendor hotkey devices the preset lists
+//! 3. Creates a virtual keyboard device via `/dev/uinput`
+//! 4. Reads raw input events from every grabbed device, applies that
+//!    device's remappings, and emits them all via the one uinput device
+//! 5. After a kernel buffer overrun (`SYN_DROPPED`), re-reads the held keys
+//!    with EVIOCGKEY and emits the presses/releases that were lost, so no
+//!    key is left stuck down on the virtual keyboard
+//!
+//! ## ASUS G14 Support
+//!
+//! Pre-configured mappings for ASUS ROG Zephyrus G14 special keys:
+//! - ROG key → configurable action (default: launch terminal or Super key)
+//! - Fan profile key → mapped to configurable target
+//! - AURA key → keyboard backlight cycle
+//! - Microphone mute → proper mic mute scancode
+//!
+//! ## Usage
+//!
+//! Run as daemon:
+//! ```bash
+//! sudo keyboard-testkit --mapper
+//! ```
+//!
+//! Run with ASUS G14 preset:
+//! ```bash
+//! sudo keyboard-testkit --mapper --preset asus-g14
+//! ```
+//!
+//! ## Preset Files
+//!
+//! Besides the built-in presets, every `*.toml` file in
+//! [`SYSTEM_PRESETS_DIR`] and in the user's [`presets_dir`] is a preset
+//! named after its file stem (user files override system ones, and both
+//! override built-ins of the same name):
+//!
+//! ```toml
+//! name = "ThinkPad X1 Carbon Gen 11"
+//! description = "Star key to Super, Fn+F11 to F13"
+//! # [source scancode, target scancode]
+//! mappings = [[374, 125], [240, 183]]
+//!
+//! [match]              # every rule given must hold; all are optional
+//! name = "AT Translated"
+//! vendor_id = 0x0001
+//! product_id = 0x0001
+//!
+//! # Dual-role keys: Caps Lock is Esc on tap, Left Ctrl on hold
+//! [[tap_hold]]
+//! key = 58
+//! tap = 1
+//! hold = 29
+//! tapping_term_ms = 180    # default 200, or the top-level tapping_term_ms
+//! permissive_hold = true   # default false, or the top-level permissive_hold
+//!
+//! # Layers: remaps switched by a layer key, momentary (default) or toggle
+//! [[layers]]
+//! name = "fn"
+//! key = 464
+//! fn_mode = "MapToFKeys"   # optional: start from an FN mode's table
+//! mappings = [[16, 113]]
+//!
+//! # Chords: J+K pressed together send Esc
+//! chord_timeout_ms = 50    # default window for every chord
+//! [[chords]]
+//! keys = [36, 37]
+//! output = 1
+//! timeout_ms = 40          # optional per-chord window
+//!
+//! # Leader sequences: Right Alt, then T, E sends F13
+//! [leader]
+//! key = 100
+//! timeout_ms = 1000        # default 1000, counted from the last key typed
+//! [[leader.sequences]]
+//! keys = [20, 18]
+//! output = 183
+//!
+//! # Further devices grabbed by the same daemon, each with its own
+//! # mappings (and tap_hold/layers/chords/leader entries) and a required [inputs.match] table
+//! [[inputs]]
+//! mappings = [[148, 186]]
+//! [inputs.match]
+//! name = "ThinkPad Extra Buttons"
+//! ```
+//!
+//! See [`engine`] for how tap-hold keys are decided, layers stack and
+//! chords and leader sequences are buffered.
+//!
+//! Files are validated when loaded; `--list-presets` shows the ones that
+//! failed and why.
+
+use crate::config::{presets_dir, Config, ConfigError};
+use crate::config::OemKeyConfig;
+use crate::keyboard::keymap::KeyCode;
+use crate::keyboard::remap::{FnKeyMode, DEFAULT_FN_SCANCODES};
+use crate::keyboard::{key_state_corrections, read_key_state, DeviceInfo};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use libc;
+
+pub mod engine;
+
+pub use engine::{
+    Chord, KeyBindings, KeyOutput, Layer, LayerActivation, Leader, MapperEngine, Sequence, TapHold,
+    DEFAULT_CHORD_TIMEOUT, DEFAULT_LEADER_TIMEOUT, DEFAULT_TAPPING_TERM,
+};
+
+/// Error type for mapper operations
+#[derive(Debug)]
+pub enum MapperError {
+    /// No suitable input devices found
+    NoDevices,
+    /// Permission denied (needs root/input group)
+    PermissionDenied(String),
+    /// Failed to create uinput device
+    UinputFailed(String),
+    /// IO error
+    Io(io::Error),
+    /// Device not found
+    DeviceNotFound(String),
+    /// A preset could not be found or failed validation
+    Preset(ConfigError),
+}
+
+impl std::fmt::Display for MapperError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapperError::NoDevices => write!(f, "No suitable keyboard input devices found"),
+            MapperError::PermissionDenied(msg) => write!(f, "Permission denied: {}", msg),
+            MapperError::UinputFailed(msg) => write!(f, "Failed to create uinput device: {}", msg),
+            MapperError::Io(e) => write!(f, "IO error: {}", e),
+            MapperError::DeviceNotFound(msg) => write!(f, "Device not found: {}", msg),
+            MapperError::Preset(e) => write!(f, "Preset error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MapperError {}
+
+impl From<io::Error> for MapperError {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::PermissionDenied {
+            MapperError::PermissionDenied(e.to_string())
+        } else {
+            MapperError::Io(e)
+        }
+    }
+}
+
+/// A raw input event from the kernel (matches struct input_event)
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct InputEvent {
+    tv_sec: i64,
+    tv_usec: i64,
+    event_type: u16,
+    code: u16,
+    value: i32,
+}
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_MSC: u16 = 0x04;
+const SYN_REPORT: u16 = 0x00;
+const SYN_DROPPED: u16 = 0x03;
+const INPUT_EVENT_SIZE: usize = std::mem::size_of::<InputEvent>();
+
+// uinput ioctl constants
+const UINPUT_MAX_NAME_SIZE: usize = 80;
+const UI_SET_EVBIT: libc::c_ulong = 0x40045564;
+const UI_SET_KEYBIT: libc::c_ulong = 0x40045565;
+const UI_DEV_CREATE: libc::c_ulong = 0x5501;
+const UI_DEV_DESTROY: libc::c_ulong = 0x5502;
+/// UI_GET_SYSNAME with a 64-byte buffer
+const UI_GET_SYSNAME: libc::c_ulong = 0x8040552C;
+
+/// Name of the mapper's virtual keyboard
+const UINPUT_DEVICE_NAME: &str = "Keyboard-TestKit Virtual Keyboard";
+
+// EVIOCGRAB ioctl for exclusive device access
+const EVIOCGRAB: libc::c_ulong = 0x40044590;
+
+/// uinput_user_dev structure for device setup
+#[repr(C)]
+struct UinputUserDev {
+    name: [u8; UINPUT_MAX_NAME_SIZE],
+    id_bustype: u16,
+    id_vendor: u16,
+    id_product: u16,
+    id_version: u16,
+    ff_effects_max: u32,
+    absmax: [i32; 64],
+    absmin: [i32; 64],
+    absfuzz: [i32; 64],
+    absflat: [i32; 64],
+}
+
+/// A uinput virtual keyboard, destroyed on drop
+pub struct UinputDevice {
+    fd: i32,
+}
+
+impl UinputDevice {
+    /// Create a virtual keyboard that can emit every key code
+    pub fn create(name: &str) -> Result<Self, MapperError> {
+        // Open uinput
+        let uinput_path = if std::path::Path::new("/dev/uinput").exists() {
+            "/dev/uinput"
+        } else {
+            "/dev/input/uinput"
+        };
+
+        let uinput_cstr = std::ffi::CString::new(uinput_path)
+            .map_err(|_| MapperError::UinputFailed("Invalid uinput path".to_string()))?;
+
+        let fd = unsafe { libc::open(uinput_cstr.as_ptr(), libc::O_WRONLY | libc::O_NONBLOCK) };
+
+        if fd < 0 {
+            return Err(MapperError::UinputFailed(format!(
+                "Cannot open {}. Ensure the uinput module is loaded: sudo modprobe uinput",
+                uinput_path
+            )));
+        }
+
+        // SAFETY: All ioctl calls below use valid fd and kernel-defined constants.
+        // UI_SET_EVBIT/UI_SET_KEYBIT configure which event types and keys the
+        // virtual device supports before creation.
+        unsafe {
+            // Enable EV_KEY and EV_SYN event types
+            if libc::ioctl(fd, UI_SET_EVBIT, EV_KEY as libc::c_int) < 0 {
+                libc::close(fd);
+                return Err(MapperError::UinputFailed(
+                    "Failed to set EV_KEY".to_string(),
+                ));
+            }
+            if libc::ioctl(fd, UI_SET_EVBIT, EV_SYN as libc::c_int) < 0 {
+                libc::close(fd);
+                return Err(MapperError::UinputFailed(
+                    "Failed to set EV_SYN".to_string(),
+                ));
+            }
+            // Also support EV_MSC for scancode passthrough
+            if libc::ioctl(fd, UI_SET_EVBIT, EV_MSC as libc::c_int) < 0 {
+                libc::close(fd);
+                return Err(MapperError::UinputFailed(
+                    "Failed to set EV_MSC".to_string(),
+                ));
+            }
+
+            // Enable all key codes 0-767 (KEY_MAX)
+            for key in 0..768 {
+                libc::ioctl(fd, UI_SET_KEYBIT, key as libc::c_int);
+            }
+        }
+
+        // Set up the device info
+        let mut dev = UinputUserDev {
+            name: [0u8; UINPUT_MAX_NAME_SIZE],
+            id_bustype: 0x03, // BUS_USB
+            id_vendor: 0x1234,
+            id_product: 0x5678,
+            id_version: 1,
+            ff_effects_max: 0,
+            absmax: [0; 64],
+            absmin: [0; 64],
+            absfuzz: [0; 64],
+            absflat: [0; 64],
+        };
+
+        let dev_name = name.as_bytes();
+        let name_len = dev_name.len().min(UINPUT_MAX_NAME_SIZE - 1);
+        dev.name[..name_len].copy_from_slice(&dev_name[..name_len]);
+
+        // Write device info
+        // SAFETY: Writing the uinput_user_dev struct to the fd is the standard
+        // way to configure a uinput device before creation.
+        let dev_bytes = unsafe {
+            std::slice::from_raw_parts(
+                &dev as *const UinputUserDev as *const u8,
+                std::mem::size_of::<UinputUserDev>(),
+            )
+        };
+
+        let written = unsafe {
+            libc::write(fd, dev_bytes.as_ptr() as *const libc::c_void, dev_bytes.len())
+        };
+
+        if written < 0 {
+            unsafe { libc::close(fd) };
+            return Err(MapperError::UinputFailed(
+                "Failed to write device info".to_string(),
+            ));
+        }
+
+        // Create the device
+        // SAFETY: UI_DEV_CREATE finalizes the virtual device. The fd is valid
+        // and the device info has been written.
+        if unsafe { libc::ioctl(fd, UI_DEV_CREATE) } < 0 {
+            unsafe { libc::close(fd) };
+            return Err(MapperError::UinputFailed(
+                "Failed to create uinput device".to_string(),
+            ));
+        }
+
+        eprintln!("Created virtual keyboard device");
+        Ok(Self { fd })
+    }
+
+    /// Write an input event to the uinput device
+    pub fn emit(&self, event_type: u16, code: u16, value: i32) {
+        let event = InputEvent {
+            tv_sec: 0,
+            tv_usec: 0,
+            event_type,
+            code,
+            value,
+        };
+
+        // SAFETY: Writing a valid InputEvent struct to the uinput fd emits
+        // the corresponding input event. The fd is valid and the struct is repr(C).
+        unsafe {
+            libc::write(
+                self.fd,
+                &event as *const InputEvent as *const libc::c_void,
+                INPUT_EVENT_SIZE,
+            );
+        }
+    }
+
+    /// Emit a SYN_REPORT to end the current frame
+    pub fn syn(&self) {
+        self.emit(EV_SYN, 0, 0);
+    }
+
+    /// The `/dev/input/event*` node the kernel created for this device,
+    /// waiting up to `timeout` for udev to create it
+    pub fn event_node(&self, timeout: std::time::Duration) -> Result<PathBuf, MapperError> {
+        let mut sysname = [0u8; 64];
+        // SAFETY: UI_GET_SYSNAME writes at most 64 bytes (the size encoded in
+        // the request) into `sysname`, a live local array of that size.
+        if unsafe { libc::ioctl(self.fd, UI_GET_SYSNAME, sysname.as_mut_ptr()) } < 0 {
+            return Err(MapperError::UinputFailed(
+                "Failed to get uinput device name".to_string(),
+            ));
+        }
+        let len = sysname.iter().position(|&b| b == 0).unwrap_or(sysname.len());
+        let sysname = String::from_utf8_lossy(&sysname[..len]).to_string();
+        let sys_dir = PathBuf::from("/sys/devices/virtual/input").join(&sysname);
+
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            let node = fs::read_dir(&sys_dir).ok().and_then(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| e.file_name().to_string_lossy().to_string())
+                    .find(|name| name.starts_with("event"))
+                    .map(|name| PathBuf::from("/dev/input").join(name))
+            });
+            match node {
+                Some(node) if node.exists() => return Ok(node),
+                _ if std::time::Instant::now() >= deadline => {
+                    return Err(MapperError::DeviceNotFound(format!(
+                        "No event node for virtual device {}",
+                        sysname
+                    )));
+                }
+                _ => std::thread::sleep(std::time::Duration::from_millis(10)),
+            }
+        }
+    }
+}
+
+impl Drop for UinputDevice {
+    fn drop(&mut self) {
+        // SAFETY: Destroying the uinput device and closing its fd are cleanup
+        // operations on a valid file descriptor we own.
+        unsafe {
+            libc::ioctl(self.fd, UI_DEV_DESTROY);
+            libc::close(self.fd);
+        }
+    }
+}
+
+/// Vendor-specific key mapping preset
+#[derive(Debug, Clone)]
+pub struct MapperPreset {
+    /// Human-readable name
+    pub name: String,
+    /// Description of what this preset does
+    pub description: String,
+    /// Key mappings: source scancode → target scancode
+    pub mappings: HashMap<u16, u16>,
+    /// Dual-role keys: one key on tap, another while held
+    pub tap_holds: Vec<TapHold>,
+    /// Layers of remaps switched by a layer key
+    pub layers: Vec<Layer>,
+    /// Keys pressed together that send one key
+    pub chords: Vec<Chord>,
+    /// Leader key and the sequences typed after it
+    pub leader: Option<Leader>,
+    /// Device name pattern to match (substring match against /sys device name)
+    pub device_match: Option<String>,
+    /// USB/bus vendor ID the device must have
+    pub vendor_id: Option<u16>,
+    /// Product ID the device must have (requires `vendor_id`)
+    pub product_id: Option<u16>,
+    /// File the preset was loaded from; `None` for built-in presets
+    pub source: Option<PathBuf>,
+    /// Further devices grabbed alongside the matched keyboard, each with
+    /// its own mappings (e.g. a vendor hotkeys device)
+    pub inputs: Vec<PresetInput>,
+}
+
+/// An extra device a preset grabs, such as the "Asus WMI hotkeys" or
+/// "ThinkPad Extra Buttons" pseudo-device that carries a laptop's special keys
+#[derive(Debug, Clone)]
+pub struct PresetInput {
+    /// Key mappings applied to events from this device
+    pub mappings: HashMap<u16, u16>,
+    /// Dual-role keys on this device
+    pub tap_holds: Vec<TapHold>,
+    /// Layers on this device
+    pub layers: Vec<Layer>,
+    /// Chords on this device
+    pub chords: Vec<Chord>,
+    /// Leader key and sequences on this device
+    pub leader: Option<Leader>,
+    /// Device name pattern to match (substring match against /sys device name)
+    pub device_match: Option<String>,
+    /// USB/bus vendor ID the device must have
+    pub vendor_id: Option<u16>,
+    /// Product ID the device must have (requires `vendor_id`)
+    pub product_id: Option<u16>,
+}
+
+impl PresetInput {
+    /// Whether the device satisfies every match rule
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        rules_match(self.device_match.as_deref(), self.vendor_id, self.product_id, device)
+    }
+
+    /// What the mapper does with this device's keys
+    pub fn bindings(&self) -> KeyBindings {
+        KeyBindings {
+            remaps: self.mappings.clone(),
+            tap_holds: self.tap_holds.clone(),
+            layers: self.layers.clone(),
+            chords: self.chords.clone(),
+            leader: self.leader.clone(),
+        }
+    }
+}
+
+/// System-wide preset directory, read before the user's [`presets_dir`]
+pub const SYSTEM_PRESETS_DIR: &str = "/etc/keyboard-testkit/presets";
+
+/// Highest key code the kernel accepts (KEY_MAX)
+const KEY_MAX: u16 = 0x2FF;
+
+/// On-disk form of a preset
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PresetFile {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default, rename = "match")]
+    device: DeviceMatch,
+    #[serde(default)]
+    mappings: Vec<(u16, u16)>,
+    /// Default tapping term for `tap_hold` entries, in milliseconds
+    tapping_term_ms: Option<u64>,
+    /// Default permissive-hold setting for `tap_hold` entries
+    #[serde(default)]
+    permissive_hold: bool,
+    /// Default window for `chords` entries, in milliseconds
+    chord_timeout_ms: Option<u64>,
+    #[serde(default)]
+    tap_hold: Vec<TapHoldFile>,
+    #[serde(default)]
+    layers: Vec<LayerFile>,
+    #[serde(default)]
+    chords: Vec<ChordFile>,
+    leader: Option<LeaderFile>,
+    #[serde(default)]
+    inputs: Vec<PresetInputFile>,
+}
+
+impl PresetFile {
+    fn tables(&self) -> BindingTables<'_> {
+        BindingTables {
+            mappings: &self.mappings,
+            tap_hold: &self.tap_hold,
+            layers: &self.layers,
+            chords: &self.chords,
+            leader: self.leader.as_ref(),
+        }
+    }
+}
+
+/// On-disk form of an `[[inputs]]` entry
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PresetInputFile {
+    #[serde(rename = "match")]
+    device: DeviceMatch,
+    #[serde(default)]
+    mappings: Vec<(u16, u16)>,
+    #[serde(default)]
+    tap_hold: Vec<TapHoldFile>,
+    #[serde(default)]
+    layers: Vec<LayerFile>,
+    #[serde(default)]
+    chords: Vec<ChordFile>,
+    leader: Option<LeaderFile>,
+}
+
+impl PresetInputFile {
+    fn tables(&self) -> BindingTables<'_> {
+        BindingTables {
+            mappings: &self.mappings,
+            tap_hold: &self.tap_hold,
+            layers: &self.layers,
+            chords: &self.chords,
+            leader: self.leader.as_ref(),
+        }
+    }
+}
+
+/// On-disk form of a `[[tap_hold]]` entry; unset options fall back to the
+/// preset's defaults
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TapHoldFile {
+    key: u16,
+    tap: u16,
+    hold: u16,
+    tapping_term_ms: Option<u64>,
+    permissive_hold: Option<bool>,
+}
+
+/// On-disk form of a `[[layers]]` entry
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayerFile {
+    name: Option<String>,
+    key: u16,
+    #[serde(default)]
+    activation: LayerActivation,
+    /// Start from the table of an FN mode (`MapToFKeys` or `MapToMedia`)
+    fn_mode: Option<FnKeyMode>,
+    #[serde(default)]
+    mappings: Vec<(u16, u16)>,
+}
+
+/// On-disk form of a `[[chords]]` entry
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChordFile {
+    keys: Vec<u16>,
+    output: u16,
+    timeout_ms: Option<u64>,
+}
+
+/// On-disk form of the `[leader]` table
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LeaderFile {
+    key: u16,
+    timeout_ms: Option<u64>,
+    sequences: Vec<SequenceFile>,
+}
+
+/// On-disk form of a `[[leader.sequences]]` entry
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SequenceFile {
+    keys: Vec<u16>,
+    output: u16,
+}
+
+/// The binding tables of a preset or of one of its `[[inputs]]`
+struct BindingTables<'a> {
+    mappings: &'a [(u16, u16)],
+    tap_hold: &'a [TapHoldFile],
+    layers: &'a [LayerFile],
+    chords: &'a [ChordFile],
+    leader: Option<&'a LeaderFile>,
+}
+
+/// Preset-wide defaults for options entries may leave unset
+struct BindingDefaults {
+    tapping_term_ms: u64,
+    permissive_hold: bool,
+    chord_timeout_ms: u64,
+}
+
+/// Longest tapping term a preset may set
+const MAX_TAPPING_TERM_MS: u64 = 5000;
+
+/// Longest chord window a preset may set
+const MAX_CHORD_TIMEOUT_MS: u64 = 1000;
+
+/// Longest leader timeout a preset may set
+const MAX_LEADER_TIMEOUT_MS: u64 = 10_000;
+
+/// Rules selecting the devices a preset file is meant for
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DeviceMatch {
+    name: Option<String>,
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+}
+
+impl DeviceMatch {
+    /// Check the rules of the `[match]` table called `table`
+    fn validate(&self, table: &str) -> Result<(), ConfigError> {
+        if self.name.as_deref().is_some_and(|n| n.trim().is_empty()) {
+            return Err(ConfigError::Invalid(format!("{}.name is empty", table)));
+        }
+        if self.product_id.is_some() && self.vendor_id.is_none() {
+            return Err(ConfigError::Invalid(format!(
+                "{0}.product_id needs {0}.vendor_id",
+                table
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Check a preset file's `[source, target]` pairs and build the mapping table
+fn parse_mappings(pairs: &[(u16, u16)]) -> Result<HashMap<u16, u16>, ConfigError> {
+    let mut mappings = HashMap::new();
+    for &(from, to) in pairs {
+        for code in [from, to] {
+            if code == 0 || code > KEY_MAX {
+                return Err(ConfigError::Invalid(format!(
+                    "key code {} in mapping [{}, {}] is outside 1-{}",
+                    code, from, to, KEY_MAX
+                )));
+            }
+        }
+        if mappings.insert(from, to).is_some() {
+            return Err(ConfigError::Invalid(format!(
+                "key code {} is mapped more than once",
+                from
+            )));
+        }
+    }
+    Ok(mappings)
+}
+
+/// Check a preset file's `tap_hold` entries against its mappings and build
+/// the dual-role keys, filling unset options from the preset's defaults
+fn parse_tap_holds(
+    entries: &[TapHoldFile],
+    mappings: &HashMap<u16, u16>,
+    default_term_ms: u64,
+    default_permissive: bool,
+) -> Result<Vec<TapHold>, ConfigError> {
+    let mut tap_holds: Vec<TapHold> = Vec::new();
+    for entry in entries {
+        for code in [entry.key, entry.tap, entry.hold] {
+            if code == 0 || code > KEY_MAX {
+                return Err(ConfigError::Invalid(format!(
+                    "key code {} in tap_hold for key {} is outside 1-{}",
+                    code, entry.key, KEY_MAX
+                )));
+            }
+        }
+        if mappings.contains_key(&entry.key) || tap_holds.iter().any(|th| th.key == entry.key) {
+            return Err(ConfigError::Invalid(format!(
+                "key code {} is mapped more than once",
+                entry.key
+            )));
+        }
+        let term_ms = entry.tapping_term_ms.unwrap_or(default_term_ms);
+        if term_ms == 0 || term_ms > MAX_TAPPING_TERM_MS {
+            return Err(ConfigError::Invalid(format!(
+                "tapping_term_ms {} is outside 1-{}",
+                term_ms, MAX_TAPPING_TERM_MS
+            )));
+        }
+        tap_holds.push(TapHold {
+            key: entry.key,
+            tap: entry.tap,
+            hold: entry.hold,
+            tapping_term: Duration::from_millis(term_ms),
+            permissive_hold: entry.permissive_hold.unwrap_or(default_permissive),
+        });
+    }
+    Ok(tap_holds)
+}
+
+/// Check a preset file's `layers` entries and build the layers. A layer
+/// key cannot also be remapped, dual-role or another layer's key.
+fn parse_layers(
+    entries: &[LayerFile],
+    mappings: &HashMap<u16, u16>,
+    tap_holds: &[TapHold],
+) -> Result<Vec<Layer>, ConfigError> {
+    let mut layers: Vec<Layer> = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let name = match &entry.name {
+            Some(name) if name.trim().is_empty() => {
+                return Err(ConfigError::Invalid(format!("layers[{}].name is empty", i + 1)));
+            }
+            Some(name) => name.clone(),
+            None => format!("layer {}", i + 1),
+        };
+        if entry.key == 0 || entry.key > KEY_MAX {
+            return Err(ConfigError::Invalid(format!(
+                "layer '{}' key {} is outside 1-{}",
+                name, entry.key, KEY_MAX
+            )));
+        }
+        if mappings.contains_key(&entry.key)
+            || tap_holds.iter().any(|th| th.key == entry.key)
+            || layers.iter().any(|layer| layer.key == entry.key)
+        {
+            return Err(ConfigError::Invalid(format!(
+                "key code {} is mapped more than once",
+                entry.key
+            )));
+        }
+
+        let mut remaps = match entry.fn_mode {
+            Some(mode) => mode.combo_table().ok_or_else(|| {
+                ConfigError::Invalid(format!(
+                    "layer '{}' fn_mode must be MapToFKeys or MapToMedia",
+                    name
+                ))
+            })?,
+            None => HashMap::new(),
+        };
+        // Own mappings override the FN table
+        for (from, to) in parse_mappings(&entry.mappings)? {
+            remaps.insert(from, to);
+        }
+        if remaps.is_empty() {
+            return Err(ConfigError::Invalid(format!("layer '{}' has no mappings", name)));
+        }
+        layers.push(Layer {
+            name,
+            key: entry.key,
+            activation: entry.activation,
+            remaps,
+        });
+    }
+    Ok(layers)
+}
+
+/// Check that a key code is one the kernel accepts
+fn check_key(code: u16, what: &str) -> Result<(), ConfigError> {
+    if code == 0 || code > KEY_MAX {
+        return Err(ConfigError::Invalid(format!(
+            "key code {} in {} is outside 1-{}",
+            code, what, KEY_MAX
+        )));
+    }
+    Ok(())
+}
+
+/// Check a timeout in milliseconds against its limit
+fn check_timeout(ms: u64, field: &str, max: u64) -> Result<Duration, ConfigError> {
+    if ms == 0 || ms > max {
+        return Err(ConfigError::Invalid(format!(
+            "{} {} is outside 1-{}",
+            field, ms, max
+        )));
+    }
+    Ok(Duration::from_millis(ms))
+}
+
+/// Check a preset file's `chords` entries and build the chords
+fn parse_chords(entries: &[ChordFile], default_timeout_ms: u64) -> Result<Vec<Chord>, ConfigError> {
+    let mut chords: Vec<Chord> = Vec::new();
+    for entry in entries {
+        let what = format!("chord {:?}", entry.keys);
+        if entry.keys.len() < 2 {
+            return Err(ConfigError::Invalid(format!("{} needs at least two keys", what)));
+        }
+        for (i, &key) in entry.keys.iter().enumerate() {
+            check_key(key, &what)?;
+            if entry.keys[..i].contains(&key) {
+                return Err(ConfigError::Invalid(format!("{} lists key {} twice", what, key)));
+            }
+        }
+        check_key(entry.output, &what)?;
+        if chords
+            .iter()
+            .any(|c| c.keys.len() == entry.keys.len() && entry.keys.iter().all(|k| c.keys.contains(k)))
+        {
+            return Err(ConfigError::Invalid(format!("{} is defined more than once", what)));
+        }
+        let timeout = check_timeout(
+            entry.timeout_ms.unwrap_or(default_timeout_ms),
+            "chord timeout_ms",
+            MAX_CHORD_TIMEOUT_MS,
+        )?;
+        chords.push(Chord {
+            keys: entry.keys.clone(),
+            output: entry.output,
+            timeout,
+        });
+    }
+    Ok(chords)
+}
+
+/// Check a preset file's `[leader]` table against its other bindings and
+/// build the leader. The leader key cannot be bound elsewhere, and no
+/// sequence may start another, since the shorter one would always fire.
+fn parse_leader(entry: &LeaderFile, bindings: &KeyBindings) -> Result<Leader, ConfigError> {
+    check_key(entry.key, "leader.key")?;
+    if bindings.remaps.contains_key(&entry.key)
+        || bindings.tap_holds.iter().any(|th| th.key == entry.key)
+        || bindings.layers.iter().any(|layer| layer.key == entry.key)
+        || bindings.chords.iter().any(|c| c.keys.contains(&entry.key))
+    {
+        return Err(ConfigError::Invalid(format!(
+            "key code {} is mapped more than once",
+            entry.key
+        )));
+    }
+    let timeout = check_timeout(
+        entry
+            .timeout_ms
+            .unwrap_or(DEFAULT_LEADER_TIMEOUT.as_millis() as u64),
+        "leader.timeout_ms",
+        MAX_LEADER_TIMEOUT_MS,
+    )?;
+    if entry.sequences.is_empty() {
+        return Err(ConfigError::Invalid("leader has no sequences".to_string()));
+    }
+
+    let mut sequences: Vec<Sequence> = Vec::new();
+    for sequence in &entry.sequences {
+        let what = format!("leader sequence {:?}", sequence.keys);
+        if sequence.keys.is_empty() {
+            return Err(ConfigError::Invalid("leader sequence has no keys".to_string()));
+        }
+        for &key in &sequence.keys {
+            check_key(key, &what)?;
+        }
+        check_key(sequence.output, &what)?;
+        if let Some(other) = sequences
+            .iter()
+            .find(|s| s.keys.starts_with(&sequence.keys) || sequence.keys.starts_with(&s.keys))
+        {
+            return Err(ConfigError::Invalid(format!(
+                "{} and {:?} start the same way; the shorter would always fire",
+                what, other.keys
+            )));
+        }
+        sequences.push(Sequence {
+            keys: sequence.keys.clone(),
+            output: sequence.output,
+        });
+    }
+    Ok(Leader {
+        key: entry.key,
+        timeout,
+        sequences,
+    })
+}
+
+/// Check and build the bindings of a preset or one of its inputs
+fn parse_bindings(
+    tables: BindingTables<'_>,
+    defaults: &BindingDefaults,
+) -> Result<KeyBindings, ConfigError> {
+    let remaps = parse_mappings(tables.mappings)?;
+    let tap_holds = parse_tap_holds(
+        tables.tap_hold,
+        &remaps,
+        defaults.tapping_term_ms,
+        defaults.permissive_hold,
+    )?;
+    let layers = parse_layers(tables.layers, &remaps, &tap_holds)?;
+    let mut bindings = KeyBindings {
+        remaps,
+        tap_holds,
+        layers,
+        chords: parse_chords(tables.chords, defaults.chord_timeout_ms)?,
+        leader: None,
+    };
+    if let Some(leader) = tables.leader {
+        bindings.leader = Some(parse_leader(leader, &bindings)?);
+    }
+    Ok(bindings)
+}
+
+/// Whether a device satisfies every given match rule
+fn rules_match(
+    pattern: Option<&str>,
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+    device: &DeviceInfo,
+) -> bool {
+    pattern.is_none_or(|p| device.name.to_lowercase().contains(&p.to_lowercase()))
+        && vendor_id.is_none_or(|id| id == device.vendor_id)
+        && product_id.is_none_or(|id| id == device.product_id)
+}
+
+impl MapperPreset {
+    /// Create the ASUS ROG Zephyrus G14 preset
+    pub fn asus_g14() -> Self {
+        let mut mappings = HashMap::new();
+
+        // ROG key (PROG1 = 148) → Super/Meta key for app launcher
+        mappings.insert(148, 125); // KEY_PROG1 → KEY_LSUPER
+
+        // AURA key (PROG2 = 149) → Keyboard backlight toggle
+        mappings.insert(149, 228); // KEY_PROG2 → KEY_KBDILLUMTOGGLE
+
+        // Fan profile key (PROG3 = 202) → mapped to F13 (unused F-key, can be
+        // bound to custom scripts via desktop environment)
+        mappings.insert(202, 183); // KEY_PROG3 → KEY_F13
+
+        // Mic mute key — ensure it emits standard KEY_MICMUTE (248)
+        // Some ASUS keyboards send a vendor-specific code instead
+        mappings.insert(248, 248); // Pass through (identity, ensures recognition)
+
+        // Screenshot key (if present) → Print Screen
+        mappings.insert(414, 99); // KEY_SELECTIVE_SCREENSHOT → KEY_PRINT
+
+        Self {
+            name: "ASUS ROG Zephyrus G14".to_string(),
+            description: "Maps ROG key, AURA, fan profile, mic mute, and screenshot keys"
+                .to_string(),
+            mappings,
+            tap_holds: Vec::new(),
+            layers: Vec::new(),
+            chords: Vec::new(),
+            leader: None,
+            device_match: Some("asus".to_string()),
+            vendor_id: None,
+            product_id: None,
+            source: None,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Create a generic laptop preset
+    pub fn generic_laptop() -> Self {
+        let mut mappings = HashMap::new();
+
+        // Common laptop keys that may need remapping
+        // Mic mute passthrough
+        mappings.insert(248, 248);
+        // Airplane mode passthrough
+        mappings.insert(247, 247);
+
+        Self {
+            name: "Generic Laptop".to_string(),
+            description: "Basic mappings for common laptop special keys".to_string(),
+            mappings,
+            tap_holds: Vec::new(),
+            layers: Vec::new(),
+            chords: Vec::new(),
+            leader: None,
+            device_match: None,
+            vendor_id: None,
+            product_id: None,
+            source: None,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Load and validate a preset file.
+    ///
+    /// A preset without a `name` is named after its file stem.
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path)?;
+        let file: PresetFile = toml::from_str(&contents)?;
+
+        let defaults = BindingDefaults {
+            tapping_term_ms: file
+                .tapping_term_ms
+                .unwrap_or(DEFAULT_TAPPING_TERM.as_millis() as u64),
+            permissive_hold: file.permissive_hold,
+            chord_timeout_ms: file
+                .chord_timeout_ms
+                .unwrap_or(DEFAULT_CHORD_TIMEOUT.as_millis() as u64),
+        };
+        let bindings = parse_bindings(file.tables(), &defaults)?;
+        if bindings.is_empty() && file.inputs.is_empty() {
+            return Err(ConfigError::Invalid("preset has no mappings".to_string()));
+        }
+        file.device.validate("match")?;
+
+        let mut inputs = Vec::new();
+        for (i, input) in file.inputs.iter().enumerate() {
+            let table = format!("inputs[{}]", i + 1);
+            if input.device.name.is_none() && input.device.vendor_id.is_none() {
+                return Err(ConfigError::Invalid(format!(
+                    "{}.match needs a name or vendor_id",
+                    table
+                )));
+            }
+            input.device.validate(&format!("{}.match", table))?;
+            let input_bindings = parse_bindings(input.tables(), &defaults)?;
+            if input_bindings.is_empty() {
+                return Err(ConfigError::Invalid(format!("{} has no mappings", table)));
+            }
+            inputs.push(PresetInput {
+                mappings: input_bindings.remaps,
+                tap_holds: input_bindings.tap_holds,
+                layers: input_bindings.layers,
+                chords: input_bindings.chords,
+                leader: input_bindings.leader,
+                device_match: input.device.name.clone(),
+                vendor_id: input.device.vendor_id,
+                product_id: input.device.product_id,
+            });
+        }
+
+        let name = if file.name.is_empty() {
+            preset_id(path)
+        } else {
+            file.name
+        };
+        Ok(Self {
+            name,
+            description: file.description,
+            mappings: bindings.remaps,
+            tap_holds: bindings.tap_holds,
+            layers: bindings.layers,
+            chords: bindings.chords,
+            leader: bindings.leader,
+            device_match: file.device.name,
+            vendor_id: file.device.vendor_id,
+            product_id: file.device.product_id,
+            source: Some(path.to_path_buf()),
+            inputs,
+        })
+    }
+
+    /// Load a preset given either a file path or a preset name
+    pub fn resolve(name_or_path: &str) -> Result<Self, ConfigError> {
+        let path = Path::new(name_or_path);
+        if path.exists() {
+            return Self::load_from(path);
+        }
+        PresetCatalog::load().take(name_or_path)
+    }
+
+    /// Get a preset by name, built-in or from a preset directory
+    pub fn by_name(name: &str) -> Option<Self> {
+        PresetCatalog::load().get(name).cloned()
+    }
+
+    /// Find the preset whose match rules select a device, preferring
+    /// preset files over built-ins
+    pub fn for_device(device: &DeviceInfo) -> Option<Self> {
+        PresetCatalog::load()
+            .presets
+            .into_iter()
+            .rev()
+            .map(|(_, preset)| preset)
+            .find(|p| p.matches(device))
+    }
+
+    /// Whether the device satisfies every match rule; a preset without
+    /// rules matches nothing
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        if self.device_match.is_none() && self.vendor_id.is_none() {
+            return false;
+        }
+        rules_match(self.device_match.as_deref(), self.vendor_id, self.product_id, device)
+    }
+
+    /// What the mapper does with the matched keyboard's keys
+    pub fn bindings(&self) -> KeyBindings {
+        KeyBindings {
+            remaps: self.mappings.clone(),
+            tap_holds: self.tap_holds.clone(),
+            layers: self.layers.clone(),
+            chords: self.chords.clone(),
+            leader: self.leader.clone(),
+        }
+    }
+
+    /// List all available preset names with their descriptions
+    pub fn available() -> Vec<(String, String)> {
+        PresetCatalog::load()
+            .presets
+            .into_iter()
+            .map(|(id, preset)| (id, preset.description))
+            .collect()
+    }
+}
+
+/// Preset name for a file: its stem, lowercased
+fn preset_id(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Canonical name of a built-in preset, accepting its aliases
+fn builtin_id(name: &str) -> Option<&'static str> {
+    match name {
+        "asus-g14" | "asus_g14" | "g14" | "asus" => Some("asus-g14"),
+        "generic" | "laptop" => Some("generic"),
+        _ => None,
+    }
+}
+
+/// Directories searched for preset files, lowest priority first
+pub fn preset_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from(SYSTEM_PRESETS_DIR)];
+    dirs.extend(presets_dir().ok());
+    dirs
+}
+
+/// Every preset available by name, and the preset files that failed to load
+#[derive(Debug)]
+pub struct PresetCatalog {
+    /// (name, preset), built-ins first, then files in directory order
+    pub presets: Vec<(String, MapperPreset)>,
+    /// Preset files that could not be read or failed validation
+    pub errors: Vec<(PathBuf, ConfigError)>,
+}
+
+impl PresetCatalog {
+    /// Built-in presets plus the files in [`preset_dirs`]
+    pub fn load() -> Self {
+        Self::load_from_dirs(&preset_dirs())
+    }
+
+    /// Built-in presets plus every `*.toml` in `dirs`; a file overrides any
+    /// earlier preset of the same name
+    pub fn load_from_dirs(dirs: &[PathBuf]) -> Self {
+        let mut catalog = Self {
+            presets: vec![
+                ("asus-g14".to_string(), MapperPreset::asus_g14()),
+                ("generic".to_string(), MapperPreset::generic_laptop()),
+            ],
+            errors: Vec::new(),
+        };
+
+        for dir in dirs {
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            let mut paths: Vec<PathBuf> = entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|e| e == "toml"))
+                .collect();
+            paths.sort();
+
+            for path in paths {
+                match MapperPreset::load_from(&path) {
+                    Ok(preset) => {
+                        let id = preset_id(&path);
+                        catalog.presets.retain(|(existing, _)| *existing != id);
+                        catalog.presets.push((id, preset));
+                    }
+                    Err(e) => catalog.errors.push((path, e)),
+                }
+            }
+        }
+        catalog
+    }
+
+    /// Look up a preset by name (case-insensitive) or built-in alias
+    pub fn get(&self, name: &str) -> Option<&MapperPreset> {
+        let name = name.to_lowercase();
+        let find = |id: &str| {
+            self.presets
+                .iter()
+                .find(|(existing, _)| existing == id)
+                .map(|(_, preset)| preset)
+        };
+        find(&name).or_else(|| builtin_id(&name).and_then(find))
+    }
+
+    /// Take a preset by name, or the reason a preset file of that name
+    /// could not be loaded
+    pub fn take(mut self, name: &str) -> Result<MapperPreset, ConfigError> {
+        if let Some(preset) = self.get(name) {
+            return Ok(preset.clone());
+        }
+        let id = name.to_lowercase();
+        match self.errors.iter().position(|(path, _)| preset_id(path) == id) {
+            Some(index) => Err(self.errors.swap_remove(index).1),
+            None => Err(ConfigError::Invalid(format!("unknown preset '{}'", name))),
+        }
+    }
+}
+
+/// How long the mapper waits in poll before checking for shutdown
+const POLL_TIMEOUT_MS: i32 = 100;
+
+/// A grabbed input device and the mappings applied to its events
+struct MapperInput {
+    /// Input device file (non-blocking)
+    file: File,
+    /// Input device path (for logging)
+    path: PathBuf,
+    /// Applies this device's bindings to its key events
+    engine: MapperEngine,
+    /// Source keys currently held down on this device
+    held: HashSet<u16>,
+    /// After SYN_DROPPED: skipping events until the next SYN_REPORT
+    dropping: bool,
+}
+
+impl MapperInput {
+    /// Open a device and grab exclusive access to it
+    fn open(path: PathBuf, bindings: &KeyBindings) -> Result<Self, MapperError> {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&path)
+            .map_err(|e| {
+                if e.kind() == io::ErrorKind::PermissionDenied {
+                    MapperError::PermissionDenied(format!(
+                        "Cannot open {}. Run with sudo or add user to 'input' group.",
+                        path.display()
+                    ))
+                } else {
+                    MapperError::Io(e)
+                }
+            })?;
+
+        // SAFETY: EVIOCGRAB is a safe ioctl that grants exclusive access to an evdev device.
+        // The fd is valid because we just opened it successfully.
+        let grab_result = unsafe { libc::ioctl(file.as_raw_fd(), EVIOCGRAB, 1 as libc::c_int) };
+        if grab_result < 0 {
+            return Err(MapperError::PermissionDenied(format!(
+                "Failed to grab exclusive access to {}. Is another mapper running?",
+                path.display()
+            )));
+        }
+
+        Ok(Self {
+            file,
+            path,
+            engine: MapperEngine::new(bindings),
+            held: HashSet::new(),
+            dropping: false,
+        })
+    }
+}
+
+impl Drop for MapperInput {
+    fn drop(&mut self) {
+        // SAFETY: Releasing the grab is a cleanup operation on a valid file
+        // descriptor.
+        unsafe {
+            libc::ioctl(self.file.as_raw_fd(), EVIOCGRAB, 0 as libc::c_int);
+        }
+        eprintln!("Key mapper released {}", self.path.display());
+    }
+}
+
+/// Output keys held down on the virtual keyboard, counted per input
+///
+/// Two grabbed devices can map to the same output key. The virtual
+/// keyboard sees one press when the first of them goes down and one
+/// release when the last comes up, so letting go of one does not release
+/// a key the other still holds.
+#[derive(Debug, Default)]
+struct OutputKeys {
+    held: HashMap<u16, usize>,
+}
+
+impl OutputKeys {
+    /// Record a key event; returns whether it should be emitted
+    fn update(&mut self, code: u16, value: i32) -> bool {
+        match value {
+            1 => {
+                let count = self.held.entry(code).or_insert(0);
+                *count += 1;
+                *count == 1
+            }
+            0 => match self.held.get_mut(&code) {
+                Some(count) if *count > 1 => {
+                    *count -= 1;
+                    false
+                }
+                // Last holder, or a key already down when the mapper started
+                _ => {
+                    self.held.remove(&code);
+                    true
+                }
+            },
+            // Repeats pass through
+            _ => true,
+        }
+    }
+}
+
+/// The key mapper daemon
+///
+/// Reads every grabbed input device, applies each device's own mappings and
+/// writes the result to a single virtual keyboard.
+pub struct KeyMapper {
+    /// Grabbed input devices
+    inputs: Vec<MapperInput>,
+    /// Virtual keyboard the remapped events are written to
+    uinput: UinputDevice,
+    /// Output keys held down and how many inputs hold each
+    output: OutputKeys,
+    /// SYN_DROPPED reports received, across all inputs
+    sync_drops: u64,
+    /// Read buffer
+    buffer: Vec<u8>,
+    /// Whether the mapper is running
+    running: Arc<AtomicBool>,
+}
+
+impl KeyMapper {
+    /// Create a new key mapper for the specified device with given mappings
+    pub fn new(
+        device_path: PathBuf,
+        mappings: HashMap<u16, u16>,
+        running: Arc<AtomicBool>,
+    ) -> Result<Self, MapperError> {
+        Self::with_inputs(vec![(device_path, KeyBindings::from_remaps(mappings))], running)
+    }
+
+    /// Create a key mapper that grabs several devices, each with its own
+    /// bindings, and merges them into one virtual keyboard
+    pub fn with_inputs(
+        devices: Vec<(PathBuf, KeyBindings)>,
+        running: Arc<AtomicBool>,
+    ) -> Result<Self, MapperError> {
+        if devices.is_empty() {
+            return Err(MapperError::NoDevices);
+        }
+
+        // Dropping the inputs already opened releases their grabs
+        let mut inputs = Vec::new();
+        for (path, bindings) in &devices {
+            inputs.push(MapperInput::open(path.clone(), bindings)?);
+            eprintln!(
+                "Key mapper active on {} with {}",
+                path.display(),
+                bindings.summary()
+            );
+        }
+
+        // Create the uinput virtual device
+        let uinput = UinputDevice::create(UINPUT_DEVICE_NAME)?;
+
+        Ok(Self {
+            inputs,
+            uinput,
+            output: OutputKeys::default(),
+            sync_drops: 0,
+            buffer: vec![0u8; INPUT_EVENT_SIZE * 64],
+            running,
+        })
+    }
+
+    /// Run the mapper loop — blocks until stopped
+    pub fn run(&mut self) -> Result<(), MapperError> {
+        let paths: Vec<String> = self
+            .inputs
+            .iter()
+            .map(|input| input.path.display().to_string())
+            .collect();
+        eprintln!("Key mapper daemon running on {}", paths.join(", "));
+
+        while self.running.load(Ordering::SeqCst) {
+            let mut fds: Vec<libc::pollfd> = self
+                .inputs
+                .iter()
+                .map(|input| libc::pollfd {
+                    fd: input.file.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                })
+                .collect();
+            // Wake up in time to decide pending tap-hold keys
+            let timeout_ms = match self.inputs.iter().filter_map(|i| i.engine.deadline()).min() {
+                Some(deadline) => {
+                    let wait = deadline.saturating_duration_since(Instant::now());
+                    (wait.as_micros().div_ceil(1000) as i32).min(POLL_TIMEOUT_MS)
+                }
+                None => POLL_TIMEOUT_MS,
+            };
+            // SAFETY: `fds` is a live local array of `fds.len()` entries
+            // that the kernel writes `revents` into. EINTR and timeouts just
+            // return early; only ready inputs are read below.
+            unsafe {
+                libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout_ms);
+            }
+
+            // Back to front so a removed input does not shift the rest
+            for index in (0..self.inputs.len()).rev() {
+                if fds[index].revents == 0 {
+                    continue;
+                }
+                match self.read_input(index) {
+                    Ok(()) => {}
+                    Err(e) if e.raw_os_error() == Some(libc::ENODEV) && self.inputs.len() > 1 => {
+                        // Unplugged: keep mapping the devices that remain
+                        eprintln!("{} was removed", self.inputs[index].path.display());
+                        self.remove_input(index);
+                    }
+                    Err(e) => {
+                        eprintln!("Read error on {}: {}", self.inputs[index].path.display(), e);
+                        return Err(MapperError::Io(e));
+                    }
+                }
+            }
+
+            let now = Instant::now();
+            for index in 0..self.inputs.len() {
+                let outputs = self.inputs[index].engine.tick(now);
+                self.emit_keys(&outputs);
+            }
+        }
+
+        eprintln!("Key mapper daemon stopped");
+        Ok(())
+    }
+
+    /// Read and map everything one input has queued
+    fn read_input(&mut self, index: usize) -> io::Result<()> {
+        loop {
+            let bytes_read = match self.inputs[index].file.read(&mut self.buffer) {
+                Ok(bytes_read) => bytes_read,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if bytes_read < INPUT_EVENT_SIZE {
+                return Ok(());
+            }
+
+            let events: Vec<InputEvent> = self.buffer[..bytes_read]
+                .chunks_exact(INPUT_EVENT_SIZE)
+                .map(|event_bytes| {
+                    // SAFETY: event_bytes has exactly INPUT_EVENT_SIZE bytes,
+                    // InputEvent is #[repr(C)] matching the kernel struct,
+                    // and all bit patterns are valid for its primitive fields.
+                    unsafe { std::ptr::read_unaligned(event_bytes.as_ptr() as *const InputEvent) }
+                })
+                .collect();
+            let now = Instant::now();
+            for event in events {
+                self.handle_event(index, event, now);
+            }
+        }
+    }
+
+    /// Map one event from an input and write it to the virtual keyboard
+    fn handle_event(&mut self, index: usize, event: InputEvent, now: Instant) {
+        let input = &mut self.inputs[index];
+        if event.event_type == EV_SYN && event.code == SYN_DROPPED {
+            input.dropping = true;
+            self.sync_drops += 1;
+            eprintln!(
+                "Kernel dropped events on {} ({} time(s)); resyncing key state",
+                input.path.display(),
+                self.sync_drops
+            );
+        } else if input.dropping {
+            // The rest of the broken frame is unreliable
+            if event.event_type == EV_SYN && event.code == SYN_REPORT {
+                self.resync(index);
+            }
+        } else if event.event_type == EV_KEY {
+            // 1 = press, 0 = release; repeats leave it held
+            if event.value == 1 {
+                input.held.insert(event.code);
+            } else if event.value == 0 {
+                input.held.remove(&event.code);
+            }
+            for (code, value) in input.engine.key(event.code, event.value, now) {
+                self.emit_key(code, value);
+            }
+        } else {
+            // Forward non-key events unchanged (SYN, MSC, etc.)
+            self.uinput.emit(event.event_type, event.code, event.value);
+        }
+    }
+
+    /// Emit an output key event unless another input already holds the key
+    fn emit_key(&mut self, code: u16, value: i32) {
+        if self.output.update(code, value) {
+            self.uinput.emit(EV_KEY, code, value);
+        }
+    }
+
+    /// Emit key events produced outside an input frame, followed by a sync
+    fn emit_keys(&mut self, outputs: &[KeyOutput]) {
+        for &(code, value) in outputs {
+            self.emit_key(code, value);
+        }
+        if !outputs.is_empty() {
+            self.uinput.syn();
+        }
+    }
+
+    /// End a SYN_DROPPED gap: emit the presses/releases lost in it so the
+    /// virtual keyboard matches what is really held
+    fn resync(&mut self, index: usize) {
+        let input = &mut self.inputs[index];
+        input.dropping = false;
+        let actual = match read_key_state(&input.file) {
+            Ok(actual) => actual,
+            Err(e) => {
+                eprintln!("Cannot read key state of {}: {}", input.path.display(), e);
+                return;
+            }
+        };
+        let now = Instant::now();
+        let outputs: Vec<KeyOutput> = key_state_corrections(&input.held, &actual)
+            .into_iter()
+            .flat_map(|(code, pressed)| input.engine.key(code, pressed as i32, now))
+            .collect();
+        input.held = actual;
+        self.emit_keys(&outputs);
+    }
+
+    /// Stop reading an input, releasing the output keys it held
+    fn remove_input(&mut self, index: usize) {
+        let mut input = self.inputs.remove(index);
+        let outputs = input.engine.release_all();
+        self.emit_keys(&outputs);
+    }
+}
+
+impl Drop for KeyMapper {
+    fn drop(&mut self) {
+        // Each input releases its grab as it drops; the uinput device is
+        // destroyed when `uinput` drops
+        eprintln!("Key mapper cleaned up for {} device(s)", self.inputs.len());
+    }
+}
+
+/// Find keyboard devices, optionally filtering by name pattern
+pub fn find_mapper_devices(name_pattern: Option<&str>) -> Result<Vec<(PathBuf, String)>, MapperError> {
+    // Keyboards typically have 50+ key capabilities
+    find_key_devices(name_pattern, 21)
+}
+
+/// Find input devices declaring at least `min_keys` keys. Vendor hotkey
+/// devices declare only a handful, so preset inputs are searched with a
+/// lower bar than the main keyboard.
+fn find_key_devices(
+    name_pattern: Option<&str>,
+    min_keys: u32,
+) -> Result<Vec<(PathBuf, String)>, MapperError> {
+    let input_dir = PathBuf::from("/dev/input");
+    if !input_dir.exists() {
+        return Err(MapperError::NoDevices);
+    }
+
+    let mut devices = Vec::new();
+
+    let entries = fs::read_dir(&input_dir).map_err(MapperError::Io)?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+        if !name.starts_with("event") {
+            continue;
+        }
+
+        // Get device name from sysfs
+        let sysfs_name_path = format!("/sys/class/input/{}/device/name", name);
+        let dev_name = fs::read_to_string(&sysfs_name_path)
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+
+        if dev_name.is_empty() {
+            continue;
+        }
+
+        // Check if this is a keyboard by capabilities
+        let caps_path = format!("/sys/class/input/{}/device/capabilities/key", name);
+        if let Ok(caps) = fs::read_to_string(&caps_path) {
+            let trimmed = caps.trim();
+            if !trimmed.is_empty() && trimmed != "0" {
+                let total_bits: u32 = trimmed
+                    .split_whitespace()
+                    .filter_map(|hex| u64::from_str_radix(hex, 16).ok())
+                    .map(|n| n.count_ones())
+                    .sum();
+
+                if total_bits >= min_keys {
+                    // Apply name filter if provided
+                    if let Some(pattern) = name_pattern {
+                        if dev_name.to_lowercase().contains(&pattern.to_lowercase()) {
+                            devices.push((path, dev_name));
+                        }
+                    } else {
+                        devices.push((path, dev_name));
+                    }
+                }
+            }
+        }
+    }
+
+    if devices.is_empty() {
+        return Err(MapperError::NoDevices);
+    }
+
+    Ok(devices)
+}
+
+/// Fn layers for the config file's FN mode: one per FN scancode, with the
+/// mode's table plus the configured `fn_combos`. Only `MapToFKeys` and
+/// `MapToMedia` remap keys.
+pub fn fn_layers(oem_keys: &OemKeyConfig) -> Vec<Layer> {
+    if !oem_keys.enabled {
+        return Vec::new();
+    }
+    let mut scancodes = DEFAULT_FN_SCANCODES.to_vec();
+    for &code in &oem_keys.fn_scancodes {
+        if !scancodes.contains(&code) {
+            scancodes.push(code);
+        }
+    }
+    scancodes
+        .into_iter()
+        .filter_map(|key| {
+            let mut layer = Layer::from_fn_mode(oem_keys.fn_mode, key)?;
+            layer.remaps.extend(oem_keys.fn_combos.iter().copied());
+            Some(layer)
+        })
+        .collect()
+}
+
+/// Run the key mapper daemon with the given configuration.
+///
+/// The preset's mappings, the config file's and `extra_mappings` apply to
+/// the keyboards in `device_paths`, or to the one the preset matches when
+/// none are given. Each of the preset's extra inputs grabs the first other
+/// device its rules select. All of them feed one virtual keyboard.
+pub fn run_mapper(
+    preset_name: Option<&str>,
+    device_paths: &[PathBuf],
+    extra_mappings: &[(u16, u16)],
+    running: Arc<AtomicBool>,
+) -> Result<(), MapperError> {
+    // Load preset bindings
+    let mut bindings = KeyBindings::default();
+
+    let preset = match preset_name {
+        Some(name) => match MapperPreset::resolve(name) {
+            Ok(preset) => Some(preset),
+            Err(e) => {
+                eprintln!("Cannot load preset '{}': {}", name, e);
+                eprintln!("Available presets:");
+                for (pname, desc) in MapperPreset::available() {
+                    eprintln!("  {} - {}", pname, desc);
+                }
+                return Err(MapperError::Preset(e));
+            }
+        },
+        None => None,
+    };
+    if let Some(preset) = &preset {
+        eprintln!("Loaded preset: {} - {}", preset.name, preset.description);
+        bindings = preset.bindings();
+    }
+
+    // Also load from config file if available
+    if let Ok(config) = Config::load() {
+        for (from, to) in &config.oem_keys.key_mappings {
+            bindings.remaps.insert(*from, *to);
+        }
+        // The TUI's FN mode as a layer, unless the preset already uses the key
+        for layer in fn_layers(&config.oem_keys) {
+            if !bindings.layers.iter().any(|l| l.key == layer.key) {
+                bindings.layers.push(layer);
+            }
+        }
+    }
+
+    // Apply extra mappings (override preset/config)
+    for (from, to) in extra_mappings {
+        bindings.remaps.insert(*from, *to);
+    }
+
+    let preset_inputs = preset.as_ref().map(|p| p.inputs.as_slice()).unwrap_or_default();
+    if bindings.is_empty() && preset_inputs.is_empty() {
+        eprintln!("No key mappings configured. Use --preset or configure mappings in config.toml");
+        eprintln!("Available presets:");
+        for (name, desc) in MapperPreset::available() {
+            eprintln!("  --preset {} : {}", name, desc);
+        }
+        return Ok(());
+    }
+
+    // Find the target devices
+    let mut targets: Vec<(PathBuf, KeyBindings)> = Vec::new();
+    if !device_paths.is_empty() {
+        for path in device_paths {
+            targets.push((path.clone(), bindings.clone()));
+        }
+    } else if !bindings.is_empty() {
+        let devices = find_mapper_devices(None)?;
+
+        eprintln!("Found {} input device(s):", devices.len());
+        for (path, name) in &devices {
+            eprintln!("  {} - {}", path.display(), name);
+        }
+
+        // Auto-detect: the first device the preset's match rules select,
+        // otherwise the first device
+        let matched = preset.as_ref().and_then(|p| {
+            devices
+                .iter()
+                .find(|(path, _)| p.matches(&DeviceInfo::from_sysfs(path)))
+        });
+        let path = matched
+            .or(devices.first())
+            .map(|(path, _)| path.clone())
+            .ok_or(MapperError::NoDevices)?;
+        targets.push((path, bindings));
+    }
+
+    if !preset_inputs.is_empty() {
+        let candidates = find_key_devices(None, 1).unwrap_or_default();
+        for (i, input) in preset_inputs.iter().enumerate() {
+            let found = candidates.iter().find(|(path, _)| {
+                !targets.iter().any(|(taken, _)| taken == path)
+                    && input.matches(&DeviceInfo::from_sysfs(path))
+            });
+            match found {
+                Some((path, name)) => {
+                    eprintln!("Preset input {} matched {} - {}", i + 1, path.display(), name);
+                    targets.push((path.clone(), input.bindings()));
+                }
+                None => eprintln!("Preset input {}: no matching device, skipped", i + 1),
+            }
+        }
+    }
+
+    if targets.is_empty() {
+        return Err(MapperError::NoDevices);
+    }
+
+    let key_name = |code: u16| crate::keyboard::keymap::get_key_info(KeyCode::new(code)).name;
+    for (path, bindings) in &targets {
+        eprintln!("Using device: {}", path.display());
+        eprintln!("Active mappings:");
+        let mut sorted: Vec<(&u16, &u16)> = bindings.remaps.iter().collect();
+        sorted.sort();
+        for (&from, &to) in sorted {
+            eprintln!(
+                "  {} (0x{:03X}) → {} (0x{:03X})",
+                key_name(from),
+                from,
+                key_name(to),
+                to
+            );
+        }
+        for layer in &bindings.layers {
+            let how = match layer.activation {
+                LayerActivation::Momentary => "hold",
+                LayerActivation::Toggle => "toggle",
+            };
+            eprintln!(
+                "  Layer '{}': {} {} (0x{:03X}), {} mapping(s)",
+                layer.name,
+                how,
+                key_name(layer.key),
+                layer.key,
+                layer.remaps.len()
+            );
+        }
+        for th in &bindings.tap_holds {
+            eprintln!(
+                "  {} (0x{:03X}) → tap {}, hold {} ({} ms{})",
+                key_name(th.key),
+                th.key,
+                key_name(th.tap),
+                key_name(th.hold),
+                th.tapping_term.as_millis(),
+                if th.permissive_hold { ", permissive hold" } else { "" }
+            );
+        }
+        let key_names = |keys: &[u16], sep: &str| {
+            keys.iter().map(|&k| key_name(k)).collect::<Vec<_>>().join(sep)
+        };
+        for chord in &bindings.chords {
+            eprintln!(
+                "  Chord {} → {} ({} ms)",
+                key_names(&chord.keys, "+"),
+                key_name(chord.output),
+                chord.timeout.as_millis()
+            );
+        }
+        if let Some(leader) = &bindings.leader {
+            eprintln!(
+                "  Leader {} (0x{:03X}), {} ms timeout:",
+                key_name(leader.key),
+                leader.key,
+                leader.timeout.as_millis()
+            );
+            for sequence in &leader.sequences {
+                eprintln!(
+                    "    {} → {}",
+                    key_names(&sequence.keys, " "),
+                    key_name(sequence.output)
+                );
+            }
+        }
+    }
+
+    let mut mapper = KeyMapper::with_inputs(targets, running)?;
+    mapper.run()
+}
+
+/// Generate a systemd service unit file content
+pub fn generate_systemd_service(preset: Option<&str>) -> String {
+    let preset_arg = preset
+        .map(|p| format!(" --preset {}", p))
+        .unwrap_or_default();
+
+    format!(
+        r#"[Unit]
+Description=Keyboard TestKit Key Mapper Daemon
+Documentation=https://github.com/kase1111-hash/Keyboard-TestKit
+After=systemd-udevd.service
+Wants=systemd-udevd.service
+
+[Service]
+Type=simple
+ExecStart=/usr/local/bin/keyboard-testkit --mapper{preset_arg}
+Restart=on-failure
+RestartSec=3
+StandardOutput=journal
+StandardError=journal
+
+# Security hardening
+ProtectSystem=strict
+ProtectHome=read-only
+PrivateTmp=true
+NoNewPrivileges=false
+# Needs access to /dev/input and /dev/uinput
+DeviceAllow=/dev/input/* rw
+DeviceAllow=/dev/uinput rw
+SupplementaryGroups=input
+
+[Install]
+WantedBy=multi-user.target
+"#
+    )
+}
+
+/// Generate a udev rule for the uinput module and device permissions
+pub fn generate_udev_rules() -> String {
+    r#"# Allow the input group to access uinput for key remapping
+KERNEL=="uinput", GROUP="input", MODE="0660"
+"#
+    .to_string()
+}
+
+/// Install the mapper as a systemd service
+pub fn install_service(preset: Option<&str>) -> Result<(), MapperError> {
+    let service_content = generate_systemd_service(preset);
+    let service_path = "/etc/systemd/system/keyboard-testkit-mapper.service";
+
+    // Write service file
+    fs::write(service_path, &service_content).map_err(|e| {
+        if e.kind() == io::ErrorKind::PermissionDenied {
+            MapperError::PermissionDenied(
+                "Cannot write to /etc/systemd/system/. Run with sudo.".to_string(),
+            )
+        } else {
+            MapperError::Io(e)
+        }
+    })?;
+
+    // Write udev rule
+    let udev_path = "/etc/udev/rules.d/99-keyboard-testkit.rules";
+    let udev_content = generate_udev_rules();
+    fs::write(udev_path, &udev_content).map_err(|e| {
+        if e.kind() == io::ErrorKind::PermissionDenied {
+            MapperError::PermissionDenied(
+                "Cannot write to /etc/udev/rules.d/. Run with sudo.".to_string(),
+            )
+        } else {
+            MapperError::Io(e)
+        }
+    })?;
+
+    // Copy the binary to /usr/local/bin
+    let current_exe = std::env::current_exe().map_err(MapperError::Io)?;
+    let target_bin = "/usr/local/bin/keyboard-testkit";
+    fs::copy(&current_exe, target_bin).map_err(|e| {
+        if e.kind() == io::ErrorKind::PermissionDenied {
+            MapperError::PermissionDenied(
+                "Cannot copy to /usr/local/bin/. Run with sudo.".to_string(),
+            )
+        } else {
+            MapperError::Io(e)
+        }
+    })?;
+
+    // Ensure the binary is executable
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let perms = std::fs::Permissions::from_mode(0o755);
+        fs::set_permissions(target_bin, perms).map_err(MapperError::Io)?;
+    }
+
+    eprintln!("Service installed to {}", service_path);
+    eprintln!("Udev rule installed to {}", udev_path);
+    eprintln!("Binary installed to {}", target_bin);
+    eprintln!();
+    eprintln!("To enable and start the service:");
+    eprintln!("  sudo systemctl daemon-reload");
+    eprintln!("  sudo systemctl enable keyboard-testkit-mapper");
+    eprintln!("  sudo systemctl start keyboard-testkit-mapper");
+    eprintln!();
+    eprintln!("To check status:");
+    eprintln!("  sudo systemctl status keyboard-testkit-mapper");
+    eprintln!("  journalctl -u keyboard-testkit-mapper -f");
+
+    Ok(())
+}
+
+/// Uninstall the mapper systemd service
+pub fn uninstall_service() -> Result<(), MapperError> {
+    let service_path = "/etc/systemd/system/keyboard-testkit-mapper.service";
+    let udev_path = "/etc/udev/rules.d/99-keyboard-testkit.rules";
+
+    // Stop and disable the service (ignore errors if not running)
+    let _ = std::process::Command::new("systemctl")
+        .args(["stop", "keyboard-testkit-mapper"])
+        .status();
+    let _ = std::process::Command::new("systemctl")
+        .args(["disable", "keyboard-testkit-mapper"])
+        .status();
+
+    // Remove files
+    if std::path::Path::new(service_path).exists() {
+        fs::remove_file(service_path).map_err(MapperError::Io)?;
+        eprintln!("Removed {}", service_path);
+    }
+    if std::path::Path::new(udev_path).exists() {
+        fs::remove_file(udev_path).map_err(MapperError::Io)?;
+        eprintln!("Removed {}", udev_path);
+    }
+
+    // Reload systemd
+    let _ = std::process::Command::new("systemctl")
+        .arg("daemon-reload")
+        .status();
+
+    eprintln!("Service uninstalled successfully");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asus_g14_preset() {
+        let preset = MapperPreset::asus_g14();
+        assert_eq!(preset.name, "ASUS ROG Zephyrus G14");
+        assert!(!preset.mappings.is_empty());
+
+        // ROG key should map to Super
+        assert_eq!(preset.mappings.get(&148), Some(&125));
+        // AURA should map to backlight toggle
+        assert_eq!(preset.mappings.get(&149), Some(&228));
+        // Fan profile should map to F13
+        assert_eq!(preset.mappings.get(&202), Some(&183));
+    }
+
+    #[test]
+    fn test_preset_by_name() {
+        assert!(MapperPreset::by_name("asus-g14").is_some());
+        assert!(MapperPreset::by_name("asus").is_some());
+        assert!(MapperPreset::by_name("g14").is_some());
+        assert!(MapperPreset::by_name("generic").is_some());
+        assert!(MapperPreset::by_name("nonexistent").is_none());
+    }
+
+    #[test]
+    fn test_available_presets() {
+        let presets = MapperPreset::available();
+        assert!(presets.len() >= 2);
+        assert!(presets.iter().any(|(name, _)| *name == "asus-g14"));
+        assert!(presets.iter().any(|(name, _)| *name == "generic"));
+    }
+
+    #[test]
+    fn test_generate_systemd_service() {
+        let service = generate_systemd_service(Some("asus-g14"));
+        assert!(service.contains("[Unit]"));
+        assert!(service.contains("[Service]"));
+        assert!(service.contains("[Install]"));
+        assert!(service.contains("--mapper"));
+        assert!(service.contains("--preset asus-g14"));
+    }
+
+    #[test]
+    fn test_generate_systemd_service_no_preset() {
+        let service = generate_systemd_service(None);
+        assert!(service.contains("--mapper"));
+        assert!(!service.contains("--preset"));
+    }
+
+    #[test]
+    fn test_generate_udev_rules() {
+        let rules = generate_udev_rules();
+        assert!(rules.contains("uinput"));
+        assert!(rules.contains("input"));
+    }
+
+    #[test]
+    fn test_preset_for_device() {
+        let asus = DeviceInfo::new("/dev/input/event3", "ASUSTeK Computer Inc. N-KEY Device", 0x0b05, 0x19b6);
+        let preset = MapperPreset::for_device(&asus).unwrap();
+        assert_eq!(preset.name, "ASUS ROG Zephyrus G14");
+        let logitech = DeviceInfo::new("/dev/input/event4", "Logitech USB Keyboard", 0x046d, 0xc31c);
+        assert!(MapperPreset::for_device(&logitech).is_none());
+    }
+
+    /// A fresh directory of preset files for one test
+    fn preset_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "keyboard-testkit-presets-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn preset_file_loads_with_match_rules() {
+        let dir = preset_dir(
+            "load",
+            &[(
+                "x1-carbon.toml",
+                "description = \"ThinkPad keys\"\nmappings = [[374, 125]]\n\n[match]\nvendor_id = 0x17ef\nproduct_id = 0x6047\n",
+            )],
+        );
+        let preset = MapperPreset::load_from(&dir.join("x1-carbon.toml")).unwrap();
+        assert_eq!(preset.name, "x1-carbon");
+        assert_eq!(preset.mappings.get(&374), Some(&125));
+
+        let matching = DeviceInfo::new("/dev/input/event2", "ThinkPad Keyboard", 0x17ef, 0x6047);
+        let other = DeviceInfo::new("/dev/input/event2", "ThinkPad Keyboard", 0x17ef, 0x6048);
+        assert!(preset.matches(&matching));
+        assert!(!preset.matches(&other));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn invalid_preset_files_are_rejected() {
+        let cases = [
+            ("empty.toml", "mappings = []"),
+            ("range.toml", "mappings = [[30, 800]]"),
+            ("twice.toml", "mappings = [[30, 31], [30, 32]]"),
+            ("product.toml", "mappings = [[30, 31]]\n[match]\nproduct_id = 1"),
+            ("typo.toml", "mapings = [[30, 31]]"),
+        ];
+        let dir = preset_dir("invalid", &cases);
+        for (file, _) in cases {
+            assert!(MapperPreset::load_from(&dir.join(file)).is_err(), "{} loaded", file);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn preset_inputs_carry_their_own_mappings() {
+        let dir = preset_dir(
+            "inputs",
+            &[(
+                "rog.toml",
+                "mappings = [[148, 125]]\n[match]\nname = \"N-KEY\"\n\n[[inputs]]\nmappings = [[202, 183]]\n[inputs.match]\nname = \"Asus WMI hotkeys\"\n",
+            )],
+        );
+        let preset = MapperPreset::load_from(&dir.join("rog.toml")).unwrap();
+        assert_eq!(preset.mappings.len(), 1);
+        assert_eq!(preset.inputs.len(), 1);
+        assert_eq!(preset.inputs[0].mappings.get(&202), Some(&183));
+        assert!(!preset.inputs[0].mappings.contains_key(&148));
+
+        let hotkeys = DeviceInfo::new("/dev/input/event7", "Asus WMI hotkeys", 0, 0);
+        assert!(preset.inputs[0].matches(&hotkeys));
+        assert!(!preset.matches(&hotkeys));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn tap_hold_entries_take_preset_defaults() {
+        let dir = preset_dir(
+            "tap-hold",
+            &[(
+                "caps.toml",
+                "tapping_term_ms = 150\npermissive_hold = true\n\n[[tap_hold]]\nkey = 58\ntap = 1\nhold = 29\n\n[[tap_hold]]\nkey = 57\ntap = 57\nhold = 42\ntapping_term_ms = 250\npermissive_hold = false\n",
+            )],
+        );
+        let preset = MapperPreset::load_from(&dir.join("caps.toml")).unwrap();
+        assert!(preset.mappings.is_empty());
+        let caps = preset.tap_holds.iter().find(|th| th.key == 58).unwrap();
+        assert_eq!((caps.tap, caps.hold), (1, 29));
+        assert_eq!(caps.tapping_term, Duration::from_millis(150));
+        assert!(caps.permissive_hold);
+        let space = preset.tap_holds.iter().find(|th| th.key == 57).unwrap();
+        assert_eq!(space.tapping_term, Duration::from_millis(250));
+        assert!(!space.permissive_hold);
+        assert!(!preset.bindings().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn invalid_tap_hold_entries_are_rejected() {
+        let cases = [
+            ("remapped.toml", "mappings = [[58, 1]]\n[[tap_hold]]\nkey = 58\ntap = 1\nhold = 29"),
+            ("twice.toml", "[[tap_hold]]\nkey = 58\ntap = 1\nhold = 29\n[[tap_hold]]\nkey = 58\ntap = 1\nhold = 42"),
+            ("range.toml", "[[tap_hold]]\nkey = 58\ntap = 1\nhold = 900"),
+            ("term.toml", "tapping_term_ms = 0\n[[tap_hold]]\nkey = 58\ntap = 1\nhold = 29"),
+            ("missing.toml", "[[tap_hold]]\nkey = 58\ntap = 1"),
+        ];
+        let dir = preset_dir("tap-hold-invalid", &cases);
+        for (file, _) in cases {
+            assert!(MapperPreset::load_from(&dir.join(file)).is_err(), "{} loaded", file);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn layers_load_with_fn_tables_and_overrides() {
+        let dir = preset_dir(
+            "layers",
+            &[(
+                "layers.toml",
+                "[[layers]]\nname = \"fn\"\nkey = 464\nfn_mode = \"MapToMedia\"\nmappings = [[57, 200]]\n\n[[layers]]\nkey = 100\nactivation = \"toggle\"\nmappings = [[36, 105]]\n",
+            )],
+        );
+        let preset = MapperPreset::load_from(&dir.join("layers.toml")).unwrap();
+        let fn_layer = &preset.layers[0];
+        assert_eq!(fn_layer.activation, LayerActivation::Momentary);
+        assert_eq!(fn_layer.remaps.get(&105), Some(&165));
+        assert_eq!(fn_layer.remaps.get(&57), Some(&200));
+        assert_eq!(preset.layers[1].name, "layer 2");
+        assert_eq!(preset.layers[1].activation, LayerActivation::Toggle);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn invalid_layers_are_rejected() {
+        let cases = [
+            ("remapped.toml", "mappings = [[464, 30]]\n[[layers]]\nkey = 464\nmappings = [[2, 59]]"),
+            ("twice.toml", "[[layers]]\nkey = 464\nmappings = [[2, 59]]\n[[layers]]\nkey = 464\nmappings = [[3, 60]]"),
+            ("mode.toml", "[[layers]]\nkey = 464\nfn_mode = \"CaptureOnly\""),
+            ("empty.toml", "[[layers]]\nkey = 464"),
+            ("activation.toml", "[[layers]]\nkey = 464\nactivation = \"sticky\"\nmappings = [[2, 59]]"),
+        ];
+        let dir = preset_dir("layers-invalid", &cases);
+        for (file, _) in cases {
+            assert!(MapperPreset::load_from(&dir.join(file)).is_err(), "{} loaded", file);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn chords_and_leader_load_with_defaults() {
+        let dir = preset_dir(
+            "chords",
+            &[(
+                "chords.toml",
+                "chord_timeout_ms = 40
+
+[[chords]]
+keys = [36, 37]
+output = 1
+
+[[chords]]
+keys = [36, 37, 38]
+output = 183
+timeout_ms = 80
+
+[leader]
+key = 100
+[[leader.sequences]]
+keys = [20, 18]
+output = 184
+
+[[inputs]]
+[inputs.match]
+name = \"Asus WMI hotkeys\"
+[[inputs.chords]]
+keys = [202, 203]
+output = 185
+",
+            )],
+        );
+        let preset = MapperPreset::load_from(&dir.join("chords.toml")).unwrap();
+        assert_eq!(preset.chords[0].keys, vec![36, 37]);
+        assert_eq!(preset.chords[0].timeout, Duration::from_millis(40));
+        assert_eq!(preset.chords[1].timeout, Duration::from_millis(80));
+        let leader = preset.leader.as_ref().unwrap();
+        assert_eq!(leader.timeout, DEFAULT_LEADER_TIMEOUT);
+        assert_eq!(leader.sequences[0].keys, vec![20, 18]);
+        assert_eq!(
+            preset.bindings().summary(),
+            "0 mapping(s), 2 chord(s), 1 leader sequence(s)"
+        );
+        assert_eq!(preset.inputs[0].chords[0].timeout, Duration::from_millis(40));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn invalid_chords_and_leaders_are_rejected() {
+        let cases = [
+            ("single.toml", "[[chords]]\nkeys = [36]\noutput = 1"),
+            ("repeat.toml", "[[chords]]\nkeys = [36, 36]\noutput = 1"),
+            ("twice.toml", "[[chords]]\nkeys = [36, 37]\noutput = 1\n[[chords]]\nkeys = [37, 36]\noutput = 2"),
+            ("window.toml", "[[chords]]\nkeys = [36, 37]\noutput = 1\ntimeout_ms = 5000"),
+            ("range.toml", "[[chords]]\nkeys = [36, 37]\noutput = 900"),
+            ("remapped.toml", "mappings = [[100, 125]]\n[leader]\nkey = 100\n[[leader.sequences]]\nkeys = [20]\noutput = 1"),
+            ("chorded.toml", "[[chords]]\nkeys = [100, 37]\noutput = 1\n[leader]\nkey = 100\n[[leader.sequences]]\nkeys = [20]\noutput = 1"),
+            ("no-sequences.toml", "[leader]\nkey = 100\nsequences = []"),
+            ("empty-sequence.toml", "[leader]\nkey = 100\n[[leader.sequences]]\nkeys = []\noutput = 1"),
+            ("prefix.toml", "[leader]\nkey = 100\n[[leader.sequences]]\nkeys = [20, 18]\noutput = 1\n[[leader.sequences]]\nkeys = [20]\noutput = 2"),
+            ("timeout.toml", "[leader]\nkey = 100\ntimeout_ms = 0\n[[leader.sequences]]\nkeys = [20]\noutput = 1"),
+        ];
+        let dir = preset_dir("chords-invalid", &cases);
+        for (file, _) in cases {
+            assert!(MapperPreset::load_from(&dir.join(file)).is_err(), "{} loaded", file);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn config_fn_mode_becomes_fn_layers() {
+        let mut oem_keys = OemKeyConfig::default();
+        assert!(fn_layers(&oem_keys).is_empty());
+
+        oem_keys.fn_mode = FnKeyMode::MapToFKeys;
+        oem_keys.fn_scancodes = vec![480, 190];
+        oem_keys.fn_combos = vec![(16, 113)];
+        let layers = fn_layers(&oem_keys);
+        let keys: Vec<u16> = layers.iter().map(|l| l.key).collect();
+        assert_eq!(keys, vec![464, 480, 190]);
+        assert_eq!(layers[0].remaps.get(&2), Some(&59));
+        assert_eq!(layers[0].remaps.get(&16), Some(&113));
+
+        oem_keys.enabled = false;
+        assert!(fn_layers(&oem_keys).is_empty());
+    }
+
+    #[test]
+    fn fn_layers_match_the_tui_remapper() {
+        use crate::keyboard::remap::KeyRemapper;
+
+        for mode in [
+            FnKeyMode::Disabled,
+            FnKeyMode::CaptureOnly,
+            FnKeyMode::RestoreWithModifier,
+            FnKeyMode::MapToFKeys,
+            FnKeyMode::MapToMedia,
+        ] {
+            let oem_keys = OemKeyConfig {
+                fn_mode: mode,
+                fn_combos: vec![(16, 113), (105, 59)],
+                ..OemKeyConfig::default()
+            };
+
+            let mut remapper = KeyRemapper::new();
+            remapper.set_fn_mode(mode);
+            for &(key, result) in &oem_keys.fn_combos {
+                remapper.add_fn_combo(key, result);
+            }
+
+            let daemon = fn_layers(&oem_keys)
+                .into_iter()
+                .next()
+                .map(|layer| layer.remaps)
+                .unwrap_or_default();
+            assert_eq!(daemon, remapper.fn_combo_table(), "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn preset_inputs_need_match_rules_and_mappings() {
+        let cases = [
+            ("unmatched.toml", "[[inputs]]\nmappings = [[30, 31]]\n[inputs.match]\n"),
+            ("unmapped.toml", "[[inputs]]\nmappings = []\n[inputs.match]\nname = \"hotkeys\""),
+            ("product.toml", "[[inputs]]\nmappings = [[30, 31]]\n[inputs.match]\nproduct_id = 1"),
+        ];
+        let dir = preset_dir("inputs-invalid", &cases);
+        for (file, _) in cases {
+            assert!(MapperPreset::load_from(&dir.join(file)).is_err(), "{} loaded", file);
+        }
+
+        // An input alone is enough; the keyboard itself needs no mappings
+        let only_input = dir.join("only.toml");
+        fs::write(&only_input, "[[inputs]]\nmappings = [[30, 31]]\n[inputs.match]\nname = \"hotkeys\"").unwrap();
+        assert!(MapperPreset::load_from(&only_input).unwrap().mappings.is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn output_key_held_by_two_inputs_is_released_once() {
+        let mut output = OutputKeys::default();
+        assert!(output.update(125, 1));
+        // The second device's press of the same output key is swallowed
+        assert!(!output.update(125, 1));
+        assert!(output.update(125, 2));
+        assert!(!output.update(125, 0));
+        assert!(output.update(125, 0));
+        // A release nobody pressed (held before the mapper started) passes
+        assert!(output.update(30, 0));
+    }
+
+    #[test]
+    fn later_directories_override_and_errors_are_kept() {
+        let system = preset_dir(
+            "system",
+            &[
+                ("fleet.toml", "description = \"system\"\nmappings = [[30, 31]]"),
+                ("broken.toml", "mappings = [[0, 31]]"),
+            ],
+        );
+        let user = preset_dir(
+            "user",
+            &[
+                ("fleet.toml", "description = \"user\"\nmappings = [[30, 32]]"),
+                ("generic.toml", "description = \"mine\"\nmappings = [[248, 113]]"),
+            ],
+        );
+        let catalog = PresetCatalog::load_from_dirs(&[system.clone(), user.clone()]);
+
+        assert_eq!(catalog.get("Fleet").unwrap().description, "user");
+        assert_eq!(catalog.get("laptop").unwrap().description, "mine");
+        assert!(catalog.get("asus").is_some());
+        assert_eq!(catalog.presets.iter().filter(|(id, _)| id == "fleet").count(), 1);
+        assert_eq!(catalog.errors.len(), 1);
+        assert!(matches!(catalog.take("broken"), Err(ConfigError::Invalid(_))));
+
+        let _ = fs::remove_dir_all(&system);
+        let _ = fs::remove_dir_all(&user);
+    }
+
+    #[test]
+    fn test_generic_preset() {
+        let preset = MapperPreset::generic_laptop();
+        assert_eq!(preset.name, "Generic Laptop");
+        assert!(preset.device_match.is_none());
+    }
+}
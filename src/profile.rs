@@ -0,0 +1,368 @@
+//! Acceptance profiles for pass/fail grading
+//!
+//! Each test picks its own Ok/Warning/Error cutoffs, which are meant for a
+//! person looking at the TUI. An acceptance profile instead states the
+//! vendor spec for one keyboard SKU, and a [`SessionReport`] is graded
+//! against it to give a single PASS/FAIL verdict.
+//!
+//! Profiles are TOML files. Every criterion is optional; only the ones
+//! present are checked.
+//!
+//! ```toml
+//! name = "K70 SKU-A"
+//! description = "1000 Hz NKRO board"
+//! min_polling_hz = 950
+//! max_jitter_us = 250
+//! min_rollover = 6
+//! max_bounces = 0
+//! max_stuck_keys = 0
+//! max_avg_event_timing_ms = 20
+//! required_keys = [1, 30, 31, 32]   # Esc, A, S, D
+//! ```
+//!
+//! ## Profile Locations
+//!
+//! `--profile` accepts either a path to a TOML file or the name of a profile
+//! in [`profiles_dir`], e.g. `~/.config/keyboard-testkit/profiles/k70.toml`
+//! for `--profile k70`.
+//!
+//! [`SessionReport`]: crate::report::SessionReport
+
+use crate::config::{profiles_dir, ConfigError};
+use crate::keyboard::{get_key_info, KeyCode};
+use crate::report::SessionMetrics;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Acceptance criteria for one keyboard model
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AcceptanceProfile {
+    /// Profile name shown in reports
+    pub name: String,
+    /// Free-form description of the SKU or spec
+    pub description: String,
+    /// Minimum average polling rate in Hz
+    pub min_polling_hz: Option<f64>,
+    /// Maximum polling interval jitter (standard deviation) in µs
+    pub max_jitter_us: Option<f64>,
+    /// Minimum simultaneous keys that must register
+    pub min_rollover: Option<usize>,
+    /// Maximum switch bounces across all keys
+    pub max_bounces: Option<u32>,
+    /// Maximum number of keys flagged as stuck
+    pub max_stuck_keys: Option<usize>,
+    /// Maximum average inter-event time in milliseconds
+    pub max_avg_event_timing_ms: Option<f64>,
+    /// Scancodes that must each be pressed at least once
+    pub required_keys: Vec<u16>,
+}
+
+/// Overall outcome of grading a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Pass,
+    Fail,
+}
+
+impl Verdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Verdict::Pass => "PASS",
+            Verdict::Fail => "FAIL",
+        }
+    }
+}
+
+/// Outcome of a single acceptance criterion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CriterionResult {
+    /// Metric name
+    pub label: String,
+    /// What the profile requires, e.g. ">= 950 Hz"
+    pub required: String,
+    /// What was measured
+    pub measured: String,
+    /// Whether the requirement was met
+    pub passed: bool,
+}
+
+/// A session graded against an acceptance profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcceptanceResult {
+    /// Name of the profile used
+    pub profile: String,
+    /// Overall verdict (fails if any criterion fails)
+    pub verdict: Verdict,
+    /// Per-criterion outcomes, in profile field order
+    pub criteria: Vec<CriterionResult>,
+}
+
+impl AcceptanceResult {
+    /// Criteria that were not met
+    pub fn failures(&self) -> impl Iterator<Item = &CriterionResult> {
+        self.criteria.iter().filter(|c| !c.passed)
+    }
+}
+
+impl AcceptanceProfile {
+    /// Load a profile from a TOML file.
+    ///
+    /// A profile without a `name` is named after its file stem.
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path)?;
+        let mut profile: AcceptanceProfile = toml::from_str(&contents)?;
+        if profile.name.is_empty() {
+            profile.name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+        }
+        Ok(profile)
+    }
+
+    /// Load a profile given either a file path or a name in [`profiles_dir`].
+    pub fn resolve(name_or_path: &str) -> Result<Self, ConfigError> {
+        let path = Path::new(name_or_path);
+        if path.exists() {
+            return Self::load_from(path);
+        }
+        Self::load_from(&profiles_dir()?.join(format!("{}.toml", name_or_path)))
+    }
+
+    /// Grade session measurements against this profile.
+    ///
+    /// A metric the session never measured (e.g. no polling samples) fails
+    /// its criterion rather than being skipped.
+    pub fn evaluate(&self, metrics: &SessionMetrics) -> AcceptanceResult {
+        let mut criteria = Vec::new();
+        let not_measured = || "not measured".to_string();
+
+        if let Some(min) = self.min_polling_hz {
+            criteria.push(CriterionResult {
+                label: "Polling Rate".to_string(),
+                required: format!(">= {:.0} Hz", min),
+                measured: metrics
+                    .polling_rate_hz
+                    .map(|hz| format!("{:.0} Hz", hz))
+                    .unwrap_or_else(not_measured),
+                passed: metrics.polling_rate_hz.is_some_and(|hz| hz >= min),
+            });
+        }
+
+        if let Some(max) = self.max_jitter_us {
+            criteria.push(CriterionResult {
+                label: "Jitter".to_string(),
+                required: format!("<= {:.0} µs", max),
+                measured: metrics
+                    .jitter_us
+                    .map(|j| format!("{:.0} µs", j))
+                    .unwrap_or_else(not_measured),
+                passed: metrics.jitter_us.is_some_and(|j| j <= max),
+            });
+        }
+
+        if let Some(min) = self.min_rollover {
+            criteria.push(CriterionResult {
+                label: "Rollover".to_string(),
+                required: format!(">= {}KRO", min),
+                measured: format!("{}KRO", metrics.max_rollover),
+                passed: metrics.max_rollover >= min,
+            });
+        }
+
+        if let Some(max) = self.max_bounces {
+            criteria.push(CriterionResult {
+                label: "Bounces".to_string(),
+                required: format!("<= {}", max),
+                measured: format!("{}", metrics.bounces),
+                passed: metrics.bounces <= max,
+            });
+        }
+
+        if let Some(max) = self.max_stuck_keys {
+            criteria.push(CriterionResult {
+                label: "Stuck Keys".to_string(),
+                required: format!("<= {}", max),
+                measured: format!("{}", metrics.stuck_keys),
+                passed: metrics.stuck_keys <= max,
+            });
+        }
+
+        if let Some(max) = self.max_avg_event_timing_ms {
+            criteria.push(CriterionResult {
+                label: "Event Timing".to_string(),
+                required: format!("<= {:.1} ms", max),
+                measured: metrics
+                    .avg_event_timing_ms
+                    .map(|ms| format!("{:.1} ms", ms))
+                    .unwrap_or_else(not_measured),
+                passed: metrics.avg_event_timing_ms.is_some_and(|ms| ms <= max),
+            });
+        }
+
+        if !self.required_keys.is_empty() {
+            let missing: Vec<u16> = self
+                .required_keys
+                .iter()
+                .copied()
+                .filter(|k| !metrics.keys_pressed.contains(k))
+                .collect();
+            let measured = if missing.is_empty() {
+                "all pressed".to_string()
+            } else {
+                let names: Vec<String> = missing.iter().map(|&k| key_label(k)).collect();
+                format!("missing {}", names.join(", "))
+            };
+            criteria.push(CriterionResult {
+                label: "Required Keys".to_string(),
+                required: format!("{} keys", self.required_keys.len()),
+                measured,
+                passed: missing.is_empty(),
+            });
+        }
+
+        let verdict = if criteria.iter().all(|c| c.passed) {
+            Verdict::Pass
+        } else {
+            Verdict::Fail
+        };
+
+        AcceptanceResult {
+            profile: self.name.clone(),
+            verdict,
+            criteria,
+        }
+    }
+}
+
+/// Key name for reports, falling back to the raw scancode
+fn key_label(code: u16) -> String {
+    let info = get_key_info(KeyCode::new(code));
+    if info.name == "Unknown" {
+        format!("#{}", code)
+    } else {
+        info.name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn good_metrics() -> SessionMetrics {
+        SessionMetrics {
+            polling_rate_hz: Some(1000.0),
+            jitter_us: Some(120.0),
+            max_rollover: 6,
+            bounces: 0,
+            stuck_keys: 0,
+            avg_event_timing_ms: Some(8.0),
+            keys_pressed: vec![1, 30, 31, 32],
+        }
+    }
+
+    fn strict_profile() -> AcceptanceProfile {
+        AcceptanceProfile {
+            name: "strict".to_string(),
+            min_polling_hz: Some(950.0),
+            max_jitter_us: Some(250.0),
+            min_rollover: Some(6),
+            max_bounces: Some(0),
+            max_stuck_keys: Some(0),
+            max_avg_event_timing_ms: Some(20.0),
+            required_keys: vec![1, 30, 31, 32],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn profile_parses_from_toml() {
+        let toml = r#"
+            name = "K70"
+            min_polling_hz = 950
+            max_bounces = 0
+            required_keys = [30, 31]
+        "#;
+        let profile: AcceptanceProfile = toml::from_str(toml).unwrap();
+        assert_eq!(profile.name, "K70");
+        assert_eq!(profile.min_polling_hz, Some(950.0));
+        assert_eq!(profile.max_bounces, Some(0));
+        assert_eq!(profile.max_jitter_us, None);
+        assert_eq!(profile.required_keys, vec![30, 31]);
+    }
+
+    #[test]
+    fn profile_rejects_unknown_fields() {
+        let result: Result<AcceptanceProfile, _> = toml::from_str("min_polling = 950");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn empty_profile_passes() {
+        let result = AcceptanceProfile::default().evaluate(&SessionMetrics::default());
+        assert_eq!(result.verdict, Verdict::Pass);
+        assert!(result.criteria.is_empty());
+    }
+
+    #[test]
+    fn meeting_every_criterion_passes() {
+        let result = strict_profile().evaluate(&good_metrics());
+        assert_eq!(result.verdict, Verdict::Pass);
+        assert_eq!(result.criteria.len(), 7);
+        assert_eq!(result.failures().count(), 0);
+    }
+
+    #[test]
+    fn one_failed_criterion_fails_the_session() {
+        let metrics = SessionMetrics {
+            bounces: 2,
+            ..good_metrics()
+        };
+        let result = strict_profile().evaluate(&metrics);
+        assert_eq!(result.verdict, Verdict::Fail);
+        let failures: Vec<_> = result.failures().collect();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].label, "Bounces");
+        assert_eq!(failures[0].measured, "2");
+    }
+
+    #[test]
+    fn unmeasured_metric_fails() {
+        let metrics = SessionMetrics {
+            polling_rate_hz: None,
+            ..good_metrics()
+        };
+        let result = strict_profile().evaluate(&metrics);
+        let polling = &result.criteria[0];
+        assert!(!polling.passed);
+        assert_eq!(polling.measured, "not measured");
+    }
+
+    #[test]
+    fn missing_required_keys_are_named() {
+        let metrics = SessionMetrics {
+            keys_pressed: vec![1, 30],
+            ..good_metrics()
+        };
+        let result = strict_profile().evaluate(&metrics);
+        let keys = result.criteria.last().unwrap();
+        assert!(!keys.passed);
+        assert_eq!(keys.measured, "missing S, D");
+    }
+
+    #[test]
+    fn load_from_names_profile_after_file() {
+        let path = std::env::temp_dir().join(format!(
+            "keyboard-testkit-profile-{}.toml",
+            std::process::id()
+        ));
+        fs::write(&path, "min_rollover = 6\n").unwrap();
+        let profile = AcceptanceProfile::load_from(&path).unwrap();
+        fs::remove_file(&path).ok();
+        assert!(profile.name.starts_with("keyboard-testkit-profile-"));
+        assert_eq!(profile.min_rollover, Some(6));
+    }
+}
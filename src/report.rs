@@ -11,6 +11,16 @@
 //! | Markdown | [`SessionReport::export_markdown`] | Human-readable formatted report |
 //! | Text | [`SessionReport::export_text`] | Plain text summary |
 //!
+//! A report can also be graded against an
+//! [`AcceptanceProfile`](crate::profile::AcceptanceProfile) with
+//! [`SessionReport::grade`]; every format then includes the per-criterion
+//! outcomes and the overall verdict.
+//!
+//! When events carry their source device, the report also lists each device
+//! with its event counts and marks the one the tests were restricted to.
+//! Where the device's declared key bitmap was read, it also shows how many
+//! of those keys were pressed and lists the ones that never were.
+//!
 //! ## Example
 //!
 //! ```no_run
@@ -24,10 +34,12 @@
 //! // report.export_text(Path::new("report.txt"))?;
 //! ```
 
-use crate::keyboard::KeyboardState;
+use crate::keyboard::{key_code_names, DeviceCapabilities, DeviceInfo, InputFidelity, KeyboardState};
+use crate::profile::{AcceptanceProfile, AcceptanceResult, Verdict};
 use crate::tests::{ResultStatus, TestResult};
 use std::time::SystemTime;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 use std::fmt::Write as FmtWrite;
 use std::fs::File;
 use std::io::Write;
@@ -43,6 +55,67 @@ pub struct SessionReport {
     pub summary: SessionSummary,
     /// Test results by category
     pub tests: TestResults,
+    /// Raw measurements used for acceptance grading
+    #[serde(default)]
+    pub metrics: SessionMetrics,
+    /// Grading against an acceptance profile, if one was given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acceptance: Option<AcceptanceResult>,
+    /// Input devices seen during the session
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub devices: Vec<DeviceActivity>,
+}
+
+/// Per-device event counts for a session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceActivity {
+    #[serde(flatten)]
+    pub device: DeviceInfo,
+    /// Key events received from the device
+    pub events: u64,
+    /// Key presses received from the device
+    pub presses: u64,
+    /// Whether tests were restricted to this device
+    #[serde(default)]
+    pub under_test: bool,
+    /// Whether the device was unplugged when the report was made
+    #[serde(default)]
+    pub disconnected: bool,
+    /// What the device declares, when its capabilities could be read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<DeviceCapabilities>,
+    /// Distinct key codes pressed on the device
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub keys_pressed: BTreeSet<u16>,
+}
+
+impl DeviceActivity {
+    pub fn new(device: DeviceInfo) -> Self {
+        Self {
+            device,
+            events: 0,
+            presses: 0,
+            under_test: false,
+            disconnected: false,
+            capabilities: None,
+            keys_pressed: BTreeSet::new(),
+        }
+    }
+
+    /// Declared keys that have not been pressed yet
+    pub fn keys_not_pressed(&self) -> Vec<u16> {
+        self.capabilities
+            .iter()
+            .flat_map(|c| c.keys.difference(&self.keys_pressed).copied())
+            .collect()
+    }
+
+    /// "pressed/declared" for the device's declared keys, when known
+    pub fn key_coverage(&self) -> Option<String> {
+        let capabilities = self.capabilities.as_ref()?;
+        let pressed = capabilities.keys.intersection(&self.keys_pressed).count();
+        Some(format!("{}/{}", pressed, capabilities.keys.len()))
+    }
 }
 
 /// Report metadata
@@ -54,6 +127,16 @@ pub struct ReportMetadata {
     pub version: String,
     /// Session duration in seconds
     pub duration_secs: f64,
+    /// What the event source could report, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_fidelity: Option<InputFidelity>,
+    /// Key events dropped because the capture queue was full
+    #[serde(default)]
+    pub dropped_events: u64,
+    /// Times the kernel dropped queued events (SYN_DROPPED); key state was
+    /// resynced, but events inside each gap were lost
+    #[serde(default)]
+    pub sync_drops: u64,
 }
 
 /// Session summary statistics
@@ -69,6 +152,25 @@ pub struct SessionSummary {
     pub issues_detected: u32,
 }
 
+/// Numeric session measurements that acceptance profiles are graded on
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionMetrics {
+    /// Average polling rate from the polling test
+    pub polling_rate_hz: Option<f64>,
+    /// Polling interval jitter (standard deviation) in µs
+    pub jitter_us: Option<f64>,
+    /// Maximum simultaneous keys pressed
+    pub max_rollover: usize,
+    /// Switch bounces across all keys
+    pub bounces: u32,
+    /// Distinct keys flagged as stuck
+    pub stuck_keys: usize,
+    /// Average inter-event time in milliseconds
+    pub avg_event_timing_ms: Option<f64>,
+    /// Scancodes pressed at least once, sorted
+    pub keys_pressed: Vec<u16>,
+}
+
 /// All test results
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TestResults {
@@ -80,6 +182,12 @@ pub struct TestResults {
     pub shortcuts: Vec<ResultEntry>,
     pub virtual_detect: Vec<ResultEntry>,
     pub oem_keys: Vec<ResultEntry>,
+    #[serde(default)]
+    pub coverage: Vec<ResultEntry>,
+    #[serde(default)]
+    pub typematic: Vec<ResultEntry>,
+    #[serde(default)]
+    pub leds: Vec<ResultEntry>,
 }
 
 /// Single result entry
@@ -110,6 +218,10 @@ impl From<&TestResult> for ResultEntry {
 pub struct ReportInput {
     pub start_time: Instant,
     pub total_events: u64,
+    pub input_fidelity: Option<InputFidelity>,
+    pub dropped_events: u64,
+    pub sync_drops: u64,
+    pub metrics: SessionMetrics,
     pub polling: Vec<TestResult>,
     pub hold_release: Vec<TestResult>,
     pub stickiness: Vec<TestResult>,
@@ -118,10 +230,14 @@ pub struct ReportInput {
     pub shortcuts: Vec<TestResult>,
     pub virtual_detect: Vec<TestResult>,
     pub oem_keys: Vec<TestResult>,
+    pub coverage: Vec<TestResult>,
+    pub typematic: Vec<TestResult>,
+    pub leds: Vec<TestResult>,
+    pub devices: Vec<DeviceActivity>,
 }
 
 impl SessionReport {
-    /// Create a new session report from all 8 test results
+    /// Create a new session report from all 11 test results
     pub fn new(input: ReportInput, keyboard_state: &KeyboardState) -> Self {
         let duration_secs = input.start_time.elapsed().as_secs_f64();
         let now_secs = SystemTime::now()
@@ -137,7 +253,7 @@ impl SessionReport {
                 .count() as u32
         };
 
-        let all_results: [&[TestResult]; 8] = [
+        let all_results: [&[TestResult]; 11] = [
             &input.polling,
             &input.hold_release,
             &input.stickiness,
@@ -146,6 +262,9 @@ impl SessionReport {
             &input.shortcuts,
             &input.virtual_detect,
             &input.oem_keys,
+            &input.coverage,
+            &input.typematic,
+            &input.leds,
         ];
         let issues: u32 = all_results.iter().map(|r| count_issues(r)).sum();
 
@@ -154,6 +273,9 @@ impl SessionReport {
                 generated_at: format!("{}Z", now_secs),
                 version: env!("CARGO_PKG_VERSION").to_string(),
                 duration_secs,
+                input_fidelity: input.input_fidelity,
+                dropped_events: input.dropped_events,
+                sync_drops: input.sync_drops,
             },
             summary: SessionSummary {
                 total_events: input.total_events,
@@ -170,7 +292,97 @@ impl SessionReport {
                 shortcuts: input.shortcuts.iter().map(ResultEntry::from).collect(),
                 virtual_detect: input.virtual_detect.iter().map(ResultEntry::from).collect(),
                 oem_keys: input.oem_keys.iter().map(ResultEntry::from).collect(),
+                coverage: input.coverage.iter().map(ResultEntry::from).collect(),
+                typematic: input.typematic.iter().map(ResultEntry::from).collect(),
+                leds: input.leds.iter().map(ResultEntry::from).collect(),
             },
+            metrics: input.metrics,
+            acceptance: None,
+            devices: input.devices,
+        }
+    }
+
+    /// Grade the session against an acceptance profile, replacing any
+    /// previous grading
+    pub fn grade(&mut self, profile: &AcceptanceProfile) {
+        self.acceptance = Some(profile.evaluate(&self.metrics));
+    }
+
+    /// Whether the session passed.
+    ///
+    /// With an acceptance profile this is the profile verdict; otherwise a
+    /// session passes when no test reported an error. Either way, a session
+    /// that lost kernel events fails, since its results cannot be trusted.
+    pub fn passed(&self) -> bool {
+        match &self.acceptance {
+            Some(acceptance) => {
+                acceptance.verdict == Verdict::Pass && self.session_errors().is_empty()
+            }
+            None => self.errors().is_empty(),
+        }
+    }
+
+    /// Errors in the session itself rather than in any test: events
+    /// dropped from a full capture queue and kernel buffer overruns
+    pub fn session_errors(&self) -> Vec<ResultEntry> {
+        let mut errors = Vec::new();
+        if self.metadata.dropped_events > 0 {
+            errors.push(ResultEntry {
+                label: "Dropped Events".to_string(),
+                value: format!("{} (capture queue full)", self.metadata.dropped_events),
+                status: "error".to_string(),
+            });
+        }
+        if self.metadata.sync_drops > 0 {
+            errors.push(ResultEntry {
+                label: "Kernel Overruns".to_string(),
+                value: format!("{} (SYN_DROPPED)", self.metadata.sync_drops),
+                status: "error".to_string(),
+            });
+        }
+        errors
+    }
+
+    /// Results for each test, labeled with their category name
+    pub fn sections(&self) -> [(&'static str, &[ResultEntry]); 11] {
+        [
+            ("Polling", &self.tests.polling),
+            ("Hold/Release", &self.tests.hold_release),
+            ("Stickiness", &self.tests.stickiness),
+            ("Rollover", &self.tests.rollover),
+            ("Event Timing", &self.tests.event_timing),
+            ("Shortcuts", &self.tests.shortcuts),
+            ("Virtual Detect", &self.tests.virtual_detect),
+            ("OEM Keys", &self.tests.oem_keys),
+            ("Key Coverage", &self.tests.coverage),
+            ("Auto-Repeat", &self.tests.typematic),
+            ("LEDs", &self.tests.leds),
+        ]
+    }
+
+    /// All error-level results, with the category they came from; session
+    /// errors come first under "Session"
+    pub fn errors(&self) -> Vec<(&'static str, ResultEntry)> {
+        let session = self.session_errors().into_iter().map(|r| ("Session", r));
+        let tests = self
+            .sections()
+            .into_iter()
+            .flat_map(|(category, results)| results.iter().map(move |r| (category, r)))
+            .filter(|(_, r)| r.status == "error")
+            .map(|(category, r)| (category, r.clone()));
+        session.chain(tests).collect()
+    }
+
+    /// Export report to a file, picking the format from its extension.
+    ///
+    /// `.csv`, `.md` and `.txt` select CSV, Markdown and plain text;
+    /// anything else is written as JSON.
+    pub fn export(&self, path: &Path) -> std::io::Result<()> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => self.export_csv(path),
+            Some("md") => self.export_markdown(path),
+            Some("txt") => self.export_text(path),
+            _ => self.export_json(path),
         }
     }
 
@@ -208,6 +420,16 @@ impl SessionReport {
 
         // Header
         writeln!(csv, "Category,Label,Value,Status").unwrap();
+        if let Some(fidelity) = self.metadata.input_fidelity {
+            let status = if fidelity.has_releases() { "info" } else { "warning" };
+            writeln!(csv, "Session,Input,{},{}", Self::csv_escape(fidelity.label()), status).unwrap();
+        }
+        if self.metadata.dropped_events > 0 {
+            writeln!(csv, "Session,Dropped Events,{},error", self.metadata.dropped_events).unwrap();
+        }
+        if self.metadata.sync_drops > 0 {
+            writeln!(csv, "Session,Kernel Overruns,{},error", self.metadata.sync_drops).unwrap();
+        }
 
         // Helper to write results
         let write_results = |csv: &mut String, category: &str, results: &[ResultEntry]| {
@@ -227,6 +449,63 @@ impl SessionReport {
         write_results(&mut csv, "Shortcuts", &self.tests.shortcuts);
         write_results(&mut csv, "Virtual Detect", &self.tests.virtual_detect);
         write_results(&mut csv, "OEM Keys", &self.tests.oem_keys);
+        write_results(&mut csv, "Key Coverage", &self.tests.coverage);
+        write_results(&mut csv, "Auto-Repeat", &self.tests.typematic);
+        write_results(&mut csv, "LEDs", &self.tests.leds);
+
+        for activity in &self.devices {
+            let mut value = format!("{} events", activity.events);
+            if activity.under_test {
+                value.push_str(" (under test)");
+            }
+            if activity.disconnected {
+                value.push_str(" (unplugged)");
+            }
+            if let Some(coverage) = activity.key_coverage() {
+                write!(value, ", {} declared keys pressed", coverage).unwrap();
+                let missing = activity.keys_not_pressed();
+                if !missing.is_empty() {
+                    write!(value, ", not pressed: {}", key_code_names(missing)).unwrap();
+                }
+            }
+            writeln!(
+                csv,
+                "Device,{},{},info",
+                Self::csv_escape(&format!(
+                    "{} [{}] {}",
+                    activity.device.label(),
+                    activity.device.usb_id(),
+                    activity.device.path
+                )),
+                Self::csv_escape(&value)
+            )
+            .unwrap();
+        }
+
+        if let Some(acceptance) = &self.acceptance {
+            for criterion in &acceptance.criteria {
+                let status = if criterion.passed { "ok" } else { "error" };
+                writeln!(
+                    csv,
+                    "Acceptance,{},{},{}",
+                    Self::csv_escape(&criterion.label),
+                    Self::csv_escape(&criterion.measured),
+                    status
+                )
+                .unwrap();
+            }
+            let status = match acceptance.verdict {
+                Verdict::Pass => "ok",
+                Verdict::Fail => "error",
+            };
+            writeln!(
+                csv,
+                "Acceptance,Verdict,{},{}",
+                acceptance.verdict.as_str(),
+                status
+            )
+            .unwrap();
+        }
 
         csv
     }
@@ -268,6 +547,25 @@ impl SessionReport {
         writeln!(md, "| Generated | {} |", self.metadata.generated_at).unwrap();
         writeln!(md, "| Version | {} |", self.metadata.version).unwrap();
         writeln!(md, "| Duration | {:.1}s |", self.metadata.duration_secs).unwrap();
+        if let Some(fidelity) = self.metadata.input_fidelity {
+            writeln!(md, "| Input | {} |", fidelity.label()).unwrap();
+        }
+        if self.metadata.dropped_events > 0 {
+            writeln!(
+                md,
+                "| Dropped Events | {} (capture queue full) |",
+                self.metadata.dropped_events
+            )
+            .unwrap();
+        }
+        if self.metadata.sync_drops > 0 {
+            writeln!(
+                md,
+                "| Kernel Overruns | {} (SYN_DROPPED, key state resynced) |",
+                self.metadata.sync_drops
+            )
+            .unwrap();
+        }
         writeln!(md).unwrap();
 
         // Summary
@@ -282,6 +580,57 @@ impl SessionReport {
         writeln!(md, "| Issues Detected | {} |", self.summary.issues_detected).unwrap();
         writeln!(md).unwrap();
 
+        if !self.devices.is_empty() {
+            writeln!(md, "## Devices\n").unwrap();
+            writeln!(md, "| Device | ID | Path | Events | Presses | Keys | Under Test |").unwrap();
+            writeln!(md, "|--------|----|------|--------|---------|------|------------|").unwrap();
+            for activity in &self.devices {
+                writeln!(
+                    md,
+                    "| {} | {} | {}{} | {} | {} | {} | {} |",
+                    activity.device.label(),
+                    activity.device.usb_id(),
+                    activity.device.path,
+                    if activity.disconnected { " (unplugged)" } else { "" },
+                    activity.events,
+                    activity.presses,
+                    activity.key_coverage().unwrap_or_default(),
+                    if activity.under_test { "✅" } else { "" }
+                )
+                .unwrap();
+            }
+            writeln!(md).unwrap();
+            for activity in &self.devices {
+                let missing = activity.keys_not_pressed();
+                if !missing.is_empty() {
+                    writeln!(
+                        md,
+                        "Declared keys not pressed on {}: {}\n",
+                        activity.device.label(),
+                        key_code_names(missing)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        if let Some(acceptance) = &self.acceptance {
+            writeln!(md, "## Acceptance: {}\n", acceptance.profile).unwrap();
+            writeln!(md, "**Verdict: {}**\n", acceptance.verdict.as_str()).unwrap();
+            writeln!(md, "| Criterion | Required | Measured | Result |").unwrap();
+            writeln!(md, "|-----------|----------|----------|--------|").unwrap();
+            for criterion in &acceptance.criteria {
+                let result = if criterion.passed { "✅" } else { "❌" };
+                writeln!(
+                    md,
+                    "| {} | {} | {} | {} |",
+                    criterion.label, criterion.required, criterion.measured, result
+                )
+                .unwrap();
+            }
+            writeln!(md).unwrap();
+        }
+
         // Test results sections
         Self::write_markdown_section(&mut md, "Polling Rate", &self.tests.polling);
         Self::write_markdown_section(&mut md, "Hold/Release", &self.tests.hold_release);
@@ -291,6 +640,9 @@ impl SessionReport {
         Self::write_markdown_section(&mut md, "Shortcuts", &self.tests.shortcuts);
         Self::write_markdown_section(&mut md, "Virtual Detect", &self.tests.virtual_detect);
         Self::write_markdown_section(&mut md, "OEM Keys", &self.tests.oem_keys);
+        Self::write_markdown_section(&mut md, "Key Coverage", &self.tests.coverage);
+        Self::write_markdown_section(&mut md, "Auto-Repeat", &self.tests.typematic);
+        Self::write_markdown_section(&mut md, "LEDs", &self.tests.leds);
 
         md
     }
@@ -344,6 +696,25 @@ impl SessionReport {
         // Metadata
         writeln!(text, "Generated: {}", self.metadata.generated_at).unwrap();
         writeln!(text, "Version:   {}", self.metadata.version).unwrap();
+        if let Some(fidelity) = self.metadata.input_fidelity {
+            writeln!(text, "Input:     {}", fidelity.label()).unwrap();
+        }
+        if self.metadata.dropped_events > 0 {
+            writeln!(
+                text,
+                "Dropped:   {} events (capture queue full)",
+                self.metadata.dropped_events
+            )
+            .unwrap();
+        }
+        if self.metadata.sync_drops > 0 {
+            writeln!(
+                text,
+                "Overruns:  {} (SYN_DROPPED, key state resynced)",
+                self.metadata.sync_drops
+            )
+            .unwrap();
+        }
         writeln!(
             text,
             "Duration:  {:.1} seconds\n",
@@ -361,6 +732,49 @@ impl SessionReport {
         }
         writeln!(text, "Issues Detected: {}\n", self.summary.issues_detected).unwrap();
 
+        if !self.devices.is_empty() {
+            writeln!(text, "DEVICES").unwrap();
+            writeln!(text, "-------\n").unwrap();
+            for activity in &self.devices {
+                let marker = if activity.under_test { "*" } else { " " };
+                writeln!(
+                    text,
+                    "{} {:<30} {}  {:<20} {:>6} events{}",
+                    marker,
+                    activity.device.label(),
+                    activity.device.usb_id(),
+                    activity.device.path,
+                    activity.events,
+                    if activity.disconnected { " (unplugged)" } else { "" }
+                )
+                .unwrap();
+                if let Some(coverage) = activity.key_coverage() {
+                    writeln!(text, "  Keys:     {} declared keys pressed", coverage).unwrap();
+                    let missing = activity.keys_not_pressed();
+                    if !missing.is_empty() {
+                        writeln!(text, "  Not pressed: {}", key_code_names(missing)).unwrap();
+                    }
+                }
+            }
+            writeln!(text).unwrap();
+        }
+
+        if let Some(acceptance) = &self.acceptance {
+            let title = format!("ACCEPTANCE: {}", acceptance.profile);
+            writeln!(text, "{}", title).unwrap();
+            writeln!(text, "{}\n", "-".repeat(title.chars().count())).unwrap();
+            for criterion in &acceptance.criteria {
+                let result = if criterion.passed { "[PASS]" } else { "[FAIL]" };
+                writeln!(
+                    text,
+                    "  {:<16} {:>14} {:>24}  {}",
+                    criterion.label, criterion.required, criterion.measured, result
+                )
+                .unwrap();
+            }
+            writeln!(text, "\nVerdict: {}\n", acceptance.verdict.as_str()).unwrap();
+        }
+
         // Test results
         Self::write_text_section(&mut text, "POLLING RATE", &self.tests.polling);
         Self::write_text_section(&mut text, "HOLD/RELEASE", &self.tests.hold_release);
@@ -370,6 +784,9 @@ impl SessionReport {
         Self::write_text_section(&mut text, "SHORTCUTS", &self.tests.shortcuts);
         Self::write_text_section(&mut text, "VIRTUAL DETECT", &self.tests.virtual_detect);
         Self::write_text_section(&mut text, "OEM KEYS", &self.tests.oem_keys);
+        Self::write_text_section(&mut text, "KEY COVERAGE", &self.tests.coverage);
+        Self::write_text_section(&mut text, "AUTO-REPEAT", &self.tests.typematic);
+        Self::write_text_section(&mut text, "LEDS", &self.tests.leds);
 
         text
     }
@@ -410,6 +827,9 @@ mod tests {
                 generated_at: "2024-01-15T12:00:00Z".to_string(),
                 version: "0.1.0".to_string(),
                 duration_secs: 30.5,
+                input_fidelity: Some(InputFidelity::Evdev),
+                dropped_events: 0,
+                sync_drops: 0,
             },
             summary: SessionSummary {
                 total_events: 1500,
@@ -441,7 +861,21 @@ mod tests {
                 shortcuts: vec![],
                 virtual_detect: vec![],
                 oem_keys: vec![],
+                coverage: vec![],
+                typematic: vec![],
+                leds: vec![],
             },
+            metrics: SessionMetrics {
+                polling_rate_hz: Some(1000.0),
+                jitter_us: Some(50.0),
+                max_rollover: 6,
+                bounces: 0,
+                stuck_keys: 0,
+                avg_event_timing_ms: Some(12.0),
+                keys_pressed: vec![30, 31, 32],
+            },
+            acceptance: None,
+            devices: vec![],
         }
     }
 
@@ -599,6 +1033,222 @@ mod tests {
         assert!(text.contains("ROLLOVER"));
     }
 
+    #[test]
+    fn errors_lists_only_error_entries() {
+        let mut report = create_test_report();
+        assert!(report.errors().is_empty());
+
+        report.tests.stickiness.push(ResultEntry {
+            label: "Stuck Keys".to_string(),
+            value: "2".to_string(),
+            status: "error".to_string(),
+        });
+        let errors = report.errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, "Stickiness");
+        assert_eq!(errors[0].1.label, "Stuck Keys");
+    }
+
+    #[test]
+    fn grade_sets_verdict_and_overrides_error_rule() {
+        let mut report = create_test_report();
+        report.tests.stickiness.push(ResultEntry {
+            label: "Stuck Keys".to_string(),
+            value: "1".to_string(),
+            status: "error".to_string(),
+        });
+        assert!(!report.passed());
+
+        let profile = AcceptanceProfile {
+            name: "sku-a".to_string(),
+            min_polling_hz: Some(950.0),
+            ..Default::default()
+        };
+        report.grade(&profile);
+        assert!(report.passed());
+
+        let strict = AcceptanceProfile {
+            min_rollover: Some(10),
+            ..profile
+        };
+        report.grade(&strict);
+        assert!(!report.passed());
+        assert_eq!(report.acceptance.as_ref().unwrap().verdict, Verdict::Fail);
+    }
+
+    #[test]
+    fn acceptance_appears_in_every_format() {
+        let mut report = create_test_report();
+        report.grade(&AcceptanceProfile {
+            name: "sku-a".to_string(),
+            max_bounces: Some(0),
+            required_keys: vec![30, 33],
+            ..Default::default()
+        });
+
+        let text = report.to_text();
+        assert!(text.contains("ACCEPTANCE: sku-a"));
+        assert!(text.contains("Verdict: FAIL"));
+
+        let md = report.to_markdown();
+        assert!(md.contains("## Acceptance: sku-a"));
+        assert!(md.contains("missing F"));
+
+        let csv = report.to_csv();
+        assert!(csv.contains("Acceptance,Bounces,0,ok"));
+        assert!(csv.contains("Acceptance,Verdict,FAIL,error"));
+
+        let json = report.to_json().unwrap();
+        let parsed: SessionReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.acceptance.unwrap().criteria.len(), 2);
+    }
+
+    #[test]
+    fn devices_appear_in_every_format() {
+        let mut report = create_test_report();
+        let mut board = DeviceActivity::new(DeviceInfo::new(
+            "/dev/input/event5",
+            "USB Keyboard",
+            0x046d,
+            0xc31c,
+        ));
+        board.events = 42;
+        board.under_test = true;
+        report.devices = vec![board];
+
+        assert!(report.to_csv().contains("42 events (under test)"));
+        assert!(report.to_markdown().contains("| USB Keyboard | 046d:c31c |"));
+        assert!(report.to_text().contains("* USB Keyboard"));
+
+        let parsed: SessionReport = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.devices, report.devices);
+    }
+
+    #[test]
+    fn declared_keys_not_pressed_are_listed() {
+        let mut report = create_test_report();
+        let mut board = DeviceActivity::new(DeviceInfo::new("/dev/input/event5", "USB Keyboard", 1, 2));
+        board.capabilities = Some(DeviceCapabilities {
+            keys: [30, 48, 46].into_iter().collect(),
+            ..DeviceCapabilities::default()
+        });
+        // KEY_Q is pressed but not declared; it does not count
+        board.keys_pressed = [30, 16].into_iter().collect();
+        report.devices = vec![board];
+
+        let activity = &report.devices[0];
+        assert_eq!(activity.keys_not_pressed(), vec![46, 48]);
+        assert_eq!(activity.key_coverage().as_deref(), Some("1/3"));
+        assert!(report.to_csv().contains("1/3 declared keys pressed"));
+        assert!(report.to_markdown().contains("| 1/3 |"));
+        assert!(report.to_text().contains("1/3 declared keys pressed"));
+
+        let parsed: SessionReport = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.devices, report.devices);
+    }
+
+    #[test]
+    fn unplugged_devices_are_marked() {
+        let mut report = create_test_report();
+        let mut board = DeviceActivity::new(DeviceInfo::new("/dev/input/event5", "USB Keyboard", 1, 2));
+        board.disconnected = true;
+        report.devices = vec![board];
+
+        assert!(report.to_csv().contains("0 events (unplugged)"));
+        assert!(report.to_markdown().contains("/dev/input/event5 (unplugged)"));
+        assert!(report.to_text().contains("(unplugged)"));
+    }
+
+    #[test]
+    fn input_fidelity_is_shown_in_every_format() {
+        let mut report = create_test_report();
+        report.metadata.input_fidelity = Some(InputFidelity::Basic);
+
+        assert!(report
+            .to_csv()
+            .contains("Session,Input,terminal (presses only),warning"));
+        assert!(report.to_markdown().contains("| Input | terminal (presses only) |"));
+        assert!(report.to_text().contains("Input:     terminal (presses only)"));
+        assert!(report.to_json().unwrap().contains("\"input_fidelity\": \"basic\""));
+    }
+
+    #[test]
+    fn dropped_events_are_shown_only_when_nonzero() {
+        let mut report = create_test_report();
+        assert!(!report.to_text().contains("Dropped"));
+
+        report.metadata.dropped_events = 42;
+        assert!(report.to_csv().contains("Session,Dropped Events,42,error"));
+        assert!(report
+            .to_markdown()
+            .contains("| Dropped Events | 42 (capture queue full) |"));
+        assert!(report.to_text().contains("Dropped:   42 events"));
+        assert!(report.to_json().unwrap().contains("\"dropped_events\": 42"));
+    }
+
+    #[test]
+    fn dropped_events_fail_the_session() {
+        let mut report = create_test_report();
+        report.metadata.dropped_events = 1;
+        assert!(!report.passed());
+        let errors = report.errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].1.label, "Dropped Events");
+    }
+
+    #[test]
+    fn kernel_overruns_are_flagged_as_errors() {
+        let mut report = create_test_report();
+        assert!(!report.to_text().contains("Overruns"));
+
+        report.metadata.sync_drops = 3;
+        assert!(report.to_csv().contains("Session,Kernel Overruns,3,error"));
+        assert!(report.to_markdown().contains("| Kernel Overruns | 3 (SYN_DROPPED"));
+        assert!(report.to_text().contains("Overruns:  3"));
+        let parsed: SessionReport = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.metadata.sync_drops, 3);
+    }
+
+    #[test]
+    fn kernel_overruns_fail_the_session() {
+        let mut report = create_test_report();
+        assert!(report.passed());
+
+        report.metadata.sync_drops = 1;
+        assert!(!report.passed());
+        let errors = report.errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, "Session");
+        assert_eq!(errors[0].1.label, "Kernel Overruns");
+
+        // A passing profile verdict does not excuse lost events
+        report.grade(&AcceptanceProfile {
+            name: "sku-a".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(report.acceptance.as_ref().unwrap().verdict, Verdict::Pass);
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn export_picks_format_from_extension() {
+        let report = create_test_report();
+        let dir = std::env::temp_dir();
+        let pid = std::process::id();
+
+        let csv = dir.join(format!("keyboard-testkit-report-{}.csv", pid));
+        report.export(&csv).unwrap();
+        let contents = std::fs::read_to_string(&csv).unwrap();
+        assert!(contents.starts_with("Category,Label,Value,Status"));
+        let _ = std::fs::remove_file(&csv);
+
+        let json = dir.join(format!("keyboard-testkit-report-{}.json", pid));
+        report.export(&json).unwrap();
+        let contents = std::fs::read_to_string(&json).unwrap();
+        assert!(contents.contains("\"total_events\""));
+        let _ = std::fs::remove_file(&json);
+    }
+
     #[test]
     fn export_json_to_invalid_path_returns_error() {
         let report = create_test_report();
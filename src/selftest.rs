@@ -0,0 +1,461 @@
+//! uinput loopback self-test
+//!
+//! Before blaming a keyboard for a low polling rate or phantom bounces, it
+//! helps to know what this machine can measure at all. The self-test creates
+//! a uinput virtual keyboard (see [`UinputDevice`]), grabs its evdev node so
+//! nothing else sees the keys, and plays scripted patterns into it with known
+//! timing:
+//!
+//! - presses at 125, 500 and 1000 Hz, checked against [`PollingRateTest`]
+//! - chattering presses inside the bounce window, checked against
+//!   [`HoldReleaseTest`]
+//! - 6-key and 20-key chords, checked against [`RolloverTest`]
+//!
+//! The events travel the same path as a real keyboard's (kernel timestamps,
+//! the [`EvdevCapture`] thread, the tests themselves), so a failing scenario points at
+//! the machine or the pipeline rather than the hardware under test.
+//!
+//! ## Usage
+//!
+//! ```bash
+//! sudo keyboard-testkit --self-test
+//! ```
+
+use crate::config::Config;
+use crate::keyboard::{CaptureOptions, EvdevCapture, EvdevError, EvdevListener, KeyEvent};
+use crate::mapper::{MapperError, UinputDevice};
+use crate::tests::{HoldReleaseTest, KeyboardTest, PollingRateTest, RolloverTest};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Name of the self-test's virtual keyboard
+const DEVICE_NAME: &str = "Keyboard-TestKit Self-Test";
+
+/// Allowed deviation of a measured polling rate from the injected one
+pub const RATE_TOLERANCE_PCT: f64 = 5.0;
+
+/// Presses injected per polling scenario
+const POLLING_REPORTS: u64 = 200;
+
+/// Keys used by the scenarios: the letter rows (Q-P, A-L, Z-M)
+const KEYS: [u16; 26] = [
+    16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 30, 31, 32, 33, 34, 35, 36, 37, 38, 44, 45, 46, 47,
+    48, 49, 50,
+];
+
+/// Time allowed for events to arrive after the last injection
+const SETTLE: Duration = Duration::from_millis(50);
+
+/// Error type for the self-test
+#[derive(Debug)]
+pub enum SelfTestError {
+    /// The virtual keyboard could not be created
+    Uinput(MapperError),
+    /// The virtual keyboard's evdev node could not be read
+    Evdev(EvdevError),
+    /// A signal stopped the run before every scenario was played
+    Interrupted,
+}
+
+impl std::fmt::Display for SelfTestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelfTestError::Uinput(e) => write!(f, "{}", e),
+            SelfTestError::Evdev(e) => write!(f, "Reading virtual keyboard failed: {}", e),
+            SelfTestError::Interrupted => write!(f, "Interrupted before every scenario ran"),
+        }
+    }
+}
+
+impl std::error::Error for SelfTestError {}
+
+impl From<MapperError> for SelfTestError {
+    fn from(e: MapperError) -> Self {
+        SelfTestError::Uinput(e)
+    }
+}
+
+impl From<EvdevError> for SelfTestError {
+    fn from(e: EvdevError) -> Self {
+        SelfTestError::Evdev(e)
+    }
+}
+
+/// A key state change, injected at an offset from the scenario start.
+/// Steps with the same offset are sent as one frame (one SYN_REPORT).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    pub at_us: u64,
+    pub code: u16,
+    pub pressed: bool,
+}
+
+/// What a scenario expects the tests to report
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Expectation {
+    /// PollingRateTest average within [`RATE_TOLERANCE_PCT`] of this rate
+    PollingRate(f64),
+    /// HoldReleaseTest counts exactly this many bounces
+    Bounces(u32),
+    /// RolloverTest reaches exactly this many simultaneous keys
+    Rollover(usize),
+}
+
+/// A scripted input pattern and the result it should produce
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub name: String,
+    pub steps: Vec<Step>,
+    pub expect: Expectation,
+}
+
+impl Scenario {
+    /// One press per report at `rate_hz`, cycling through the letter keys.
+    /// Each report releases the previous key and presses the next, as a
+    /// keyboard polled at that rate would when typing quickly.
+    pub fn polling(rate_hz: u32, reports: u64) -> Self {
+        let period_us = 1_000_000 / rate_hz as u64;
+        let mut steps = Vec::new();
+        for i in 0..reports {
+            let at_us = i * period_us;
+            if i > 0 {
+                steps.push(Step {
+                    at_us,
+                    code: KEYS[(i as usize - 1) % KEYS.len()],
+                    pressed: false,
+                });
+            }
+            steps.push(Step {
+                at_us,
+                code: KEYS[i as usize % KEYS.len()],
+                pressed: true,
+            });
+        }
+        steps.push(Step {
+            at_us: reports * period_us,
+            code: KEYS[(reports as usize - 1) % KEYS.len()],
+            pressed: false,
+        });
+
+        Self {
+            name: format!("Polling {} Hz", rate_hz),
+            steps,
+            expect: Expectation::PollingRate(rate_hz as f64),
+        }
+    }
+
+    /// `presses` clean presses, every other one chattering: a release and
+    /// re-press a quarter and half of the bounce window after it goes down.
+    /// HoldReleaseTest counts both chatter transitions as bounces.
+    pub fn bounce(presses: u32, bounce_window_ms: u64) -> Self {
+        let window_us = bounce_window_ms.max(1) * 1000;
+        let mut steps = Vec::new();
+        let mut chatters = 0;
+        for i in 0..presses {
+            let code = KEYS[i as usize % KEYS.len()];
+            let at_us = i as u64 * window_us * 20;
+            steps.push(Step {
+                at_us,
+                code,
+                pressed: true,
+            });
+            if i % 2 == 1 {
+                chatters += 1;
+                steps.push(Step {
+                    at_us: at_us + window_us / 4,
+                    code,
+                    pressed: false,
+                });
+                steps.push(Step {
+                    at_us: at_us + window_us / 2,
+                    code,
+                    pressed: true,
+                });
+            }
+            steps.push(Step {
+                at_us: at_us + window_us * 10,
+                code,
+                pressed: false,
+            });
+        }
+
+        Self {
+            name: format!("Bounce ({} chattering)", chatters),
+            steps,
+            expect: Expectation::Bounces(chatters * 2),
+        }
+    }
+
+    /// `keys` pressed in a single frame, held, then released together
+    pub fn chord(keys: usize) -> Self {
+        let keys = keys.min(KEYS.len());
+        let mut steps: Vec<Step> = KEYS[..keys]
+            .iter()
+            .map(|&code| Step {
+                at_us: 0,
+                code,
+                pressed: true,
+            })
+            .collect();
+        steps.extend(KEYS[..keys].iter().map(|&code| Step {
+            at_us: 50_000,
+            code,
+            pressed: false,
+        }));
+
+        Self {
+            name: format!("{}-key chord", keys),
+            steps,
+            expect: Expectation::Rollover(keys),
+        }
+    }
+
+    /// The full self-test: three polling rates, bounces and two chords
+    pub fn standard(config: &Config) -> Vec<Self> {
+        vec![
+            Self::polling(125, POLLING_REPORTS),
+            Self::polling(500, POLLING_REPORTS),
+            Self::polling(1000, POLLING_REPORTS),
+            Self::bounce(10, config.hold_release.bounce_window_ms),
+            Self::chord(6),
+            Self::chord(20),
+        ]
+    }
+
+    /// Run captured events through the test this scenario checks
+    pub fn evaluate(&self, events: &[KeyEvent], config: &Config) -> Outcome {
+        let (expected, measured, passed) = match self.expect {
+            Expectation::PollingRate(rate) => {
+                let mut test = PollingRateTest::new(
+                    config.polling.test_duration_secs,
+                    config.polling.sample_window_ms,
+                );
+                feed(&mut test, events);
+                let measured = test.avg_rate_hz();
+                let passed = measured
+                    .is_some_and(|hz| (hz - rate).abs() / rate * 100.0 <= RATE_TOLERANCE_PCT);
+                (
+                    format!("{:.1} Hz", rate),
+                    measured
+                        .map(|hz| format!("{:.1} Hz", hz))
+                        .unwrap_or_else(|| "no data".to_string()),
+                    passed,
+                )
+            }
+            Expectation::Bounces(bounces) => {
+                let mut test = HoldReleaseTest::new(config.hold_release.bounce_window_ms);
+                feed(&mut test, events);
+                let measured = test.total_bounces();
+                (
+                    bounces.to_string(),
+                    measured.to_string(),
+                    measured == bounces,
+                )
+            }
+            Expectation::Rollover(keys) => {
+                let mut test = RolloverTest::new();
+                feed(&mut test, events);
+                let measured = test.max_rollover();
+                (keys.to_string(), measured.to_string(), measured == keys)
+            }
+        };
+
+        let expected_events = self.steps.len();
+        Outcome {
+            scenario: self.name.clone(),
+            expected,
+            measured,
+            events: events.len(),
+            expected_events,
+            passed: passed && events.len() == expected_events,
+        }
+    }
+}
+
+fn feed(test: &mut impl KeyboardTest, events: &[KeyEvent]) {
+    for event in events {
+        test.process_event(event);
+    }
+}
+
+/// Result of one scenario
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub scenario: String,
+    pub expected: String,
+    pub measured: String,
+    /// Key events captured
+    pub events: usize,
+    /// Key events injected
+    pub expected_events: usize,
+    pub passed: bool,
+}
+
+/// Sleep until `deadline`, spinning for the last stretch so frames go out
+/// within a few microseconds of their schedule
+fn wait_until(deadline: Instant) {
+    const SPIN: Duration = Duration::from_micros(300);
+    let now = Instant::now();
+    if deadline > now + SPIN {
+        std::thread::sleep(deadline - now - SPIN);
+    }
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+/// Play a scenario's steps into the virtual keyboard on schedule
+fn inject(device: &UinputDevice, steps: &[Step]) {
+    const EV_KEY: u16 = 0x01;
+    let start = Instant::now();
+    for frame in steps.chunk_by(|a, b| a.at_us == b.at_us) {
+        wait_until(start + Duration::from_micros(frame[0].at_us));
+        for step in frame {
+            device.emit(EV_KEY, step.code, step.pressed as i32);
+        }
+        device.syn();
+    }
+}
+
+/// Run every scenario through a grabbed uinput loopback device, read by the
+/// same capture thread the TUI uses
+pub fn run(
+    config: &Config,
+    options: CaptureOptions,
+    running: &AtomicBool,
+) -> Result<Vec<Outcome>, SelfTestError> {
+    let mut device = UinputDevice::create(DEVICE_NAME)?;
+    let node = device.event_node(Duration::from_secs(2))?;
+
+    let capture = EvdevCapture::spawn(|tx| EvdevListener::for_device(&node, tx), options)?;
+    // Keep the scripted keys away from the desktop
+    capture
+        .listener()
+        .set_grab(true)
+        .map_err(|e| SelfTestError::Evdev(e.into()))?;
+
+    // Let the desktop finish probing the new device
+    std::thread::sleep(Duration::from_millis(200));
+    capture.try_iter().for_each(drop);
+
+    let mut outcomes = Vec::new();
+    for scenario in Scenario::standard(config) {
+        if !running.load(Ordering::SeqCst) {
+            return Err(SelfTestError::Interrupted);
+        }
+
+        // Inject on another thread while this one drains the capture queue;
+        // the device comes back when the scenario is done
+        let steps = scenario.steps.clone();
+        let injector = std::thread::spawn(move || {
+            inject(&device, &steps);
+            device
+        });
+
+        let mut events = Vec::new();
+        let mut settled_at = None;
+        loop {
+            events.extend(capture.try_iter());
+            if settled_at.is_none() && injector.is_finished() {
+                settled_at = Some(Instant::now() + SETTLE);
+            }
+            if settled_at.is_some_and(|at| Instant::now() >= at) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        device = injector.join().expect("self-test injector panicked");
+
+        outcomes.push(scenario.evaluate(&events, config));
+    }
+
+    let _ = capture.listener().set_grab(false);
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::{KeyCode, KeyEventType};
+
+    /// Events a perfect pipeline would deliver for the scenario, with kernel
+    /// timestamps shifted by `jitter_us(i)` for the i-th frame
+    fn delivered(scenario: &Scenario, jitter_us: impl Fn(usize) -> u64) -> Vec<KeyEvent> {
+        let now = Instant::now();
+        let mut frame = 0;
+        let mut last_at = None;
+        scenario
+            .steps
+            .iter()
+            .map(|step| {
+                if last_at.is_some_and(|at| at != step.at_us) {
+                    frame += 1;
+                }
+                last_at = Some(step.at_us);
+                let event_type = if step.pressed {
+                    KeyEventType::Press
+                } else {
+                    KeyEventType::Release
+                };
+                KeyEvent::new(KeyCode(step.code), event_type, now, 0)
+                    .with_hw_timestamp(1_000_000 + step.at_us + jitter_us(frame))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn polling_steps_press_once_per_report() {
+        let scenario = Scenario::polling(1000, 10);
+        let presses: Vec<u64> = scenario
+            .steps
+            .iter()
+            .filter(|s| s.pressed)
+            .map(|s| s.at_us)
+            .collect();
+        assert_eq!(presses, (0..10).map(|i| i * 1000).collect::<Vec<_>>());
+        // Every key that goes down comes back up
+        assert_eq!(scenario.steps.len(), 20);
+    }
+
+    #[test]
+    fn on_time_delivery_passes_every_scenario() {
+        let config = Config::default();
+        for scenario in Scenario::standard(&config) {
+            let outcome = scenario.evaluate(&delivered(&scenario, |_| 0), &config);
+            assert!(outcome.passed, "{} failed: {:?}", scenario.name, outcome);
+        }
+    }
+
+    #[test]
+    fn slow_delivery_fails_the_polling_rate() {
+        let config = Config::default();
+        let scenario = Scenario::polling(1000, POLLING_REPORTS);
+        // Every frame 100us late compounds into a 10% lower rate
+        let outcome = scenario.evaluate(&delivered(&scenario, |i| i as u64 * 100), &config);
+        assert!(!outcome.passed);
+        assert_eq!(outcome.expected, "1000.0 Hz");
+    }
+
+    #[test]
+    fn late_chatter_is_not_a_bounce() {
+        let config = Config::default();
+        let scenario = Scenario::bounce(4, config.hold_release.bounce_window_ms);
+        assert_eq!(scenario.expect, Expectation::Bounces(4));
+
+        // Frames delayed by a whole bounce window each spread the chatter out
+        let outcome = scenario.evaluate(&delivered(&scenario, |i| i as u64 * 5_000), &config);
+        assert!(!outcome.passed);
+    }
+
+    #[test]
+    fn lost_events_fail_the_scenario() {
+        let config = Config::default();
+        let scenario = Scenario::chord(6);
+        let mut events = delivered(&scenario, |_| 0);
+        events.truncate(8);
+
+        let outcome = scenario.evaluate(&events, &config);
+        assert_eq!(outcome.measured, "6");
+        assert!(!outcome.passed);
+    }
+}
@@ -1,7 +1,7 @@
 //! Hold down and release test with bounce detection
 
 use super::{KeyboardTest, TestResult};
-use crate::keyboard::{keymap, KeyCode, KeyEvent, KeyEventType};
+use crate::keyboard::{keymap, EventTime, KeyCode, KeyEvent, KeyEventType};
 use std::collections::{HashMap, VecDeque};
 use std::time::{Duration, Instant};
 
@@ -9,7 +9,7 @@ use std::time::{Duration, Instant};
 #[derive(Debug, Clone)]
 struct KeyEventRecord {
     event_type: KeyEventType,
-    timestamp: Instant,
+    time: EventTime,
 }
 
 /// Statistics for a single key's hold/release behavior
@@ -30,7 +30,7 @@ struct KeyHoldStats {
     /// Currently pressed
     is_pressed: bool,
     /// Current press start time
-    press_start: Option<Instant>,
+    press_start: Option<EventTime>,
 }
 
 /// Test for hold down, release, and bounce detection
@@ -48,7 +48,7 @@ pub struct HoldReleaseTest {
     /// Test start time
     start_time: Option<Instant>,
     /// Last event for repeat rate detection
-    last_event_time: Option<Instant>,
+    last_event_time: Option<EventTime>,
     /// Repeat events detected (same key pressed rapidly)
     repeat_intervals: VecDeque<u64>,
 }
@@ -81,9 +81,9 @@ impl HoldReleaseTest {
             .find(|e| e.event_type != event.event_type);
 
         if let Some(last) = last_opposite {
-            let time_since = event.timestamp.duration_since(last.timestamp);
+            let time_since_us = event.time().micros_since(last.time);
             // If the opposite event happened within bounce window, it's a bounce
-            time_since < bounce_window
+            time_since_us < bounce_window.as_micros() as u64
         } else {
             false
         }
@@ -112,6 +112,11 @@ impl HoldReleaseTest {
         }
     }
 
+    /// Total bounces detected across all keys
+    pub fn total_bounces(&self) -> u32 {
+        self.total_bounces
+    }
+
     /// Get keys with bounces
     pub fn bouncy_keys(&self) -> Vec<(KeyCode, u32)> {
         self.key_stats
@@ -147,6 +152,11 @@ impl KeyboardTest for HoldReleaseTest {
     }
 
     fn process_event(&mut self, event: &KeyEvent) {
+        // Auto-repeat is generated while the key is held, not by the switch
+        if event.event_type == KeyEventType::Repeat {
+            return;
+        }
+
         if self.start_time.is_none() {
             self.start_time = Some(Instant::now());
         }
@@ -164,7 +174,7 @@ impl KeyboardTest for HoldReleaseTest {
         // Record the event
         stats.events.push_back(KeyEventRecord {
             event_type: event.event_type,
-            timestamp: event.timestamp,
+            time: event.time(),
         });
 
         // Keep only last 100 events per key to save memory
@@ -175,7 +185,7 @@ impl KeyboardTest for HoldReleaseTest {
         match event.event_type {
             KeyEventType::Press => {
                 stats.is_pressed = true;
-                stats.press_start = Some(event.timestamp);
+                stats.press_start = Some(event.time());
                 stats.press_count += 1;
                 self.total_presses += 1;
 
@@ -186,7 +196,7 @@ impl KeyboardTest for HoldReleaseTest {
 
                 // Track repeat intervals
                 if let Some(last) = self.last_event_time {
-                    let interval = event.timestamp.duration_since(last).as_micros() as u64;
+                    let interval = event.time().micros_since(last);
                     if interval < 500_000 {
                         // Only track intervals < 500ms
                         self.repeat_intervals.push_back(interval);
@@ -195,14 +205,15 @@ impl KeyboardTest for HoldReleaseTest {
                         }
                     }
                 }
-                self.last_event_time = Some(event.timestamp);
+                self.last_event_time = Some(event.time());
             }
+            KeyEventType::Repeat => {}
             KeyEventType::Release => {
                 stats.is_pressed = false;
 
                 // Calculate hold duration
                 if let Some(start) = stats.press_start.take() {
-                    let hold_ms = event.timestamp.duration_since(start).as_secs_f64() * 1000.0;
+                    let hold_ms = event.time().micros_since(start) as f64 / 1000.0;
                     stats.total_hold_ms += hold_ms;
 
                     stats.min_hold_ms =
@@ -0,0 +1,351 @@
+//! Guided "press every key" coverage test
+
+use super::{KeyboardTest, TestResult};
+use crate::keyboard::layout::{arrow_keys, layout_rows, KeyboardLayout};
+use crate::keyboard::{keymap, KeyCode, KeyEvent, KeyEventType};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// Display names for the layout rows, plus the arrow cluster
+const ROW_NAMES: [&str; 6] = [
+    "Number row",
+    "Top row",
+    "Home row",
+    "Shift row",
+    "Bottom row",
+    "Arrows",
+];
+
+/// Test that every physical key of the layout registers a press and release
+pub struct KeyCoverageTest {
+    /// Layout the key set was built from
+    layout: KeyboardLayout,
+    /// Keys to cover, grouped by row in layout order
+    rows: Vec<Vec<KeyCode>>,
+    /// Keys that have been pressed at least once
+    pressed: HashSet<KeyCode>,
+    /// Keys that have completed a press and release (tested)
+    tested: HashSet<KeyCode>,
+    /// Pressed keys outside the layout (OEM, F-keys, numpad, ...)
+    extra_keys: HashSet<KeyCode>,
+    /// Test start time (first event)
+    start_time: Option<Instant>,
+    /// Time from first event until the last key was tested
+    completed_in: Option<Duration>,
+}
+
+impl KeyCoverageTest {
+    pub fn new(layout: KeyboardLayout) -> Self {
+        let mut rows: Vec<Vec<KeyCode>> = layout_rows(layout)
+            .iter()
+            .map(|row| row.iter().map(|k| KeyCode(k.code)).collect())
+            .collect();
+        rows.push(arrow_keys().iter().map(|k| KeyCode(k.code)).collect());
+
+        Self {
+            layout,
+            rows,
+            pressed: HashSet::new(),
+            tested: HashSet::new(),
+            extra_keys: HashSet::new(),
+            start_time: None,
+            completed_in: None,
+        }
+    }
+
+    /// Layout the key set was built from
+    pub fn layout(&self) -> KeyboardLayout {
+        self.layout
+    }
+
+    /// Switch to a different layout, discarding progress
+    pub fn set_layout(&mut self, layout: KeyboardLayout) {
+        *self = Self::new(layout);
+    }
+
+    /// Whether a key belongs to the layout being covered
+    pub fn is_covered_key(&self, key: KeyCode) -> bool {
+        self.rows.iter().any(|row| row.contains(&key))
+    }
+
+    /// Whether a key has completed a press and release
+    pub fn is_tested(&self, key: KeyCode) -> bool {
+        self.tested.contains(&key)
+    }
+
+    /// Number of keys in the layout
+    pub fn total_keys(&self) -> usize {
+        self.rows.iter().map(Vec::len).sum()
+    }
+
+    /// Number of layout keys tested so far
+    pub fn tested_count(&self) -> usize {
+        self.tested.len()
+    }
+
+    /// Layout keys not yet tested, in layout order
+    pub fn missing_keys(&self) -> Vec<KeyCode> {
+        self.rows
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|k| !self.tested.contains(k))
+            .collect()
+    }
+
+    /// Layout keys that were pressed but never released
+    pub fn awaiting_release(&self) -> Vec<KeyCode> {
+        self.rows
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|k| self.pressed.contains(k) && !self.tested.contains(k))
+            .collect()
+    }
+
+    /// Fraction of layout keys tested (0.0 to 1.0)
+    pub fn progress(&self) -> f64 {
+        let total = self.total_keys();
+        if total == 0 {
+            return 1.0;
+        }
+        self.tested_count() as f64 / total as f64
+    }
+
+    fn key_names(keys: &[KeyCode]) -> String {
+        keys.iter()
+            .map(|&k| keymap::get_key_info(k).name)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Default for KeyCoverageTest {
+    fn default() -> Self {
+        Self::new(KeyboardLayout::Ansi)
+    }
+}
+
+impl KeyboardTest for KeyCoverageTest {
+    fn name(&self) -> &'static str {
+        "Key Coverage Test"
+    }
+
+    fn description(&self) -> &'static str {
+        "Checks that every key on the layout registers a press and release"
+    }
+
+    fn process_event(&mut self, event: &KeyEvent) {
+        if self.start_time.is_none() {
+            self.start_time = Some(event.timestamp);
+        }
+
+        if !self.is_covered_key(event.key) {
+            if event.event_type == KeyEventType::Press {
+                self.extra_keys.insert(event.key);
+            }
+            return;
+        }
+
+        match event.event_type {
+            KeyEventType::Press => {
+                self.pressed.insert(event.key);
+            }
+            KeyEventType::Repeat => {}
+            KeyEventType::Release => {
+                if self.pressed.contains(&event.key) && self.tested.insert(event.key) {
+                    if let (true, Some(start)) = (self.is_complete(), self.start_time) {
+                        self.completed_in = Some(event.timestamp.duration_since(start));
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.tested_count() == self.total_keys()
+    }
+
+    fn get_results(&self) -> Vec<TestResult> {
+        // Tooltip: Explain what this test measures
+        let mut results = vec![
+            TestResult::info("--- What This Measures ---", ""),
+            TestResult::info("Press and release every", "key once; untested keys"),
+            TestResult::info("are highlighted above", "Ctrl+Q/N/P/R/E for controls"),
+            TestResult::info("", ""),
+            TestResult::info("Layout", self.layout.name()),
+        ];
+
+        if self.start_time.is_none() {
+            results.push(TestResult::info(
+                "Keys Tested",
+                format!("Not started (0 / {})", self.total_keys()),
+            ));
+            return results;
+        }
+
+        let progress = format!(
+            "{} / {} ({:.0}%)",
+            self.tested_count(),
+            self.total_keys(),
+            self.progress() * 100.0
+        );
+        if self.is_complete() {
+            results.push(TestResult::ok("Keys Tested", progress));
+            if let Some(elapsed) = self.completed_in {
+                results.push(TestResult::info(
+                    "Completed In",
+                    format!("{:.1}s", elapsed.as_secs_f64()),
+                ));
+            }
+        } else {
+            results.push(TestResult::info("Keys Tested", progress));
+        }
+
+        if !self.extra_keys.is_empty() {
+            results.push(TestResult::info(
+                "Other Keys Pressed",
+                format!("{}", self.extra_keys.len()),
+            ));
+        }
+
+        let awaiting = self.awaiting_release();
+        if !awaiting.is_empty() {
+            results.push(TestResult::warning(
+                "Awaiting Release",
+                Self::key_names(&awaiting),
+            ));
+        }
+
+        let missing = self.missing_keys();
+        if !missing.is_empty() {
+            results.push(TestResult::warning(
+                "Missing Keys",
+                format!("{}", missing.len()),
+            ));
+            for (name, row) in ROW_NAMES.iter().zip(&self.rows) {
+                let row_missing: Vec<KeyCode> = row
+                    .iter()
+                    .copied()
+                    .filter(|k| !self.tested.contains(k))
+                    .collect();
+                if !row_missing.is_empty() {
+                    results.push(TestResult::warning(
+                        format!("  {}", name),
+                        Self::key_names(&row_missing),
+                    ));
+                }
+            }
+        }
+
+        results
+    }
+
+    fn reset(&mut self) {
+        self.pressed.clear();
+        self.tested.clear();
+        self.extra_keys.clear();
+        self.start_time = None;
+        self.completed_in = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_helpers::*;
+
+    fn tap(test: &mut KeyCoverageTest, code: u16) {
+        test.process_event(&press(KeyCode(code)));
+        test.process_event(&release(KeyCode(code)));
+    }
+
+    #[test]
+    fn untouched_test_reports_no_warnings() {
+        let test = KeyCoverageTest::default();
+        assert!(test
+            .get_results()
+            .iter()
+            .all(|r| r.status == crate::tests::ResultStatus::Info));
+    }
+
+    #[test]
+    fn key_set_follows_layout() {
+        let ansi = KeyCoverageTest::new(KeyboardLayout::Ansi);
+        let iso = KeyCoverageTest::new(KeyboardLayout::Iso);
+        assert!(!ansi.is_covered_key(KeyCode(86)));
+        assert!(iso.is_covered_key(KeyCode(86)));
+        assert!(ansi.is_covered_key(KeyCode(103))); // arrows included
+        assert_eq!(ansi.missing_keys().len(), ansi.total_keys());
+    }
+
+    #[test]
+    fn key_counts_once_pressed_and_released() {
+        let mut test = KeyCoverageTest::default();
+        test.process_event(&press(KeyCode(30)));
+        assert!(!test.is_tested(KeyCode(30)));
+        assert_eq!(test.awaiting_release(), vec![KeyCode(30)]);
+
+        test.process_event(&release(KeyCode(30)));
+        assert!(test.is_tested(KeyCode(30)));
+        assert!(test.awaiting_release().is_empty());
+        assert_eq!(test.tested_count(), 1);
+    }
+
+    #[test]
+    fn release_without_press_does_not_count() {
+        let mut test = KeyCoverageTest::default();
+        test.process_event(&release(KeyCode(30)));
+        assert!(!test.is_tested(KeyCode(30)));
+    }
+
+    #[test]
+    fn keys_outside_layout_are_ignored() {
+        let mut test = KeyCoverageTest::default();
+        tap(&mut test, 59); // F1 is not in the layout rows
+        assert_eq!(test.tested_count(), 0);
+        assert!(test
+            .get_results()
+            .iter()
+            .any(|r| r.label == "Other Keys Pressed"));
+    }
+
+    #[test]
+    fn completes_when_every_key_is_tested() {
+        let mut test = KeyCoverageTest::new(KeyboardLayout::Jis);
+        for key in test.missing_keys() {
+            assert!(!test.is_complete());
+            tap(&mut test, key.0);
+        }
+        assert!(test.is_complete());
+        assert!(test.missing_keys().is_empty());
+        assert!(test
+            .get_results()
+            .iter()
+            .any(|r| r.label == "Completed In"));
+    }
+
+    #[test]
+    fn results_list_missing_keys_by_row() {
+        let mut test = KeyCoverageTest::default();
+        for key in test.missing_keys() {
+            if key != KeyCode(31) && key != KeyCode(103) {
+                tap(&mut test, key.0);
+            }
+        }
+        let results = test.get_results();
+        let home = results.iter().find(|r| r.label == "  Home row").unwrap();
+        assert_eq!(home.value, "S");
+        assert!(results.iter().any(|r| r.label == "  Arrows"));
+        assert!(!results.iter().any(|r| r.label == "  Number row"));
+    }
+
+    #[test]
+    fn reset_clears_progress_but_keeps_layout() {
+        let mut test = KeyCoverageTest::new(KeyboardLayout::Iso);
+        tap(&mut test, 30);
+        test.reset();
+        assert_eq!(test.tested_count(), 0);
+        assert_eq!(test.layout(), KeyboardLayout::Iso);
+    }
+}
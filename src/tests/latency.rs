@@ -1,12 +1,13 @@
 //! Event timing measurement test module
 //!
 //! Measures inter-event timing (time between consecutive poll cycles that
-//! detect key events). This reflects the poll-to-poll interval, **not** true
+//! detect key events). When events carry kernel timestamps (evdev), the
+//! interval is taken between those instead. Either way this is **not** true
 //! end-to-end input latency from physical switch actuation to application
 //! delivery. True input latency requires external hardware measurement.
 
 use super::{KeyboardTest, ResultStatus, TestResult};
-use crate::keyboard::{keymap, KeyCode, KeyEvent, KeyEventType};
+use crate::keyboard::{keymap, EventTime, KeyCode, KeyEvent, KeyEventType};
 use crate::utils::MinMaxExt;
 use std::collections::HashMap;
 use std::time::Instant;
@@ -44,8 +45,8 @@ pub struct EventTimingTest {
     key_stats: HashMap<KeyCode, KeyTimingStats>,
     /// Global timing samples
     global_samples: Vec<u64>,
-    /// Last event timestamp (for consecutive key timing)
-    last_event_time: Option<Instant>,
+    /// Last event time (for consecutive key timing)
+    last_event_time: Option<EventTime>,
     /// Total events processed
     total_events: u64,
     /// Test start time
@@ -145,10 +146,26 @@ impl KeyboardTest for EventTimingTest {
     }
 
     fn process_event(&mut self, event: &KeyEvent) {
+        // Auto-repeat is generated by the kernel, not the keyboard, and
+        // must not become the baseline for the next press
+        if event.event_type == KeyEventType::Repeat {
+            return;
+        }
+
         if self.start_time.is_none() {
             self.start_time = Some(Instant::now());
         }
 
+        // Kernel timestamps give the real interval since the previous event;
+        // otherwise use delta_us, the time since the last poll cycle
+        let last_event_time = self.last_event_time.replace(event.time());
+        let timing_us = match last_event_time {
+            Some(last) if event.hw_timestamp_us.is_some() && last.hw_us.is_some() => {
+                event.time().micros_since(last)
+            }
+            _ => event.delta_us,
+        };
+
         // Only measure key presses for timing
         if event.event_type != KeyEventType::Press {
             return;
@@ -156,10 +173,6 @@ impl KeyboardTest for EventTimingTest {
 
         self.total_events += 1;
 
-        // Use delta_us from the event as our timing measurement
-        // This represents the time since the last poll cycle
-        let timing_us = event.delta_us;
-
         // Record global sample
         if timing_us < 1_000_000 {
             // Ignore >1s gaps
@@ -173,8 +186,6 @@ impl KeyboardTest for EventTimingTest {
                 .or_default()
                 .add_sample(timing_us);
         }
-
-        self.last_event_time = Some(event.timestamp);
     }
 
     fn is_complete(&self) -> bool {
@@ -282,7 +293,7 @@ impl KeyboardTest for EventTimingTest {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tests::test_helpers::{make_press, release};
+    use crate::tests::test_helpers::{make_press, press_hw, release, release_hw, repeat_hw};
 
     #[test]
     fn new_test_initial_state() {
@@ -420,6 +431,32 @@ mod tests {
         assert!((slowest_timing - 5000.0).abs() < 0.01);
     }
 
+    #[test]
+    fn process_event_uses_kernel_timestamps() {
+        let mut test = EventTimingTest::new();
+        test.process_event(&press_hw(KeyCode(30), 100_000));
+        test.process_event(&release_hw(KeyCode(30), 101_500));
+        test.process_event(&press_hw(KeyCode(31), 102_000));
+
+        // Second press is timed from the release 500us before it,
+        // not from the (zero) poll delta
+        assert_eq!(test.global_samples.len(), 2);
+        assert_eq!(test.global_samples[1], 500);
+    }
+
+    #[test]
+    fn repeats_do_not_reset_the_timing_baseline() {
+        let mut test = EventTimingTest::new();
+        test.process_event(&press_hw(KeyCode(30), 100_000));
+        test.process_event(&repeat_hw(KeyCode(30), 600_000));
+        test.process_event(&repeat_hw(KeyCode(30), 633_000));
+        test.process_event(&press_hw(KeyCode(31), 700_000));
+
+        // Second press is timed from the first press, not the last repeat
+        assert_eq!(test.global_samples.len(), 2);
+        assert_eq!(test.global_samples[1], 600_000);
+    }
+
     #[test]
     fn reset_clears_all() {
         let mut test = EventTimingTest::new();
@@ -0,0 +1,365 @@
+//! Keyboard LED verification test module
+//!
+//! Walks through the keyboard's indicator LEDs one at a time: each is
+//! switched on with an `EV_LED` write while the others are off, the kernel's
+//! LED state is read back (EVIOCGLED), and the operator confirms whether the
+//! LED actually lit. The read-back only proves the kernel accepted the
+//! write; the operator's answer is what tests the LED itself.
+//!
+//! The test does no I/O of its own. It queues [`LedCommand`]s for the
+//! caller to perform on the device and report back via
+//! [`LedTest::command_done`].
+
+use super::{KeyboardTest, TestResult};
+use crate::keyboard::{DeviceInfo, KeyEvent, Led};
+use std::collections::VecDeque;
+
+/// An LED write for the caller to perform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LedCommand {
+    pub led: Led,
+    pub on: bool,
+}
+
+/// Outcome for one LED
+#[derive(Debug, Clone)]
+struct LedCheck {
+    led: Led,
+    /// Whether every read-back matched the requested state so far
+    readback_ok: Option<bool>,
+    /// Operator's answer to "is it lit?"
+    lit: Option<bool>,
+    /// Write or read-back failure
+    error: Option<String>,
+}
+
+/// Test for driving and confirming keyboard indicator LEDs
+pub struct LedTest {
+    /// Device the LEDs belong to
+    device: Option<DeviceInfo>,
+    /// LEDs the device declares
+    available: Vec<Led>,
+    /// LEDs lit before the check started, restored afterwards
+    original: Vec<Led>,
+    /// Per-LED outcomes for the current check
+    checks: Vec<LedCheck>,
+    /// Index into `checks` of the LED awaiting an answer
+    current: Option<usize>,
+    /// Writes not yet taken by the caller
+    commands: VecDeque<LedCommand>,
+}
+
+impl LedTest {
+    pub fn new() -> Self {
+        Self {
+            device: None,
+            available: Vec::new(),
+            original: Vec::new(),
+            checks: Vec::new(),
+            current: None,
+            commands: VecDeque::new(),
+        }
+    }
+
+    /// Set the device under test and the LEDs it has
+    pub fn set_device(&mut self, device: Option<DeviceInfo>, leds: Vec<Led>) {
+        self.device = device;
+        self.available = leds;
+    }
+
+    /// Device whose LEDs are being checked
+    pub fn device(&self) -> Option<&DeviceInfo> {
+        self.device.as_ref()
+    }
+
+    /// Start a check from the LEDs currently lit, which are restored at the
+    /// end. Returns false when the device has no LEDs.
+    pub fn start(&mut self, lit: Vec<Led>) -> bool {
+        self.reset();
+        if self.available.is_empty() {
+            return false;
+        }
+        self.original = lit;
+        self.checks = self
+            .available
+            .iter()
+            .map(|&led| LedCheck {
+                led,
+                readback_ok: None,
+                lit: None,
+                error: None,
+            })
+            .collect();
+
+        // One LED lit at a time, so the operator knows which to look at
+        for &led in &self.available {
+            self.commands.push_back(LedCommand { led, on: false });
+        }
+        self.commands.push_back(LedCommand {
+            led: self.available[0],
+            on: true,
+        });
+        self.current = Some(0);
+        true
+    }
+
+    /// LED the operator is being asked about
+    pub fn prompt(&self) -> Option<Led> {
+        self.current.map(|i| self.checks[i].led)
+    }
+
+    /// Record the operator's answer and move to the next LED
+    pub fn confirm(&mut self, lit: bool) {
+        let Some(index) = self.current else {
+            return;
+        };
+        let led = self.checks[index].led;
+        self.checks[index].lit = Some(lit);
+        self.commands.push_back(LedCommand { led, on: false });
+
+        if index + 1 < self.checks.len() {
+            self.current = Some(index + 1);
+            self.commands.push_back(LedCommand {
+                led: self.checks[index + 1].led,
+                on: true,
+            });
+        } else {
+            self.current = None;
+            for &led in &self.available {
+                let on = self.original.contains(&led);
+                self.commands.push_back(LedCommand { led, on });
+            }
+        }
+    }
+
+    /// Writes to perform on the device, in order
+    pub fn take_commands(&mut self) -> Vec<LedCommand> {
+        self.commands.drain(..).collect()
+    }
+
+    /// Report a performed write: `Ok(lit)` with the read-back state of the
+    /// LED, or the error that stopped it
+    pub fn command_done(&mut self, command: LedCommand, result: Result<bool, String>) {
+        let Some(check) = self.checks.iter_mut().find(|c| c.led == command.led) else {
+            return;
+        };
+        match result {
+            Ok(lit) => {
+                let matched = lit == command.on;
+                check.readback_ok = Some(check.readback_ok.unwrap_or(true) && matched);
+            }
+            Err(e) => check.error = Some(e),
+        }
+    }
+
+    /// Number of LEDs the operator confirmed lit
+    pub fn confirmed_count(&self) -> usize {
+        self.checks.iter().filter(|c| c.lit == Some(true)).count()
+    }
+}
+
+impl Default for LedTest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeyboardTest for LedTest {
+    fn name(&self) -> &'static str {
+        "LED Test"
+    }
+
+    fn description(&self) -> &'static str {
+        "Drives each indicator LED and asks the operator to confirm it"
+    }
+
+    fn process_event(&mut self, _event: &KeyEvent) {
+        // LEDs are driven by the test, not by key events
+    }
+
+    fn is_complete(&self) -> bool {
+        !self.checks.is_empty() && self.checks.iter().all(|c| c.lit.is_some())
+    }
+
+    fn get_results(&self) -> Vec<TestResult> {
+        // Tooltip: Explain what this test measures
+        let mut results = vec![
+            TestResult::info("--- What This Measures ---", ""),
+            TestResult::info("Lights each lock LED", "and asks if it lit"),
+            TestResult::info("Kernel LED state is", "read back (EVIOCGLED)"),
+            TestResult::info("Look for: every LED lit", "when prompted"),
+            TestResult::info("", ""),
+        ];
+
+        let Some(device) = &self.device else {
+            results.push(TestResult::info("LEDs", "No LED-capable keyboard"));
+            return results;
+        };
+        results.push(TestResult::info("Device", device.label().to_string()));
+        results.push(TestResult::info(
+            "LEDs",
+            self.available
+                .iter()
+                .map(|l| l.name())
+                .collect::<Vec<_>>()
+                .join(", "),
+        ));
+
+        if self.checks.is_empty() {
+            results.push(TestResult::info("Press l", "to start the LED check"));
+            return results;
+        }
+
+        if let Some(led) = self.prompt() {
+            results.push(TestResult::warning(
+                format!("Is {} lit?", led.name()),
+                "y = yes, n = no",
+            ));
+        }
+
+        results.push(TestResult::info("--- Results ---", ""));
+        for check in &self.checks {
+            let label = format!("  {}", check.led.name());
+            let result = if let Some(error) = &check.error {
+                TestResult::error(label, error.clone())
+            } else if check.readback_ok == Some(false) {
+                TestResult::error(label, "Kernel state mismatch")
+            } else {
+                match check.lit {
+                    Some(true) => TestResult::ok(label, "Lit"),
+                    Some(false) => TestResult::error(label, "Not lit"),
+                    None => TestResult::info(label, "Waiting"),
+                }
+            };
+            results.push(result);
+        }
+
+        if self.is_complete() {
+            let confirmed = self.confirmed_count();
+            let summary = format!("{}/{} confirmed", confirmed, self.checks.len());
+            if confirmed == self.checks.len() {
+                results.push(TestResult::ok("LED Check", summary));
+            } else {
+                results.push(TestResult::error("LED Check", summary));
+            }
+        }
+
+        results
+    }
+
+    fn reset(&mut self) {
+        self.original.clear();
+        self.checks.clear();
+        self.current = None;
+        self.commands.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::ResultStatus;
+
+    fn test_with_leds() -> LedTest {
+        let mut test = LedTest::new();
+        test.set_device(
+            Some(DeviceInfo::new("/dev/input/event3", "USB Keyboard", 0x046d, 0xc31c)),
+            vec![Led::NumLock, Led::CapsLock, Led::ScrollLock],
+        );
+        test
+    }
+
+    /// Perform queued writes against a simulated kernel LED state
+    fn run_commands(test: &mut LedTest, kernel: &mut Vec<Led>) {
+        for command in test.take_commands() {
+            kernel.retain(|&l| l != command.led);
+            if command.on {
+                kernel.push(command.led);
+            }
+            test.command_done(command, Ok(kernel.contains(&command.led)));
+        }
+    }
+
+    #[test]
+    fn no_device_means_nothing_to_check() {
+        let mut test = LedTest::new();
+        assert!(!test.start(Vec::new()));
+        assert!(test.take_commands().is_empty());
+        assert!(!test.is_complete());
+    }
+
+    #[test]
+    fn leds_are_lit_one_at_a_time() {
+        let mut test = test_with_leds();
+        let mut kernel = vec![Led::NumLock];
+        assert!(test.start(kernel.clone()));
+        run_commands(&mut test, &mut kernel);
+
+        assert_eq!(test.prompt(), Some(Led::NumLock));
+        assert_eq!(kernel, vec![Led::NumLock]);
+
+        test.confirm(true);
+        run_commands(&mut test, &mut kernel);
+        assert_eq!(test.prompt(), Some(Led::CapsLock));
+        assert_eq!(kernel, vec![Led::CapsLock]);
+    }
+
+    #[test]
+    fn original_state_is_restored_after_the_last_answer() {
+        let mut test = test_with_leds();
+        let mut kernel = vec![Led::NumLock];
+        test.start(kernel.clone());
+        run_commands(&mut test, &mut kernel);
+        for lit in [true, false, true] {
+            test.confirm(lit);
+            run_commands(&mut test, &mut kernel);
+        }
+
+        assert!(test.is_complete());
+        assert_eq!(test.prompt(), None);
+        assert_eq!(kernel, vec![Led::NumLock]);
+        assert_eq!(test.confirmed_count(), 2);
+
+        let results = test.get_results();
+        let caps = results.iter().find(|r| r.label == "  Caps Lock").unwrap();
+        assert_eq!(caps.status, ResultStatus::Error);
+        let summary = results.iter().find(|r| r.label == "LED Check").unwrap();
+        assert_eq!(summary.value, "2/3 confirmed");
+    }
+
+    #[test]
+    fn readback_mismatch_fails_the_led() {
+        let mut test = test_with_leds();
+        test.start(Vec::new());
+        for command in test.take_commands() {
+            // The kernel never reports Caps Lock as lit
+            let lit = command.on && command.led != Led::CapsLock;
+            test.command_done(command, Ok(lit));
+        }
+        test.confirm(true);
+        for command in test.take_commands() {
+            let lit = command.on && command.led != Led::CapsLock;
+            test.command_done(command, Ok(lit));
+        }
+        test.confirm(true);
+
+        let results = test.get_results();
+        let caps = results.iter().find(|r| r.label == "  Caps Lock").unwrap();
+        assert_eq!(caps.value, "Kernel state mismatch");
+        let num = results.iter().find(|r| r.label == "  Num Lock").unwrap();
+        assert_eq!(num.status, ResultStatus::Ok);
+    }
+
+    #[test]
+    fn reset_clears_the_check_but_keeps_the_device() {
+        let mut test = test_with_leds();
+        test.start(Vec::new());
+        test.confirm(true);
+        test.reset();
+
+        assert_eq!(test.prompt(), None);
+        assert!(test.take_commands().is_empty());
+        assert!(test.device().is_some());
+        assert!(test.start(Vec::new()));
+    }
+}
@@ -14,6 +14,9 @@
 //! | [`ShortcutTest`] | Validates keyboard shortcut combinations |
 //! | [`VirtualKeyboardTest`] | Compares physical vs virtual key events |
 //! | [`OemKeyTest`] | Captures OEM keys and provides FN key restoration |
+//! | [`KeyCoverageTest`] | Guided check that every key on the layout registers |
+//! | [`TypematicTest`] | Measures auto-repeat delay and rate against EVIOCGREP |
+//! | [`LedTest`] | Drives indicator LEDs for the operator to confirm |
 //!
 //! ## Usage
 //!
@@ -40,24 +43,30 @@
 //! ```
 
 mod bounce;
+mod coverage;
 mod latency;
+mod leds;
 mod oem_keys;
 mod polling;
 mod rollover;
 mod shortcuts;
 mod stickiness;
+mod typematic;
 mod virtual_detect;
 
 #[cfg(test)]
 pub mod test_helpers;
 
 pub use bounce::HoldReleaseTest;
+pub use coverage::KeyCoverageTest;
 pub use latency::EventTimingTest;
-pub use oem_keys::OemKeyTest;
+pub use leds::{LedCommand, LedTest};
+pub use oem_keys::{OemKeyTest, UnknownKey};
 pub use polling::PollingRateTest;
-pub use rollover::RolloverTest;
+pub use rollover::{ChordOutcome, RolloverTest};
 pub use shortcuts::ShortcutTest;
-pub use stickiness::StickinessTest;
+pub use stickiness::{KeyStateDesync, StickinessTest};
+pub use typematic::TypematicTest;
 pub use virtual_detect::VirtualKeyboardTest;
 
 use crate::keyboard::KeyEvent;
@@ -76,8 +85,9 @@ pub trait KeyboardTest {
 
     /// Processes a single keyboard event.
     ///
-    /// Called for every key press and release. The test should update its
-    /// internal state and statistics based on the event data.
+    /// Called for every key press, release and auto-repeat. The test should
+    /// update its internal state and statistics based on the event data;
+    /// most tests ignore [`KeyEventType::Repeat`](crate::keyboard::KeyEventType::Repeat).
     fn process_event(&mut self, event: &KeyEvent);
 
     /// Returns `true` if the test has completed.
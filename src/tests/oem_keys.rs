@@ -7,6 +7,7 @@
 //! - Unknown/unmapped key detection
 
 use super::{KeyboardTest, ResultStatus, TestResult};
+use crate::keyboard::hid::{describe_scancode, usage_page_name, ReportField};
 use crate::keyboard::remap::{FnKeyMode, KeyRemapper, RemapResult, RemapStats};
 use crate::keyboard::{keymap, KeyCode, KeyEvent, KeyEventType};
 use std::collections::{HashMap, VecDeque};
@@ -29,6 +30,30 @@ pub struct OemKeyEvent {
     pub was_remapped: bool,
     /// Target key if remapped
     pub remapped_to: Option<KeyCode>,
+    /// Raw hardware scancode (MSC_SCAN), if the driver sent one
+    pub scancode: Option<u32>,
+}
+
+/// An unrecognized key, identified by its keycode and raw scancode.
+///
+/// Many OEM keys share a keycode (typically KEY_UNKNOWN), so the scancode
+/// is what tells them apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UnknownKey {
+    /// Kernel keycode
+    pub code: u16,
+    /// Raw hardware scancode (MSC_SCAN), if the driver sent one
+    pub scancode: Option<u32>,
+}
+
+impl UnknownKey {
+    /// Keycode plus scancode, e.g. "0x0F0 (Consumer 0x29D)"
+    pub fn describe(&self) -> String {
+        match self.scancode {
+            Some(scan) => format!("0x{:03X} ({})", self.code, describe_scancode(scan)),
+            None => format!("0x{:03X}", self.code),
+        }
+    }
 }
 
 /// Test for OEM key capture and FN key restoration
@@ -42,7 +67,7 @@ pub struct OemKeyTest {
     /// Detected OEM keys with press counts
     detected_oem_keys: HashMap<u16, u32>,
     /// Detected unknown keys (not in keymap)
-    detected_unknown: HashMap<u16, u32>,
+    detected_unknown: HashMap<UnknownKey, u32>,
     /// FN key press count
     fn_press_count: u32,
     /// FN combo activations
@@ -55,6 +80,8 @@ pub struct OemKeyTest {
     last_oem_key: Option<OemKeyEvent>,
     /// Last FN combo result
     last_fn_combo: Option<(KeyCode, KeyCode)>,
+    /// Consumer and vendor-page input fields from the HID descriptor
+    hid_sources: Vec<ReportField>,
 }
 
 impl OemKeyTest {
@@ -71,9 +98,16 @@ impl OemKeyTest {
             start_time: None,
             last_oem_key: None,
             last_fn_combo: None,
+            hid_sources: Vec::new(),
         }
     }
 
+    /// Set the HID input fields OEM keys can arrive on (Consumer and
+    /// vendor-defined pages), as found in the report descriptor
+    pub fn set_hid_sources(&mut self, sources: Vec<ReportField>) {
+        self.hid_sources = sources;
+    }
+
     /// Create with a specific FN mode
     pub fn with_fn_mode(mode: FnKeyMode) -> Self {
         let mut test = Self::new();
@@ -121,7 +155,7 @@ impl OemKeyTest {
         self.detected_unknown
             .iter()
             .max_by_key(|(_, &count)| count)
-            .map(|(&scancode, _)| scancode)
+            .map(|(unknown, _)| unknown.code)
     }
 
     /// Add an FN+key combination mapping
@@ -145,12 +179,13 @@ impl OemKeyTest {
     }
 
     /// Get all detected unknown keys with counts
-    pub fn detected_unknown(&self) -> &HashMap<u16, u32> {
+    pub fn detected_unknown(&self) -> &HashMap<UnknownKey, u32> {
         &self.detected_unknown
     }
 
     /// Apply the current remap result and record the event
-    fn record_event(&mut self, key: KeyCode, event_type: KeyEventType, result: &RemapResult) {
+    fn record_event(&mut self, event: &KeyEvent, result: &RemapResult) {
+        let (key, event_type) = (event.key, event.event_type);
         let scancode = key.as_u16();
         let is_oem = keymap::is_oem_key(key);
         let key_info = keymap::get_key_info(key);
@@ -162,7 +197,11 @@ impl OemKeyTest {
 
         // Track unknown keys
         if key_info.name == "Unknown" && event_type == KeyEventType::Press {
-            *self.detected_unknown.entry(scancode).or_insert(0) += 1;
+            let unknown = UnknownKey {
+                code: scancode,
+                scancode: event.scancode,
+            };
+            *self.detected_unknown.entry(unknown).or_insert(0) += 1;
         }
 
         // Determine if remapped and to what
@@ -199,6 +238,7 @@ impl OemKeyTest {
                 is_oem,
                 was_remapped,
                 remapped_to,
+                scancode: event.scancode,
             };
 
             if event_type == KeyEventType::Press {
@@ -242,6 +282,10 @@ impl KeyboardTest for OemKeyTest {
     }
 
     fn process_event(&mut self, event: &KeyEvent) {
+        // The remapper only tracks press/release state
+        if event.event_type == KeyEventType::Repeat {
+            return;
+        }
         if self.start_time.is_none() {
             self.start_time = Some(Instant::now());
         }
@@ -253,7 +297,7 @@ impl KeyboardTest for OemKeyTest {
         self.stats.record(&result);
 
         // Record the event
-        self.record_event(event.key, event.event_type, &result);
+        self.record_event(event, &result);
     }
 
     fn is_complete(&self) -> bool {
@@ -358,6 +402,7 @@ impl KeyboardTest for OemKeyTest {
                 let action = match evt.event_type {
                     KeyEventType::Press => "pressed",
                     KeyEventType::Release => "released",
+                    KeyEventType::Repeat => "repeated",
                 };
 
                 let status = if evt.is_oem {
@@ -373,6 +418,9 @@ impl KeyboardTest for OemKeyTest {
                     let target_info = keymap::get_key_info(target);
                     desc = format!("{} -> {}", desc, target_info.name);
                 }
+                if let Some(scan) = evt.scancode {
+                    desc = format!("{} [{}]", desc, describe_scancode(scan));
+                }
 
                 results.push(TestResult::new(
                     format!("  0x{:03X}", evt.key.as_u16()),
@@ -393,14 +441,36 @@ impl KeyboardTest for OemKeyTest {
             let mut unknown: Vec<_> = self.detected_unknown.iter().collect();
             unknown.sort_by(|a, b| b.1.cmp(a.1)); // Sort by count
 
-            for (scancode, count) in unknown.iter().take(5) {
+            for (unknown, count) in unknown.iter().take(5) {
                 results.push(TestResult::warning(
-                    format!("  Scancode 0x{:03X}", scancode),
+                    format!("  Key {}", unknown.describe()),
                     format!("pressed {} time(s)", count),
                 ));
             }
         }
 
+        // Where OEM keys arrive according to the HID descriptor
+        if !self.hid_sources.is_empty() {
+            results.push(TestResult::info("", ""));
+            results.push(TestResult::info("--- HID OEM Usages ---", ""));
+
+            for field in &self.hid_sources {
+                let label = match field.report_id {
+                    Some(id) => format!("  Report {}", id),
+                    None => "  Report".to_string(),
+                };
+                results.push(TestResult::info(
+                    label,
+                    format!(
+                        "{} 0x{:X}-0x{:X}",
+                        usage_page_name(field.usage_page),
+                        field.usage_min,
+                        field.usage_max
+                    ),
+                ));
+            }
+        }
+
         // Active mappings
         let mappings = self.remapper.mappings();
         if !mappings.is_empty() {
@@ -540,7 +610,31 @@ mod tests {
         test.process_event(&event);
 
         assert_eq!(test.detected_unknown.len(), 1);
-        assert_eq!(*test.detected_unknown.get(&999).unwrap(), 1);
+        let unknown = UnknownKey {
+            code: 999,
+            scancode: None,
+        };
+        assert_eq!(*test.detected_unknown.get(&unknown).unwrap(), 1);
+    }
+
+    #[test]
+    fn unknown_keys_with_same_code_are_told_apart_by_scancode() {
+        let mut test = OemKeyTest::new();
+
+        // Two different vendor keys, both delivered as KEY_UNKNOWN (240)
+        for scan in [0xFF00_0001, 0xFF00_0002, 0xFF00_0002] {
+            test.process_event(&make_event(240, true).with_scancode(scan));
+            test.process_event(&make_event(240, false).with_scancode(scan));
+        }
+
+        assert_eq!(test.detected_unknown().len(), 2);
+        let results = test.get_results();
+        assert!(results
+            .iter()
+            .any(|r| r.label == "  Key 0x0F0 (Vendor 0xFF00 0x02)" && r.value == "pressed 2 time(s)"));
+        assert!(results
+            .iter()
+            .any(|r| r.value.contains("[Vendor 0xFF00 0x01]")));
     }
 
     #[test]
@@ -584,4 +678,26 @@ mod tests {
         assert!(labels.contains(&"FN Key Held"));
         assert!(labels.contains(&"FN Mode"));
     }
+
+    #[test]
+    fn test_hid_sources_listed() {
+        use crate::keyboard::hid::{ReportKind, PAGE_CONSUMER};
+
+        let mut test = OemKeyTest::new();
+        test.set_hid_sources(vec![ReportField {
+            kind: ReportKind::Input,
+            report_id: Some(3),
+            usage_page: PAGE_CONSUMER,
+            usage_min: 0,
+            usage_max: 0x23C,
+            report_size: 16,
+            report_count: 1,
+            constant: false,
+            variable: false,
+        }]);
+
+        let results = test.get_results();
+        let source = results.iter().find(|r| r.label == "  Report 3").unwrap();
+        assert_eq!(source.value, "Consumer 0x0-0x23C");
+    }
 }
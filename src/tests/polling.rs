@@ -1,7 +1,7 @@
 //! Polling rate test module
 
 use super::{KeyboardTest, ResultStatus, TestResult};
-use crate::keyboard::{KeyEvent, KeyEventType};
+use crate::keyboard::{EventTime, KeyEvent, KeyEventType};
 use crate::utils::MinMaxExt;
 use std::time::{Duration, Instant};
 
@@ -15,8 +15,8 @@ pub struct PollingRateTest {
     start_time: Option<Instant>,
     /// Recorded intervals between events (in microseconds)
     intervals_us: Vec<u64>,
-    /// Last event timestamp
-    last_event: Option<Instant>,
+    /// Last event time (kernel timestamp preferred)
+    last_event: Option<EventTime>,
     /// Number of events recorded
     event_count: u64,
     /// Minimum interval seen
@@ -144,7 +144,7 @@ impl KeyboardTest for PollingRateTest {
 
         // Record interval from last event
         if let Some(last) = self.last_event {
-            let interval_us = event.timestamp.duration_since(last).as_micros() as u64;
+            let interval_us = event.time().micros_since(last);
 
             // NOTE: 100ms threshold is arbitrary. Should be configurable or derived
             // from the keyboard's expected polling rate.
@@ -156,7 +156,7 @@ impl KeyboardTest for PollingRateTest {
             }
         }
 
-        self.last_event = Some(event.timestamp);
+        self.last_event = Some(event.time());
         self.event_count += 1;
     }
 
@@ -259,7 +259,7 @@ impl KeyboardTest for PollingRateTest {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tests::test_helpers::{press_at, release_at, DEFAULT_KEY};
+    use crate::tests::test_helpers::{press_at, press_hw, release_at, DEFAULT_KEY};
 
     #[test]
     fn new_test_has_no_data() {
@@ -371,6 +371,20 @@ mod tests {
         assert!(test.intervals_us.is_empty());
     }
 
+    #[test]
+    fn process_event_uses_kernel_timestamps() {
+        let mut test = PollingRateTest::new(10, 100);
+
+        // Same poll batch (identical Instants) but 1ms apart in the kernel
+        test.process_event(&press_hw(DEFAULT_KEY, 10_000));
+        test.process_event(&press_hw(DEFAULT_KEY, 11_000));
+        test.process_event(&press_hw(DEFAULT_KEY, 12_000));
+
+        assert_eq!(test.intervals_us, vec![1000, 1000]);
+        let rate = test.avg_rate_hz().unwrap();
+        assert!((rate - 1000.0).abs() < 0.01);
+    }
+
     #[test]
     fn reset_clears_all() {
         let mut test = PollingRateTest::new(10, 100);
@@ -1,10 +1,43 @@
 //! Stickiness detection test module
+//!
+//! Besides timing held keys, the test can compare the app's idea of which
+//! keys are down with the kernel's (EVIOCGKEY). A key the app holds but the
+//! kernel has released is a lost release, i.e. a phantom stuck key rather
+//! than a sticky switch; the opposite is a lost press. Events still in
+//! flight make single disagreements normal, so a desync is only reported
+//! once two consecutive checks agree on it.
 
 use super::{KeyboardTest, ResultStatus, TestResult};
 use crate::keyboard::{keymap, KeyCode, KeyEvent, KeyEventType};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::{Duration, Instant};
 
+/// Which side thinks a key is held when the kernel and the app disagree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyStateDesync {
+    /// The kernel reports the key down but the app has it up (lost press)
+    KernelDown,
+    /// The app has the key down but the kernel reports it up (lost release)
+    AppDown,
+}
+
+impl KeyStateDesync {
+    pub fn label(&self) -> &'static str {
+        match self {
+            KeyStateDesync::KernelDown => "kernel down, app up",
+            KeyStateDesync::AppDown => "app down, kernel up",
+        }
+    }
+}
+
+/// Record of a confirmed kernel/app disagreement
+#[derive(Debug, Clone)]
+struct DesyncRecord {
+    key: KeyCode,
+    kind: KeyStateDesync,
+    occurrences: u32,
+}
+
 /// Record of a potentially sticky key
 #[derive(Debug, Clone)]
 struct StickyKeyRecord {
@@ -27,6 +60,14 @@ pub struct StickinessTest {
     keys_tested: u32,
     /// Test start time
     start_time: Option<Instant>,
+    /// Disagreements seen by the last kernel check, awaiting confirmation
+    pending_desyncs: HashMap<KeyCode, KeyStateDesync>,
+    /// Confirmed disagreements that have not cleared yet
+    active_desyncs: HashMap<KeyCode, KeyStateDesync>,
+    /// Every confirmed disagreement this session
+    desyncs: Vec<DesyncRecord>,
+    /// Kernel state comparisons made
+    kernel_checks: u32,
 }
 
 impl StickinessTest {
@@ -38,7 +79,82 @@ impl StickinessTest {
             currently_flagged: HashMap::new(),
             keys_tested: 0,
             start_time: None,
+            pending_desyncs: HashMap::new(),
+            active_desyncs: HashMap::new(),
+            desyncs: Vec::new(),
+            kernel_checks: 0,
+        }
+    }
+
+    /// Forget disagreements awaiting confirmation, for when the app's view
+    /// of held keys was just resynced
+    pub fn clear_pending_desyncs(&mut self) {
+        self.pending_desyncs.clear();
+    }
+
+    /// Compare the kernel's held keys with the app's (`app_pressed`, from
+    /// the keyboard state, and this test's own held set). Returns the
+    /// disagreements confirmed by this check.
+    pub fn check_kernel_state(
+        &mut self,
+        kernel: &HashSet<KeyCode>,
+        app_pressed: &[KeyCode],
+    ) -> Vec<(KeyCode, KeyStateDesync)> {
+        self.kernel_checks += 1;
+
+        let app: HashSet<KeyCode> = app_pressed.iter().copied().collect();
+        let keys: HashSet<KeyCode> = kernel
+            .iter()
+            .chain(&app)
+            .chain(self.held_keys.keys())
+            .copied()
+            .collect();
+        let mut current = HashMap::new();
+        for key in keys {
+            let app_down = app.contains(&key) && self.held_keys.contains_key(&key);
+            let app_up = !app.contains(&key) && !self.held_keys.contains_key(&key);
+            if kernel.contains(&key) && !app_down {
+                current.insert(key, KeyStateDesync::KernelDown);
+            } else if !kernel.contains(&key) && !app_up {
+                current.insert(key, KeyStateDesync::AppDown);
+            }
         }
+
+        let mut candidates: Vec<(KeyCode, KeyStateDesync)> =
+            current.iter().map(|(&key, &kind)| (key, kind)).collect();
+        candidates.sort_by_key(|(key, _)| key.as_u16());
+
+        let mut confirmed = Vec::new();
+        for (key, kind) in candidates {
+            if self.pending_desyncs.get(&key) != Some(&kind)
+                || self.active_desyncs.get(&key) == Some(&kind)
+            {
+                continue;
+            }
+            self.active_desyncs.insert(key, kind);
+            match self
+                .desyncs
+                .iter_mut()
+                .find(|r| r.key == key && r.kind == kind)
+            {
+                Some(record) => record.occurrences += 1,
+                None => self.desyncs.push(DesyncRecord {
+                    key,
+                    kind,
+                    occurrences: 1,
+                }),
+            }
+            confirmed.push((key, kind));
+        }
+        self.active_desyncs
+            .retain(|key, kind| current.get(key) == Some(kind));
+        self.pending_desyncs = current;
+        confirmed
+    }
+
+    /// Number of distinct kernel/app disagreements confirmed this session
+    pub fn desync_count(&self) -> usize {
+        self.desyncs.len()
     }
 
     /// Check all held keys for stickiness
@@ -79,6 +195,11 @@ impl StickinessTest {
             .collect()
     }
 
+    /// Number of distinct keys flagged as stuck this session
+    pub fn stuck_key_count(&self) -> usize {
+        self.flagged_keys.len()
+    }
+
     /// Get the threshold duration
     pub fn threshold(&self) -> Duration {
         self.threshold
@@ -113,6 +234,7 @@ impl KeyboardTest for StickinessTest {
                 self.held_keys.remove(&event.key);
                 self.currently_flagged.remove(&event.key);
             }
+            KeyEventType::Repeat => {}
         }
     }
 
@@ -176,6 +298,24 @@ impl KeyboardTest for StickinessTest {
             }
         }
 
+        if self.kernel_checks > 0 {
+            results.push(TestResult::info("--- Kernel Key State ---", ""));
+            results.push(TestResult::info(
+                "Kernel Checks",
+                format!("{}", self.kernel_checks),
+            ));
+            if self.desyncs.is_empty() {
+                results.push(TestResult::ok("Kernel State", "In sync"));
+            }
+            for record in &self.desyncs {
+                let key_info = keymap::get_key_info(record.key);
+                results.push(TestResult::error(
+                    format!("  {}", key_info.name),
+                    format!("{} ({}x)", record.kind.label(), record.occurrences),
+                ));
+            }
+        }
+
         // Show currently held keys
         let held = self.held_keys();
         if !held.is_empty() {
@@ -204,5 +344,67 @@ impl KeyboardTest for StickinessTest {
         self.currently_flagged.clear();
         self.keys_tested = 0;
         self.start_time = None;
+        self.pending_desyncs.clear();
+        self.active_desyncs.clear();
+        self.desyncs.clear();
+        self.kernel_checks = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_helpers::{press, release};
+
+    fn kernel(keys: &[u16]) -> HashSet<KeyCode> {
+        keys.iter().map(|&k| KeyCode(k)).collect()
+    }
+
+    #[test]
+    fn desync_needs_two_checks_to_confirm() {
+        let mut test = StickinessTest::new(1000);
+        test.process_event(&press(KeyCode(30)));
+
+        // The release is still in flight on the first check
+        assert!(test.check_kernel_state(&kernel(&[]), &[KeyCode(30)]).is_empty());
+        test.process_event(&release(KeyCode(30)));
+        assert!(test.check_kernel_state(&kernel(&[]), &[]).is_empty());
+        assert_eq!(test.desync_count(), 0);
+    }
+
+    #[test]
+    fn lost_release_and_lost_press_are_distinct_findings() {
+        let mut test = StickinessTest::new(1000);
+        test.process_event(&press(KeyCode(30)));
+
+        for _ in 0..3 {
+            test.check_kernel_state(&kernel(&[48]), &[KeyCode(30)]);
+        }
+        assert_eq!(test.desync_count(), 2);
+
+        let results = test.get_results();
+        let a = results.iter().find(|r| r.label == "  A").unwrap();
+        assert_eq!(a.value, "app down, kernel up (1x)");
+        assert_eq!(a.status, ResultStatus::Error);
+        let b = results.iter().find(|r| r.label == "  B").unwrap();
+        assert_eq!(b.value, "kernel down, app up (1x)");
+    }
+
+    #[test]
+    fn recurring_desync_counts_each_episode() {
+        let mut test = StickinessTest::new(1000);
+        let lost_press = kernel(&[30]);
+        test.check_kernel_state(&lost_press, &[]);
+        assert_eq!(
+            test.check_kernel_state(&lost_press, &[]),
+            vec![(KeyCode(30), KeyStateDesync::KernelDown)]
+        );
+        test.check_kernel_state(&kernel(&[]), &[]);
+        test.check_kernel_state(&lost_press, &[]);
+        test.check_kernel_state(&lost_press, &[]);
+
+        let results = test.get_results();
+        let a = results.iter().find(|r| r.label == "  A").unwrap();
+        assert_eq!(a.value, "kernel down, app up (2x)");
     }
 }
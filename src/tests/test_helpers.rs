@@ -23,6 +23,9 @@ pub fn make_event(
         event_type,
         timestamp,
         delta_us,
+        hw_timestamp_us: None,
+        device: None,
+        scancode: None,
     }
 }
 
@@ -35,6 +38,9 @@ pub fn make_press(key: KeyCode, delta_us: u64) -> KeyEvent {
         event_type: KeyEventType::Press,
         timestamp: Instant::now(),
         delta_us,
+        hw_timestamp_us: None,
+        device: None,
+        scancode: None,
     }
 }
 
@@ -47,6 +53,9 @@ pub fn make_release(key: KeyCode, delta_us: u64) -> KeyEvent {
         event_type: KeyEventType::Release,
         timestamp: Instant::now(),
         delta_us,
+        hw_timestamp_us: None,
+        device: None,
+        scancode: None,
     }
 }
 
@@ -72,6 +81,23 @@ pub fn release_at(key: KeyCode, timestamp: Instant) -> KeyEvent {
     make_event(key, KeyEventType::Release, timestamp, 0)
 }
 
+/// Creates a key press event carrying a kernel timestamp (microseconds).
+///
+/// All events share the same `Instant`, as they would within one poll batch.
+pub fn press_hw(key: KeyCode, hw_us: u64) -> KeyEvent {
+    make_press(key, 0).with_hw_timestamp(hw_us)
+}
+
+/// Creates a key release event carrying a kernel timestamp (microseconds).
+pub fn release_hw(key: KeyCode, hw_us: u64) -> KeyEvent {
+    make_release(key, 0).with_hw_timestamp(hw_us)
+}
+
+/// Creates an auto-repeat event carrying a kernel timestamp (microseconds).
+pub fn repeat_hw(key: KeyCode, hw_us: u64) -> KeyEvent {
+    make_event(key, KeyEventType::Repeat, Instant::now(), 0).with_hw_timestamp(hw_us)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
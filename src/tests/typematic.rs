@@ -0,0 +1,388 @@
+//! Auto-repeat (typematic) characterization test module
+//!
+//! Measures the repeat delay (press to first repeat) and repeat rate of
+//! held keys from the kernel's `value == 2` events, and compares them with
+//! the device's configured settings (EVIOCGREP). On most keyboards the
+//! input core generates repeats in software, so a mismatch points at
+//! system load or a desktop overriding the rate rather than the hardware.
+
+use super::{KeyboardTest, ResultStatus, TestResult};
+use crate::keyboard::{keymap, EventTime, KeyCode, KeyEvent, KeyEventType, RepeatSettings};
+use std::collections::HashMap;
+
+/// Per-key repeat measurements
+#[derive(Debug, Clone, Default)]
+struct KeyRepeatStats {
+    /// Press to first repeat, one sample per repeat burst
+    delays_us: Vec<u64>,
+    /// Intervals between consecutive repeats
+    intervals_us: Vec<u64>,
+}
+
+/// A key currently held down
+#[derive(Debug, Clone, Copy)]
+struct HeldKey {
+    pressed_at: EventTime,
+    last_repeat: Option<EventTime>,
+}
+
+fn mean(samples: &[u64]) -> Option<f64> {
+    if samples.is_empty() {
+        return None;
+    }
+    Some(samples.iter().sum::<u64>() as f64 / samples.len() as f64)
+}
+
+fn std_dev(samples: &[u64]) -> Option<f64> {
+    let avg = mean(samples)?;
+    let variance = samples
+        .iter()
+        .map(|&s| (s as f64 - avg).powi(2))
+        .sum::<f64>()
+        / samples.len() as f64;
+    Some(variance.sqrt())
+}
+
+/// Test for measuring key auto-repeat delay, rate and jitter
+pub struct TypematicTest {
+    /// Keys held down, waiting for or producing repeats
+    held: HashMap<KeyCode, HeldKey>,
+    /// Measurements per key
+    key_stats: HashMap<KeyCode, KeyRepeatStats>,
+    /// Settings reported by the device, when known
+    configured: Option<RepeatSettings>,
+    /// Allowed deviation from the configured delay and rate, in percent
+    tolerance_pct: u32,
+}
+
+impl TypematicTest {
+    pub fn new(tolerance_pct: u32) -> Self {
+        Self {
+            held: HashMap::new(),
+            key_stats: HashMap::new(),
+            configured: None,
+            tolerance_pct,
+        }
+    }
+
+    /// Set the device's configured repeat settings to compare against
+    pub fn set_configured(&mut self, settings: Option<RepeatSettings>) {
+        self.configured = settings;
+    }
+
+    /// Configured repeat settings, if known
+    pub fn configured(&self) -> Option<RepeatSettings> {
+        self.configured
+    }
+
+    /// Set the allowed deviation in percent
+    pub fn set_tolerance_pct(&mut self, tolerance_pct: u32) {
+        self.tolerance_pct = tolerance_pct;
+    }
+
+    /// Total repeat events seen
+    pub fn repeat_count(&self) -> usize {
+        self.key_stats
+            .values()
+            .map(|s| s.delays_us.len() + s.intervals_us.len())
+            .sum()
+    }
+
+    fn all_delays(&self) -> Vec<u64> {
+        self.key_stats
+            .values()
+            .flat_map(|s| s.delays_us.iter().copied())
+            .collect()
+    }
+
+    fn all_intervals(&self) -> Vec<u64> {
+        self.key_stats
+            .values()
+            .flat_map(|s| s.intervals_us.iter().copied())
+            .collect()
+    }
+
+    /// Average measured delay before the first repeat, in milliseconds
+    pub fn avg_delay_ms(&self) -> Option<f64> {
+        mean(&self.all_delays()).map(|us| us / 1000.0)
+    }
+
+    /// Average measured repeat rate in Hz
+    pub fn avg_rate_hz(&self) -> Option<f64> {
+        mean(&self.all_intervals())
+            .filter(|&us| us > 0.0)
+            .map(|us| 1_000_000.0 / us)
+    }
+
+    /// Standard deviation of the repeat interval, in milliseconds
+    pub fn interval_jitter_ms(&self) -> Option<f64> {
+        std_dev(&self.all_intervals()).map(|us| us / 1000.0)
+    }
+
+    /// Status for a measured value against its configured counterpart
+    fn compare(&self, measured: f64, expected: f64) -> ResultStatus {
+        if expected <= 0.0 {
+            return ResultStatus::Info;
+        }
+        let deviation_pct = (measured - expected).abs() / expected * 100.0;
+        if deviation_pct <= self.tolerance_pct as f64 {
+            ResultStatus::Ok
+        } else if deviation_pct <= self.tolerance_pct as f64 * 2.0 {
+            ResultStatus::Warning
+        } else {
+            ResultStatus::Error
+        }
+    }
+}
+
+impl Default for TypematicTest {
+    fn default() -> Self {
+        Self::new(10)
+    }
+}
+
+impl KeyboardTest for TypematicTest {
+    fn name(&self) -> &'static str {
+        "Auto-Repeat Test"
+    }
+
+    fn description(&self) -> &'static str {
+        "Measures key repeat delay and rate against the configured settings"
+    }
+
+    fn process_event(&mut self, event: &KeyEvent) {
+        let time = event.time();
+        match event.event_type {
+            KeyEventType::Press => {
+                self.held.insert(
+                    event.key,
+                    HeldKey {
+                        pressed_at: time,
+                        last_repeat: None,
+                    },
+                );
+            }
+            KeyEventType::Repeat => {
+                let Some(held) = self.held.get_mut(&event.key) else {
+                    return;
+                };
+                let stats = self.key_stats.entry(event.key).or_default();
+                match held.last_repeat {
+                    None => stats.delays_us.push(time.micros_since(held.pressed_at)),
+                    Some(last) => stats.intervals_us.push(time.micros_since(last)),
+                }
+                held.last_repeat = Some(time);
+            }
+            KeyEventType::Release => {
+                self.held.remove(&event.key);
+            }
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        // This test runs continuously
+        false
+    }
+
+    fn get_results(&self) -> Vec<TestResult> {
+        // Tooltip: Explain what this test measures
+        let mut results = vec![
+            TestResult::info("--- What This Measures ---", ""),
+            TestResult::info("Delay and rate of repeats", "while a key is held"),
+            TestResult::info("Compared with the device's", "configured repeat (EVIOCGREP)"),
+            TestResult::info("Look for: measured values", "close to the configured ones"),
+            TestResult::info("", ""),
+        ];
+
+        match self.configured {
+            Some(settings) if settings.period_ms > 0 => {
+                results.push(TestResult::info(
+                    "Configured",
+                    format!(
+                        "{} ms delay, {:.1} Hz",
+                        settings.delay_ms,
+                        settings.rate_hz()
+                    ),
+                ));
+            }
+            Some(_) => results.push(TestResult::info("Configured", "repeat disabled")),
+            None => results.push(TestResult::info("Configured", "unknown (evdev only)")),
+        }
+
+        if self.repeat_count() == 0 {
+            results.push(TestResult::info(
+                "Repeats Seen",
+                "0 - hold a key (e.g. A) for 1s",
+            ));
+            return results;
+        }
+
+        results.push(TestResult::info(
+            "Repeats Seen",
+            format!("{}", self.repeat_count()),
+        ));
+
+        let delays = self.all_delays();
+        if let Some(avg_ms) = self.avg_delay_ms() {
+            let min_ms = delays.iter().min().copied().unwrap_or(0) as f64 / 1000.0;
+            let max_ms = delays.iter().max().copied().unwrap_or(0) as f64 / 1000.0;
+            let status = match self.configured {
+                Some(settings) => self.compare(avg_ms, settings.delay_ms as f64),
+                None => ResultStatus::Info,
+            };
+            results.push(TestResult::new(
+                "Repeat Delay",
+                format!("{:.0} ms ({:.0}-{:.0})", avg_ms, min_ms, max_ms),
+                status,
+            ));
+        }
+
+        if let Some(rate) = self.avg_rate_hz() {
+            let status = match self.configured {
+                Some(settings) => self.compare(rate, settings.rate_hz()),
+                None => ResultStatus::Info,
+            };
+            results.push(TestResult::new(
+                "Repeat Rate",
+                format!("{:.1} Hz", rate),
+                status,
+            ));
+        }
+
+        if let Some(jitter) = self.interval_jitter_ms() {
+            let status = if jitter < 2.0 {
+                ResultStatus::Ok
+            } else if jitter < 5.0 {
+                ResultStatus::Warning
+            } else {
+                ResultStatus::Error
+            };
+            results.push(TestResult::new(
+                "Interval Jitter",
+                format!("{:.2} ms", jitter),
+                status,
+            ));
+        }
+
+        results.push(TestResult::info(
+            "Tolerance",
+            format!("±{}%", self.tolerance_pct),
+        ));
+
+        results.push(TestResult::info("--- Per Key ---", ""));
+        let mut keys: Vec<_> = self.key_stats.iter().collect();
+        keys.sort_by_key(|(key, _)| key.0);
+        for (key, stats) in keys {
+            let delay = mean(&stats.delays_us)
+                .map(|us| format!("{:.0} ms", us / 1000.0))
+                .unwrap_or_else(|| "-".to_string());
+            let rate = mean(&stats.intervals_us)
+                .filter(|&us| us > 0.0)
+                .map(|us| format!("{:.1} Hz", 1_000_000.0 / us))
+                .unwrap_or_else(|| "-".to_string());
+            results.push(TestResult::info(
+                format!("  {}", keymap::get_key_info(*key).name),
+                format!("{}, {}", delay, rate),
+            ));
+        }
+
+        results
+    }
+
+    fn reset(&mut self) {
+        self.held.clear();
+        self.key_stats.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_helpers::{press_hw, release_hw, repeat_hw, DEFAULT_KEY};
+
+    /// Hold a key: press at `start`, first repeat after `delay_ms`, then
+    /// `count` more repeats every `period_ms`
+    fn hold(test: &mut TypematicTest, key: KeyCode, start: u64, delay_ms: u64, period_ms: u64, count: u64) {
+        test.process_event(&press_hw(key, start));
+        let first = start + delay_ms * 1000;
+        for i in 0..=count {
+            test.process_event(&repeat_hw(key, first + i * period_ms * 1000));
+        }
+        test.process_event(&release_hw(key, first + (count + 1) * period_ms * 1000));
+    }
+
+    fn result<'a>(results: &'a [TestResult], label: &str) -> &'a TestResult {
+        results.iter().find(|r| r.label == label).unwrap()
+    }
+
+    #[test]
+    fn measures_delay_and_rate() {
+        let mut test = TypematicTest::default();
+        hold(&mut test, DEFAULT_KEY, 0, 250, 40, 10);
+
+        assert_eq!(test.repeat_count(), 11);
+        assert!((test.avg_delay_ms().unwrap() - 250.0).abs() < 0.01);
+        assert!((test.avg_rate_hz().unwrap() - 25.0).abs() < 0.01);
+        assert_eq!(test.interval_jitter_ms(), Some(0.0));
+    }
+
+    #[test]
+    fn matches_configured_settings() {
+        let mut test = TypematicTest::default();
+        test.set_configured(Some(RepeatSettings {
+            delay_ms: 250,
+            period_ms: 33,
+        }));
+        hold(&mut test, DEFAULT_KEY, 0, 252, 33, 20);
+
+        let results = test.get_results();
+        assert_eq!(result(&results, "Repeat Delay").status, ResultStatus::Ok);
+        assert_eq!(result(&results, "Repeat Rate").status, ResultStatus::Ok);
+    }
+
+    #[test]
+    fn flags_rate_far_from_configured() {
+        let mut test = TypematicTest::default();
+        test.set_configured(Some(RepeatSettings {
+            delay_ms: 500,
+            period_ms: 33,
+        }));
+        // 500 ms delay as configured, but repeating at 10 Hz instead of 30
+        hold(&mut test, DEFAULT_KEY, 0, 500, 100, 5);
+
+        let results = test.get_results();
+        assert_eq!(result(&results, "Repeat Delay").status, ResultStatus::Ok);
+        assert_eq!(result(&results, "Repeat Rate").status, ResultStatus::Error);
+    }
+
+    #[test]
+    fn repeat_without_press_is_ignored() {
+        let mut test = TypematicTest::default();
+        test.process_event(&repeat_hw(DEFAULT_KEY, 1_000));
+        assert_eq!(test.repeat_count(), 0);
+    }
+
+    #[test]
+    fn each_hold_measures_its_own_delay() {
+        let mut test = TypematicTest::default();
+        hold(&mut test, DEFAULT_KEY, 0, 200, 30, 2);
+        hold(&mut test, DEFAULT_KEY, 1_000_000, 300, 30, 2);
+
+        assert!((test.avg_delay_ms().unwrap() - 250.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn reset_keeps_configured_settings() {
+        let mut test = TypematicTest::default();
+        let settings = RepeatSettings {
+            delay_ms: 600,
+            period_ms: 40,
+        };
+        test.set_configured(Some(settings));
+        hold(&mut test, DEFAULT_KEY, 0, 600, 40, 3);
+        test.reset();
+
+        assert_eq!(test.repeat_count(), 0);
+        assert_eq!(test.configured(), Some(settings));
+    }
+}
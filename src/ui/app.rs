@@ -1,16 +1,24 @@
 //! Main application state and logic
 
 use crate::config::{Config, Theme};
+use crate::hwdb::{HwdbKeymap, ScancodeLog};
+use crate::keyboard::hid::{device_rollovers, HidDevice, HidRollover};
 use crate::keyboard::layout::KeyboardLayout;
 use crate::keyboard::remap::FnKeyMode;
-use crate::keyboard::{KeyEvent, KeyboardState};
-use crate::report::{ReportInput, SessionReport};
+use crate::keyboard::{
+    keymap, DeviceCapabilities, DeviceInfo, InputFidelity, KeyCode, KeyEvent, KeyEventType,
+    KeyboardState, Led,
+};
+use crate::profile::{AcceptanceProfile, Verdict};
+use crate::report::{DeviceActivity, ReportInput, SessionMetrics, SessionReport};
 use crate::tests::{
-    EventTimingTest, HoldReleaseTest, KeyboardTest, OemKeyTest, PollingRateTest, RolloverTest,
-    ShortcutTest, StickinessTest, TestResult, VirtualKeyboardTest,
+    EventTimingTest, HoldReleaseTest, KeyCoverageTest, KeyStateDesync, KeyboardTest, LedTest,
+    OemKeyTest, PollingRateTest, RolloverTest, ShortcutTest, StickinessTest, TestResult,
+    TypematicTest, VirtualKeyboardTest,
 };
 use crate::ui::theme::ThemeColors;
 use crate::ui::widgets::SettingsItem;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::time::Instant;
 
@@ -26,8 +34,12 @@ pub enum AppView {
     Shortcuts,
     Virtual,
     OemKeys,
+    Coverage,
+    Typematic,
+    Leds,
     Help,
     Settings,
+    Devices,
 }
 
 impl AppView {
@@ -42,12 +54,17 @@ impl AppView {
             Self::Shortcuts => "Shortcuts",
             Self::Virtual => "Virtual",
             Self::OemKeys => "OEM/FN",
+            Self::Coverage => "Coverage",
+            Self::Typematic => "Repeat",
+            Self::Leds => "LEDs",
             Self::Help => "Help",
             Self::Settings => "Settings",
+            Self::Devices => "Devices",
         }
     }
 
-    /// Views shown in the tab bar (excludes Settings which is accessed via 's')
+    /// Views shown in the tab bar (excludes Settings and Devices, which are
+    /// opened with 'S' and 'd')
     pub fn tab_views() -> &'static [AppView] {
         &[
             Self::Dashboard,
@@ -59,6 +76,9 @@ impl AppView {
             Self::Shortcuts,
             Self::Virtual,
             Self::OemKeys,
+            Self::Coverage,
+            Self::Typematic,
+            Self::Leds,
             Self::Help,
         ]
     }
@@ -78,8 +98,12 @@ impl AppView {
             Self::Shortcuts => 6,
             Self::Virtual => 7,
             Self::OemKeys => 8,
-            Self::Help => 9,
-            Self::Settings => 10,
+            Self::Coverage => 9,
+            Self::Typematic => 10,
+            Self::Leds => 11,
+            Self::Help => 12,
+            Self::Settings => 13,
+            Self::Devices => 14,
         }
     }
 
@@ -94,6 +118,9 @@ impl AppView {
             6 => Self::Shortcuts,
             7 => Self::Virtual,
             8 => Self::OemKeys,
+            9 => Self::Coverage,
+            10 => Self::Typematic,
+            11 => Self::Leds,
             _ => Self::Help,
         }
     }
@@ -133,6 +160,12 @@ pub struct App {
     pub virtual_test: VirtualKeyboardTest,
     /// OEM key capture and FN restoration test
     pub oem_test: OemKeyTest,
+    /// Guided "press every key" coverage test
+    pub coverage_test: KeyCoverageTest,
+    /// Auto-repeat delay/rate test
+    pub typematic_test: TypematicTest,
+    /// Indicator LED check
+    pub led_test: LedTest,
     /// Application start time
     pub start_time: Instant,
     /// Total events processed
@@ -155,6 +188,24 @@ pub struct App {
     pub last_shortcut_time: Option<Instant>,
     /// Detected keyboard layout
     pub keyboard_layout: KeyboardLayout,
+    /// Acceptance profile that reports are graded against
+    pub profile: Option<AcceptanceProfile>,
+    /// Input devices seen this session, with event counts
+    pub devices: Vec<DeviceActivity>,
+    /// When set, only events from this device path reach the tests
+    pub device_filter: Option<String>,
+    /// HID rollover ceiling of each keyboard, by device path
+    hid_rollovers: HashMap<String, HidRollover>,
+    /// Selected row in the Devices view (0 = all devices)
+    pub devices_selected: usize,
+    /// MSC_SCAN codes seen per keycode, for hwdb keymap export
+    pub scancodes: ScancodeLog,
+    /// What the event source can report, when known
+    pub input_fidelity: Option<InputFidelity>,
+    /// Key events the evdev capture thread dropped (queue full)
+    pub dropped_events: u64,
+    /// Kernel buffer overruns (SYN_DROPPED) the listener resynced from
+    pub sync_drops: u64,
 }
 
 impl App {
@@ -197,6 +248,9 @@ impl App {
             shortcut_test: ShortcutTest::new(),
             virtual_test: VirtualKeyboardTest::new(),
             oem_test,
+            coverage_test: KeyCoverageTest::new(keyboard_layout),
+            typematic_test: TypematicTest::new(config.typematic.tolerance_pct),
+            led_test: LedTest::new(),
             start_time: Instant::now(),
             total_events: 0,
             status_message: None,
@@ -208,11 +262,20 @@ impl App {
             last_shortcut_desc: None,
             last_shortcut_time: None,
             keyboard_layout,
+            profile: None,
+            devices: Vec::new(),
+            device_filter: None,
+            hid_rollovers: HashMap::new(),
+            devices_selected: 0,
+            scancodes: ScancodeLog::new(),
+            input_fidelity: None,
+            dropped_events: 0,
+            sync_drops: 0,
         }
     }
 
-    /// Returns mutable references to all 8 test modules for batch operations.
-    fn all_tests_mut(&mut self) -> [&mut dyn KeyboardTest; 8] {
+    /// Returns mutable references to all 11 test modules for batch operations.
+    fn all_tests_mut(&mut self) -> [&mut dyn KeyboardTest; 11] {
         [
             &mut self.polling_test,
             &mut self.hold_release_test,
@@ -222,11 +285,14 @@ impl App {
             &mut self.shortcut_test,
             &mut self.virtual_test,
             &mut self.oem_test,
+            &mut self.coverage_test,
+            &mut self.typematic_test,
+            &mut self.led_test,
         ]
     }
 
-    /// Returns immutable references to all 8 test modules.
-    fn all_tests(&self) -> [&dyn KeyboardTest; 8] {
+    /// Returns immutable references to all 11 test modules.
+    fn all_tests(&self) -> [&dyn KeyboardTest; 11] {
         [
             &self.polling_test,
             &self.hold_release_test,
@@ -236,10 +302,13 @@ impl App {
             &self.shortcut_test,
             &self.virtual_test,
             &self.oem_test,
+            &self.coverage_test,
+            &self.typematic_test,
+            &self.led_test,
         ]
     }
 
-    /// Map an AppView (for test views 1-8) to the corresponding test index.
+    /// Map an AppView (for test views) to the corresponding test index.
     fn test_index_for_view(view: AppView) -> Option<usize> {
         match view {
             AppView::PollingRate => Some(0),
@@ -250,6 +319,9 @@ impl App {
             AppView::Shortcuts => Some(5),
             AppView::Virtual => Some(6),
             AppView::OemKeys => Some(7),
+            AppView::Coverage => Some(8),
+            AppView::Typematic => Some(9),
+            AppView::Leds => Some(10),
             _ => None,
         }
     }
@@ -260,7 +332,15 @@ impl App {
             return;
         }
 
-        self.total_events += 1;
+        self.scancodes.record(event);
+        if !self.track_device(event) {
+            return;
+        }
+
+        // Auto-repeat is not a keystroke; only the typematic test uses it
+        if event.event_type != KeyEventType::Repeat {
+            self.total_events += 1;
+        }
         self.keyboard_state.process_event(event);
 
         // Dispatch to all tests
@@ -284,6 +364,195 @@ impl App {
         }
     }
 
+    /// Count an event against its source device. Returns false when a
+    /// device filter is set and the event comes from another device.
+    fn track_device(&mut self, event: &KeyEvent) -> bool {
+        if let Some(device) = &event.device {
+            let index = match self.devices.iter().position(|d| d.device.path == device.path) {
+                Some(index) => index,
+                None => {
+                    self.register_devices(vec![(**device).clone()]);
+                    self.devices.len() - 1
+                }
+            };
+            let activity = &mut self.devices[index];
+            activity.events += 1;
+            if event.event_type == KeyEventType::Press {
+                activity.presses += 1;
+                activity.keys_pressed.insert(event.key.as_u16());
+            }
+        }
+
+        match &self.device_filter {
+            Some(path) => event.device_path() == Some(path.as_str()),
+            None => true,
+        }
+    }
+
+    /// Add devices the listener opened, so they can be picked before any
+    /// key is pressed on them
+    pub fn register_devices(&mut self, devices: Vec<DeviceInfo>) {
+        for device in devices {
+            if !self.devices.iter().any(|d| d.device.path == device.path) {
+                let mut activity = DeviceActivity::new(device);
+                activity.under_test =
+                    self.device_filter.as_deref() == Some(activity.device.path.as_str());
+                self.devices.push(activity);
+            }
+        }
+    }
+
+    /// Attach what a device declares (key bitmap, IDs, phys) to its entry
+    pub fn set_device_capabilities(&mut self, device: DeviceInfo, capabilities: DeviceCapabilities) {
+        if !self.devices.iter().any(|d| d.device.path == device.path) {
+            self.register_devices(vec![device.clone()]);
+        }
+        if let Some(activity) = self.devices.iter_mut().find(|d| d.device.path == device.path) {
+            activity.capabilities = Some(capabilities);
+        }
+    }
+
+    /// Compare the kernel's held keys (EVIOCGKEY) with the app's, flagging
+    /// disagreements confirmed by this check. Returns them for logging.
+    ///
+    /// Nothing is checked while paused, since events are ignored then.
+    pub fn check_kernel_key_state(&mut self, kernel: &HashSet<u16>) -> Vec<(KeyCode, KeyStateDesync)> {
+        if self.state != AppState::Running {
+            return Vec::new();
+        }
+        let kernel: HashSet<KeyCode> = kernel.iter().map(|&code| KeyCode::new(code)).collect();
+        let desyncs = self
+            .stickiness_test
+            .check_kernel_state(&kernel, self.keyboard_state.pressed_keys());
+        if let Some((key, kind)) = desyncs.first() {
+            self.set_status(format!(
+                "Key state desync: {} ({})",
+                keymap::get_key_info(*key).name,
+                kind.label()
+            ));
+        }
+        desyncs
+    }
+
+    /// Bring the held keys in line with the kernel's after a pause: keys
+    /// released while paused are released and keys pressed while paused
+    /// are pressed, and disagreements awaiting confirmation are dropped
+    pub fn resync_key_state(&mut self, kernel: &HashSet<u16>) {
+        self.stickiness_test.clear_pending_desyncs();
+        let tracked: HashSet<u16> = self
+            .keyboard_state
+            .pressed_keys()
+            .iter()
+            .map(|key| key.as_u16())
+            .collect();
+        let mut released: Vec<u16> = tracked.difference(kernel).copied().collect();
+        let mut pressed: Vec<u16> = kernel.difference(&tracked).copied().collect();
+        released.sort_unstable();
+        pressed.sort_unstable();
+
+        let now = Instant::now();
+        let corrections = released
+            .into_iter()
+            .map(|code| (code, KeyEventType::Release))
+            .chain(pressed.into_iter().map(|code| (code, KeyEventType::Press)));
+        for (code, event_type) in corrections {
+            let event = KeyEvent::new(KeyCode::new(code), event_type, now, 0);
+            self.keyboard_state.process_event(&event);
+            for test in self.all_tests_mut() {
+                test.process_event(&event);
+            }
+        }
+    }
+
+    /// A keyboard was plugged in while the session runs.
+    ///
+    /// If the device under test was unplugged and a device with the same
+    /// USB ID comes back on another node, the filter follows it.
+    pub fn device_connected(&mut self, device: DeviceInfo) {
+        let message = format!("Connected: {} ({})", device.label(), device.path);
+
+        let replaced = self.devices.iter().position(|d| {
+            d.under_test
+                && d.disconnected
+                && d.device.path != device.path
+                && d.device.usb_id() == device.usb_id()
+                && device.vendor_id != 0
+        });
+        if let Some(index) = replaced {
+            self.devices[index].under_test = false;
+            self.device_filter = Some(device.path.clone());
+        }
+
+        match self.devices.iter_mut().find(|d| d.device.path == device.path) {
+            Some(activity) => {
+                activity.device = device;
+                activity.disconnected = false;
+            }
+            None => self.register_devices(vec![device]),
+        }
+
+        if replaced.is_some() {
+            self.update_hid_rollover();
+            for activity in &mut self.devices {
+                activity.under_test =
+                    self.device_filter.as_deref() == Some(activity.device.path.as_str());
+            }
+            self.set_status(format!("{} - still under test", message));
+        } else {
+            self.set_status(message);
+        }
+    }
+
+    /// A keyboard was unplugged, or closed after a read error
+    pub fn device_disconnected(&mut self, device: &DeviceInfo, error: Option<&str>) {
+        if let Some(activity) = self.devices.iter_mut().find(|d| d.device.path == device.path) {
+            activity.disconnected = true;
+        }
+        let mut message = match error {
+            Some(e) => format!("Lost {} ({}): {}", device.label(), device.path, e),
+            None => format!("Disconnected: {} ({})", device.label(), device.path),
+        };
+        if self.device_filter.as_deref() == Some(device.path.as_str()) {
+            message.push_str(" - reconnect it to continue");
+        }
+        self.set_status(message);
+    }
+
+    /// Restrict the tests to one device (None = all devices).
+    ///
+    /// Results gathered so far mix devices, so every test is reset.
+    pub fn select_device(&mut self, path: Option<String>) {
+        for activity in &mut self.devices {
+            activity.under_test = path.as_deref() == Some(activity.device.path.as_str());
+        }
+        self.device_filter = path;
+        self.reset_all();
+        self.update_hid_rollover();
+        let message = match self.selected_device() {
+            Some(device) => format!("Testing only {} ({})", device.label(), device.path),
+            None => "Testing all devices".to_string(),
+        };
+        self.set_status(message);
+    }
+
+    /// Apply the row highlighted in the Devices view
+    pub fn select_highlighted_device(&mut self) {
+        let path = match self.devices_selected {
+            0 => None,
+            i => self.devices.get(i - 1).map(|d| d.device.path.clone()),
+        };
+        self.select_device(path);
+    }
+
+    /// Device the tests are restricted to, if any
+    pub fn selected_device(&self) -> Option<&DeviceInfo> {
+        let path = self.device_filter.as_deref()?;
+        self.devices
+            .iter()
+            .map(|d| &d.device)
+            .find(|d| d.path == path)
+    }
+
     /// Switch to the next view
     pub fn next_view(&mut self) {
         let views = AppView::tab_views();
@@ -373,11 +642,21 @@ impl App {
             AppView::Dashboard => self.dashboard_results(),
             AppView::Help | AppView::Settings => Vec::new(),
             other => {
-                if let Some(idx) = Self::test_index_for_view(other) {
-                    self.all_tests()[idx].get_results()
-                } else {
-                    Vec::new()
+                let Some(idx) = Self::test_index_for_view(other) else {
+                    return Vec::new();
+                };
+                let mut results = self.all_tests()[idx].get_results();
+                let needs_releases = matches!(
+                    other,
+                    AppView::HoldRelease | AppView::Stickiness | AppView::Rollover
+                );
+                if needs_releases && self.input_fidelity == Some(InputFidelity::Basic) {
+                    results.insert(
+                        0,
+                        TestResult::warning("Input: presses only", "releases are synthesized"),
+                    );
                 }
+                results
             }
         }
     }
@@ -418,9 +697,87 @@ impl App {
             self.keyboard_layout.name().to_string(),
         ));
 
+        if let Some(fidelity) = self.input_fidelity {
+            results.push(if fidelity.has_releases() {
+                TestResult::info("Input", fidelity.label())
+            } else {
+                TestResult::warning("Input", fidelity.label())
+            });
+        }
+
+        if self.dropped_events > 0 {
+            results.push(TestResult::error(
+                "Dropped Events",
+                format!("{} (capture queue full)", self.dropped_events),
+            ));
+        }
+
+        if self.sync_drops > 0 {
+            results.push(TestResult::error(
+                "Kernel Overruns",
+                format!("{} (SYN_DROPPED, resynced)", self.sync_drops),
+            ));
+        }
+
+        if !self.devices.is_empty() {
+            let device = match (self.selected_device(), &self.device_filter) {
+                (Some(device), _) => device.label().to_string(),
+                (None, Some(path)) => path.clone(),
+                (None, None) => format!("All ({})", self.devices.len()),
+            };
+            results.push(TestResult::info("Device", device));
+        }
+
+        results.push(TestResult::info(
+            "Key Coverage",
+            format!(
+                "{} / {}",
+                self.coverage_test.tested_count(),
+                self.coverage_test.total_keys()
+            ),
+        ));
+
+        if let Some(rate) = self.typematic_test.avg_rate_hz() {
+            results.push(TestResult::info("Repeat Rate", format!("{:.1} Hz", rate)));
+        }
+
+        if let Some(profile) = &self.profile {
+            let acceptance = profile.evaluate(&self.session_metrics());
+            let label = format!("Profile: {}", acceptance.profile);
+            let failed = acceptance.failures().count();
+            results.push(match acceptance.verdict {
+                Verdict::Pass => TestResult::ok(label, "PASS"),
+                Verdict::Fail => TestResult::error(
+                    label,
+                    format!("FAIL ({}/{})", failed, acceptance.criteria.len()),
+                ),
+            });
+        }
+
         results
     }
 
+    /// Collect the numeric measurements acceptance profiles grade on
+    pub fn session_metrics(&self) -> SessionMetrics {
+        let mut keys_pressed: Vec<u16> = self
+            .keyboard_state
+            .all_keys()
+            .filter(|(_, state)| state.press_count > 0)
+            .map(|(key, _)| key.as_u16())
+            .collect();
+        keys_pressed.sort_unstable();
+
+        SessionMetrics {
+            polling_rate_hz: self.polling_test.avg_rate_hz(),
+            jitter_us: self.polling_test.jitter_us(),
+            max_rollover: self.keyboard_state.max_rollover(),
+            bounces: self.hold_release_test.total_bounces(),
+            stuck_keys: self.stickiness_test.stuck_key_count(),
+            avg_event_timing_ms: self.event_timing_test.global_avg_ms(),
+            keys_pressed,
+        }
+    }
+
     /// Get elapsed time formatted
     pub fn elapsed_formatted(&self) -> String {
         let secs = self.start_time.elapsed().as_secs();
@@ -429,12 +786,16 @@ impl App {
         format!("{:02}:{:02}", mins, secs)
     }
 
-    /// Generate a session report
+    /// Generate a session report, graded against the acceptance profile if set
     pub fn generate_report(&self) -> SessionReport {
-        SessionReport::new(
+        let mut report = SessionReport::new(
             ReportInput {
                 start_time: self.start_time,
                 total_events: self.total_events,
+                input_fidelity: self.input_fidelity,
+                dropped_events: self.dropped_events,
+                sync_drops: self.sync_drops,
+                metrics: self.session_metrics(),
                 polling: self.polling_test.get_results(),
                 hold_release: self.hold_release_test.get_results(),
                 stickiness: self.stickiness_test.get_results(),
@@ -443,9 +804,17 @@ impl App {
                 shortcuts: self.shortcut_test.get_results(),
                 virtual_detect: self.virtual_test.get_results(),
                 oem_keys: self.oem_test.get_results(),
+                coverage: self.coverage_test.get_results(),
+                typematic: self.typematic_test.get_results(),
+                leds: self.led_test.get_results(),
+                devices: self.devices.clone(),
             },
             &self.keyboard_state,
-        )
+        );
+        if let Some(profile) = &self.profile {
+            report.grade(profile);
+        }
+        report
     }
 
     /// Export session report to JSON file
@@ -468,17 +837,92 @@ impl App {
         }
 
         // Get the most recently pressed unknown key
-        if let Some((&scancode, &_count)) = unknown_keys.iter().max_by_key(|(_, &c)| c) {
+        if let Some((&unknown, &_count)) = unknown_keys.iter().max_by_key(|(_, &c)| c) {
             // Map unknown key to itself initially (pass-through, but registered)
             // User can later configure specific mappings in config file
+            let scancode = unknown.code;
             self.oem_test.add_fn_scancode(scancode);
             self.set_status(format!(
-                "Added scancode 0x{:03X} ({}) as FN key. Edit config for custom mapping.",
-                scancode, scancode
+                "Added scancode {} ({}) as FN key. Edit config for custom mapping.",
+                unknown.describe(),
+                scancode
             ));
         }
     }
 
+    /// Build a hwdb keymap for the keyboard under test (or the last one
+    /// that reported scancodes) from the OEM key mappings, layered over
+    /// `base`, e.g. a mapper preset's mappings
+    pub fn hwdb_keymap(&self, base: &HashMap<u16, u16>) -> Option<HwdbKeymap> {
+        let device = self
+            .selected_device()
+            .or_else(|| self.scancodes.last_device())?;
+        let mut mappings = base.clone();
+        mappings.extend(self.oem_test.remapper().mappings());
+        Some(HwdbKeymap::build(device, &mappings, &self.scancodes))
+    }
+
+    /// Feed HID report descriptor findings to the rollover and OEM tests
+    pub fn apply_hid_devices(&mut self, devices: &[HidDevice]) {
+        self.hid_rollovers = device_rollovers(devices);
+        self.update_hid_rollover();
+        self.oem_test.set_hid_sources(
+            devices
+                .iter()
+                .filter_map(|d| d.descriptor.as_ref())
+                .flat_map(|d| d.oem_inputs().into_iter().cloned())
+                .collect(),
+        );
+    }
+
+    /// Give the rollover test the HID ceiling of the device under test;
+    /// without a device filter the ceiling is unknown
+    fn update_hid_rollover(&mut self) {
+        let rollover = self
+            .device_filter
+            .as_ref()
+            .and_then(|path| self.hid_rollovers.get(path))
+            .copied();
+        self.rollover_test.set_hid_rollover(rollover);
+    }
+
+    /// Start the guided ghosting check on the NKRO view
+    pub fn start_ghosting_guide(&mut self) {
+        self.rollover_test.start_ghosting_guide();
+        self.set_status(
+            "Ghosting check: hold each chord shown, then release all keys".to_string(),
+        );
+    }
+
+    /// Start the LED check from the LEDs currently lit on the device
+    pub fn start_led_check(&mut self, lit: Vec<Led>) {
+        if self.led_test.start(lit) {
+            self.prompt_led();
+        } else {
+            self.set_status("No keyboard with LEDs found".to_string());
+        }
+    }
+
+    /// Record the operator's answer for the LED being checked
+    pub fn answer_led_check(&mut self, lit: bool) {
+        if self.led_test.prompt().is_none() {
+            return;
+        }
+        self.led_test.confirm(lit);
+        self.prompt_led();
+    }
+
+    fn prompt_led(&mut self) {
+        let message = match self.led_test.prompt() {
+            Some(led) => format!("LED check: is {} lit? (y/n)", led.name()),
+            None => format!(
+                "LED check complete: {} confirmed",
+                self.led_test.confirmed_count()
+            ),
+        };
+        self.set_status(message);
+    }
+
     /// Cycle through FN key modes
     pub fn cycle_fn_mode(&mut self) {
         let current_mode = self.oem_test.fn_mode();
@@ -567,6 +1011,11 @@ impl App {
                 value: format!("{}", self.config.ui.warning_duration_secs),
                 editable: true,
             },
+            SettingsItem {
+                label: "Repeat Tolerance (%)".to_string(),
+                value: format!("{}", self.config.typematic.tolerance_pct),
+                editable: true,
+            },
         ]
     }
 
@@ -639,6 +1088,18 @@ impl App {
                         self.config.ui.warning_duration_secs.saturating_sub(1).max(1);
                 }
             }
+            7 => {
+                // Repeat tolerance
+                if increase {
+                    self.config.typematic.tolerance_pct =
+                        self.config.typematic.tolerance_pct.saturating_add(5).min(50);
+                } else {
+                    self.config.typematic.tolerance_pct =
+                        self.config.typematic.tolerance_pct.saturating_sub(5).max(5);
+                }
+                self.typematic_test
+                    .set_tolerance_pct(self.config.typematic.tolerance_pct);
+            }
             _ => {}
         }
     }
@@ -840,6 +1301,31 @@ mod tests {
         assert!(json.contains("\"oem_keys\""));
     }
 
+    #[test]
+    fn app_report_is_graded_against_profile() {
+        let mut app = App {
+            profile: Some(AcceptanceProfile {
+                name: "sku".to_string(),
+                min_rollover: Some(2),
+                required_keys: vec![30],
+                ..Default::default()
+            }),
+            ..App::default()
+        };
+        app.process_event(&press(30, 1000));
+        app.process_event(&release(30, 1000));
+
+        let report = app.generate_report();
+        assert_eq!(report.metrics.keys_pressed, vec![30]);
+        let acceptance = report.acceptance.as_ref().unwrap();
+        assert_eq!(acceptance.verdict, Verdict::Fail);
+        assert_eq!(acceptance.failures().next().unwrap().label, "Rollover");
+
+        app.process_event(&press(30, 1000));
+        app.process_event(&press(31, 1000));
+        assert!(app.generate_report().passed());
+    }
+
     #[test]
     fn app_reset_current_only_resets_active_view() {
         let mut app = App::default();
@@ -950,4 +1436,278 @@ mod tests {
         // No shortcut detected yet
         assert!(app.shortcut_overlay().is_none());
     }
+
+    fn press_on(key: u16, device: &std::sync::Arc<DeviceInfo>) -> KeyEvent {
+        press(key, 1000).with_device(std::sync::Arc::clone(device))
+    }
+
+    #[test]
+    fn app_counts_events_per_device() {
+        let mut app = App::default();
+        let internal = std::sync::Arc::new(DeviceInfo::new("/dev/input/event2", "AT Keyboard", 1, 1));
+        let external = std::sync::Arc::new(DeviceInfo::new("/dev/input/event7", "USB Board", 2, 2));
+
+        app.process_event(&press_on(30, &internal));
+        app.process_event(&press_on(31, &external));
+        app.process_event(&press_on(32, &external));
+
+        assert_eq!(app.devices.len(), 2);
+        assert_eq!(app.devices[0].presses, 1);
+        assert_eq!(app.devices[1].events, 2);
+        assert_eq!(app.total_events, 3);
+    }
+
+    #[test]
+    fn app_tracks_declared_keys_pressed_per_device() {
+        let mut app = App::default();
+        let board = std::sync::Arc::new(DeviceInfo::new("/dev/input/event7", "USB Board", 2, 2));
+        app.set_device_capabilities(
+            (*board).clone(),
+            DeviceCapabilities {
+                keys: [30, 31, 32].into_iter().collect(),
+                ..DeviceCapabilities::default()
+            },
+        );
+
+        app.process_event(&press_on(30, &board));
+        app.process_event(&press_on(30, &board));
+        app.process_event(&press_on(32, &board));
+
+        assert_eq!(app.devices.len(), 1);
+        assert_eq!(app.devices[0].keys_not_pressed(), vec![31]);
+        assert_eq!(app.devices[0].key_coverage().as_deref(), Some("2/3"));
+    }
+
+    #[test]
+    fn hid_rollover_follows_the_device_under_test() {
+        use crate::keyboard::hid::ReportDescriptor;
+        let hid_device = |path: &str, descriptor: &[u8]| HidDevice {
+            event_path: path.into(),
+            name: String::new(),
+            hidraw: None,
+            descriptor: Some(ReportDescriptor::parse(descriptor)),
+        };
+        // Keyboard page: a 6-byte key array, and a 120-key bitmap
+        let six_key = [
+            0x05, 0x07, 0x19, 0x00, 0x29, 0x65, 0x75, 0x08, 0x95, 0x06, 0x81, 0x00,
+        ];
+        let nkro = [
+            0x05, 0x07, 0x19, 0x00, 0x29, 0x77, 0x75, 0x01, 0x95, 0x78, 0x81, 0x02,
+        ];
+        let devices = [
+            hid_device("/dev/input/event3", &nkro),
+            hid_device("/dev/input/event9", &six_key),
+        ];
+
+        let mut app = App::default();
+        app.apply_hid_devices(&devices);
+        assert_eq!(app.rollover_test.hid_rollover(), None);
+
+        app.select_device(Some("/dev/input/event9".to_string()));
+        assert_eq!(app.rollover_test.hid_rollover(), Some(HidRollover::KeyArray(6)));
+        app.select_device(Some("/dev/input/event3".to_string()));
+        assert_eq!(app.rollover_test.hid_rollover(), Some(HidRollover::Bitmap));
+        app.select_device(Some("/dev/input/event12".to_string()));
+        assert_eq!(app.rollover_test.hid_rollover(), None);
+        app.select_device(None);
+        assert_eq!(app.rollover_test.hid_rollover(), None);
+    }
+
+    #[test]
+    fn app_device_filter_keeps_other_devices_out_of_tests() {
+        let mut app = App::default();
+        let internal = std::sync::Arc::new(DeviceInfo::new("/dev/input/event2", "AT Keyboard", 1, 1));
+        let external = std::sync::Arc::new(DeviceInfo::new("/dev/input/event7", "USB Board", 2, 2));
+        app.register_devices(vec![(*internal).clone(), (*external).clone()]);
+
+        app.devices_selected = 2;
+        app.select_highlighted_device();
+        assert_eq!(app.selected_device().unwrap().name, "USB Board");
+
+        app.process_event(&press_on(30, &internal));
+        app.process_event(&press_on(31, &external));
+
+        assert_eq!(app.total_events, 1);
+        assert_eq!(app.rollover_test.current_count(), 1);
+        assert_eq!(app.devices[0].events, 1); // still counted for the picker
+
+        let report = app.generate_report();
+        assert!(report.devices[1].under_test);
+        assert!(!report.devices[0].under_test);
+    }
+
+    #[test]
+    fn app_selecting_all_devices_clears_filter() {
+        let mut app = App::default();
+        app.register_devices(vec![DeviceInfo::new("/dev/input/event7", "USB Board", 2, 2)]);
+        app.select_device(Some("/dev/input/event7".to_string()));
+        app.devices_selected = 0;
+        app.select_highlighted_device();
+        assert!(app.device_filter.is_none());
+        assert!(app.devices.iter().all(|d| !d.under_test));
+    }
+
+    #[test]
+    fn app_marks_unplugged_devices_and_reconnects() {
+        let mut app = App::default();
+        let board = DeviceInfo::new("/dev/input/event7", "USB Board", 2, 2);
+        app.register_devices(vec![board.clone()]);
+
+        app.device_disconnected(&board, None);
+        assert!(app.devices[0].disconnected);
+        assert!(app.get_status().unwrap().contains("Disconnected"));
+
+        app.device_connected(board);
+        assert_eq!(app.devices.len(), 1);
+        assert!(!app.devices[0].disconnected);
+    }
+
+    #[test]
+    fn app_filter_follows_replugged_device_to_new_node() {
+        let mut app = App::default();
+        let board = DeviceInfo::new("/dev/input/event7", "USB Board", 0x046d, 0xc31c);
+        app.register_devices(vec![board.clone()]);
+        app.select_device(Some(board.path.clone()));
+
+        app.device_disconnected(&board, None);
+        app.device_connected(DeviceInfo::new("/dev/input/event9", "USB Board", 0x046d, 0xc31c));
+
+        assert_eq!(app.device_filter.as_deref(), Some("/dev/input/event9"));
+        assert!(app.devices[1].under_test);
+        assert!(!app.devices[0].under_test);
+        assert!(app.get_status().unwrap().contains("still under test"));
+    }
+
+    #[test]
+    fn app_hwdb_keymap_uses_oem_mappings_and_observed_scancodes() {
+        let mut app = App::default();
+        assert!(app.hwdb_keymap(&HashMap::new()).is_none());
+
+        let board = std::sync::Arc::new(DeviceInfo::new("/dev/input/event4", "N-KEY", 0x0b05, 0x19b6));
+        app.process_event(&press_on(148, &board).with_scancode(0xff31_00a6));
+        app.oem_test.add_mapping(148, 125);
+
+        let keymap = app.hwdb_keymap(&HashMap::from([(148, 1), (202, 183)])).unwrap();
+        assert_eq!(keymap.device.path, "/dev/input/event4");
+        // OEM mappings override the preset's
+        assert_eq!(keymap.entries.get(&0xff31_00a6), Some(&125));
+        assert_eq!(keymap.unresolved, vec![202]);
+    }
+
+    #[test]
+    fn app_led_check_prompts_each_led_in_turn() {
+        let mut app = App::default();
+        app.start_led_check(Vec::new());
+        assert!(app.get_status().unwrap().contains("No keyboard with LEDs"));
+
+        app.led_test.set_device(
+            Some(DeviceInfo::new("/dev/input/event3", "USB Keyboard", 1, 1)),
+            vec![Led::NumLock, Led::CapsLock],
+        );
+        app.start_led_check(Vec::new());
+        assert_eq!(app.get_status(), Some("LED check: is Num Lock lit? (y/n)"));
+
+        app.answer_led_check(true);
+        app.answer_led_check(false);
+        assert_eq!(app.get_status(), Some("LED check complete: 1 confirmed"));
+        assert!(app.led_test.is_complete());
+    }
+
+    #[test]
+    fn app_marks_results_that_need_real_releases() {
+        let mut app = App {
+            view: AppView::HoldRelease,
+            ..App::default()
+        };
+        assert_ne!(app.current_results()[0].label, "Input: presses only");
+
+        app.input_fidelity = Some(InputFidelity::Basic);
+        assert_eq!(app.current_results()[0].label, "Input: presses only");
+        app.view = AppView::PollingRate;
+        assert_ne!(app.current_results()[0].label, "Input: presses only");
+
+        app.input_fidelity = Some(InputFidelity::Enhanced);
+        let report = app.generate_report();
+        assert_eq!(report.metadata.input_fidelity, Some(InputFidelity::Enhanced));
+    }
+
+    #[test]
+    fn app_flags_dropped_events() {
+        let mut app = App::default();
+        assert!(!app.current_results().iter().any(|r| r.label == "Dropped Events"));
+
+        app.dropped_events = 12;
+        let row = app
+            .current_results()
+            .into_iter()
+            .find(|r| r.label == "Dropped Events")
+            .unwrap();
+        assert_eq!(row.status, crate::tests::ResultStatus::Error);
+        assert_eq!(app.generate_report().metadata.dropped_events, 12);
+    }
+
+    #[test]
+    fn app_reports_keys_the_kernel_has_released() {
+        let mut app = App::default();
+        app.process_event(&press(30, 1000));
+
+        let kernel = HashSet::new();
+        assert!(app.check_kernel_key_state(&kernel).is_empty());
+        assert_eq!(
+            app.check_kernel_key_state(&kernel),
+            vec![(KeyCode(30), KeyStateDesync::AppDown)]
+        );
+        assert!(app.status_message.as_deref().unwrap().contains("app down, kernel up"));
+    }
+
+    #[test]
+    fn pause_does_not_turn_missed_events_into_desyncs() {
+        let mut app = App::default();
+        app.process_event(&press(30, 1000));
+
+        // A released and B pressed while paused: the app never sees either
+        app.toggle_pause();
+        app.process_event(&release(30, 1000));
+        app.process_event(&press(48, 1000));
+        let kernel = HashSet::from([48]);
+        assert!(app.check_kernel_key_state(&kernel).is_empty());
+        assert!(app.check_kernel_key_state(&kernel).is_empty());
+
+        app.toggle_pause();
+        app.resync_key_state(&kernel);
+        assert_eq!(app.keyboard_state.pressed_keys(), &[KeyCode(48)]);
+        assert!(app.check_kernel_key_state(&kernel).is_empty());
+        assert!(app.check_kernel_key_state(&kernel).is_empty());
+        assert_eq!(app.stickiness_test.desync_count(), 0);
+    }
+
+    #[test]
+    fn app_flags_kernel_overruns() {
+        let mut app = App::default();
+        assert!(!app.current_results().iter().any(|r| r.label == "Kernel Overruns"));
+
+        app.sync_drops = 2;
+        let row = app
+            .current_results()
+            .into_iter()
+            .find(|r| r.label == "Kernel Overruns")
+            .unwrap();
+        assert_eq!(row.status, crate::tests::ResultStatus::Error);
+        assert_eq!(app.generate_report().metadata.sync_drops, 2);
+    }
+
+    #[test]
+    fn app_repeats_only_feed_typematic_test() {
+        let mut app = App::default();
+        app.process_event(&press(30, 1000));
+        let repeat = KeyEvent::new(KeyCode(30), KeyEventType::Repeat, Instant::now(), 250_000)
+            .with_hw_timestamp(250_000);
+        app.process_event(&repeat);
+        app.process_event(&repeat);
+
+        assert_eq!(app.total_events, 1);
+        assert_eq!(app.keyboard_state.total_events(), 1);
+        assert_eq!(app.typematic_test.repeat_count(), 2);
+        assert_eq!(app.rollover_test.current_count(), 1);
+    }
 }
@@ -1,8 +1,9 @@
 //! Visual keyboard layout rendering
 
 use super::theme::ThemeColors;
-use crate::keyboard::layout::{layout_rows, KeyboardLayout};
+use crate::keyboard::layout::{arrow_keys, layout_rows, KeyboardLayout};
 use crate::keyboard::{KeyCode, KeyboardState};
+use crate::tests::KeyCoverageTest;
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
@@ -15,6 +16,7 @@ pub struct KeyboardVisual<'a> {
     keyboard_state: &'a KeyboardState,
     colors: ThemeColors,
     layout: KeyboardLayout,
+    coverage: Option<&'a KeyCoverageTest>,
 }
 
 impl<'a> KeyboardVisual<'a> {
@@ -23,6 +25,7 @@ impl<'a> KeyboardVisual<'a> {
             keyboard_state,
             colors: ThemeColors::dark(),
             layout: KeyboardLayout::Ansi,
+            coverage: None,
         }
     }
 
@@ -36,10 +39,21 @@ impl<'a> KeyboardVisual<'a> {
         self
     }
 
+    /// Highlight keys the coverage test has not seen yet
+    pub fn coverage(mut self, coverage: &'a KeyCoverageTest) -> Self {
+        self.coverage = Some(coverage);
+        self
+    }
+
     fn key_style(&self, code: KeyCode) -> (Color, Color, bool) {
         let pressed = self.keyboard_state.pressed_keys().contains(&code);
+        let untested = self
+            .coverage
+            .is_some_and(|c| c.is_covered_key(code) && !c.is_tested(code));
         if pressed {
             (self.colors.key_on, self.colors.key_text_on, true)
+        } else if untested {
+            (self.colors.yellow, self.colors.key_text_on, false)
         } else if self
             .keyboard_state
             .get_key_state(code)
@@ -92,12 +106,17 @@ impl<'a> Widget for KeyboardVisual<'a> {
         // Arrow keys (rendered separately, offset from main block)
         if area.width > 70 {
             let w = 4u16;
-            // Position arrows after the bottom row
+            // Position arrows after the bottom row: ▲ above ◀ ▼ ▶
             let ax = x0 + 68;
-            self.render_key(buf, ax + w + 1, y0 + 3, "\u{25B2}", KeyCode(103), w); // ▲
-            self.render_key(buf, ax, y0 + 4, "\u{25C0}", KeyCode(105), w); // ◀
-            self.render_key(buf, ax + w + 1, y0 + 4, "\u{25BC}", KeyCode(108), w); // ▼
-            self.render_key(buf, ax + (w + 1) * 2, y0 + 4, "\u{25B6}", KeyCode(106), w); // ▶
+            let positions = [
+                (ax + w + 1, y0 + 3),
+                (ax, y0 + 4),
+                (ax + w + 1, y0 + 4),
+                (ax + (w + 1) * 2, y0 + 4),
+            ];
+            for (key, (x, y)) in arrow_keys().iter().zip(positions) {
+                self.render_key(buf, x, y, key.label, KeyCode(key.code), w);
+            }
         }
     }
 }
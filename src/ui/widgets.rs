@@ -1,6 +1,8 @@
 //! Custom TUI widgets
 
 use super::theme::ThemeColors;
+use crate::keyboard::key_code_names;
+use crate::report::DeviceActivity;
 use crate::tests::{ResultStatus, TestResult};
 use ratatui::{
     buffer::Buffer,
@@ -53,8 +55,7 @@ impl<'a> Widget for ResultsPanel<'a> {
         let inner = block.inner(area);
         block.render(area, buf);
 
-        let mut y = inner.y;
-        for result in self.results {
+        for (y, result) in (inner.y..).zip(self.results.iter()) {
             if y >= inner.y + inner.height {
                 break;
             }
@@ -86,7 +87,6 @@ impl<'a> Widget for ResultsPanel<'a> {
                 ]);
                 buf.set_line(inner.x, y, &line, inner.width);
             }
-            y += 1;
         }
     }
 }
@@ -164,6 +164,7 @@ impl Widget for HelpPanel {
                     ("r/R", "Reset"),
                     ("e", "Export"),
                     ("t", "Toggle theme"),
+                    ("d", "Devices"),
                     ("?", "Help"),
                 ][..],
             ),
@@ -182,14 +183,31 @@ impl Widget for HelpPanel {
                     ("0", "Help"),
                 ][..],
             ),
+            (
+                "COVERAGE",
+                &[
+                    ("Ctrl+N/P", "Next/prev view"),
+                    ("Ctrl+Q", "Quit"),
+                    ("Ctrl+R/E", "Reset/Export"),
+                ][..],
+            ),
+            (
+                "NKRO",
+                &[("g", "Guided ghosting check")][..],
+            ),
             (
                 "OEM",
                 &[
                     ("a", "Add FN scancode"),
                     ("f", "Cycle FN mode"),
                     ("c", "Clear mappings"),
+                    ("w/W", "Write/apply hwdb"),
                 ][..],
             ),
+            (
+                "LEDS",
+                &[("l", "Start LED check"), ("y/n", "LED lit / not lit")][..],
+            ),
         ];
 
         for (header, items) in &sections {
@@ -454,6 +472,146 @@ impl<'a> Widget for SettingsPanel<'a> {
     }
 }
 
+/// Device picker - lists input devices and which one is under test
+pub struct DevicePanel<'a> {
+    devices: &'a [DeviceActivity],
+    /// Highlighted row (0 = all devices)
+    selected: usize,
+    colors: ThemeColors,
+}
+
+impl<'a> DevicePanel<'a> {
+    pub fn new(devices: &'a [DeviceActivity], selected: usize) -> Self {
+        Self {
+            devices,
+            selected,
+            colors: ThemeColors::dark(),
+        }
+    }
+
+    pub fn theme(mut self, colors: ThemeColors) -> Self {
+        self.colors = colors;
+        self
+    }
+}
+
+impl<'a> Widget for DevicePanel<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .title(" ⌨ Devices ")
+            .borders(Borders::ALL)
+            .border_set(border::ROUNDED)
+            .border_style(Style::default().fg(self.colors.cyan));
+
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let mut y = inner.y;
+        buf.set_string(
+            inner.x + 2,
+            y,
+            "Use ↑↓ to select, Enter to test only that device, Esc to go back",
+            Style::default().fg(self.colors.dim),
+        );
+        y += 2;
+
+        let any_under_test = self.devices.iter().any(|d| d.under_test);
+        let all_events: u64 = self.devices.iter().map(|d| d.events).sum();
+        let mut rows = vec![(
+            "All devices".to_string(),
+            String::new(),
+            format!("{} events", all_events),
+            !any_under_test,
+        )];
+        rows.extend(self.devices.iter().map(|d| {
+            let status = if d.disconnected { " (unplugged)" } else { "" };
+            let keys = d
+                .key_coverage()
+                .map(|coverage| format!("  {} keys", coverage))
+                .unwrap_or_default();
+            (
+                d.device.label().to_string(),
+                format!("{}  {}", d.device.usb_id(), d.device.path),
+                format!("{} events{}{}", d.events, keys, status),
+                d.under_test,
+            )
+        }));
+
+        for (i, (name, id, events, active)) in rows.iter().enumerate() {
+            if y >= inner.y + inner.height {
+                break;
+            }
+
+            let is_selected = i == self.selected;
+            let name_style = if is_selected {
+                Style::default()
+                    .fg(self.colors.cyan)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(self.colors.fg)
+            };
+            let cursor = if is_selected { "▸ " } else { "  " };
+            let marker = if *active { "● " } else { "  " };
+
+            let line = Line::from(vec![
+                Span::styled(cursor, Style::default().fg(self.colors.cyan)),
+                Span::styled(marker, Style::default().fg(self.colors.green)),
+                Span::styled(format!("{:<32}", name), name_style),
+                Span::styled(format!("{:<34}", id), Style::default().fg(self.colors.dim)),
+                Span::styled(events.as_str(), Style::default().fg(self.colors.yellow)),
+            ]);
+            buf.set_line(inner.x, y, &line, inner.width);
+            y += 1;
+        }
+
+        // Declared keys the highlighted device has not sent yet
+        let highlighted = self.selected.checked_sub(1).and_then(|i| self.devices.get(i));
+        if let Some(activity) = highlighted.filter(|a| a.capabilities.is_some()) {
+            let missing = activity.keys_not_pressed();
+            y += 1;
+            if y < inner.y + inner.height {
+                let heading = if missing.is_empty() {
+                    "Every declared key has been pressed".to_string()
+                } else {
+                    format!("Declared keys not pressed yet ({}):", missing.len())
+                };
+                buf.set_string(inner.x + 2, y, heading, Style::default().fg(self.colors.fg));
+                y += 1;
+            }
+
+            let width = inner.width.saturating_sub(4) as usize;
+            let mut line = String::new();
+            for name in missing.iter().map(|&code| key_code_names([code])) {
+                if !line.is_empty() && line.len() + 1 + name.len() > width {
+                    if y >= inner.y + inner.height {
+                        break;
+                    }
+                    buf.set_string(inner.x + 2, y, &line, Style::default().fg(self.colors.yellow));
+                    y += 1;
+                    line.clear();
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(&name);
+            }
+            if !line.is_empty() && y < inner.y + inner.height {
+                buf.set_string(inner.x + 2, y, &line, Style::default().fg(self.colors.yellow));
+            }
+        }
+
+        if self.devices.is_empty() && y + 2 < inner.y + inner.height {
+            y += 1;
+            buf.set_string(
+                inner.x + 2,
+                y,
+                "No device identity available (needs evdev or a capture with devices)",
+                Style::default().fg(self.colors.dim),
+            );
+        }
+    }
+}
+
 /// Shortcut warning overlay - displayed in any view when a shortcut is detected
 pub struct ShortcutOverlay<'a> {
     combo: &'a str,
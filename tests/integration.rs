@@ -3,10 +3,12 @@
 //! These tests exercise the full App pipeline: event processing through
 //! all 8 test modules, state management, and report generation.
 
+use keyboard_testkit::capture::{Capture, CaptureHeader, CaptureRecorder, ReplayListener};
 use keyboard_testkit::config::{Config, Theme};
 use keyboard_testkit::keyboard::{KeyCode, KeyEvent, KeyEventType};
 use keyboard_testkit::tests::KeyboardTest;
 use keyboard_testkit::ui::{App, AppState, AppView};
+use std::sync::mpsc;
 use std::time::Instant;
 
 // ---------------------------------------------------------------------------
@@ -95,6 +97,26 @@ fn full_pipeline_rollover_tracking() {
     assert_eq!(app.keyboard_state.current_rollover(), 0);
 }
 
+#[test]
+fn full_pipeline_bounce_uses_kernel_timestamps() {
+    let mut app = App::default();
+    let now = Instant::now();
+    let at = |key: u16, event_type: KeyEventType, hw_us: u64| {
+        KeyEvent::new(KeyCode(key), event_type, now, 0).with_hw_timestamp(hw_us)
+    };
+
+    // Clean 20ms taps on 'A': identical poll Instants, but the kernel
+    // timestamps are far outside the 5ms bounce window
+    app.process_event(&at(30, KeyEventType::Press, 1_000_000));
+    app.process_event(&at(30, KeyEventType::Release, 1_020_000));
+    app.process_event(&at(30, KeyEventType::Press, 1_040_000));
+    assert!(app.hold_release_test.bouncy_keys().is_empty());
+
+    // A release 1ms after the press is chatter
+    app.process_event(&at(30, KeyEventType::Release, 1_041_000));
+    assert_eq!(app.hold_release_test.bouncy_keys(), vec![(KeyCode(30), 1)]);
+}
+
 // ---------------------------------------------------------------------------
 // State management
 // ---------------------------------------------------------------------------
@@ -182,7 +204,7 @@ fn view_navigation_backward_wraps() {
     assert_eq!(app.view, AppView::Help);
 
     app.prev_view();
-    assert_eq!(app.view, AppView::OemKeys);
+    assert_eq!(app.view, AppView::Leds);
 }
 
 #[test]
@@ -283,6 +305,248 @@ fn report_file_export_json() {
     let _ = std::fs::remove_file(&path);
 }
 
+// ---------------------------------------------------------------------------
+// Capture & replay
+// ---------------------------------------------------------------------------
+
+#[test]
+fn recorded_session_replays_to_same_results() {
+    let path = std::env::temp_dir().join(format!(
+        "keyboard-testkit-test-{}.kbcap",
+        std::process::id()
+    ));
+
+    // Live session, recording every event as the main loop does
+    let mut live = App::default();
+    {
+        let header = CaptureHeader::new("test", Vec::new());
+        let mut recorder = CaptureRecorder::create(&path, &header).unwrap();
+        for key in [30u16, 31, 32, 33, 34] {
+            for event in [press(key, 1000), release(key, 1000)] {
+                recorder.record(&event).unwrap();
+                live.process_event(&event);
+            }
+        }
+    }
+
+    // Offline session fed from the capture file
+    let capture = Capture::load(&path).unwrap();
+    let (tx, rx) = mpsc::channel();
+    let mut replay = ReplayListener::new(capture, 0.0, tx);
+    replay.poll();
+    assert!(replay.is_finished());
+
+    let mut offline = App::default();
+    for event in rx.try_iter() {
+        offline.process_event(&event);
+    }
+
+    assert_eq!(offline.total_events, live.total_events);
+    assert_eq!(
+        offline.keyboard_state.max_rollover(),
+        live.keyboard_state.max_rollover()
+    );
+    assert_eq!(
+        offline.rollover_test.get_results().len(),
+        live.rollover_test.get_results().len()
+    );
+
+    let _ = std::fs::remove_file(&path);
+}
+
+// ---------------------------------------------------------------------------
+// Headless mode
+// ---------------------------------------------------------------------------
+
+fn headless_command() -> std::process::Command {
+    let mut cmd = std::process::Command::new(env!("CARGO_BIN_EXE_keyboard-testkit"));
+    // Keep the user's config out of the test
+    cmd.env(
+        "XDG_CONFIG_HOME",
+        std::env::temp_dir().join("keyboard-testkit-test-config"),
+    );
+    cmd
+}
+
+/// Ten A presses 60ms apart, each held for 30ms
+fn write_single_key_capture(path: &std::path::Path) {
+    let header = CaptureHeader::new("test", Vec::new());
+    let mut recorder = CaptureRecorder::create(path, &header).unwrap();
+    let now = Instant::now();
+    for i in 0..10u64 {
+        let at = |offset_ms: u64| now + std::time::Duration::from_millis(offset_ms);
+        recorder
+            .record(&KeyEvent::new(
+                KeyCode(30),
+                KeyEventType::Press,
+                at(i * 60),
+                1000,
+            ))
+            .unwrap();
+        recorder
+            .record(&KeyEvent::new(
+                KeyCode(30),
+                KeyEventType::Release,
+                at(i * 60 + 30),
+                1000,
+            ))
+            .unwrap();
+    }
+}
+
+#[test]
+fn headless_replay_writes_report_and_fails_on_errors() {
+    let pid = std::process::id();
+    let capture_path =
+        std::env::temp_dir().join(format!("keyboard-testkit-headless-{}.kbcap", pid));
+    let report_path = std::env::temp_dir().join(format!("keyboard-testkit-headless-{}.json", pid));
+
+    // Single-key typing at ~17Hz: polling rate and rollover both report errors
+    write_single_key_capture(&capture_path);
+
+    let status = headless_command()
+        .args(["--headless", "--replay"])
+        .arg(&capture_path)
+        .arg("--report")
+        .arg(&report_path)
+        .stderr(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(1));
+
+    let report = std::fs::read_to_string(&report_path).expect("report not written");
+    assert!(report.contains("\"total_events\": 20"));
+
+    let _ = std::fs::remove_file(&capture_path);
+    let _ = std::fs::remove_file(&report_path);
+}
+
+#[test]
+fn headless_profile_verdict_sets_exit_code() {
+    let dir = std::env::temp_dir();
+    let pid = std::process::id();
+    let capture_path = dir.join(format!("keyboard-testkit-profile-{}.kbcap", pid));
+    let report_path = dir.join(format!("keyboard-testkit-profile-{}.md", pid));
+    let lenient = dir.join(format!("keyboard-testkit-lenient-{}.toml", pid));
+    let strict = dir.join(format!("keyboard-testkit-strict-{}.toml", pid));
+    write_single_key_capture(&capture_path);
+    std::fs::write(&lenient, "max_bounces = 0\nrequired_keys = [30]\n").unwrap();
+    std::fs::write(&strict, "max_bounces = 0\nrequired_keys = [30, 31]\n").unwrap();
+
+    let run = |profile: &std::path::Path| {
+        headless_command()
+            .args(["--headless", "--replay"])
+            .arg(&capture_path)
+            .arg("--profile")
+            .arg(profile)
+            .arg("--report")
+            .arg(&report_path)
+            .stderr(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .status()
+            .unwrap()
+            .code()
+    };
+
+    // The built-in cutoffs fail this session, but the profile does not ask
+    // for polling rate or rollover
+    assert_eq!(run(&lenient), Some(0));
+    let report = std::fs::read_to_string(&report_path).unwrap();
+    assert!(report.contains("**Verdict: PASS**"));
+
+    assert_eq!(run(&strict), Some(1));
+    let report = std::fs::read_to_string(&report_path).unwrap();
+    assert!(report.contains("missing S"));
+
+    for path in [&capture_path, &report_path, &lenient, &strict] {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[test]
+fn headless_missing_profile_is_setup_error() {
+    let status = headless_command()
+        .args(["--headless", "--profile", "no-such-profile-xyz"])
+        .stderr(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(2));
+}
+
+#[test]
+fn headless_missing_matrix_is_setup_error() {
+    let status = headless_command()
+        .args(["--headless", "--matrix", "no-such-matrix-xyz"])
+        .stderr(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(2));
+}
+
+#[test]
+fn headless_missing_capture_is_setup_error() {
+    let status = headless_command()
+        .args(["--headless", "--replay", "/nonexistent/capture.kbcap"])
+        .stderr(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(2));
+}
+
+#[test]
+fn bad_numeric_option_is_usage_error() {
+    for args in [
+        vec!["--speed", "4x"],
+        vec!["--speed", "-2"],
+        vec!["--speed", "NaN"],
+        vec!["--speed", "0"],
+        vec!["--duration", "30s"],
+        vec!["--duration", "0"],
+        vec!["--duration"],
+        vec!["--rt-priority", "abc"],
+        vec!["--rt-priority", "0"],
+        vec!["--rt-priority", "150"],
+        vec!["--rt-priority"],
+    ] {
+        let output = headless_command()
+            .arg("--headless")
+            .args(&args)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(2), "{:?} accepted", args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(args[0]), "{:?}: {}", args, stderr);
+    }
+}
+
+#[test]
+fn option_without_value_is_usage_error() {
+    for (args, flag) in [
+        (vec!["--headless", "--report", "--record", "x.kbcap"], "--report"),
+        (vec!["--headless", "--replay"], "--replay"),
+        (vec!["--headless", "--profile"], "--profile"),
+        (vec!["--headless", "--matrix", "--headless"], "--matrix"),
+        (vec!["--headless", "--device"], "--device"),
+        (vec!["--mapper", "--device", "/dev/input/event3", "--device"], "--device"),
+        (vec!["--mapper", "--preset"], "--preset"),
+        (vec!["--mapper-install", "--preset", "--device"], "--preset"),
+    ] {
+        let output = headless_command().args(&args).output().unwrap();
+        assert_eq!(output.status.code(), Some(2), "{:?} accepted", args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains(&format!("{} needs a value", flag)),
+            "{:?}: {}",
+            args,
+            stderr
+        );
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Configuration integration
 // ---------------------------------------------------------------------------